@@ -0,0 +1,111 @@
+#![feature(test)]
+
+extern crate cbordata;
+extern crate test;
+
+use test::Bencher;
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering::SeqCst},
+};
+
+use cbordata::{Cbor, CborRef, Cborize, FromCbor, FromCborRef, IntoCbor};
+
+// Count allocations, to compare owned decode with borrowed decode.
+struct Counter;
+
+static ALLOCS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counter {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCS.fetch_add(1, SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counter = Counter;
+
+#[derive(Cborize)]
+struct Owned {
+    id: u64,
+    name: String,
+    path: String,
+    tags: Vec<String>,
+    retries: u32,
+}
+
+impl Owned {
+    const ID: &'static str = "request";
+}
+
+#[derive(FromCborRef)]
+struct Borrowed<'a> {
+    id: u64,
+    name: &'a str,
+    path: &'a str,
+    tags: Vec<&'a str>,
+    retries: u32,
+}
+
+impl<'a> Borrowed<'a> {
+    const ID: &'static str = "request";
+}
+
+fn encoded() -> Vec<u8> {
+    let val = Owned {
+        id: 1024,
+        name: "gateway".to_string(),
+        path: "/api/v1/documents/index".to_string(),
+        tags: vec!["fast".to_string(), "read-mostly".to_string(), "proxy".to_string()],
+        retries: 3,
+    };
+    let mut buf = vec![];
+    val.into_cbor().unwrap().encode(&mut buf).unwrap();
+    buf
+}
+
+fn allocs<F: FnOnce()>(f: F) -> usize {
+    let start = ALLOCS.load(SeqCst);
+    f();
+    ALLOCS.load(SeqCst) - start
+}
+
+#[bench]
+fn bench_owned_decode(b: &mut Bencher) {
+    let buf = encoded();
+    let n = allocs(|| {
+        let (val, _) = Cbor::decode(&mut buf.as_slice()).unwrap();
+        Owned::from_cbor(val).unwrap();
+    });
+    println!("owned decode, {} allocations", n);
+
+    b.bytes = buf.len() as u64;
+    b.iter(|| {
+        let (val, _) = Cbor::decode(&mut buf.as_slice()).unwrap();
+        let val = Owned::from_cbor(val).unwrap();
+        (val.id, val.name.len(), val.path.len(), val.tags.len(), val.retries)
+    });
+}
+
+#[bench]
+fn bench_borrowed_decode(b: &mut Bencher) {
+    let buf = encoded();
+    let n = allocs(|| {
+        let (val, _) = CborRef::decode(&buf).unwrap();
+        Borrowed::from_cbor_ref(&val).unwrap();
+    });
+    println!("borrowed decode, {} allocations", n);
+
+    b.bytes = buf.len() as u64;
+    b.iter(|| {
+        let (val, _) = CborRef::decode(&buf).unwrap();
+        let val = Borrowed::from_cbor_ref(&val).unwrap();
+        (val.id, val.name.len(), val.path.len(), val.tags.len(), val.retries)
+    });
+}
@@ -14,7 +14,7 @@ fn bench_null(b: &mut Bencher) {
 
     b.iter(|| {
         let val: Cbor = SimpleValue::Null.into_cbor().unwrap();
-        buf.truncate(0);
+        buf.clear();
         n += val.encode(&mut buf).unwrap();
     });
 }
@@ -26,7 +26,7 @@ fn bench_bool(b: &mut Bencher) {
 
     b.iter(|| {
         let val: Cbor = true.into_cbor().unwrap();
-        buf.truncate(0);
+        buf.clear();
         n += val.encode(&mut buf).unwrap();
     });
 }
@@ -38,7 +38,7 @@ fn bench_num(b: &mut Bencher) {
 
     b.iter(|| {
         let val: Cbor = 123121.2234234.into_cbor().unwrap();
-        buf.truncate(0);
+        buf.clear();
         n += val.encode(&mut buf).unwrap();
     });
 }
@@ -51,7 +51,7 @@ fn bench_string(b: &mut Bencher) {
 
     b.iter(|| {
         let val: Cbor = s.into_cbor().unwrap();
-        buf.truncate(0);
+        buf.clear();
         n += val.encode(&mut buf).unwrap();
     });
 }
@@ -69,7 +69,7 @@ fn bench_array(b: &mut Bencher) {
 
     b.iter(|| {
         let val: Cbor = arr.clone().into_cbor().unwrap();
-        buf.truncate(0);
+        buf.clear();
         n += val.encode(&mut buf).unwrap();
     });
 }
@@ -87,7 +87,7 @@ fn bench_map(b: &mut Bencher) {
     ];
     b.iter(|| {
         let val: Cbor = map.clone().into_cbor().unwrap();
-        buf.truncate(0);
+        buf.clear();
         n += val.encode(&mut buf).unwrap();
     });
 }
@@ -0,0 +1,129 @@
+#![feature(test)]
+
+extern crate cbordata;
+extern crate test;
+
+use test::Bencher;
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering::SeqCst},
+};
+
+use cbordata::{Cbor, CborBuffer, CborRef, IntoCbor, Key};
+
+// Count allocations, for a time-series of documents repeating the same text
+// keys. Text keys are not interned while decoding, the recycling decode,
+// using `CborBuffer`, and the borrowed decode, using `CborRef`, are measured
+// instead. Owned decode allocates 10 times per sample, borrowed decode twice,
+// and recycling decode none, once storage is recycled.
+struct Counter;
+
+static ALLOCS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counter {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCS.fetch_add(1, SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counter = Counter;
+
+const KEYS: [&str; 6] =
+    ["timestamp", "sensor", "temperature", "humidity", "pressure", "status"];
+
+// Samples, each a map of 6 text keys, repeated across samples.
+fn series() -> Vec<Vec<u8>> {
+    let mut bufs = vec![];
+    for i in 0..100_u64 {
+        let vals = vec![
+            (1_700_000_000 + i).into_cbor().unwrap(),
+            format!("sensor-{}", i % 4).into_cbor().unwrap(),
+            (20.0 + (i as f64) / 10.0).into_cbor().unwrap(),
+            (i % 100).into_cbor().unwrap(),
+            (1000 + i).into_cbor().unwrap(),
+            "ok".into_cbor().unwrap(),
+        ];
+        let doc: Vec<(Key, Cbor)> =
+            KEYS.iter().map(|key| Key::from(*key)).zip(vals).collect();
+        let mut buf = vec![];
+        doc.into_cbor().unwrap().encode(&mut buf).unwrap();
+        bufs.push(buf);
+    }
+    bufs
+}
+
+fn allocs<F: FnOnce()>(f: F) -> usize {
+    let start = ALLOCS.load(SeqCst);
+    f();
+    ALLOCS.load(SeqCst) - start
+}
+
+#[bench]
+fn bench_keys_decode(b: &mut Bencher) {
+    let bufs = series();
+    let n = allocs(|| {
+        for buf in bufs.iter() {
+            Cbor::decode(&mut buf.as_slice()).unwrap();
+        }
+    });
+    println!("keys decode, {} allocations per sample", n / bufs.len());
+
+    b.bytes = bufs.iter().map(|buf| buf.len() as u64).sum();
+    b.iter(|| {
+        let mut n = 0;
+        for buf in bufs.iter() {
+            n += Cbor::decode(&mut buf.as_slice()).unwrap().1;
+        }
+        n
+    });
+}
+
+#[bench]
+fn bench_keys_decode_buffer(b: &mut Bencher) {
+    let bufs = series();
+    let mut buffer = CborBuffer::new();
+    let decode = |buffer: &mut CborBuffer| {
+        let mut n = 0;
+        for buf in bufs.iter() {
+            let (val, m) = buffer.decode(&mut buf.as_slice()).unwrap();
+            buffer.recycle(val);
+            n += m;
+        }
+        n
+    };
+    decode(&mut buffer);
+    let n = allocs(|| {
+        decode(&mut buffer);
+    });
+    println!("keys decode with buffer, {} allocations per sample", n / bufs.len());
+
+    b.bytes = bufs.iter().map(|buf| buf.len() as u64).sum();
+    b.iter(|| decode(&mut buffer));
+}
+
+#[bench]
+fn bench_keys_decode_borrowed(b: &mut Bencher) {
+    let bufs = series();
+    let n = allocs(|| {
+        for buf in bufs.iter() {
+            CborRef::decode(buf).unwrap();
+        }
+    });
+    println!("keys borrowed decode, {} allocations per sample", n / bufs.len());
+
+    b.bytes = bufs.iter().map(|buf| buf.len() as u64).sum();
+    b.iter(|| {
+        let mut n = 0;
+        for buf in bufs.iter() {
+            n += CborRef::decode(buf).unwrap().1;
+        }
+        n
+    });
+}
@@ -0,0 +1,124 @@
+#![feature(test)]
+
+extern crate cbordata;
+extern crate test;
+
+use test::Bencher;
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering::SeqCst},
+};
+
+use cbordata::{Cbor, CborBuffer, IntoCbor, Key};
+
+// Count allocations, for a corpus of documents with small arrays and maps.
+// Collections are not backed by inline storage, like `SmallVec`, since the
+// `Vec` in `Cbor::Major4` and `Cbor::Major5` is part of the public API. The
+// recycling decode, using `CborBuffer`, is measured instead.
+struct Counter;
+
+static ALLOCS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counter {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCS.fetch_add(1, SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counter = Counter;
+
+// Documents with at most 4 items in every array and map.
+fn corpus() -> Vec<Vec<u8>> {
+    let mut bufs = vec![];
+    for i in 0..100_u64 {
+        let point = vec![i, i + 1, i + 2].into_cbor().unwrap();
+        let attrs: Vec<(Key, Cbor)> = vec![
+            (Key::U64(1), (i * 10).into_cbor().unwrap()),
+            (Key::U64(2), true.into_cbor().unwrap()),
+        ];
+        let doc: Vec<(Key, Cbor)> = vec![
+            (Key::U64(0), i.into_cbor().unwrap()),
+            (Key::U64(1), point),
+            (Key::U64(2), attrs.into_cbor().unwrap()),
+            (Key::U64(3), vec![-1_i64, -2].into_cbor().unwrap()),
+        ];
+        let mut buf = vec![];
+        doc.into_cbor().unwrap().encode(&mut buf).unwrap();
+        bufs.push(buf);
+    }
+    bufs
+}
+
+fn allocs<F: FnOnce()>(f: F) -> usize {
+    let start = ALLOCS.load(SeqCst);
+    f();
+    ALLOCS.load(SeqCst) - start
+}
+
+#[bench]
+fn bench_small_decode(b: &mut Bencher) {
+    let bufs = corpus();
+    let n = allocs(|| {
+        for buf in bufs.iter() {
+            Cbor::decode(&mut buf.as_slice()).unwrap();
+        }
+    });
+    println!("small decode, {} allocations per document", n / bufs.len());
+
+    b.bytes = bufs.iter().map(|buf| buf.len() as u64).sum();
+    b.iter(|| {
+        let mut n = 0;
+        for buf in bufs.iter() {
+            n += Cbor::decode(&mut buf.as_slice()).unwrap().1;
+        }
+        n
+    });
+}
+
+#[bench]
+fn bench_small_decode_buffer(b: &mut Bencher) {
+    let bufs = corpus();
+    let mut buffer = CborBuffer::new();
+    let decode = |buffer: &mut CborBuffer| {
+        let mut n = 0;
+        for buf in bufs.iter() {
+            let (val, m) = buffer.decode(&mut buf.as_slice()).unwrap();
+            buffer.recycle(val);
+            n += m;
+        }
+        n
+    };
+    decode(&mut buffer);
+    let n = allocs(|| {
+        decode(&mut buffer);
+    });
+    println!("small decode with buffer, {} allocations per document", n / bufs.len());
+
+    b.bytes = bufs.iter().map(|buf| buf.len() as u64).sum();
+    b.iter(|| decode(&mut buffer));
+}
+
+#[bench]
+fn bench_small_encode(b: &mut Bencher) {
+    let vals: Vec<Cbor> = corpus()
+        .into_iter()
+        .map(|buf| Cbor::decode(&mut buf.as_slice()).unwrap().0)
+        .collect();
+    let mut buf: Vec<u8> = Vec::with_capacity(1024);
+
+    b.iter(|| {
+        let mut n = 0;
+        for val in vals.iter() {
+            buf.clear();
+            n += val.encode(&mut buf).unwrap();
+        }
+        n
+    });
+}
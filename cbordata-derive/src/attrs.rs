@@ -0,0 +1,177 @@
+use proc_macro_error::abort;
+use syn::{
+    parse::ParseStream, punctuated::Punctuated, spanned::Spanned, DeriveInput, Field,
+    Ident, Lit, LitStr, Meta, MetaNameValue, NestedMeta, Token, Variant,
+};
+
+/// Attributes applicable on struct and enum types, as `#[cbor(...)]`.
+#[derive(Default)]
+pub struct ContainerAttrs {
+    /// Encode single-field struct as its only field.
+    pub transparent: bool,
+    /// Encode struct as map, keyed by field names, `repr = "named_map"`.
+    pub named_map: bool,
+    /// Match only the first item of a list `ID`, `id_match = "prefix"`.
+    pub id_prefix: bool,
+    /// Catch-all variant for unknown variants of enum, `unknown = Variant`.
+    pub unknown: Option<Ident>,
+}
+
+pub fn container_attrs(input: &DeriveInput) -> ContainerAttrs {
+    let mut attrs = ContainerAttrs::default();
+    for nested in cbor_attrs(&input.attrs).into_iter() {
+        match &nested {
+            NestedMeta::Meta(Meta::Path(path)) if path.is_ident("transparent") => {
+                attrs.transparent = true
+            }
+            NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("repr") => {
+                match &nv.lit {
+                    Lit::Str(lit) if lit.value() == "named_map" => attrs.named_map = true,
+                    lit => abort!(lit.span(), "expected repr = \"named_map\""),
+                }
+            }
+            NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("id_match") => {
+                match &nv.lit {
+                    Lit::Str(lit) if lit.value() == "prefix" => attrs.id_prefix = true,
+                    Lit::Str(lit) if lit.value() == "exact" => attrs.id_prefix = false,
+                    lit => {
+                        abort!(lit.span(), "expected id_match = \"prefix\" or \"exact\"")
+                    }
+                }
+            }
+            NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("unknown") => {
+                match &nv.lit {
+                    Lit::Str(lit) => match lit.parse::<Ident>() {
+                        Ok(ident) => attrs.unknown = Some(ident),
+                        Err(err) => abort!(lit.span(), "{}", err),
+                    },
+                    lit => abort!(lit.span(), "expected unknown = Variant"),
+                }
+            }
+            _ => abort!(nested.span(), "unknown cbor attribute for type"),
+        }
+    }
+    if attrs.transparent && attrs.named_map {
+        abort!(input.span(), "transparent and named_map are mutually exclusive")
+    }
+    if attrs.id_prefix && (attrs.transparent || attrs.named_map) {
+        abort!(input.span(), "id_match not applicable without ID")
+    }
+    if attrs.unknown.is_some() && !matches!(input.data, syn::Data::Enum(_)) {
+        abort!(input.span(), "unknown applicable only for enum")
+    }
+    attrs
+}
+
+/// Attributes applicable on fields, as `#[cbor(...)]`.
+#[derive(Default)]
+pub struct FieldAttrs {
+    /// Capture items, not matched by other fields, into this field.
+    pub flatten_rest: bool,
+    /// Omit this field, initialized with `Default::default()`, `skip`.
+    pub skip: bool,
+    /// Initialize with `Default::default()` when missing, `default`.
+    pub default: bool,
+    /// Key for this field in named_map, `rename = "key"`.
+    pub rename: Option<String>,
+}
+
+impl FieldAttrs {
+    /// Whether the field is encoded as an item of its own.
+    pub fn is_item(&self) -> bool {
+        !self.flatten_rest && !self.skip
+    }
+}
+
+pub fn field_attrs(field: &Field) -> FieldAttrs {
+    let mut attrs = FieldAttrs::default();
+    for nested in cbor_attrs(&field.attrs).into_iter() {
+        match &nested {
+            NestedMeta::Meta(Meta::Path(path)) if path.is_ident("flatten_rest") => {
+                attrs.flatten_rest = true
+            }
+            NestedMeta::Meta(Meta::Path(path)) if path.is_ident("skip") => {
+                attrs.skip = true
+            }
+            NestedMeta::Meta(Meta::Path(path)) if path.is_ident("default") => {
+                attrs.default = true
+            }
+            NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("rename") => {
+                match &nv.lit {
+                    Lit::Str(lit) => attrs.rename = Some(lit.value()),
+                    lit => abort!(lit.span(), "expected rename = \"key\""),
+                }
+            }
+            _ => abort!(nested.span(), "unknown cbor attribute for field"),
+        }
+    }
+    if attrs.flatten_rest && (attrs.skip || attrs.default || attrs.rename.is_some()) {
+        abort!(field.span(), "flatten_rest cannot be combined with other attributes")
+    }
+    if attrs.skip && (attrs.default || attrs.rename.is_some()) {
+        abort!(field.span(), "skip cannot be combined with other attributes")
+    }
+    attrs
+}
+
+/// Attributes applicable on enum variants, as `#[cbor(...)]`.
+#[derive(Default)]
+pub struct VariantAttrs {
+    /// Explicit integer discriminant for this variant, `n = K` or `tag = K`.
+    pub n: Option<u64>,
+}
+
+pub fn variant_attrs(variant: &Variant) -> VariantAttrs {
+    let mut attrs = VariantAttrs::default();
+    for nested in cbor_attrs(&variant.attrs).into_iter() {
+        match &nested {
+            NestedMeta::Meta(Meta::NameValue(nv))
+                if nv.path.is_ident("n") || nv.path.is_ident("tag") =>
+            {
+                if attrs.n.is_some() {
+                    abort!(nv.span(), "duplicate discriminant for variant")
+                }
+                match &nv.lit {
+                    Lit::Int(lit) => match lit.base10_parse::<u64>() {
+                        Ok(n) => attrs.n = Some(n),
+                        Err(err) => abort!(lit.span(), "{}", err),
+                    },
+                    lit => abort!(lit.span(), "expected unsigned integer"),
+                }
+            }
+            _ => abort!(nested.span(), "unknown cbor attribute for variant"),
+        }
+    }
+    attrs
+}
+
+fn cbor_attrs(attrs: &[syn::Attribute]) -> Vec<NestedMeta> {
+    let mut items = vec![];
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("cbor")) {
+        let parser = |input: ParseStream| {
+            Punctuated::<NestedMeta, Token![,]>::parse_terminated_with(
+                input,
+                parse_nested,
+            )
+        };
+        match attr.parse_args_with(parser) {
+            Ok(nested) => items.extend(nested),
+            _ => abort!(attr.span(), "expected #[cbor(...)] attribute"),
+        }
+    }
+    items
+}
+
+// Same as parsing `NestedMeta`, except that values can also be identifiers,
+// like `unknown = Unknown`, which are treated as string literals.
+fn parse_nested(input: ParseStream) -> syn::Result<NestedMeta> {
+    if input.peek(Ident) && input.peek2(Token![=]) && input.peek3(Ident) {
+        let path: Ident = input.parse()?;
+        let eq_token = input.parse()?;
+        let value: Ident = input.parse()?;
+        let lit = Lit::Str(LitStr::new(&value.to_string(), value.span()));
+        let nv = MetaNameValue { path: path.into(), eq_token, lit };
+        return Ok(NestedMeta::Meta(Meta::NameValue(nv)));
+    }
+    input.parse()
+}
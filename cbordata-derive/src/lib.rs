@@ -6,10 +6,11 @@ extern crate syn;
 
 use lazy_static::lazy_static;
 use proc_macro2::TokenStream;
-use proc_macro_error::{abort_call_site, proc_macro_error};
+use proc_macro_error::{abort, abort_call_site, proc_macro_error};
 use quote::quote;
 use syn::{spanned::Spanned, *};
 
+mod attrs;
 mod ty;
 
 lazy_static! {
@@ -17,6 +18,107 @@ lazy_static! {
         vec!["a", "b", "c", "d", "e", "f", "g", "h", "i", "j", "k", "l"];
 }
 
+/// Derive `IntoCbor` and `FromCbor` for struct and enum types.
+///
+/// Along with the conversion traits, an associated function
+/// `fn schema_hash() -> Result<u64>` is generated, a fingerprint of the type's
+/// `ID` and layout. Layout is hashed at compile time, and the encoded `ID`,
+/// which is not known until then, is folded into it at runtime. Following
+/// changes shall alter the fingerprint:
+///
+/// * Changing the value of the type's `ID`. Except for `transparent` and
+///   `named_map` types, which do not encode their `ID`.
+///
+/// * Adding, removing, renaming or re-ordering fields.
+/// * Changing the type of a field, where types are compared by their source
+///   text, that is `Vec<u8>` and `std::vec::Vec<u8>` are treated as different.
+/// * Adding, removing, renaming or re-ordering enum variants.
+/// * Adding, removing or changing an explicit discriminant of enum variant.
+/// * Changing a struct into an enum, or named fields into unnamed fields.
+/// * Adding or removing the `transparent` or `repr` attribute.
+/// * Adding or removing the `flatten_rest`, `skip` or `default` attribute on
+///   a field, or changing its `rename` attribute.
+///
+/// And following changes shall not alter the fingerprint:
+///
+/// * Renaming the type itself, changing its visibility or documentation.
+///
+/// Structs are encoded as a list of items, starting with the type's `ID`
+/// followed by the fields in declaration order, this applies to tuple
+/// structs as well. Structs without fields, like `struct Marker;`,
+/// `struct Marker {}` or `struct Marker();`, are encoded as a list holding
+/// just the `ID`, hence such types are distinguished only by their `ID`.
+/// Type attributes:
+///
+/// * `#[cbor(transparent)]`, on a struct with exactly one field, encodes the
+///   struct as its only field, without the `ID` and the enclosing list.
+///   `ID` is not required for transparent structs.
+///
+/// * `#[cbor(repr = "named_map")]`, on a struct with named fields, encodes
+///   the struct as a map keyed by field names, as text. Converting from
+///   cbor matches fields by name, irrespective of their order, and ignores
+///   unknown keys. A `flatten_rest` field, of type `Vec<(Key, Cbor)>`, can
+///   capture the unknown keys. `ID` is not required for such structs.
+///
+/// * `#[cbor(id_match = "prefix")]`, on a struct whose `ID` is a list, like
+///   `const ID: [u64; 2] = [TYPE, VERSION];`, matches only the first item of
+///   the `ID` when converting from cbor, so that values written with other
+///   versions of the `ID` are accepted. Additionally generates
+///   `fn from_cbor_with_id(Cbor) -> Result<(Self, Cbor)>`, returning the `ID`
+///   found in the value, say to decide on the version, and its counterpart
+///   `from_cbor_with_id_config`, refer to `FromCbor::from_cbor_with`. Defaults to
+///   `id_match = "exact"`.
+///
+/// Field attributes:
+///
+/// * `#[cbor(flatten_rest)]`, on a field of type `Vec<Cbor>`, captures items
+///   trailing the known fields, which are re-emitted after the known fields
+///   when converting back into cbor. Newer versions of a type can thus be
+///   forwarded, without loss, by readers that know only an older version.
+///   At most one field can be marked with `flatten_rest`.
+///
+/// * `#[cbor(skip)]`, omit the field when converting into cbor, and
+///   initialize the field with `Default::default()` when converting from
+///   cbor. Useful for transient fields, like caches.
+///
+/// * `#[cbor(default)]`, initialize the field with `Default::default()` when
+///   it is missing in the cbor value, so that documents written by older
+///   versions of the type, without the newer fields, can be converted.
+///   For structs encoded as list, such fields must follow the other fields.
+///
+/// * `#[cbor(rename = "key")]`, on structs with `repr = "named_map"`, use
+///   `key` as the field's name in the map, say for compact keys.
+///
+/// Fields of enum variants can be marked with `skip` and `default`, while
+/// `flatten_rest` applies only to named fields of structs.
+///
+/// Generic types can have type, lifetime and const parameters, like
+/// `struct Entry<K, V, const N: usize>`. Each type parameter is bounded by
+/// `IntoCbor`, and `FromCbor`, for the respective implementation, along with
+/// the bounds declared on the type. `ID` is defined for all instantiations
+/// of the type, like `impl<K, V, const N: usize> Entry<K, V, N> { .. }`.
+///
+/// Structs with a lifetime parameter, like `struct Msg<'a> { name: &'a str }`,
+/// can only borrow from the input, hence `FromCborRef` is generated in place
+/// of `FromCbor`, with the same restrictions as the `FromCborRef` derive.
+///
+/// Enum variants are identified by their name, encoded as text. Variant
+/// attributes:
+///
+/// * `#[cbor(n = K)]`, identify the variant by an explicit unsigned integer
+///   `K`, instead of its name. Keeps the encoding compact and stable across
+///   renaming of variants. Explicit discriminants must be unique.
+/// * `#[cbor(tag = K)]`, same as `n = K`. Pinning every variant to a
+///   discriminant also keeps the encoding stable across re-ordering of
+///   variants, since decoding dispatches on the discriminant, never on the
+///   position of variant in source.
+///
+/// Open enums, that can receive variants added by newer versions of the type,
+/// can be marked with `#[cbor(unknown = Unknown)]`, naming a catch-all variant
+/// like `Unknown(Cbor)`. Converting from cbor captures the unrecognized
+/// discriminant, along with its fields, as a list into the catch-all variant,
+/// which is re-emitted as is when converting back into cbor. The catch-all
+/// variant is never matched by its own name.
 #[proc_macro_derive(Cborize, attributes(cbor))]
 #[proc_macro_error]
 pub fn cborize_type(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
@@ -43,23 +145,75 @@ pub fn local_cborize_type(input: proc_macro::TokenStream) -> proc_macro::TokenSt
     gen.into()
 }
 
+/// Derive `FromCborRef` for struct types, to convert from the borrowed
+/// `CborRef` value without copying.
+///
+/// Expects the same encoding as `Cborize`, including the type's `ID` and the
+/// `transparent` attribute. Each field is converted using its own
+/// `FromCborRef` implementation, hence fields can borrow from the input, like
+/// `&'a str` and `&'a [u8]`, while other fields, like `String` and `u64`, are
+/// owned. Structs can have at most one lifetime parameter, which is the
+/// lifetime of the borrowed input. `repr = "named_map"`, `id_match` and
+/// `flatten_rest` are not supported.
+#[proc_macro_derive(FromCborRef, attributes(cbor))]
+#[proc_macro_error]
+pub fn from_cbor_ref_type(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input: DeriveInput = syn::parse(input).unwrap();
+    impl_from_cbor_ref(&input, false).into()
+}
+
+#[proc_macro_derive(LocalFromCborRef, attributes(cbor))]
+#[proc_macro_error]
+pub fn local_from_cbor_ref_type(
+    input: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let input: DeriveInput = syn::parse(input).unwrap();
+    impl_from_cbor_ref(&input, true).into()
+}
+
 fn impl_cborize_struct(input: &DeriveInput, crate_local: bool) -> TokenStream {
     let name = &input.ident;
     let generics = no_default_generics(input);
+    let cattrs = attrs::container_attrs(input);
+    let repr = match (cattrs.transparent, cattrs.named_map) {
+        (true, _) => "transparent",
+        (_, true) => "named_map",
+        (false, false) => "struct",
+    };
+
+    let mut ts = schema_hash(name, &generics, &input.data, repr, crate_local);
+    // with a lifetime, the type can only borrow from the input, hence
+    // generate FromCborRef in place of FromCbor.
+    if generics.lifetimes().next().is_some() {
+        let fields = match &input.data {
+            Data::Struct(ast) => &ast.fields,
+            _ => unreachable!(),
+        };
+        if cattrs.transparent {
+            ts.extend(transparent_to_cbor(name, &generics, fields, crate_local));
+        } else {
+            ts.extend(from_struct_to_cbor(name, &generics, fields, crate_local));
+        }
+        ts.extend(impl_from_cbor_ref(input, crate_local));
+        return ts;
+    }
 
-    let mut ts = TokenStream::new();
     match &input.data {
+        Data::Struct(ast) if cattrs.transparent => {
+            ts.extend(transparent_struct(name, &generics, &ast.fields, crate_local));
+            ts
+        }
+        Data::Struct(ast) if cattrs.named_map => {
+            ts.extend(named_map_struct(name, &generics, &ast.fields, crate_local));
+            ts
+        }
         Data::Struct(ast) => {
-            ts.extend(from_struct_to_cbor(
-                name,
-                &generics,
-                &ast.fields,
-                crate_local,
-            ));
+            ts.extend(from_struct_to_cbor(name, &generics, &ast.fields, crate_local));
             ts.extend(from_cbor_to_struct(
                 name,
                 &generics,
                 &ast.fields,
+                cattrs.id_prefix,
                 crate_local,
             ));
             ts
@@ -87,25 +241,14 @@ fn from_struct_to_cbor(
     let token_fields = match fields {
         Fields::Unit => quote! {},
         Fields::Named(fields) => named_fields_to_cbor(fields, croot.clone()),
-        Fields::Unnamed(_) => {
-            abort_call_site!("unnamed struct not supported for Cborize {}", name)
-        }
+        Fields::Unnamed(fields) => tuple_fields_to_cbor(fields, croot.clone()),
     };
 
-    let mut where_clause = match &generics.where_clause {
-        Some(where_clause) => quote! { #where_clause },
-        None => quote! { where },
-    };
-    for param in generics.params.iter() {
-        let type_var = match param {
-            GenericParam::Type(param) => &param.ident,
-            _ => abort_call_site!("only type parameter are supported"),
-        };
-        where_clause.extend(quote! { #type_var: #croot::IntoCbor, });
-    }
+    let where_clause = bounded_where(generics, quote! { #croot::IntoCbor });
+    let (impl_generics, ty_generics, _) = generics.split_for_impl();
 
     quote! {
-        impl #generics #croot::IntoCbor for #name #generics #where_clause {
+        impl #impl_generics #croot::IntoCbor for #name #ty_generics #where_clause {
             fn into_cbor(self) -> #croot::Result<#croot::Cbor> {
                 let value = self;
                 let mut items: Vec<#croot::Cbor> = Vec::default();
@@ -123,35 +266,66 @@ fn from_cbor_to_struct(
     name: &Ident,
     generics: &Generics,
     fields: &Fields,
+    id_prefix: bool,
     crate_local: bool,
 ) -> TokenStream {
     let name_lit = name.to_string();
     let croot = get_root_crate(crate_local);
-    let n_fields = match fields {
-        Fields::Unit => 0,
-        Fields::Named(fields) => fields.named.len(),
-        Fields::Unnamed(_) => {
-            abort_call_site!("unnamed struct not supported for Cborize {}", name)
+    let has_rest = match fields {
+        Fields::Named(fields) => rest_field(fields).is_some(),
+        _ => false,
+    };
+    let (n_min, n_fields) = arity(fields);
+    let check_arity = if has_rest {
+        quote! {
+            if #n_min > items.len() {
+                #croot::err_at!(FailConvert, msg: "bad arity {} {}", #n_fields, items.len())?;
+            }
+        }
+    } else if n_min == n_fields {
+        quote! {
+            if #n_fields != items.len() {
+                #croot::err_at!(FailConvert, msg: "bad arity {} {}", #n_fields, items.len())?;
+            }
+        }
+    } else {
+        quote! {
+            if items.len() < #n_min || items.len() > #n_fields {
+                #croot::err_at!(FailConvert, msg: "bad arity {} {}", #n_fields, items.len())?;
+            }
         }
     };
 
     let id_declr = let_id(name, generics);
+    let check_id = if id_prefix {
+        quote! {
+            let data_id = {
+                #id_declr;
+                match #croot::match_cborize_id_prefix(data_id, &id) {
+                    Some(data_id) => data_id,
+                    None => #croot::err_at!(FailConvert, msg: "bad id for {}", #name_lit)?,
+                }
+            };
+        }
+    } else {
+        quote! {
+            let type_id: #croot::Cbor = {
+                #id_declr;
+                #croot::Tag::from_identifier(id).into()
+            };
+            if data_id != type_id {
+                #croot::err_at!(FailConvert, msg: "bad id for {}", #name_lit)?;
+            }
+        }
+    };
     let preamble = quote! {
         // validate the cbor msg for this type.
         if items.len() == 0 {
             #croot::err_at!(FailConvert, msg: "empty msg for {}", #name_lit)?;
         }
         let data_id = items.remove(0);
-        let type_id: #croot::Cbor = {
-            #id_declr;
-            #croot::Tag::from_identifier(id).into()
-        };
-        if data_id != type_id {
-            #croot::err_at!(FailConvert, msg: "bad id for {}", #name_lit)?;
-        }
-        if #n_fields != items.len() {
-            #croot::err_at!(FailConvert, msg: "bad arity {} {}", #n_fields, items.len())?;
-        }
+        #check_id
+        #check_arity
     };
 
     let token_fields = match fields {
@@ -160,33 +334,388 @@ fn from_cbor_to_struct(
             let token_fields = cbor_to_named_fields(fields, croot.clone());
             quote! { { #token_fields } }
         }
-        Fields::Unnamed(_) => {
-            abort_call_site!("unnamed struct not supported for Cborize {}", name)
+        Fields::Unnamed(fields) => {
+            let token_fields = cbor_to_tuple_fields(fields, croot.clone());
+            quote! { ( #token_fields ) }
+        }
+    };
+
+    let where_clause = bounded_where(generics, quote! { #croot::FromCbor });
+    let (impl_generics, ty_generics, _) = generics.split_for_impl();
+
+    if id_prefix {
+        quote! {
+            impl #impl_generics #name #ty_generics #where_clause {
+                /// Convert from cbor, along with the `ID` found in the value,
+                /// which can differ from the type's `ID` except for its first
+                /// item.
+                pub fn from_cbor_with_id(
+                    value: #croot::Cbor
+                ) -> #croot::Result<(Self, #croot::Cbor)> {
+                    Self::from_cbor_with_id_config(value, &Default::default())
+                }
+
+                /// Same as `from_cbor_with_id`, applying the coercions
+                /// enabled in `config`, refer to `FromCbor::from_cbor_with`.
+                pub fn from_cbor_with_id_config(
+                    value: #croot::Cbor,
+                    config: &#croot::DecodeConfig,
+                ) -> #croot::Result<(Self, #croot::Cbor)> {
+                    use #croot::{IntoCbor, Error, FromCbor};
+
+                    let mut items = Vec::<#croot::Cbor>::from_cbor(value)?;
+
+                    #preamble
+
+                    Ok((#name #token_fields, data_id))
+                }
+            }
+
+            impl #impl_generics #croot::FromCbor for #name #ty_generics #where_clause {
+                fn from_cbor(value: #croot::Cbor) -> #croot::Result<Self> {
+                    Ok(Self::from_cbor_with_id(value)?.0)
+                }
+
+                fn from_cbor_with(
+                    value: #croot::Cbor,
+                    config: &#croot::DecodeConfig,
+                ) -> #croot::Result<Self> {
+                    Ok(Self::from_cbor_with_id_config(value, config)?.0)
+                }
+            }
+        }
+    } else {
+        quote! {
+            impl #impl_generics #croot::FromCbor for #name #ty_generics #where_clause {
+                fn from_cbor(value: #croot::Cbor) -> #croot::Result<Self> {
+                    Self::from_cbor_with(value, &Default::default())
+                }
+
+                fn from_cbor_with(
+                    value: #croot::Cbor,
+                    config: &#croot::DecodeConfig,
+                ) -> #croot::Result<Self> {
+                    use #croot::{IntoCbor, Error};
+
+                    let mut items = Vec::<#croot::Cbor>::from_cbor(value)?;
+
+                    #preamble
+
+                    Ok(#name #token_fields)
+                }
+            }
+        }
+    }
+}
+
+fn transparent_field<'a>(name: &Ident, fields: &'a Fields) -> &'a Field {
+    let field = match fields.iter().collect::<Vec<&Field>>().as_slice() {
+        [field] => *field,
+        _ => abort_call_site!("transparent struct {} must have exactly one field", name),
+    };
+    let fattrs = attrs::field_attrs(field);
+    if fattrs.flatten_rest || fattrs.skip || fattrs.default || fattrs.rename.is_some() {
+        abort!(field.span(), "field attributes not supported for transparent struct")
+    }
+    field
+}
+
+fn transparent_to_cbor(
+    name: &Ident,
+    generics: &Generics,
+    fields: &Fields,
+    crate_local: bool,
+) -> TokenStream {
+    let croot = get_root_crate(crate_local);
+    let field = transparent_field(name, fields);
+
+    let member = match &field.ident {
+        Some(ident) => quote! { #ident },
+        None => {
+            let index = Index::from(0);
+            quote! { #index }
+        }
+    };
+    let into_field = if is_bytes_ty(&field.ty) {
+        quote! { #croot::Cbor::from_bytes(self.#member) }
+    } else {
+        quote! { #croot::IntoCbor::into_cbor(self.#member) }
+    };
+
+    let where_clause = bounded_where(generics, quote! { #croot::IntoCbor });
+    let (impl_generics, ty_generics, _) = generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics #croot::IntoCbor for #name #ty_generics #where_clause {
+            fn into_cbor(self) -> #croot::Result<#croot::Cbor> {
+                #into_field
+            }
+        }
+    }
+}
+
+fn transparent_struct(
+    name: &Ident,
+    generics: &Generics,
+    fields: &Fields,
+    crate_local: bool,
+) -> TokenStream {
+    let croot = get_root_crate(crate_local);
+    let field = transparent_field(name, fields);
+
+    let is_bytes = is_bytes_ty(&field.ty);
+    let ctor = match &field.ident {
+        Some(ident) => {
+            let item = from_item(&ident.to_string(), &field.ty, is_bytes, croot.clone());
+            quote! { { #ident: #item } }
+        }
+        None => {
+            let item = from_item("0", &field.ty, is_bytes, croot.clone());
+            quote! { ( #item ) }
         }
     };
 
-    let mut where_clause = match &generics.where_clause {
-        Some(where_clause) => quote! { #where_clause },
-        None => quote! { where },
+    let from_where = bounded_where(generics, quote! { #croot::FromCbor });
+    let (impl_generics, ty_generics, _) = generics.split_for_impl();
+
+    let mut ts = transparent_to_cbor(name, generics, fields, crate_local);
+    ts.extend(quote! {
+        impl #impl_generics #croot::FromCbor for #name #ty_generics #from_where {
+            fn from_cbor(value: #croot::Cbor) -> #croot::Result<Self> {
+                Self::from_cbor_with(value, &Default::default())
+            }
+
+            fn from_cbor_with(
+                value: #croot::Cbor,
+                config: &#croot::DecodeConfig,
+            ) -> #croot::Result<Self> {
+                let mut items = vec![value];
+                Ok(#name #ctor)
+            }
+        }
+    });
+    ts
+}
+
+fn named_map_struct(
+    name: &Ident,
+    generics: &Generics,
+    fields: &Fields,
+    crate_local: bool,
+) -> TokenStream {
+    let croot = get_root_crate(crate_local);
+    let fields = match fields {
+        Fields::Named(fields) => fields,
+        _ => abort_call_site!("named_map requires named fields for {}", name),
     };
-    for param in generics.params.iter() {
-        let type_var = match param {
-            GenericParam::Type(param) => &param.ident,
-            _ => abort_call_site!("only type parameter are supported"),
+    let rest = rest_field(fields);
+
+    let mut into_fields = TokenStream::new();
+    let mut from_fields = TokenStream::new();
+    for field in fields.named.iter() {
+        let fattrs = attrs::field_attrs(field);
+        let field_name = field.ident.as_ref().unwrap();
+        if fattrs.skip {
+            from_fields.extend(quote! { #field_name: Default::default(), });
+            continue;
+        } else if fattrs.flatten_rest {
+            continue;
+        }
+        let label = fattrs.rename.unwrap_or_else(|| field_name.to_string());
+        let is_bytes = is_bytes_ty(&field.ty);
+
+        let val = if is_bytes {
+            quote! { #croot::Cbor::from_bytes(value.#field_name)? }
+        } else {
+            quote! { value.#field_name.into_cbor()? }
         };
-        where_clause.extend(quote! { #type_var: #croot::FromCbor, });
+        into_fields.extend(quote! {
+            items.push((#croot::Key::Text(#label.to_string()), #val));
+        });
+        let item = from_item(&field_name.to_string(), &field.ty, is_bytes, croot.clone());
+        if fattrs.default {
+            from_fields.extend(quote! {
+                #field_name: match #croot::take_named_field(&mut items, #label) {
+                    Ok(item) => {
+                        let mut items = vec![item];
+                        #item
+                    }
+                    Err(_) => Default::default(),
+                },
+            });
+        } else {
+            from_fields.extend(quote! {
+                #field_name: {
+                    let mut items = vec![#croot::take_named_field(&mut items, #label)?];
+                    #item
+                },
+            });
+        }
+    }
+    if let Some(field) = rest {
+        let field_name = field.ident.as_ref().unwrap();
+        into_fields.extend(quote! { items.extend(value.#field_name); });
+        from_fields.extend(quote! { #field_name: items.drain(..).collect(), });
     }
 
+    let into_where = bounded_where(generics, quote! { #croot::IntoCbor });
+    let from_where = bounded_where(generics, quote! { #croot::FromCbor });
+    let (impl_generics, ty_generics, _) = generics.split_for_impl();
+
     quote! {
-        impl #generics #croot::FromCbor for #name #generics #where_clause {
-            fn from_cbor(value: #croot::Cbor) -> #croot::Result<#name #generics> {
-                use #croot::{IntoCbor, Error};
+        impl #impl_generics #croot::IntoCbor for #name #ty_generics #into_where {
+            fn into_cbor(self) -> #croot::Result<#croot::Cbor> {
+                let value = self;
+                let mut items: Vec<(#croot::Key, #croot::Cbor)> = Vec::default();
+                #into_fields
+                items.into_cbor()
+            }
+        }
 
-                let mut items = Vec::<#croot::Cbor>::from_cbor(value)?;
+        impl #impl_generics #croot::FromCbor for #name #ty_generics #from_where {
+            fn from_cbor(value: #croot::Cbor) -> #croot::Result<Self> {
+                Self::from_cbor_with(value, &Default::default())
+            }
 
-                #preamble
+            fn from_cbor_with(
+                value: #croot::Cbor,
+                config: &#croot::DecodeConfig,
+            ) -> #croot::Result<Self> {
+                let mut items = Vec::<(#croot::Key, #croot::Cbor)>::from_cbor(value)?;
+                Ok(#name { #from_fields })
+            }
+        }
+    }
+}
+
+fn impl_from_cbor_ref(input: &DeriveInput, crate_local: bool) -> TokenStream {
+    let name = &input.ident;
+    let name_lit = name.to_string();
+    let generics = no_default_generics(input);
+    let croot = get_root_crate(crate_local);
+
+    let fields = match &input.data {
+        Data::Struct(ast) => &ast.fields,
+        _ => abort_call_site!("FromCborRef supported only for struct {}", name),
+    };
+    let cattrs = attrs::container_attrs(input);
+    if cattrs.named_map {
+        abort_call_site!("named_map not supported by FromCborRef for {}", name)
+    }
+    if cattrs.id_prefix {
+        abort_call_site!("id_match not supported by FromCborRef for {}", name)
+    }
+    if let Some(field) = fields.iter().find(|f| attrs::field_attrs(f).flatten_rest) {
+        abort!(field.span(), "flatten_rest not supported by FromCborRef")
+    }
+    for field in fields.iter() {
+        let fattrs = attrs::field_attrs(field);
+        if fattrs.skip || fattrs.default || fattrs.rename.is_some() {
+            abort!(field.span(), "skip, default and rename not supported by FromCborRef")
+        }
+    }
+
+    // input's lifetime, introduce one if struct does not have a lifetime.
+    let mut impl_generics = generics.clone();
+    let lifetime = match generics.lifetimes().collect::<Vec<&LifetimeDef>>().as_slice() {
+        [] => {
+            let lifetime = Lifetime::new("'a", name.span());
+            let param = LifetimeDef::new(lifetime.clone());
+            impl_generics.params.insert(0, GenericParam::Lifetime(param));
+            lifetime
+        }
+        [param] => param.lifetime.clone(),
+        _ => abort_call_site!("FromCborRef supports at most one lifetime for {}", name),
+    };
+
+    let from_item = |label: String, field: &Field, item: TokenStream| {
+        let ty = &field.ty;
+        let item = if is_bytes_ty(ty) {
+            quote! {
+                <&[u8] as #croot::FromCborRef<#lifetime>>::from_cbor_ref(#item)
+                    .map(|data| data.to_vec())
+            }
+        } else {
+            quote! { <#ty as #croot::FromCborRef<#lifetime>>::from_cbor_ref(#item) }
+        };
+        quote! { #item.map_err(|err| err.in_field(#label))? }
+    };
+
+    let body = if cattrs.transparent {
+        let field = match fields.iter().collect::<Vec<&Field>>().as_slice() {
+            [field] => *field,
+            _ => abort_call_site!(
+                "transparent struct {} must have exactly one field",
+                name
+            ),
+        };
+        match &field.ident {
+            Some(ident) => {
+                let item = from_item(ident.to_string(), field, quote! { value });
+                quote! { Ok(#name { #ident: #item }) }
+            }
+            None => {
+                let item = from_item("0".to_string(), field, quote! { value });
+                quote! { Ok(#name(#item)) }
+            }
+        }
+    } else {
+        let n_items = fields.len() + 1;
+        let mut token_fields = TokenStream::new();
+        for (i, field) in fields.iter().enumerate() {
+            let item = quote! { &items[#i + 1] };
+            token_fields.extend(match &field.ident {
+                Some(ident) => {
+                    let item = from_item(ident.to_string(), field, item);
+                    quote! { #ident: #item, }
+                }
+                None => {
+                    let item = from_item(i.to_string(), field, item);
+                    quote! { #item, }
+                }
+            });
+        }
+        let token_fields = match fields {
+            Fields::Unit => quote! {},
+            Fields::Named(_) => quote! { { #token_fields } },
+            Fields::Unnamed(_) => quote! { ( #token_fields ) },
+        };
+
+        let id_declr = let_id(name, &generics);
+        quote! {
+            use #croot::{IntoCbor, Error};
+
+            let items = match value {
+                #croot::CborRef::Major4(_, items) => items,
+                _ => #croot::err_at!(FailConvert, msg: "not a list for {}", #name_lit)?,
+            };
+
+            // validate the cbor msg for this type.
+            if items.len() == 0 {
+                #croot::err_at!(FailConvert, msg: "empty msg for {}", #name_lit)?;
+            }
+            #id_declr;
+            if !#croot::is_cborize_id_ref(&items[0], id) {
+                #croot::err_at!(FailConvert, msg: "bad id for {}", #name_lit)?;
+            }
+            if #n_items != items.len() {
+                #croot::err_at!(FailConvert, msg: "bad arity {} {}", #n_items - 1, items.len() - 1)?;
+            }
+
+            Ok(#name #token_fields)
+        }
+    };
+
+    let where_clause =
+        bounded_where(&generics, quote! { #croot::FromCborRef<#lifetime> });
+    let ty_generics = generics.split_for_impl().1;
 
-                Ok(#name #token_fields)
+    quote! {
+        impl #impl_generics #croot::FromCborRef<#lifetime> for #name #ty_generics #where_clause {
+            fn from_cbor_ref(
+                value: &#lifetime #croot::CborRef<#lifetime>
+            ) -> #croot::Result<Self> {
+                #body
             }
         }
     }
@@ -196,22 +725,68 @@ fn impl_cborize_enum(input: &DeriveInput, crate_local: bool) -> TokenStream {
     let name = &input.ident;
     let generics = no_default_generics(input);
 
-    let mut ts = TokenStream::new();
+    let cattrs = attrs::container_attrs(input);
+    if cattrs.transparent || cattrs.named_map {
+        abort_call_site!("transparent and named_map not supported for enum {}", name)
+    }
+    if cattrs.id_prefix {
+        abort_call_site!("id_match not supported for enum {}", name)
+    }
+
+    let mut ts = schema_hash(name, &generics, &input.data, "enum", crate_local);
     match &input.data {
         Data::Enum(ast) => {
             let variants: Vec<&Variant> = ast.variants.iter().collect();
-            ts.extend(from_enum_to_cbor(name, &generics, &variants, crate_local));
-            ts.extend(from_cbor_to_enum(name, &generics, &variants, crate_local));
+            let unknown = cattrs.unknown.as_ref();
+            if let Some(unknown) = unknown {
+                unknown_variant(unknown, &variants)
+            }
+            ts.extend(from_enum_to_cbor(
+                name,
+                &generics,
+                &variants,
+                unknown,
+                crate_local,
+            ));
+            ts.extend(from_cbor_to_enum(
+                name,
+                &generics,
+                &variants,
+                unknown,
+                crate_local,
+            ));
             ts
         }
         _ => unreachable!(),
     }
 }
 
+// validate the catch-all variant, that must be a tuple variant with exactly
+// one field and without an explicit discriminant.
+fn unknown_variant(unknown: &Ident, variants: &[&Variant]) {
+    let variant = match variants.iter().find(|v| &v.ident == unknown) {
+        Some(variant) => *variant,
+        None => abort!(unknown.span(), "unknown variant {} not found", unknown),
+    };
+    match &variant.fields {
+        Fields::Unnamed(fields) if fields.unnamed.len() == 1 => (),
+        _ => abort!(
+            variant.span(),
+            "unknown variant {} must be like {}(Cbor)",
+            unknown,
+            unknown
+        ),
+    }
+    if attrs::variant_attrs(variant).n.is_some() {
+        abort!(variant.span(), "unknown variant {} cannot have a discriminant", unknown)
+    }
+}
+
 fn from_enum_to_cbor(
     name: &Ident,
     generics: &Generics,
     variants: &[&Variant],
+    unknown: Option<&Ident>,
     crate_local: bool,
 ) -> TokenStream {
     let id_declr = let_id(name, generics);
@@ -225,18 +800,35 @@ fn from_enum_to_cbor(
     };
 
     let mut tok_variants: TokenStream = TokenStream::new();
-    for variant in variants.iter() {
+    if let Some(unknown) = unknown {
+        // re-emit the captured discriminant and fields as is.
+        tok_variants.extend(quote! {
+            #name::#unknown(val) => {
+                let val = <Vec<#croot::Cbor> as #croot::FromCbor>::from_cbor(val)?;
+                items.extend(val)
+            },
+        })
+    }
+    for variant in variants.iter().filter(|v| Some(&v.ident) != unknown) {
         let variant_name = &variant.ident;
-        let variant_lit = variant.ident.to_string();
+        let discriminant = match attrs::variant_attrs(variant).n {
+            Some(n) => quote! { #n.into_cbor()? },
+            None => {
+                let variant_lit = variant.ident.to_string();
+                quote! { #variant_lit.into_cbor()? }
+            }
+        };
         let arm = match &variant.fields {
             Fields::Unit => {
-                quote! { #name::#variant_name => #variant_lit.into_cbor()? }
+                quote! {
+                    #name::#variant_name => items.push(#discriminant),
+                }
             }
             Fields::Named(fields) => {
                 let (params, body) = named_var_fields_to_cbor(fields, croot.clone());
                 quote! {
                     #name::#variant_name{#params} => {
-                        items.push(#variant_lit.into_cbor()?);
+                        items.push(#discriminant);
                         #body
                     },
                 }
@@ -245,7 +837,7 @@ fn from_enum_to_cbor(
                 let (params, body) = unnamed_fields_to_cbor(fields, croot.clone());
                 quote! {
                     #name::#variant_name(#params) => {
-                        items.push(#variant_lit.into_cbor()?);
+                        items.push(#discriminant);
                         #body
                     },
                 }
@@ -254,20 +846,11 @@ fn from_enum_to_cbor(
         tok_variants.extend(arm)
     }
 
-    let mut where_clause = match &generics.where_clause {
-        Some(where_clause) => quote! { #where_clause },
-        None => quote! { where },
-    };
-    for param in generics.params.iter() {
-        let type_var = match param {
-            GenericParam::Type(param) => &param.ident,
-            _ => abort_call_site!("only type parameter are supported"),
-        };
-        where_clause.extend(quote! { #type_var: #croot::IntoCbor, });
-    }
+    let where_clause = bounded_where(generics, quote! { #croot::IntoCbor });
+    let (impl_generics, ty_generics, _) = generics.split_for_impl();
 
     quote! {
-        impl #generics #croot::IntoCbor for #name #generics #where_clause {
+        impl #impl_generics #croot::IntoCbor for #name #ty_generics #where_clause {
             fn into_cbor(self) -> #croot::Result<#croot::Cbor> {
                 let value = self;
 
@@ -287,11 +870,77 @@ fn from_cbor_to_enum(
     name: &Ident,
     generics: &Generics,
     variants: &[&Variant],
+    unknown: Option<&Ident>,
     crate_local: bool,
 ) -> TokenStream {
     let name_lit = name.to_string();
     let id_declr = let_id(name, generics);
     let croot = get_root_crate(crate_local);
+
+    let variants: Vec<&Variant> =
+        variants.iter().filter(|v| Some(&v.ident) != unknown).copied().collect();
+
+    // map explicit discriminants to variant names, while rejecting names
+    // for such variants.
+    let mut discriminants: Vec<(u64, &Variant)> = vec![];
+    for variant in variants.iter() {
+        if let Some(n) = attrs::variant_attrs(variant).n {
+            if let Some((_, other)) = discriminants.iter().find(|(m, _)| *m == n) {
+                abort!(
+                    variant.span(),
+                    "duplicate discriminant {} for {} and {}",
+                    n,
+                    other.ident,
+                    variant.ident
+                )
+            }
+            discriminants.push((n, variant));
+        }
+    }
+    let mut n_arms = TokenStream::new();
+    let mut n_names = TokenStream::new();
+    for (n, variant) in discriminants.iter() {
+        let variant_lit = variant.ident.to_string();
+        n_arms.extend(quote! { #n => #variant_lit.to_string(), });
+        n_names.extend(quote! { #variant_lit => #croot::err_at!(
+            FailCbor, msg: "variant {} expects a discriminant", #variant_lit
+        )?, });
+    }
+
+    // capture unrecognized discriminants, along with the fields, into the
+    // catch-all variant.
+    let capture_unknown = match unknown {
+        Some(unknown) => {
+            let mut known_n = TokenStream::new();
+            let mut known_names = TokenStream::new();
+            for variant in variants.iter() {
+                if let Some(n) = attrs::variant_attrs(variant).n {
+                    known_n.extend(quote! { #n => true, })
+                }
+                let lit =
+                    LitByteStr::new(variant.ident.to_string().as_bytes(), variant.span());
+                known_names.extend(quote! { #lit => true, });
+            }
+            quote! {
+                let known = match &items[0] {
+                    #croot::Cbor::Major0(_, n) => match n {
+                        #known_n
+                        _ => false,
+                    },
+                    #croot::Cbor::Major3(_, text) => match text.as_slice() {
+                        #known_names
+                        _ => false,
+                    },
+                    _ => false,
+                };
+                if !known {
+                    return Ok(#name::#unknown(items.into_cbor()?));
+                }
+            }
+        }
+        None => TokenStream::new(),
+    };
+
     let preamble = quote! {
         // validate the cbor msg for this type.
         if items.len() < 2 {
@@ -306,31 +955,32 @@ fn from_cbor_to_enum(
             #croot::err_at!(FailConvert, msg: "bad {}", #name_lit)?
         }
 
-        let variant_name = String::from_cbor(items.remove(0))?;
+        #capture_unknown
+
+        let variant_name = match items.remove(0) {
+            #croot::Cbor::Major0(_, n) => match n {
+                #n_arms
+                _ => #croot::err_at!(FailCbor, msg: "invalid discriminant {}", n)?,
+            },
+            val => {
+                let variant_name = String::from_cbor(val)?;
+                match variant_name.as_str() {
+                    #n_names
+                    _ => variant_name,
+                }
+            }
+        };
     };
 
     let mut check_variants: TokenStream = TokenStream::new();
     for variant in variants.iter() {
         let variant_lit = &variant.ident.to_string();
         let arm = match &variant.fields {
-            Fields::Named(fields) => {
-                let n_fields = fields.named.len();
-                quote! {
-                   #variant_lit => {
-                        if #n_fields != items.len() {
-                            #croot::err_at!(
-                                FailConvert, msg: "bad arity {} {}",
-                                #n_fields, items.len()
-                            )?;
-                        }
-                    }
-                }
-            }
-            Fields::Unnamed(fields) => {
-                let n_fields = fields.unnamed.len();
+            Fields::Named(_) | Fields::Unnamed(_) => {
+                let (n_min, n_fields) = arity(&variant.fields);
                 quote! {
                     #variant_lit => {
-                        if #n_fields != items.len() {
+                        if items.len() < #n_min || items.len() > #n_fields {
                             #croot::err_at!(
                                 FailConvert, msg: "bad arity {} {}",
                                 #n_fields, items.len()
@@ -360,7 +1010,7 @@ fn from_cbor_to_enum(
         let variant_lit = &variant.ident.to_string();
         let arm = match &variant.fields {
             Fields::Unit => quote! {
-                #variant_lit => #name::#variant_name
+                #variant_lit => #name::#variant_name,
             },
             Fields::Named(fields) => {
                 let (_, body) = cbor_to_named_var_fields(fields, croot.clone());
@@ -374,20 +1024,18 @@ fn from_cbor_to_enum(
         tok_variants.extend(arm);
     }
 
-    let mut where_clause = match &generics.where_clause {
-        Some(where_clause) => quote! { #where_clause },
-        None => quote! { where },
-    };
-    for param in generics.params.iter() {
-        let type_var = match param {
-            GenericParam::Type(param) => &param.ident,
-            _ => abort_call_site!("only type parameter are supported"),
-        };
-        where_clause.extend(quote! { #type_var: #croot::FromCbor, });
-    }
+    let where_clause = bounded_where(generics, quote! { #croot::FromCbor });
+    let (impl_generics, ty_generics, _) = generics.split_for_impl();
     quote! {
-        impl #generics #croot::FromCbor for #name #generics #where_clause {
-            fn from_cbor(value: #croot::Cbor) -> #croot::Result<#name #generics> {
+        impl #impl_generics #croot::FromCbor for #name #ty_generics #where_clause {
+            fn from_cbor(value: #croot::Cbor) -> #croot::Result<Self> {
+                Self::from_cbor_with(value, &Default::default())
+            }
+
+            fn from_cbor_with(
+                value: #croot::Cbor,
+                config: &#croot::DecodeConfig,
+            ) -> #croot::Result<Self> {
                 use #croot::{IntoCbor, Error};
 
                 let mut items =  Vec::<#croot::Cbor>::from_cbor(value)?;
@@ -397,7 +1045,7 @@ fn from_cbor_to_enum(
                 match variant_name.as_str() {
                     #check_variants
                     _ => #croot::err_at!(
-                        FailConvert, msg: "invalid variant_name {}", variant_name
+                        FailCbor, msg: "invalid variant_name {}", variant_name
                     )?,
                 }
 
@@ -414,8 +1062,13 @@ fn from_cbor_to_enum(
 }
 
 fn named_fields_to_cbor(fields: &FieldsNamed, croot: TokenStream) -> TokenStream {
+    let rest = rest_field(fields);
+
     let mut tokens = TokenStream::new();
     for field in fields.named.iter() {
+        if !positional_attrs(field).is_item() {
+            continue;
+        }
         let is_bytes = is_bytes_ty(&field.ty);
 
         match &field.ident {
@@ -428,6 +1081,28 @@ fn named_fields_to_cbor(fields: &FieldsNamed, croot: TokenStream) -> TokenStream
             None => (),
         }
     }
+    if let Some(field) = rest {
+        let field_name = field.ident.as_ref().unwrap();
+        tokens.extend(quote! { items.extend(value.#field_name); });
+    }
+    tokens
+}
+
+fn tuple_fields_to_cbor(fields: &FieldsUnnamed, croot: TokenStream) -> TokenStream {
+    let mut tokens = TokenStream::new();
+    for (i, field) in fields.unnamed.iter().enumerate() {
+        if unnamed_attrs(field).skip {
+            continue;
+        }
+        let index = Index::from(i);
+        if is_bytes_ty(&field.ty) {
+            tokens.extend(quote! {
+                items.push(#croot::Cbor::from_bytes(value.#index)?);
+            })
+        } else {
+            tokens.extend(quote! { items.push(value.#index.into_cbor()?); })
+        }
+    }
     tokens
 }
 
@@ -441,6 +1116,10 @@ fn named_var_fields_to_cbor(
         let is_bytes = is_bytes_ty(&field.ty);
 
         let field_name = field.ident.as_ref().unwrap();
+        if variant_attrs(field).skip {
+            params.extend(quote! { #field_name: _, });
+            continue;
+        }
         params.extend(quote! { #field_name, });
 
         match &field.ident {
@@ -466,6 +1145,10 @@ fn unnamed_fields_to_cbor(
         let field_name = Ident::new(field_name, field.span());
         let is_bytes = is_bytes_ty(&field.ty);
 
+        if variant_attrs(field).skip {
+            params.extend(quote! { _, });
+            continue;
+        }
         params.extend(quote! { #field_name, });
 
         if is_bytes {
@@ -482,26 +1165,110 @@ fn unnamed_fields_to_cbor(
 }
 
 fn cbor_to_named_fields(fields: &FieldsNamed, croot: TokenStream) -> TokenStream {
+    let rest = rest_field(fields);
+
     let mut tokens = TokenStream::new();
     for field in fields.named.iter() {
+        let fattrs = positional_attrs(field);
+        if fattrs.flatten_rest {
+            continue;
+        }
         let is_bytes = is_bytes_ty(&field.ty);
 
         let field_name = field.ident.as_ref().unwrap();
         let ty = &field.ty;
-        let field_tokens = if is_bytes {
-            quote! {
-                #field_name: items.remove(0).into_bytes()?,
-            }
-        } else {
-            quote! {
-                #field_name: <#ty as #croot::FromCbor>::from_cbor(items.remove(0))?,
-            }
-        };
-        tokens.extend(field_tokens);
+        let item = from_item(&field_name.to_string(), ty, is_bytes, croot.clone());
+        let item = default_item(&fattrs, item);
+        tokens.extend(quote! { #field_name: #item, });
+    }
+    // initialized last, after known fields have consumed their items.
+    if let Some(field) = rest {
+        let field_name = field.ident.as_ref().unwrap();
+        tokens.extend(quote! { #field_name: items.drain(..).collect(), });
+    }
+    tokens
+}
+
+fn cbor_to_tuple_fields(fields: &FieldsUnnamed, croot: TokenStream) -> TokenStream {
+    let mut tokens = TokenStream::new();
+    for (i, field) in fields.unnamed.iter().enumerate() {
+        let is_bytes = is_bytes_ty(&field.ty);
+        let item = from_item(&i.to_string(), &field.ty, is_bytes, croot.clone());
+        let item = default_item(&unnamed_attrs(field), item);
+        tokens.extend(quote! { #item, });
     }
     tokens
 }
 
+// field attributes for structs encoded as list, where fields are not named.
+fn positional_attrs(field: &Field) -> attrs::FieldAttrs {
+    let fattrs = attrs::field_attrs(field);
+    if fattrs.rename.is_some() {
+        abort!(field.span(), "rename applicable only for repr = \"named_map\"")
+    }
+    fattrs
+}
+
+// field attributes for tuple structs, items following the known fields are
+// captured only by named fields.
+fn unnamed_attrs(field: &Field) -> attrs::FieldAttrs {
+    let fattrs = positional_attrs(field);
+    if fattrs.flatten_rest {
+        abort!(field.span(), "flatten_rest applicable only for named fields")
+    }
+    fattrs
+}
+
+// field attributes for enum variants, which are encoded as list, like
+// structs, but without capturing the trailing items.
+fn variant_attrs(field: &Field) -> attrs::FieldAttrs {
+    let fattrs = positional_attrs(field);
+    if fattrs.flatten_rest {
+        abort!(field.span(), "flatten_rest not supported for enum variant")
+    }
+    fattrs
+}
+
+// initialize skipped fields, and default fields missing in the list, with
+// Default::default().
+fn default_item(fattrs: &attrs::FieldAttrs, item: TokenStream) -> TokenStream {
+    if fattrs.skip {
+        quote! { Default::default() }
+    } else if fattrs.default {
+        quote! { if items.is_empty() { Default::default() } else { #item } }
+    } else {
+        item
+    }
+}
+
+// minimum and maximum number of items for struct's fields, fields with
+// `default` must follow other fields.
+fn arity(fields: &Fields) -> (usize, usize) {
+    let (mut n_min, mut n_max) = (0, 0);
+    for field in fields.iter() {
+        let fattrs = attrs::field_attrs(field);
+        if !fattrs.is_item() {
+            continue;
+        }
+        n_max += 1;
+        if fattrs.default {
+            continue;
+        } else if n_min + 1 != n_max {
+            abort!(field.span(), "field with default must follow other fields")
+        }
+        n_min += 1;
+    }
+    (n_min, n_max)
+}
+
+fn rest_field(fields: &FieldsNamed) -> Option<&Field> {
+    let mut rest = fields.named.iter().filter(|f| attrs::field_attrs(f).flatten_rest);
+    match (rest.next(), rest.next()) {
+        (_, Some(field)) => abort!(field.span(), "more than one flatten_rest field"),
+        (field, None) => field,
+    }
+}
+
 fn cbor_to_named_var_fields(
     fields: &FieldsNamed,
     croot: TokenStream,
@@ -515,15 +1282,9 @@ fn cbor_to_named_var_fields(
         params.extend(quote! { #field_name, });
 
         let ty = &field.ty;
-        if is_bytes {
-            body.extend(quote! {
-                #field_name: items.remove(0).into_bytes()?,
-            });
-        } else {
-            body.extend(quote! {
-                #field_name: <#ty as #croot::FromCbor>::from_cbor(items.remove(0))?,
-            });
-        }
+        let item = from_item(&field_name.to_string(), ty, is_bytes, croot.clone());
+        let item = default_item(&variant_attrs(field), item);
+        body.extend(quote! { #field_name: #item, });
     }
     (params, body)
 }
@@ -534,29 +1295,159 @@ fn cbor_to_unnamed_fields(
 ) -> (TokenStream, TokenStream) {
     let mut params = TokenStream::new();
     let mut body = TokenStream::new();
-    for (field_name, field) in UNNAMED_FIELDS.iter().zip(fields.unnamed.iter()) {
+    let iter = UNNAMED_FIELDS.iter().zip(fields.unnamed.iter()).enumerate();
+    for (i, (field_name, field)) in iter {
         let field_name = Ident::new(field_name, field.span());
         let is_bytes = is_bytes_ty(&field.ty);
 
         params.extend(quote! { #field_name, });
 
         let ty = &field.ty;
-        if is_bytes {
-            body.extend(quote! { items.remove(0).into_bytes()?, });
-        } else {
-            body.extend(
-                quote! { <#ty as #croot::FromCbor>::from_cbor(items.remove(0))?, },
-            );
-        }
+        let item = from_item(&i.to_string(), ty, is_bytes, croot.clone());
+        let item = default_item(&variant_attrs(field), item);
+        body.extend(quote! { #item, });
     }
     (params, body)
 }
 
+// Convert next item into field's type, errors carry the field's position.
+fn from_item(
+    field: &str,
+    ty: &syn::Type,
+    is_bytes: bool,
+    croot: TokenStream,
+) -> TokenStream {
+    let item = if is_bytes {
+        quote! { items.remove(0).into_bytes() }
+    } else {
+        quote! { <#ty as #croot::FromCbor>::from_cbor_with(items.remove(0), config) }
+    };
+    quote! { #item.map_err(|err| err.in_field(#field))? }
+}
+
+fn schema_hash(
+    name: &Ident,
+    generics: &Generics,
+    data: &Data,
+    repr: &str,
+    crate_local: bool,
+) -> TokenStream {
+    let croot = get_root_crate(crate_local);
+    let mut layout = String::new();
+    match data {
+        Data::Struct(ast) => {
+            layout.push_str(repr);
+            layout.push_str(&fields_layout(&ast.fields));
+        }
+        Data::Enum(ast) => {
+            layout.push_str(repr);
+            for variant in ast.variants.iter() {
+                layout.push_str(&format!("|{}", variant.ident));
+                if let Some(n) = attrs::variant_attrs(variant).n {
+                    layout.push_str(&format!("={}", n));
+                }
+                layout.push_str(&fields_layout(&variant.fields));
+            }
+        }
+        Data::Union(_) => unreachable!(),
+    }
+
+    // FNV-1a, stable across compiler versions unlike std's DefaultHasher.
+    let hash = layout.bytes().fold(0xcbf29ce484222325_u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    });
+
+    // fold in the `ID`, known only at runtime, for types that encode it.
+    let body = match repr {
+        "transparent" | "named_map" => quote! { Ok(#hash) },
+        _ => {
+            let id_declr = let_id(name, generics);
+            quote! {
+                use #croot::IntoCbor;
+
+                #id_declr;
+                let mut buf: Vec<u8> = vec![];
+                id.encode(&mut buf)?;
+                let hash = buf.iter().fold(#hash, |hash, byte| {
+                    (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+                });
+                Ok(hash)
+            }
+        }
+    };
+
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Fingerprint of this type's `ID` and layout, generated by
+            /// `Cborize`.
+            pub fn schema_hash() -> #croot::Result<u64> {
+                #body
+            }
+        }
+    }
+}
+
+fn fields_layout(fields: &Fields) -> String {
+    let mut layout = String::new();
+    match fields {
+        Fields::Unit => layout.push_str("()"),
+        Fields::Named(fields) => {
+            layout.push('{');
+            for field in fields.named.iter() {
+                let ty = &field.ty;
+                let ty: String = quote! { #ty }
+                    .to_string()
+                    .chars()
+                    .filter(|ch| !ch.is_whitespace())
+                    .collect();
+                let name = field.ident.as_ref().unwrap();
+                layout.push_str(&format!("{}:{}{};", name, ty, field_attrs_layout(field)));
+            }
+            layout.push('}');
+        }
+        Fields::Unnamed(fields) => {
+            layout.push('(');
+            for field in fields.unnamed.iter() {
+                let ty = &field.ty;
+                let ty: String = quote! { #ty }
+                    .to_string()
+                    .chars()
+                    .filter(|ch| !ch.is_whitespace())
+                    .collect();
+                layout.push_str(&format!("{}{};", ty, field_attrs_layout(field)));
+            }
+            layout.push(')');
+        }
+    }
+    layout
+}
+
+// field attributes alter the encoding, hence part of the layout.
+fn field_attrs_layout(field: &Field) -> String {
+    let fattrs = attrs::field_attrs(field);
+    let mut layout = String::new();
+    if fattrs.flatten_rest {
+        layout.push_str("#flatten_rest");
+    }
+    if fattrs.skip {
+        layout.push_str("#skip");
+    }
+    if fattrs.default {
+        layout.push_str("#default");
+    }
+    if let Some(rename) = fattrs.rename {
+        layout.push_str(&format!("#rename={:?}", rename));
+    }
+    layout
+}
+
 fn let_id(name: &Ident, generics: &Generics) -> TokenStream {
     if generics.params.is_empty() {
         quote! { let id = #name::ID.into_cbor()? }
     } else {
-        quote! { let id = #name::#generics::ID.into_cbor()? }
+        let ty_generics = generics.split_for_impl().1;
+        quote! { let id = <#name #ty_generics>::ID.into_cbor()? }
     }
 }
 
@@ -570,15 +1461,31 @@ fn get_root_crate(crate_local: bool) -> TokenStream {
 
 fn no_default_generics(input: &DeriveInput) -> Generics {
     let mut generics = input.generics.clone();
-    generics.params.iter_mut().for_each(|param| {
-        if let GenericParam::Type(param) = param {
+    generics.params.iter_mut().for_each(|param| match param {
+        GenericParam::Type(param) => {
             param.eq_token = None;
             param.default = None;
         }
+        GenericParam::Const(param) => {
+            param.eq_token = None;
+            param.default = None;
+        }
+        GenericParam::Lifetime(_) => (),
     });
     generics
 }
 
+// where clause of `generics`, along with `bound` on each type parameter,
+// lifetime and const parameters are left as is.
+fn bounded_where(generics: &Generics, bound: TokenStream) -> TokenStream {
+    let predicates = match &generics.where_clause {
+        Some(where_clause) => where_clause.predicates.iter().collect(),
+        None => vec![],
+    };
+    let type_vars = generics.type_params().map(|param| &param.ident);
+    quote! { where #(#predicates,)* #(#type_vars: #bound,)* }
+}
+
 fn is_bytes_ty(ty: &syn::Type) -> bool {
     match ty::subty_of_vec(ty) {
         Some(subty) => ty::ty_u8(subty),
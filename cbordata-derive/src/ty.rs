@@ -6,9 +6,7 @@ pub fn subty_of_vec(ty: &syn::Type) -> Option<&syn::Type> {
 
 pub fn ty_u8(ty: &syn::Type) -> bool {
     let ty = strip_group(ty);
-    only_last_segment(ty)
-        .filter(|seg| seg.ident == "u8")
-        .is_some()
+    only_last_segment(ty).filter(|seg| seg.ident == "u8").is_some()
 }
 
 fn subty_if<F>(ty: &syn::Type, f: F) -> Option<&syn::Type>
@@ -17,21 +15,19 @@ where
 {
     let ty = strip_group(ty);
 
-    only_last_segment(ty)
-        .filter(|segment| f(segment))
-        .and_then(|segment| {
-            if let AngleBracketed(args) = &segment.arguments {
-                only_one(args.args.iter()).and_then(|genneric| {
-                    if let syn::GenericArgument::Type(ty) = genneric {
-                        Some(ty)
-                    } else {
-                        None
-                    }
-                })
-            } else {
-                None
-            }
-        })
+    only_last_segment(ty).filter(|segment| f(segment)).and_then(|segment| {
+        if let AngleBracketed(args) = &segment.arguments {
+            only_one(args.args.iter()).and_then(|genneric| {
+                if let syn::GenericArgument::Type(ty) = genneric {
+                    Some(ty)
+                } else {
+                    None
+                }
+            })
+        } else {
+            None
+        }
+    })
 }
 
 // If the struct is placed inside of a macro_rules! declaration,
@@ -55,11 +51,7 @@ fn only_last_segment(ty: &syn::Type) -> Option<&syn::PathSegment> {
     match ty {
         syn::Type::Path(syn::TypePath {
             qself: None,
-            path:
-                syn::Path {
-                    leading_colon: None,
-                    segments,
-                },
+            path: syn::Path { leading_colon: None, segments },
         }) => only_one(segments.iter()),
 
         _ => None,
@@ -0,0 +1,96 @@
+//! Dispatch messages of different types, decoded from the same stream
+
+#[macro_use]
+extern crate cbordata;
+
+use cbordata::{Cbor, Cborize, IntoCbor, Result};
+
+#[derive(Cborize, Clone, Debug)]
+struct Ping {
+    seqno: u64,
+}
+
+impl Ping {
+    const ID: &'static str = "ping";
+}
+
+#[derive(Cborize, Clone, Debug)]
+struct Pong {
+    seqno: u64,
+    latency_us: u64,
+}
+
+impl Pong {
+    const ID: &'static str = "pong";
+}
+
+#[derive(Cborize, Clone, Debug)]
+struct Publish {
+    topic: String,
+    payload: Vec<u8>,
+}
+
+impl Publish {
+    const ID: &'static str = "publish";
+}
+
+#[derive(Debug)]
+enum Message {
+    Ping(Ping),
+    Pong(Pong),
+    Publish(Publish),
+}
+
+impl From<Ping> for Message {
+    fn from(val: Ping) -> Message {
+        Message::Ping(val)
+    }
+}
+
+impl From<Pong> for Message {
+    fn from(val: Pong) -> Message {
+        Message::Pong(val)
+    }
+}
+
+impl From<Publish> for Message {
+    fn from(val: Publish) -> Message {
+        Message::Publish(val)
+    }
+}
+
+fn encode(val: Cbor) -> Vec<u8> {
+    let mut buf = vec![];
+    val.encode(&mut buf).unwrap();
+    buf
+}
+
+fn main() {
+    let frames = [
+        encode(Ping { seqno: 1 }.into_cbor().unwrap()),
+        encode(Pong { seqno: 1, latency_us: 120 }.into_cbor().unwrap()),
+        encode(
+            Publish {
+                topic: "metrics".to_string(),
+                payload: vec![1, 2, 3],
+            }
+            .into_cbor()
+            .unwrap(),
+        ),
+        encode("not a message".into_cbor().unwrap()),
+    ];
+
+    for frame in frames.iter() {
+        let msg: Result<Message> = try_decode_any!(frame, [Ping, Pong, Publish]);
+        match msg {
+            Ok(Message::Ping(val)) => println!("ping {}", val.seqno),
+            Ok(Message::Pong(val)) => {
+                println!("pong {} in {}us", val.seqno, val.latency_us)
+            }
+            Ok(Message::Publish(val)) => {
+                println!("publish {} {:?}", val.topic, val.payload)
+            }
+            Err(err) => println!("{}", err),
+        }
+    }
+}
@@ -26,7 +26,7 @@ impl Parent {
     const ID: &'static str = "floats";
 }
 
-#[derive(Cborize, Default, Clone, Debug)]
+#[derive(Cborize, Default, Clone, Debug, PartialEq)]
 #[allow(non_snake_case)]
 struct Floats {
     field1: f32,
@@ -59,4 +59,12 @@ fn main() {
     println!("{:?}", p);
     println!("{:?}", p_ref);
     assert_eq!(p_ref, p);
+
+    let f_ref = Floats { field1: 10.2, field2: -10.4 };
+
+    let val: Cbor = f_ref.clone().into_cbor().unwrap();
+    let f: Floats = Floats::from_cbor(val).unwrap();
+    println!("{:?}", f);
+    println!("{:?}", f_ref);
+    assert_eq!(f_ref, f);
 }
@@ -0,0 +1,35 @@
+//! Using Cbor::walk_mut to redact byte-strings nested deeper than depth 2.
+
+extern crate cbordata;
+
+use cbordata::{pretty_print, Cbor, IntoCbor, VisitorMut};
+
+struct Redact {
+    max_depth: u32,
+    n_redacted: usize,
+}
+
+impl VisitorMut for Redact {
+    fn enter(&mut self, val: &mut Cbor, depth: u32) -> bool {
+        if depth > self.max_depth && val.is_bytes() {
+            *val = Cbor::null();
+            self.n_redacted += 1;
+        }
+        true
+    }
+}
+
+fn main() {
+    let secret = Cbor::from_bytes(vec![0xde, 0xad, 0xbe, 0xef]).unwrap();
+    let mut doc = Cbor::map_from(vec![
+        ("checksum".into_cbor().unwrap(), secret.clone()),
+        ("keys".into_cbor().unwrap(), vec![secret.clone(), secret].into_cbor().unwrap()),
+    ])
+    .unwrap();
+
+    let mut visitor = Redact { max_depth: 2, n_redacted: 0 };
+    doc.walk_mut(&mut visitor);
+
+    println!("redacted {} byte-strings", visitor.n_redacted);
+    println!("{}", pretty_print(&doc).unwrap());
+}
@@ -0,0 +1,397 @@
+//! Module implement asynchronous encoding and decoding of cbor values.
+
+use std::{
+    cmp,
+    future::Future,
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use crate::{cbor::Scanner, Cbor, Decoder, Error, Result};
+
+/// Asynchronous counterpart of `std::io::Read`.
+///
+/// Signature is same as `futures::io::AsyncRead`. Readers from futures-io
+/// and tokio are adapted by wrapping them in `FuturesIo` and `TokioIo`,
+/// with the `futures-io` and `tokio` features respectively.
+pub trait AsyncRead {
+    /// Attempt to read bytes into `buf`, return the number of bytes read,
+    /// where zero means EOF. If no bytes are available, return
+    /// `Poll::Pending` and arrange for the current task to be woken up.
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>>;
+}
+
+/// Asynchronous counterpart of `std::io::Write`.
+///
+/// Signature is same as `futures::io::AsyncWrite`, minus `poll_close`,
+/// refer to [AsyncRead] on adapting writers from futures-io and tokio.
+pub trait AsyncWrite {
+    /// Attempt to write bytes from `buf`, return the number of bytes written.
+    /// If the writer is not ready, return `Poll::Pending` and arrange for the
+    /// current task to be woken up.
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>>;
+
+    /// Attempt to flush buffered bytes, if any, to their destination.
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>>;
+}
+
+impl<T: AsyncRead + Unpin + ?Sized> AsyncRead for &mut T {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut **self).poll_read(cx, buf)
+    }
+}
+
+impl<T: AsyncRead + Unpin + ?Sized> AsyncRead for Box<T> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut **self).poll_read(cx, buf)
+    }
+}
+
+impl AsyncRead for &[u8] {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Poll::Ready(io::Read::read(&mut *self, buf))
+    }
+}
+
+impl<T: AsyncWrite + Unpin + ?Sized> AsyncWrite for &mut T {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut **self).poll_write(cx, buf)
+    }
+
+    fn poll_flush(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut **self).poll_flush(cx)
+    }
+}
+
+impl<T: AsyncWrite + Unpin + ?Sized> AsyncWrite for Box<T> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut **self).poll_write(cx, buf)
+    }
+
+    fn poll_flush(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut **self).poll_flush(cx)
+    }
+}
+
+impl AsyncWrite for Vec<u8> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().extend_from_slice(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Adapt readers and writers implementing `futures_io::AsyncRead` and
+/// `futures_io::AsyncWrite` into [AsyncRead] and [AsyncWrite].
+///
+/// ```ignore
+/// let (val, n) = Cbor::decode_async(&mut FuturesIo(stream)).await?;
+/// ```
+#[cfg(feature = "futures-io")]
+pub struct FuturesIo<T>(pub T);
+
+#[cfg(feature = "futures-io")]
+impl<T> AsyncRead for FuturesIo<T>
+where
+    T: futures_io::AsyncRead + Unpin,
+{
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        futures_io::AsyncRead::poll_read(Pin::new(&mut self.0), cx, buf)
+    }
+}
+
+#[cfg(feature = "futures-io")]
+impl<T> AsyncWrite for FuturesIo<T>
+where
+    T: futures_io::AsyncWrite + Unpin,
+{
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        futures_io::AsyncWrite::poll_write(Pin::new(&mut self.0), cx, buf)
+    }
+
+    fn poll_flush(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        futures_io::AsyncWrite::poll_flush(Pin::new(&mut self.0), cx)
+    }
+}
+
+/// Adapt readers and writers implementing `tokio::io::AsyncRead` and
+/// `tokio::io::AsyncWrite` into [AsyncRead] and [AsyncWrite].
+///
+/// ```ignore
+/// let (val, n) = Cbor::decode_async(&mut TokioIo(stream)).await?;
+/// ```
+#[cfg(feature = "tokio")]
+pub struct TokioIo<T>(pub T);
+
+#[cfg(feature = "tokio")]
+impl<T> AsyncRead for TokioIo<T>
+where
+    T: tokio::io::AsyncRead + Unpin,
+{
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut buf = tokio::io::ReadBuf::new(buf);
+        match tokio::io::AsyncRead::poll_read(Pin::new(&mut self.0), cx, &mut buf) {
+            Poll::Ready(Ok(())) => Poll::Ready(Ok(buf.filled().len())),
+            Poll::Ready(Err(err)) => Poll::Ready(Err(err)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<T> AsyncWrite for TokioIo<T>
+where
+    T: tokio::io::AsyncWrite + Unpin,
+{
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        tokio::io::AsyncWrite::poll_write(Pin::new(&mut self.0), cx, buf)
+    }
+
+    fn poll_flush(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        tokio::io::AsyncWrite::poll_flush(Pin::new(&mut self.0), cx)
+    }
+}
+
+// Maximum number of bytes read from the reader at a time.
+const CHUNK_SIZE: usize = 4096;
+
+impl Cbor {
+    /// Asynchronous version of [Cbor::encode], the returned future resolves
+    /// to the number of bytes written, after flushing `w`.
+    ///
+    /// Value is encoded into memory before writing, dropping the future
+    /// before it resolves can leave `w` with a partially written value.
+    pub fn encode_async<'a, W>(&self, w: &'a mut W) -> EncodeFuture<'a, W>
+    where
+        W: AsyncWrite + Unpin + ?Sized,
+    {
+        let mut buf = vec![];
+        let err = self.encode(&mut buf).err();
+        EncodeFuture { w, buf, off: 0, err }
+    }
+
+    /// Asynchronous version of [Cbor::decode], the returned future resolves
+    /// to the decoded value and the number of bytes consumed.
+    ///
+    /// Only the bytes known to be part of the value are read, not to consume
+    /// bytes beyond the value, that is, a string at a time, but a header at
+    /// a time otherwise, hence `r` is better buffered. Partially received
+    /// value is held by the future, which is resumed as and when more bytes
+    /// arrive. To decode a sequence of values with fewer reads, use
+    /// [Decoder::pull_async].
+    pub fn decode_async<R>(r: &mut R) -> DecodeFuture<'_, R>
+    where
+        R: AsyncRead + Unpin + ?Sized,
+    {
+        DecodeFuture { r, buf: vec![], scanner: Scanner::default() }
+    }
+}
+
+impl Decoder {
+    /// Asynchronously read from `r`, until the next item is complete. Bytes
+    /// read beyond the item are buffered for subsequent pulls, refer to
+    /// [Decoder::pull].
+    ///
+    /// Resolves to None on EOF, when there are no buffered bytes, while EOF
+    /// after a partially received item shall fail with [Error::IOError].
+    /// Dropping the future before it resolves is safe, bytes read until then
+    /// are held by the decoder.
+    pub fn pull_async<'a, R>(&'a mut self, r: &'a mut R) -> PullFuture<'a, R>
+    where
+        R: AsyncRead + Unpin + ?Sized,
+    {
+        PullFuture { decoder: self, r }
+    }
+}
+
+/// Future returned by [Cbor::encode_async].
+pub struct EncodeFuture<'a, W: ?Sized> {
+    w: &'a mut W,
+    buf: Vec<u8>,
+    off: usize,
+    err: Option<Error>,
+}
+
+impl<'a, W> Future for EncodeFuture<'a, W>
+where
+    W: AsyncWrite + Unpin + ?Sized,
+{
+    type Output = Result<usize>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<usize>> {
+        let this = self.get_mut();
+        if let Some(err) = this.err.take() {
+            return Poll::Ready(Err(err));
+        }
+
+        while this.off < this.buf.len() {
+            match Pin::new(&mut *this.w).poll_write(cx, &this.buf[this.off..]) {
+                Poll::Ready(Ok(0)) => {
+                    return Poll::Ready(err_at!(IOError, msg: "write zero bytes"));
+                }
+                Poll::Ready(Ok(n)) => this.off += n,
+                Poll::Ready(Err(err)) if err.kind() == io::ErrorKind::Interrupted => (),
+                Poll::Ready(Err(err)) => return Poll::Ready(err_at!(IOError, Err(err))),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+
+        match Pin::new(&mut *this.w).poll_flush(cx) {
+            Poll::Ready(Ok(())) => Poll::Ready(Ok(this.buf.len())),
+            Poll::Ready(Err(err)) => Poll::Ready(err_at!(IOError, Err(err))),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// Future returned by [Cbor::decode_async].
+pub struct DecodeFuture<'a, R: ?Sized> {
+    r: &'a mut R,
+    buf: Vec<u8>,
+    scanner: Scanner,
+}
+
+impl<'a, R> Future for DecodeFuture<'a, R>
+where
+    R: AsyncRead + Unpin + ?Sized,
+{
+    type Output = Result<(Cbor, usize)>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(Cbor, usize)>> {
+        let this = self.get_mut();
+        loop {
+            let need = match this.scanner.scan_need(&this.buf) {
+                Ok(Ok(_)) => return Poll::Ready(Cbor::decode(&mut this.buf.as_slice())),
+                Ok(Err(need)) => cmp::min(need, CHUNK_SIZE),
+                Err(err) => return Poll::Ready(Err(err)),
+            };
+
+            let off = this.buf.len();
+            this.buf.resize(off + need, 0);
+            let res = Pin::new(&mut *this.r).poll_read(cx, &mut this.buf[off..]);
+            let n = match &res {
+                Poll::Ready(Ok(n)) => *n,
+                _ => 0,
+            };
+            this.buf.truncate(off + n);
+            match res {
+                Poll::Ready(Ok(0)) => {
+                    return Poll::Ready(
+                        err_at!(IOError, msg: "truncated cbor, {} bytes", off),
+                    );
+                }
+                Poll::Ready(Ok(_)) => (),
+                Poll::Ready(Err(err)) if err.kind() == io::ErrorKind::Interrupted => (),
+                Poll::Ready(Err(err)) => return Poll::Ready(err_at!(IOError, Err(err))),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+/// Future returned by [Decoder::pull_async].
+pub struct PullFuture<'a, R: ?Sized> {
+    decoder: &'a mut Decoder,
+    r: &'a mut R,
+}
+
+impl<'a, R> Future for PullFuture<'a, R>
+where
+    R: AsyncRead + Unpin + ?Sized,
+{
+    type Output = Result<Option<Cbor>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<Option<Cbor>>> {
+        let this = self.get_mut();
+        loop {
+            match this.decoder.pull() {
+                Ok(None) => (),
+                res => return Poll::Ready(res),
+            }
+
+            let mut chunk = [0_u8; CHUNK_SIZE];
+            match Pin::new(&mut *this.r).poll_read(cx, &mut chunk) {
+                Poll::Ready(Ok(0)) => match this.decoder.buffered() {
+                    0 => return Poll::Ready(Ok(None)),
+                    n => {
+                        return Poll::Ready(
+                            err_at!(IOError, msg: "truncated cbor, {} bytes", n),
+                        )
+                    }
+                },
+                Poll::Ready(Ok(n)) => this.decoder.feed(&chunk[..n]),
+                Poll::Ready(Err(err)) if err.kind() == io::ErrorKind::Interrupted => (),
+                Poll::Ready(Err(err)) => return Poll::Ready(err_at!(IOError, Err(err))),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "aio_test.rs"]
+mod aio_test;
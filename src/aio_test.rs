@@ -0,0 +1,251 @@
+use std::{collections::VecDeque, task::Waker};
+
+use super::*;
+use crate::IntoCbor;
+
+// poll `fut` to completion, return the result along with the number of
+// times the future returned Poll::Pending.
+fn block_on<F: Future + Unpin>(mut fut: F) -> (F::Output, usize) {
+    let mut cx = Context::from_waker(Waker::noop());
+    let mut pending = 0;
+    loop {
+        match Pin::new(&mut fut).poll(&mut cx) {
+            Poll::Ready(res) => break (res, pending),
+            Poll::Pending => pending += 1,
+        }
+    }
+}
+
+// Reader that returns Poll::Pending before every chunk.
+struct ChunkReader {
+    chunks: VecDeque<Vec<u8>>,
+    ready: bool,
+}
+
+impl ChunkReader {
+    fn new(buf: &[u8], chunk: usize) -> ChunkReader {
+        let chunks = buf.chunks(chunk).map(|c| c.to_vec()).collect();
+        ChunkReader { chunks, ready: false }
+    }
+}
+
+impl AsyncRead for ChunkReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        if !self.ready {
+            self.ready = true;
+            return Poll::Pending;
+        }
+        let n = match self.chunks.front_mut() {
+            Some(chunk) => {
+                let n = std::cmp::min(buf.len(), chunk.len());
+                buf[..n].copy_from_slice(&chunk[..n]);
+                chunk.drain(..n);
+                n
+            }
+            None => 0,
+        };
+        if self.chunks.front().map(|c| c.is_empty()).unwrap_or(false) {
+            self.chunks.pop_front();
+            self.ready = false;
+        }
+        Poll::Ready(Ok(n))
+    }
+}
+
+// Writer that accepts at most `limit` bytes per write, after a Poll::Pending.
+struct SlowWriter {
+    buf: Vec<u8>,
+    limit: usize,
+    ready: bool,
+}
+
+impl AsyncWrite for SlowWriter {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        if !self.ready {
+            self.ready = true;
+            return Poll::Pending;
+        }
+        self.ready = false;
+        let n = std::cmp::min(buf.len(), self.limit);
+        self.buf.extend_from_slice(&buf[..n]);
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+fn sample() -> Cbor {
+    let items = vec![
+        "hello world".into_cbor().unwrap(),
+        1234567_u64.into_cbor().unwrap(),
+        (-20_i64).into_cbor().unwrap(),
+        vec![1_u64, 2, 3].into_cbor().unwrap(),
+    ];
+    items.into_cbor().unwrap()
+}
+
+#[test]
+fn test_encode_async() {
+    let val = sample();
+    let mut refb = vec![];
+    let n = val.encode(&mut refb).unwrap();
+
+    let mut buf = vec![];
+    let (res, pending) = block_on(val.encode_async(&mut buf));
+    assert_eq!(res.unwrap(), n);
+    assert_eq!(pending, 0);
+    assert_eq!(buf, refb);
+
+    let mut w = SlowWriter { buf: vec![], limit: 3, ready: false };
+    let (res, pending) = block_on(val.encode_async(&mut w));
+    assert_eq!(res.unwrap(), n);
+    assert_eq!(pending, n.div_ceil(3));
+    assert_eq!(w.buf, refb);
+}
+
+#[test]
+fn test_decode_async() {
+    let val = sample();
+    let mut buf = vec![];
+    let n = val.encode(&mut buf).unwrap();
+    buf.extend_from_slice(&[0x01, 0x02]);
+
+    let mut r = buf.as_slice();
+    let (res, _) = block_on(Cbor::decode_async(&mut r));
+    assert_eq!(res.unwrap(), (val.clone(), n));
+    assert_eq!(r, &[0x01, 0x02]);
+
+    // resume partially received item after Poll::Pending.
+    let mut r = ChunkReader::new(&buf, 4);
+    let (res, pending) = block_on(Cbor::decode_async(&mut r));
+    assert_eq!(res.unwrap(), (val.clone(), n));
+    assert!(pending > 1, "{}", pending);
+
+    let mut r = &buf[..n - 1];
+    match block_on(Cbor::decode_async(&mut r)).0 {
+        Err(Error::IOError(_, _)) => (),
+        res => panic!("{:?}", res),
+    }
+
+    let mut r: &[u8] = &[0x1c];
+    match block_on(Cbor::decode_async(&mut r)).0 {
+        Err(Error::FailCbor(_, _)) => (),
+        res => panic!("{:?}", res),
+    }
+
+    // strings are read in chunks, and headers one at a time.
+    let val = vec![Cbor::from_bytes(vec![0xab; 10_000]).unwrap()].into_cbor().unwrap();
+    let mut buf = vec![];
+    let n = val.encode(&mut buf).unwrap();
+    buf.push(0x01);
+    let mut r = CountReader { r: buf.as_slice(), reads: 0 };
+    let (res, _) = block_on(Cbor::decode_async(&mut r));
+    assert_eq!(res.unwrap(), (val, n));
+    assert_eq!(r.r, &[0x01]);
+    assert_eq!(r.reads, 6);
+}
+
+// Reader that counts the number of reads.
+struct CountReader<'a> {
+    r: &'a [u8],
+    reads: usize,
+}
+
+impl<'a> AsyncRead for CountReader<'a> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        self.reads += 1;
+        Pin::new(&mut self.r).poll_read(cx, buf)
+    }
+}
+
+#[test]
+fn test_pull_async() {
+    let vals = vec![sample(), "abc".into_cbor().unwrap(), 1000_u64.into_cbor().unwrap()];
+    let mut buf = vec![];
+    for val in vals.iter() {
+        val.encode(&mut buf).unwrap();
+    }
+
+    for chunk in [1, 3, 7, buf.len()].iter() {
+        let mut r = ChunkReader::new(&buf, *chunk);
+        let mut decoder = Decoder::new();
+        let mut outs = vec![];
+        while let Some(val) = block_on(decoder.pull_async(&mut r)).0.unwrap() {
+            outs.push(val);
+        }
+        assert_eq!(outs, vals, "chunk {}", chunk);
+        assert_eq!(decoder.buffered(), 0);
+    }
+
+    let mut r = &buf[..buf.len() - 1];
+    let mut decoder = Decoder::new();
+    for val in vals[..2].iter() {
+        assert_eq!(block_on(decoder.pull_async(&mut r)).0.unwrap().as_ref(), Some(val));
+    }
+    match block_on(decoder.pull_async(&mut r)).0 {
+        Err(Error::IOError(_, _)) => (),
+        res => panic!("{:?}", res),
+    }
+}
+
+#[cfg(feature = "futures-io")]
+#[test]
+fn test_futures_io() {
+    let vals = vec![sample(), "abc".into_cbor().unwrap()];
+    let mut w = FuturesIo(vec![]);
+    let mut n = 0;
+    for val in vals.iter() {
+        n += block_on(val.encode_async(&mut w)).0.unwrap();
+    }
+    assert_eq!(w.0.len(), n);
+
+    let mut r = FuturesIo(w.0.as_slice());
+    let (res, _) = block_on(Cbor::decode_async(&mut r));
+    assert_eq!(res.unwrap().0, vals[0]);
+
+    let mut r = FuturesIo(w.0.as_slice());
+    let mut decoder = Decoder::new();
+    let mut outs = vec![];
+    while let Some(val) = block_on(decoder.pull_async(&mut r)).0.unwrap() {
+        outs.push(val);
+    }
+    assert_eq!(outs, vals);
+}
+
+#[cfg(feature = "tokio")]
+#[test]
+fn test_tokio() {
+    let vals = vec![sample(), "abc".into_cbor().unwrap()];
+    let mut w = TokioIo(vec![]);
+    let mut n = 0;
+    for val in vals.iter() {
+        n += block_on(val.encode_async(&mut w)).0.unwrap();
+    }
+    assert_eq!(w.0.len(), n);
+
+    let mut r = TokioIo(w.0.as_slice());
+    let (res, _) = block_on(Cbor::decode_async(&mut r));
+    assert_eq!(res.unwrap().0, vals[0]);
+
+    let mut r = TokioIo(w.0.as_slice());
+    let mut decoder = Decoder::new();
+    let mut outs = vec![];
+    while let Some(val) = block_on(decoder.pull_async(&mut r)).0.unwrap() {
+        outs.push(val);
+    }
+    assert_eq!(outs, vals);
+}
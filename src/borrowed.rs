@@ -0,0 +1,384 @@
+//! Module implement borrowed view into encoded cbor data.
+
+use std::{borrow::Cow, convert::TryFrom, str::from_utf8};
+
+use crate::{
+    cbor::{decode_addnl, decode_hdr, f16_to_f64, BREAK},
+    Cbor, Error, Info, Key, MapOrder, Result, SimpleValue, Tag, RECURSION_LIMIT,
+};
+
+/// Borrowed counterpart of [Cbor][crate::Cbor], decoded from a byte slice.
+///
+/// Byte-strings and text-strings refer to the input slice, without copying
+/// their payload. Indefinite-length strings are assembled from their chunks,
+/// hence they are the only strings that own their data. Text-strings are
+/// validated as utf8 while decoding.
+///
+/// Map keys are limited to the types allowed by [Key][crate::Key], and
+/// unlike [Cbor][crate::Cbor], tags are kept as tag-number and its content.
+///
+/// ```
+/// use cbordata::{CborRef, FromCborRef, IntoCbor};
+///
+/// let mut buf = vec![];
+/// vec!["hello", "world"].into_cbor().unwrap().encode(&mut buf).unwrap();
+///
+/// let (val, n) = CborRef::decode(&buf).unwrap();
+/// assert_eq!(n, buf.len());
+/// let words: Vec<&str> = Vec::from_cbor_ref(&val).unwrap();
+/// assert_eq!(words, ["hello", "world"]);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum CborRef<'a> {
+    Major0(Info, u64),
+    Major1(Info, u64),
+    Major2(Info, Cow<'a, [u8]>),
+    Major3(Info, Cow<'a, str>),
+    Major4(Info, Vec<CborRef<'a>>),
+    Major5(Info, Vec<(CborRef<'a>, CborRef<'a>)>),
+    Major6(Info, u64, Box<CborRef<'a>>),
+    Major7(Info, SimpleValue),
+}
+
+impl<'a> CborRef<'a> {
+    /// Decode a cbor value from the beginning of `buf`, return the value
+    /// and number of bytes consumed to construct the value.
+    pub fn decode(buf: &'a [u8]) -> Result<(CborRef<'a>, usize)> {
+        CborRef::do_decode(buf, 1)
+    }
+
+    fn do_decode(buf: &'a [u8], depth: u32) -> Result<(CborRef<'a>, usize)> {
+        if depth > RECURSION_LIMIT {
+            return err_at!(LimitExceeded, msg: "decode recursion limit exceeded");
+        }
+
+        let mut r = buf;
+        let (major, info, n) = decode_hdr(&mut r)?;
+        if major == 7 {
+            let (sval, m) = SimpleValue::decode(info, &mut r)?;
+            return Ok((CborRef::Major7(info, sval), n + m));
+        }
+        let (num, m) = decode_addnl(info, &mut r)?;
+        let mut off = n + m;
+
+        let val = match (major, info) {
+            (0, info) => CborRef::Major0(info, num),
+            (1, info) => CborRef::Major1(info, num),
+            (2, Info::Indefinite) => {
+                let mut data: Vec<u8> = vec![];
+                while !CborRef::is_break(buf, &mut off)? {
+                    match CborRef::decode_child(buf, &mut off, depth)? {
+                        CborRef::Major2(_, chunk) => data.extend_from_slice(&chunk),
+                        _ => err_at!(FailConvert, msg: "expected byte chunk")?,
+                    }
+                }
+                CborRef::Major2(info, Cow::Owned(data))
+            }
+            (2, info) => CborRef::Major2(info, Cow::Borrowed(take(buf, &mut off, num)?)),
+            (3, Info::Indefinite) => {
+                let mut text = String::default();
+                while !CborRef::is_break(buf, &mut off)? {
+                    match CborRef::decode_child(buf, &mut off, depth)? {
+                        CborRef::Major3(_, chunk) => text.push_str(&chunk),
+                        _ => err_at!(FailConvert, msg: "expected text chunk")?,
+                    }
+                }
+                CborRef::Major3(info, Cow::Owned(text))
+            }
+            (3, info) => {
+                let text = err_at!(FailCbor, from_utf8(take(buf, &mut off, num)?))?;
+                CborRef::Major3(info, Cow::Borrowed(text))
+            }
+            (4, info) => {
+                let mut list: Vec<CborRef> = vec![];
+                while CborRef::more(buf, &mut off, info, list.len(), num)? {
+                    list.push(CborRef::decode_child(buf, &mut off, depth)?);
+                }
+                CborRef::Major4(info, list)
+            }
+            (5, info) => {
+                let mut map: Vec<(CborRef, CborRef)> = vec![];
+                while CborRef::more(buf, &mut off, info, map.len(), num)? {
+                    let key = CborRef::decode_child(buf, &mut off, depth)?;
+                    key.check_key()?;
+                    let val = CborRef::decode_child(buf, &mut off, depth)?;
+                    map.push((key, val));
+                }
+                CborRef::Major5(info, map)
+            }
+            (6, info) => {
+                let val = CborRef::decode_child(buf, &mut off, depth)?;
+                CborRef::Major6(info, num, Box::new(val))
+            }
+            _ => unreachable!(),
+        };
+
+        Ok((val, off))
+    }
+
+    /// Return byte-string, or text-string, as `Bytes` sharing the storage of
+    /// `buf`, from which this value is decoded, without copying. Strings of
+    /// indefinite length, assembled from their chunks, and strings that don't
+    /// refer to `buf` are copied. Return None for other types.
+    #[cfg(feature = "bytes")]
+    pub fn to_shared_bytes(&self, buf: &bytes::Bytes) -> Option<bytes::Bytes> {
+        let (data, borrowed) = match self {
+            CborRef::Major2(_, data) => (data.as_ref(), matches!(data, Cow::Borrowed(_))),
+            CborRef::Major3(_, text) => {
+                (text.as_bytes(), matches!(text, Cow::Borrowed(_)))
+            }
+            _ => return None,
+        };
+        let within = {
+            let range = buf.as_ptr_range();
+            range.start <= data.as_ptr() && data.as_ptr_range().end <= range.end
+        };
+        match borrowed && within {
+            true => Some(buf.slice_ref(data)),
+            false => Some(bytes::Bytes::copy_from_slice(data)),
+        }
+    }
+
+    /// Detach from the input buffer, copying borrowed strings and bytes into
+    /// an owned [Cbor] value, while reusing storage that is already owned,
+    /// like text gathered from indefinite chunks.
+    pub fn into_owned(self) -> Cbor {
+        match self {
+            CborRef::Major0(info, val) => Cbor::Major0(info, val),
+            CborRef::Major1(info, val) => Cbor::Major1(info, val),
+            CborRef::Major2(info, data) => Cbor::Major2(info, data.into_owned()),
+            CborRef::Major3(info, text) => {
+                Cbor::Major3(info, text.into_owned().into_bytes())
+            }
+            CborRef::Major4(info, list) => {
+                Cbor::Major4(info, list.into_iter().map(|x| x.into_owned()).collect())
+            }
+            CborRef::Major5(info, map) => {
+                let map: Vec<(Key, Cbor)> =
+                    map.into_iter().map(|(k, v)| (k.to_key(), v.into_owned())).collect();
+                let order = MapOrder::of(&map);
+                Cbor::Major5(info, map, order)
+            }
+            CborRef::Major6(info, num, val) => {
+                Cbor::Major6(info, Tag::from_num(num, val.into_owned()))
+            }
+            CborRef::Major7(info, sval) => Cbor::Major7(info, sval),
+        }
+    }
+
+    // Deep copy into owned value, map keys are expected to be validated.
+    pub(crate) fn to_cbor(&self) -> Cbor {
+        match self {
+            CborRef::Major0(info, val) => Cbor::Major0(*info, *val),
+            CborRef::Major1(info, val) => Cbor::Major1(*info, *val),
+            CborRef::Major2(info, data) => Cbor::Major2(*info, data.to_vec()),
+            CborRef::Major3(info, text) => Cbor::Major3(*info, text.as_bytes().to_vec()),
+            CborRef::Major4(info, list) => {
+                Cbor::Major4(*info, list.iter().map(|x| x.to_cbor()).collect())
+            }
+            CborRef::Major5(info, map) => {
+                let map: Vec<(Key, Cbor)> =
+                    map.iter().map(|(k, v)| (k.to_key(), v.to_cbor())).collect();
+                let order = MapOrder::of(&map);
+                Cbor::Major5(*info, map, order)
+            }
+            CborRef::Major6(info, num, val) => {
+                Cbor::Major6(*info, Tag::from_num(*num, val.to_cbor()))
+            }
+            CborRef::Major7(info, sval) => Cbor::Major7(*info, *sval),
+        }
+    }
+
+    fn to_key(&self) -> Key {
+        match self {
+            CborRef::Major0(_, key) => Key::U64(*key),
+            CborRef::Major1(_, key) => Key::N64(-1 - (*key as i64)),
+            CborRef::Major2(_, key) => Key::Bytes(key.to_vec()),
+            CborRef::Major3(_, key) => Key::Text(key.to_string()),
+            CborRef::Major7(_, SimpleValue::True) => Key::Bool(true),
+            CborRef::Major7(_, SimpleValue::False) => Key::Bool(false),
+            // half precision keys are widened, like owned keys.
+            CborRef::Major7(_, SimpleValue::F16(key)) => Key::F64(f16_to_f64(*key)),
+            CborRef::Major7(_, SimpleValue::F32(key)) => Key::F32(*key),
+            CborRef::Major7(_, SimpleValue::F64(key)) => Key::F64(*key),
+            key => panic!("cbor not a valid key {:?}", key),
+        }
+    }
+
+    fn decode_child(buf: &'a [u8], off: &mut usize, depth: u32) -> Result<CborRef<'a>> {
+        let (val, n) = CborRef::do_decode(&buf[*off..], depth + 1)?;
+        *off += n;
+        Ok(val)
+    }
+
+    // Check for a break code at `off`, consume it if present.
+    fn is_break(buf: &[u8], off: &mut usize) -> Result<bool> {
+        match buf.get(*off) {
+            Some(&BREAK) => {
+                *off += 1;
+                Ok(true)
+            }
+            Some(_) => Ok(false),
+            None => err_at!(IOError, msg: "missing break code for indefinite item"),
+        }
+    }
+
+    // Whether there are more items to decode in an array or map of `len`,
+    // or terminated by break code when `info` is indefinite.
+    fn more(buf: &[u8], off: &mut usize, info: Info, n: usize, len: u64) -> Result<bool> {
+        match info {
+            Info::Indefinite => Ok(!CborRef::is_break(buf, off)?),
+            _ => Ok(err_at!(FailConvert, u64::try_from(n))? < len),
+        }
+    }
+
+    fn check_key(&self) -> Result<()> {
+        match self {
+            CborRef::Major0(_, _) | CborRef::Major2(_, _) | CborRef::Major3(_, _) => {
+                Ok(())
+            }
+            CborRef::Major1(_, key) if *key < (i64::MAX as u64) => Ok(()),
+            CborRef::Major7(_, SimpleValue::True)
+            | CborRef::Major7(_, SimpleValue::False)
+            | CborRef::Major7(_, SimpleValue::F16(_))
+            | CborRef::Major7(_, SimpleValue::F32(_))
+            | CborRef::Major7(_, SimpleValue::F64(_)) => Ok(()),
+            _ => err_at!(FailCbor, msg: "cbor not a valid key"),
+        }
+    }
+}
+
+// Take `len` bytes from `buf` starting at `off`.
+fn take<'a>(buf: &'a [u8], off: &mut usize, len: u64) -> Result<&'a [u8]> {
+    let len: usize = err_at!(FailConvert, usize::try_from(len))?;
+    match buf.get(*off..).and_then(|buf| buf.get(..len)) {
+        Some(data) => {
+            *off += len;
+            Ok(data)
+        }
+        None => err_at!(IOError, msg: "truncated string, expected {} bytes", len),
+    }
+}
+
+/// Convert from a borrowed [CborRef] value, without copying strings and
+/// byte-strings, where possible.
+///
+/// The converted value can borrow from the [CborRef] value as well as from
+/// the input buffer it was decoded from, for lifetime `'a`. Hence, a `&'a str`
+/// or `&'a [u8]` target can be converted only while the [CborRef] value and
+/// the input buffer are both alive. To hold on to the converted value beyond
+/// that, convert into owned types like `String` and `Vec<u8>`.
+///
+/// Refer to [FromCbor][crate::FromCbor] for conversion from owned values.
+pub trait FromCborRef<'a>: Sized {
+    /// Convert value from [CborRef] into type's value.
+    fn from_cbor_ref(val: &'a CborRef<'a>) -> Result<Self>;
+}
+
+impl<'a> FromCborRef<'a> for &'a str {
+    fn from_cbor_ref(val: &'a CborRef<'a>) -> Result<&'a str> {
+        match val {
+            CborRef::Major3(_, text) => Ok(text.as_ref()),
+            _ => err_at!(FailConvert, msg: "not a text"),
+        }
+    }
+}
+
+impl<'a> FromCborRef<'a> for &'a [u8] {
+    fn from_cbor_ref(val: &'a CborRef<'a>) -> Result<&'a [u8]> {
+        match val {
+            CborRef::Major2(_, data) => Ok(data.as_ref()),
+            _ => err_at!(FailConvert, msg: "not bytes"),
+        }
+    }
+}
+
+impl<'a> FromCborRef<'a> for String {
+    fn from_cbor_ref(val: &'a CborRef<'a>) -> Result<String> {
+        Ok(<&str>::from_cbor_ref(val)?.to_string())
+    }
+}
+
+impl<'a> FromCborRef<'a> for bool {
+    fn from_cbor_ref(val: &'a CborRef<'a>) -> Result<bool> {
+        match val {
+            CborRef::Major7(_, SimpleValue::True) => Ok(true),
+            CborRef::Major7(_, SimpleValue::False) => Ok(false),
+            _ => err_at!(FailConvert, msg: "not a bool"),
+        }
+    }
+}
+
+impl<'a> FromCborRef<'a> for f32 {
+    fn from_cbor_ref(val: &'a CborRef<'a>) -> Result<f32> {
+        match val {
+            CborRef::Major7(_, SimpleValue::F32(val)) => Ok(*val),
+            _ => err_at!(FailConvert, msg: "not f32"),
+        }
+    }
+}
+
+impl<'a> FromCborRef<'a> for f64 {
+    fn from_cbor_ref(val: &'a CborRef<'a>) -> Result<f64> {
+        match val {
+            CborRef::Major7(_, SimpleValue::F64(val)) => Ok(*val),
+            _ => err_at!(FailConvert, msg: "not f64"),
+        }
+    }
+}
+
+macro_rules! convert_num {
+    ($($t:ty)*) => {$(
+        impl<'a> FromCborRef<'a> for $t {
+            fn from_cbor_ref(val: &'a CborRef<'a>) -> Result<$t> {
+                let val: i128 = match val {
+                    CborRef::Major0(_, val) => i128::from(*val),
+                    CborRef::Major1(_, val) => -1 - i128::from(*val),
+                    _ => err_at!(FailConvert, msg: "not a number")?,
+                };
+                match <$t>::try_from(val) {
+                    Ok(val) => Ok(val),
+                    Err(_) => {
+                        err_at!(FailConvert, msg: "value {} exceeds {}", val, stringify!($t))
+                    }
+                }
+            }
+        }
+    )*}
+}
+
+convert_num! {u64 u32 u16 u8 usize i64 i32 i16 i8 isize}
+
+impl<'a, T> FromCborRef<'a> for Option<T>
+where
+    T: FromCborRef<'a>,
+{
+    fn from_cbor_ref(val: &'a CborRef<'a>) -> Result<Option<T>> {
+        match val {
+            CborRef::Major7(_, SimpleValue::Null) => Ok(None),
+            val => Ok(Some(T::from_cbor_ref(val)?)),
+        }
+    }
+}
+
+impl<'a, T> FromCborRef<'a> for Vec<T>
+where
+    T: FromCborRef<'a>,
+{
+    fn from_cbor_ref(val: &'a CborRef<'a>) -> Result<Vec<T>> {
+        match val {
+            CborRef::Major4(_, data) => {
+                let mut arr = vec![];
+                for (i, item) in data.iter().enumerate() {
+                    arr.push(T::from_cbor_ref(item).map_err(|err| err.in_index(i))?)
+                }
+                Ok(arr)
+            }
+            _ => err_at!(FailConvert, msg: "not a vector"),
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "borrowed_test.rs"]
+mod borrowed_test;
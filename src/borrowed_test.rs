@@ -0,0 +1,188 @@
+use super::*;
+
+use crate::{Cbor, IntoCbor, Key};
+
+#[test]
+fn test_borrowed_decode() {
+    let val: Cbor = vec![
+        (Key::from("name"), "hello".into_cbor().unwrap()),
+        (Key::from(10_u64), Cbor::from_bytes(vec![1, 2, 3]).unwrap()),
+        (Key::from(-2_i64), vec![1_u64, 2].into_cbor().unwrap()),
+    ]
+    .into_cbor()
+    .unwrap();
+    // [map, tag(7, 0)]
+    let mut buf: Vec<u8> = vec![0x82];
+    val.encode(&mut buf).unwrap();
+    buf.extend_from_slice(&[0xc7, 0x00]);
+
+    let (val, n) = CborRef::decode(&buf).unwrap();
+    assert_eq!(n, buf.len());
+    let (map, tag) = match &val {
+        CborRef::Major4(_, list) => match list.as_slice() {
+            [CborRef::Major5(_, map), tag] => (map, tag),
+            list => panic!("{:?}", list),
+        },
+        val => panic!("{:?}", val),
+    };
+    assert_eq!(map.len(), 3);
+
+    let ptrs = buf.as_ptr_range();
+    match &map[0] {
+        (
+            CborRef::Major3(_, Cow::Borrowed(key)),
+            CborRef::Major3(_, Cow::Borrowed(text)),
+        ) => {
+            assert_eq!((*key, *text), ("name", "hello"));
+            assert!(ptrs.contains(&text.as_ptr()));
+        }
+        item => panic!("{:?}", item),
+    }
+    assert_eq!(<&[u8]>::from_cbor_ref(&map[1].1).unwrap(), &[1, 2, 3]);
+    assert_eq!(i64::from_cbor_ref(&map[2].0).unwrap(), -2);
+    assert_eq!(Vec::<u8>::from_cbor_ref(&map[2].1).unwrap(), vec![1, 2]);
+    match tag {
+        CborRef::Major6(_, 7, val) => assert_eq!(u8::from_cbor_ref(val).unwrap(), 0),
+        val => panic!("{:?}", val),
+    }
+
+    assert!(<&str>::from_cbor_ref(&map[1].1).is_err());
+    assert!(u8::from_cbor_ref(&CborRef::Major0(Info::U16, 256)).is_err());
+}
+
+#[test]
+fn test_borrowed_indefinite() {
+    // [_ (_ "ab" "c"), (_ h'01' h'02'), {_ "k": null}]
+    let buf = [
+        0x9f, 0x7f, 0x62, 0x61, 0x62, 0x61, 0x63, 0xff, 0x5f, 0x41, 0x01, 0x41, 0x02,
+        0xff, 0xbf, 0x61, 0x6b, 0xf6, 0xff, 0xff,
+    ];
+    let (val, n) = CborRef::decode(&buf).unwrap();
+    assert_eq!(n, buf.len());
+    match &val {
+        CborRef::Major4(Info::Indefinite, list) => {
+            assert_eq!(
+                list[0],
+                CborRef::Major3(Info::Indefinite, Cow::Owned("abc".into()))
+            );
+            assert_eq!(<&[u8]>::from_cbor_ref(&list[1]).unwrap(), &[1, 2]);
+            match &list[2] {
+                CborRef::Major5(_, map) => {
+                    assert_eq!(<&str>::from_cbor_ref(&map[0].0).unwrap(), "k");
+                    assert_eq!(Option::<u64>::from_cbor_ref(&map[0].1).unwrap(), None);
+                }
+                val => panic!("{:?}", val),
+            }
+        }
+        val => panic!("{:?}", val),
+    }
+}
+
+#[test]
+fn test_borrowed_errors() {
+    let bufs: Vec<Vec<u8>> = vec![
+        vec![0x63, 0x61, 0x62],       // truncated text
+        vec![0x62, 0xff, 0xfe],       // invalid utf8
+        vec![0x9f, 0x01],             // missing break
+        vec![0xa1, 0x80, 0x01],       // array as key
+        vec![0x7f, 0x41, 0x01, 0xff], // bytes chunk in text
+    ];
+    for buf in bufs.into_iter() {
+        assert!(CborRef::decode(&buf).is_err(), "{:?}", buf);
+    }
+
+    let mut buf: Vec<u8> = [0x81].repeat(RECURSION_LIMIT as usize);
+    buf.push(0x01);
+    let handle =
+        std::thread::Builder::new().stack_size(16 * 1024 * 1024).spawn(move || {
+            match CborRef::decode(&buf) {
+                Err(Error::LimitExceeded(_, msg)) => {
+                    assert!(msg.contains("recursion"), "{}", msg)
+                }
+                res => panic!("{:?}", res),
+            }
+        });
+    handle.unwrap().join().unwrap();
+}
+
+#[test]
+fn test_borrowed_into_owned() {
+    let val: Cbor = vec![
+        (Key::from("name"), "hello".into_cbor().unwrap()),
+        (Key::from(10_u64), Cbor::from_bytes(vec![1, 2, 3]).unwrap()),
+        (Key::from(-2_i64), vec![1_u64, 2].into_cbor().unwrap()),
+    ]
+    .into_cbor()
+    .unwrap();
+    let mut buf: Vec<u8> = vec![];
+    val.encode(&mut buf).unwrap();
+    // tag(7, "he" "llo")
+    buf.extend_from_slice(&[0xc7, 0x7f, 0x62, 0x68, 0x65, 0x63, 0x6c, 0x6c, 0x6f, 0xff]);
+
+    let owned = {
+        let input = buf.clone();
+        let (item, n) = CborRef::decode(&input).unwrap();
+        let (tag, m) = CborRef::decode(&input[n..]).unwrap();
+        assert_eq!(n + m, input.len());
+        (item.into_owned(), tag.into_owned())
+    };
+    assert_eq!(owned.0, val);
+    let text = Cbor::Major3(Info::Indefinite, b"hello".to_vec());
+    assert_eq!(owned.1, Cbor::from(Tag::from_value(7, text)));
+
+    let (item, _) = CborRef::decode(&buf).unwrap();
+    assert_eq!(item.to_cbor(), item.into_owned());
+}
+
+#[test]
+fn test_borrowed_f16_key() {
+    // {1.5: null}, with half precision key.
+    let buf = [0xa1, 0xf9, 0x3e, 0x00, 0xf6];
+    let (item, n) = CborRef::decode(&buf).unwrap();
+    assert_eq!(n, buf.len());
+    let (val, _) = Cbor::decode(&mut buf.as_ref()).unwrap();
+    assert_eq!(item.into_owned(), val);
+}
+
+#[cfg(feature = "bytes")]
+#[test]
+fn test_borrowed_shared_bytes() {
+    let val = vec![
+        Cbor::from_bytes(vec![1, 2, 3]).unwrap(),
+        "hello".into_cbor().unwrap(),
+        10.into_cbor().unwrap(),
+    ]
+    .into_cbor()
+    .unwrap();
+    let mut buf = vec![];
+    val.encode(&mut buf).unwrap();
+    // "he" "llo" in indefinite chunks.
+    buf.extend_from_slice(&[0x7f, 0x62, 0x68, 0x65, 0x63, 0x6c, 0x6c, 0x6f, 0xff]);
+    let buf = bytes::Bytes::from(buf);
+
+    let (val, n) = CborRef::decode(&buf).unwrap();
+    let items = match &val {
+        CborRef::Major4(_, items) => items,
+        val => panic!("{:?}", val),
+    };
+    let range = buf.as_ptr_range();
+
+    let data = items[0].to_shared_bytes(&buf).unwrap();
+    assert_eq!(data.as_ref(), &[1, 2, 3]);
+    assert!(range.contains(&data.as_ptr()));
+    let text = items[1].to_shared_bytes(&buf).unwrap();
+    assert_eq!(text.as_ref(), b"hello");
+    assert!(range.contains(&text.as_ptr()));
+    assert!(items[2].to_shared_bytes(&buf).is_none());
+
+    let (val, _) = CborRef::decode(&buf[n..]).unwrap();
+    let text = val.to_shared_bytes(&buf).unwrap();
+    assert_eq!(text.as_ref(), b"hello");
+    assert!(!range.contains(&text.as_ptr()));
+
+    // decoded from other buffer.
+    let other = bytes::Bytes::from(buf.to_vec());
+    let data = items[0].to_shared_bytes(&other).unwrap();
+    assert_eq!(data.as_ref(), &[1, 2, 3]);
+    assert!(!other.as_ptr_range().contains(&data.as_ptr()));
+}
@@ -0,0 +1,181 @@
+//! Module implement decoding of cbor values with reuse of storage.
+
+use std::{cmp, io, mem};
+
+use crate::{
+    cbor::{DecodeConfig, DecodeState},
+    Cbor, Key, Result,
+};
+
+/// Decode cbor values, reusing the storage of values recycled back into
+/// the buffer.
+///
+/// Decoding a value allocates a `Vec` for every string, byte-string, array
+/// and map in the value. To decode a stream of values without as many
+/// allocations, hand over values that are no longer needed to
+/// [CborBuffer::recycle], whose vectors, with their capacity, are reused by
+/// subsequent calls to [CborBuffer::decode].
+///
+/// ```
+/// use cbordata::{Cbor, CborBuffer, IntoCbor};
+///
+/// let mut buf = vec![];
+/// for i in 0..10_u64 {
+///     vec![i; 8].into_cbor().unwrap().encode_into(&mut buf).unwrap();
+/// }
+///
+/// let mut buffer = CborBuffer::new();
+/// let mut r = buf.as_slice();
+/// for i in 0..10_u64 {
+///     let (val, _) = buffer.decode(&mut r).unwrap();
+///     assert_eq!(val, vec![i; 8].into_cbor().unwrap());
+///     buffer.recycle(val);
+/// }
+/// ```
+///
+/// Recycled storage is retained by the buffer until it is dropped, or until
+/// [CborBuffer::clear] is called. Storage of strings and byte-strings is
+/// reused only for strings of comparable length, that is, a large recycled
+/// string does not back a small one.
+#[derive(Default)]
+pub struct CborBuffer {
+    config: DecodeConfig,
+    pool: Pool,
+    stack: Vec<Cbor>,
+}
+
+impl CborBuffer {
+    /// Create a new buffer, with default [DecodeConfig].
+    pub fn new() -> CborBuffer {
+        CborBuffer::default()
+    }
+
+    /// Create a new buffer, with `config` applied to every decoded value.
+    pub fn with_config(config: DecodeConfig) -> CborBuffer {
+        CborBuffer { config, ..CborBuffer::default() }
+    }
+
+    /// Same as [Cbor::decode_with], except that storage for the decoded
+    /// value is taken from recycled values, if any.
+    pub fn decode<R>(&mut self, r: &mut R) -> Result<(Cbor, usize)>
+    where
+        R: io::Read,
+    {
+        let mut state = DecodeState {
+            pool: mem::take(&mut self.pool),
+            ..Default::default()
+        };
+        let res = Cbor::decode_root(r, &self.config, &mut state);
+        self.pool = state.pool;
+        res
+    }
+
+    /// Take apart `val`, at any depth, retaining its vectors for reuse.
+    pub fn recycle(&mut self, val: Cbor) {
+        self.stack.push(val);
+        while let Some(val) = self.stack.pop() {
+            match val {
+                Cbor::Major2(_, data) | Cbor::Major3(_, data) | Cbor::Binary(data) => {
+                    self.pool.put_bytes(data)
+                }
+                Cbor::Major4(_, mut list) => {
+                    self.stack.append(&mut list);
+                    self.pool.put_list(list);
+                }
+                Cbor::Major5(_, mut map, _) => {
+                    for (key, val) in map.drain(..) {
+                        match key {
+                            Key::Bytes(data) => self.pool.put_bytes(data),
+                            Key::Text(text) => self.pool.put_bytes(text.into_bytes()),
+                            _ => (),
+                        }
+                        self.stack.push(val);
+                    }
+                    self.pool.put_map(map);
+                }
+                Cbor::Major6(_, tag) => self.stack.push(tag.into_content()),
+                _ => (),
+            }
+        }
+    }
+
+    /// Release the recycled storage held by this buffer.
+    pub fn clear(&mut self) {
+        self.pool = Pool::default();
+        self.stack = Vec::default();
+    }
+}
+
+// Vectors available for reuse while decoding, refer to [CborBuffer]. Empty
+// pool allocates afresh. Byte vectors are bucketed by capacity, bucket `b`
+// holding vectors with capacity in `2^b..2^(b+1)`.
+#[derive(Default)]
+pub(crate) struct Pool {
+    bytes: Vec<Vec<Vec<u8>>>,
+    lists: Vec<Vec<Cbor>>,
+    maps: Vec<Vec<(Key, Cbor)>>,
+}
+
+impl Pool {
+    // Return a byte vector of `len` zeros. Recycled vectors are reused if
+    // their capacity is within `len..4*len`, without reallocating.
+    pub(crate) fn bytes(&mut self, len: usize) -> Vec<u8> {
+        let b = bucket(cmp::max(len, 1));
+        let data = self.bytes.get_mut(b).and_then(|bucket| {
+            let off = bucket.iter().position(|data| data.capacity() >= len)?;
+            Some(bucket.swap_remove(off))
+        });
+        let data = match data {
+            Some(data) => Some(data),
+            None => self.bytes.get_mut(b + 1).and_then(|bucket| bucket.pop()),
+        };
+        match data {
+            Some(mut data) => {
+                data.clear();
+                data.resize(len, 0);
+                data
+            }
+            None => vec![0; len],
+        }
+    }
+
+    pub(crate) fn list(&mut self) -> Vec<Cbor> {
+        self.lists.pop().unwrap_or_default()
+    }
+
+    pub(crate) fn map(&mut self) -> Vec<(Key, Cbor)> {
+        self.maps.pop().unwrap_or_default()
+    }
+
+    // Vectors without capacity hold no storage worth reusing.
+    pub(crate) fn put_bytes(&mut self, data: Vec<u8>) {
+        if data.capacity() > 0 {
+            let b = bucket(data.capacity());
+            if self.bytes.len() <= b {
+                self.bytes.resize_with(b + 1, Vec::default)
+            }
+            self.bytes[b].push(data)
+        }
+    }
+
+    fn put_list(&mut self, list: Vec<Cbor>) {
+        if list.capacity() > 0 {
+            self.lists.push(list)
+        }
+    }
+
+    fn put_map(&mut self, map: Vec<(Key, Cbor)>) {
+        if map.capacity() > 0 {
+            self.maps.push(map)
+        }
+    }
+}
+
+// Bucket for vectors with capacity `n`, that is `log2(n)`, for `n > 0`.
+fn bucket(n: usize) -> usize {
+    (usize::BITS - 1 - n.leading_zeros()) as usize
+}
+
+#[cfg(test)]
+#[path = "buffer_test.rs"]
+mod buffer_test;
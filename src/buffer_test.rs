@@ -0,0 +1,124 @@
+use super::*;
+use crate::{Error, IntoCbor, Tag};
+
+fn sample(i: u64) -> Cbor {
+    let map = Cbor::map_from(vec![
+        ("name".into_cbor().unwrap(), format!("item-{}", i).into_cbor().unwrap()),
+        (i.into_cbor().unwrap(), Cbor::from_bytes(vec![i as u8; 16]).unwrap()),
+    ])
+    .unwrap();
+    let items = vec![map, vec![i; 4].into_cbor().unwrap(), Cbor::null()];
+    Tag::from_identifier(items.into_cbor().unwrap()).into()
+}
+
+#[test]
+fn test_cbor_buffer() {
+    let mut buf = vec![];
+    for i in 0..100 {
+        sample(i).encode_into(&mut buf).unwrap();
+    }
+
+    let mut buffer = CborBuffer::new();
+    let mut r = buf.as_slice();
+    for i in 0..100 {
+        let (val, n) = buffer.decode(&mut r).unwrap();
+        assert_eq!(val, sample(i));
+        assert_eq!(n, sample(i).encoded_len().unwrap());
+        buffer.recycle(val);
+    }
+    assert!(r.is_empty());
+    assert!(buffer.decode(&mut r).is_err());
+}
+
+#[test]
+fn test_cbor_buffer_reuse() {
+    let mut buf = vec![];
+    Cbor::from_bytes(vec![1; 64]).unwrap().encode(&mut buf).unwrap();
+    vec![1_u64; 64].into_cbor().unwrap().encode(&mut buf).unwrap();
+
+    let mut buffer = CborBuffer::new();
+    let (val, _) = buffer.decode(&mut buf.as_slice()).unwrap();
+    let ptr = match &val {
+        Cbor::Major2(_, data) => data.as_ptr(),
+        val => panic!("{:?}", val),
+    };
+    buffer.recycle(val);
+    // "abc", is too small to reuse the storage of previous byte-string.
+    let (val, _) = buffer.decode(&mut [0x63, 0x61, 0x62, 0x63].as_ref()).unwrap();
+    match &val {
+        Cbor::Major3(_, text) => {
+            assert_eq!(text, b"abc");
+            assert_ne!(text.as_ptr(), ptr);
+            assert!(text.capacity() < 64);
+        }
+        val => panic!("{:?}", val),
+    }
+    // text of 40 bytes, shall reuse the storage of previous byte-string.
+    let mut text = vec![0x78, 40];
+    text.extend_from_slice(&[0x61; 40]);
+    let (val, _) = buffer.decode(&mut text.as_slice()).unwrap();
+    match &val {
+        Cbor::Major3(_, text) => {
+            assert_eq!(text, &[0x61; 40].to_vec());
+            assert_eq!(text.as_ptr(), ptr);
+        }
+        val => panic!("{:?}", val),
+    }
+
+    let (val, _) = buffer.decode(&mut &buf[buf.len() - 66..]).unwrap();
+    let (ptr, cap) = match &val {
+        Cbor::Major4(_, list) => (list.as_ptr(), list.capacity()),
+        val => panic!("{:?}", val),
+    };
+    buffer.recycle(val);
+    let (val, _) = buffer.decode(&mut [0x82, 0x01, 0x02].as_ref()).unwrap();
+    match &val {
+        Cbor::Major4(_, list) => {
+            assert_eq!(list.as_ptr(), ptr);
+            assert_eq!(list.capacity(), cap);
+        }
+        val => panic!("{:?}", val),
+    }
+
+    buffer.clear();
+    let (val, _) = buffer.decode(&mut [0x82, 0x01, 0x02].as_ref()).unwrap();
+    assert_eq!(val, vec![1_u64, 2].into_cbor().unwrap());
+}
+
+#[test]
+fn test_cbor_buffer_config() {
+    let config = DecodeConfig { max_bytes: Some(4), ..DecodeConfig::default() };
+    let mut buffer = CborBuffer::with_config(config);
+    buffer.recycle(Cbor::from_bytes(vec![0; 32]).unwrap());
+    match buffer.decode(&mut [0x45, 1, 2, 3, 4, 5].as_ref()) {
+        Err(Error::LimitExceeded(_, _)) => (),
+        res => panic!("{:?}", res),
+    }
+    // indefinite byte-string, with two chunks.
+    let buf = [0x5f, 0x42, 0x01, 0x02, 0x42, 0x03, 0x04, 0xff];
+    let (val, n) = buffer.decode(&mut buf.as_ref()).unwrap();
+    assert_eq!(n, buf.len());
+    assert_eq!(val, Cbor::decode(&mut buf.as_ref()).unwrap().0);
+}
+
+#[test]
+fn test_cbor_buffer_sizes() {
+    let big = Cbor::from_bytes(vec![0xab; 1 << 20]).unwrap();
+    let mut buf = vec![];
+    big.encode(&mut buf).unwrap();
+
+    let mut buffer = CborBuffer::new();
+    buffer.recycle(big);
+    // small byte-string shall not be backed by the recycled megabyte.
+    let (val, _) = buffer.decode(&mut [0x43, 0x01, 0x02, 0x03].as_ref()).unwrap();
+    match &val {
+        Cbor::Major2(_, data) => assert!(data.capacity() < 16, "{}", data.capacity()),
+        val => panic!("{:?}", val),
+    }
+    // large byte-string shall reuse it.
+    let (val, _) = buffer.decode(&mut buf.as_slice()).unwrap();
+    match &val {
+        Cbor::Major2(_, data) => assert_eq!(data.capacity(), 1 << 20),
+        val => panic!("{:?}", val),
+    }
+}
@@ -2,10 +2,10 @@
 use arbitrary::{Arbitrary, Unstructured};
 use num_bigint::{BigInt, Sign};
 
-use crate::{Error, FromCbor, IntoCbor, Result};
+use crate::{buffer::Pool, Error, FromCbor, IntoCbor, Result};
 
 use std::convert::{TryFrom, TryInto};
-use std::{cmp, io};
+use std::{cmp, collections::BTreeMap, io, mem, result};
 
 macro_rules! read_r {
     ($r:ident, $buf:expr) => {
@@ -15,28 +15,61 @@ macro_rules! read_r {
 
 macro_rules! write_w {
     ($w:ident, $buf:expr) => {
-        err_at!(IOError, $w.write($buf))?
+        err_at!(IOError, $w.write_all($buf))?
     };
 }
 
+// Terminates indefinite length items.
+pub(crate) const BREAK: u8 = 0xff;
+
 /// Recursion limit for nested Cbor objects.
+///
+/// Arrays, maps and tags count towards this limit, both while encoding and
+/// decoding. Note that each level of nesting consumes stack, unoptimized
+/// builds may need more than the default thread stack to nest upto this
+/// limit.
 pub const RECURSION_LIMIT: u32 = 1000;
 
 /// Cbor type enumerated over its major variants.
 ///
 /// Use one of the conversion trait to convert language-native-type to a
 /// Cbor variant. For lazy decoding, use [Cbor::Binary] variant.
+///
+/// Arrays and maps are held in `Vec`, as part of the public variants, hence
+/// small collections are not stored inline. To cut allocations while
+/// decoding many small values, refer to [CborBuffer][crate::CborBuffer].
+///
+/// Maps carry a [MapOrder], recording whether their entries are known to be
+/// sorted by key, refer to [Cbor::map_get]. Maps compare equal irrespective
+/// of their [MapOrder].
+///
+/// Bytes held by [Cbor::Binary] are supplied by the application, they are
+/// decoded with the default [DecodeConfig], when an operation like
+/// [Cbor::encode_canonical], [Cbor::normalize] or [crate::diagnostic]
+/// needs the decoded value.
+///
+/// Cbor values can be compared with native integers, floats, booleans and
+/// strings, like `val == 10_u64` or `val == "hello"`, ignoring the encoding
+/// width recorded in [Info]:
+///
+/// * Integers are equal to major type 0 and 1 values of the same numeric
+///   value, irrespective of the integer type, that is `val == 10_u8` and
+///   `val == 10_i64` agree. Big-nums are not compared.
+/// * Floats are equal to half, single and double precision values of the
+///   same numeric value, NaN is never equal and `-0.0 == 0.0`.
+/// * Integers are never equal to floats, `1_u64` is not equal to `1.0`.
+/// * Strings are equal to text-strings with the same bytes.
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum Cbor {
-    Major0(Info, u64),              // uint 0-23,24,25,26,27
-    Major1(Info, u64),              // nint 0-23,24,25,26,27
-    Major2(Info, Vec<u8>),          // byts 0-23,24,25,26,27,31
-    Major3(Info, Vec<u8>),          // text 0-23,24,25,26,27,31
-    Major4(Info, Vec<Cbor>),        // list 0-23,24,25,26,27,31
-    Major5(Info, Vec<(Key, Cbor)>), // dict 0-23,24,25,26,27,31
-    Major6(Info, Tag),              // tags similar to major0
-    Major7(Info, SimpleValue),      // type refer SimpleValue
-    Binary(Vec<u8>),                // for lazy decoding cbor data
+    Major0(Info, u64),                        // uint 0-23,24,25,26,27
+    Major1(Info, u64),                        // nint 0-23,24,25,26,27
+    Major2(Info, Vec<u8>),                    // byts 0-23,24,25,26,27,31
+    Major3(Info, Vec<u8>),                    // text 0-23,24,25,26,27,31
+    Major4(Info, Vec<Cbor>),                  // list 0-23,24,25,26,27,31
+    Major5(Info, Vec<(Key, Cbor)>, MapOrder), // dict 0-23,24,25,26,27,31
+    Major6(Info, Tag),                        // tags similar to major0
+    Major7(Info, SimpleValue),                // type refer SimpleValue
+    Binary(Vec<u8>),                          // for lazy decoding cbor data
 }
 
 #[cfg(any(feature = "arbitrary", test))]
@@ -81,7 +114,8 @@ impl<'a> Arbitrary<'a> for Cbor {
                 let val: Vec<(Key, Cbor)> = u.arbitrary()?;
                 let n: u64 = val.len().try_into().unwrap();
                 let info: Info = n.into();
-                Major5(info, val)
+                let order = MapOrder::of(&val);
+                Major5(info, val, order)
             }
             6 => {
                 let tag: Tag = u.arbitrary()?;
@@ -99,7 +133,7 @@ impl<'a> Arbitrary<'a> for Cbor {
 }
 
 impl Cbor {
-    fn pretty_print(&self, p: &str) -> Result<String> {
+    fn pretty_print(&self, p: &str, indent: &str) -> Result<String> {
         use std::str::from_utf8;
 
         let s = match self {
@@ -116,27 +150,27 @@ impl Cbor {
             }
             Cbor::Major4(_info, vals) => {
                 let mut ss = vec![format!("{}List({})", p, vals.len())];
-                let p = p.to_owned() + "  ";
+                let p = p.to_owned() + indent;
                 for val in vals.iter() {
-                    ss.push(val.pretty_print(&p)?);
+                    ss.push(val.pretty_print(&p, indent)?);
                 }
                 ss.join("\n")
             }
-            Cbor::Major5(_info, vals) => {
+            Cbor::Major5(_info, vals, _) => {
                 let mut ss = vec![format!("{}Dict({})", p, vals.len())];
-                let p = p.to_owned() + "  ";
+                let p = p.to_owned() + indent;
                 for (key, val) in vals.iter() {
                     ss.push(key.pretty_print()?);
-                    ss.push(val.pretty_print(&p)?);
+                    ss.push(val.pretty_print(&p, indent)?);
                 }
                 ss.join("\n")
             }
-            Cbor::Major6(_info, val) => format!("{}{}", p, val.pretty_print(p)?),
+            Cbor::Major6(_info, val) => format!("{}{}", p, val.pretty_print(p, indent)?),
             Cbor::Major7(info, val) => {
                 format!("{}Maj7({},{})", p, info.pretty_print()?, val.pretty_print()?)
             }
             Cbor::Binary(bytes) => {
-                Cbor::decode(&mut bytes.as_slice())?.0.pretty_print(p)?
+                Cbor::decode(&mut bytes.as_slice())?.0.pretty_print(p, indent)?
             }
         };
 
@@ -146,6 +180,13 @@ impl Cbor {
 
 impl Cbor {
     /// Serialize this cbor value.
+    ///
+    /// Integers, lengths, tag numbers and floats are encoded in the width
+    /// recorded by their [Info], hence decoded values are re-encoded to the
+    /// exact bytes received, even when not in the shortest form. Except for
+    /// map keys, which are held as [Key] and encoded in the shortest form,
+    /// and chunks of indefinite length strings, which are re-encoded as a
+    /// single chunk. Refer to [Cbor::encode_canonical] to ignore the widths.
     pub fn encode<W>(&self, w: &mut W) -> Result<usize>
     where
         W: io::Write,
@@ -153,7 +194,70 @@ impl Cbor {
         self.do_encode(w, 1)
     }
 
-    fn do_encode<W>(&self, w: &mut W, depth: u32) -> Result<usize>
+    /// Serialize this cbor value into `out`, return the number of bytes written.
+    /// Fail with [Error::FailCbor] if `out` is too small to hold the encoded
+    /// value, use [Cbor::encoded_len] to size the buffer.
+    pub fn encode_slice(&self, out: &mut [u8]) -> Result<usize> {
+        let n = self.encoded_len()?;
+        if n > out.len() {
+            err_at!(FailCbor, msg: "buffer too small {} < {}", out.len(), n)?
+        }
+        self.do_encode(&mut &mut out[..n], 1)
+    }
+
+    /// Return the number of bytes needed to encode this value.
+    pub fn encoded_len(&self) -> Result<usize> {
+        self.do_encode(&mut io::sink(), 1)
+    }
+
+    /// Serialize this cbor value, appending to `buf`, return the number of
+    /// bytes appended. Reuse the same buffer to encode a batch of values
+    /// without allocating per value. On error, `buf` is restored to its
+    /// original length.
+    pub fn encode_into(&self, buf: &mut Vec<u8>) -> Result<usize> {
+        let n = buf.len();
+        match self.do_encode(buf, 1) {
+            Ok(m) => Ok(m),
+            Err(err) => {
+                buf.truncate(n);
+                Err(err)
+            }
+        }
+    }
+
+    /// Serialize this cbor value in deterministic, canonical, form as per
+    /// RFC-8949 section 4.2.1, refer to [is_canonical] for the rules. The
+    /// value's [Info] is ignored while encoding in canonical form, that is,
+    /// integers and lengths are encoded in the shortest form and indefinite
+    /// length items are encoded with definite length. Map keys are sorted
+    /// by their encoded bytes, duplicate keys fail with [Error::FailCbor].
+    /// [Cbor::Binary] values are decoded and re-encoded in canonical form.
+    pub fn encode_canonical<W>(&self, w: &mut W) -> Result<usize>
+    where
+        W: io::Write,
+    {
+        self.do_encode_canonical(w, 1)
+    }
+
+    /// Return the number of bytes needed to encode this value in canonical
+    /// form, refer to [Cbor::encode_canonical].
+    pub fn encoded_len_canonical(&self) -> Result<usize> {
+        self.do_encode_canonical(&mut io::sink(), 1)
+    }
+
+    /// Check whether this value, as encoded by [Cbor::encode], is in
+    /// deterministic, canonical, form, refer to [is_canonical] for the rules.
+    /// Map keys don't preserve the width of their encoding, hence a decoded
+    /// value can be reported canonical though its bytes are not, use
+    /// [is_canonical] on the bytes to validate incoming documents. Use
+    /// [Cbor::encode_canonical] to re-encode non-canonical values.
+    pub fn is_canonical(&self) -> Result<bool> {
+        let mut buf = vec![];
+        self.do_encode(&mut buf, 1)?;
+        is_canonical(&buf)
+    }
+
+    fn do_encode_canonical<W>(&self, w: &mut W, depth: u32) -> Result<usize>
     where
         W: io::Write,
     {
@@ -163,53 +267,159 @@ impl Cbor {
 
         let major = self.to_major_val();
         let n = match self {
-            Cbor::Major0(info, num) => {
-                let n = encode_hdr(major, *info, w)?;
-                n + encode_addnl(*num, w)?
+            Cbor::Major0(_, num) | Cbor::Major1(_, num) => {
+                encode_hdr(major, (*num).into(), w)? + encode_addnl(*num, w)?
             }
-            Cbor::Major1(info, num) => {
-                let n = encode_hdr(major, *info, w)?;
-                n + encode_addnl(*num, w)?
+            Cbor::Major2(_, data) | Cbor::Major3(_, data) => {
+                let len = err_at!(FailConvert, u64::try_from(data.len()))?;
+                let n = encode_hdr(major, len.into(), w)? + encode_addnl(len, w)?;
+                write_w!(w, data);
+                n + data.len()
+            }
+            Cbor::Major4(_, list) => {
+                let len = err_at!(FailConvert, u64::try_from(list.len()))?;
+                let mut n = encode_hdr(major, len.into(), w)? + encode_addnl(len, w)?;
+                for x in list.iter() {
+                    n += x.do_encode_canonical(w, depth + 1)?;
+                }
+                n
+            }
+            Cbor::Major5(_, map, _) => {
+                let mut items: Vec<(Vec<u8>, &Cbor)> = Vec::with_capacity(map.len());
+                for (key, val) in map.iter() {
+                    let mut buf = vec![];
+                    key.clone().into_cbor()?.do_encode_canonical(&mut buf, depth + 1)?;
+                    items.push((buf, val));
+                }
+                items.sort_by(|a, b| a.0.cmp(&b.0));
+                if let Some(k) = items.windows(2).position(|x| x[0].0 == x[1].0) {
+                    err_at!(FailCbor, msg: "duplicate map key {:?}", items[k].0)?
+                }
+
+                let len = err_at!(FailConvert, u64::try_from(items.len()))?;
+                let mut n = encode_hdr(major, len.into(), w)? + encode_addnl(len, w)?;
+                for (key, val) in items.into_iter() {
+                    write_w!(w, &key);
+                    n += key.len() + val.do_encode_canonical(w, depth + 1)?;
+                }
+                n
+            }
+            Cbor::Major6(_, tag) => {
+                // encode the chain of tags in a loop, refer to Tag::encode.
+                let (mut tag, mut depth, mut n) = (tag, depth, 0);
+                loop {
+                    let num = tag.to_tag_value();
+                    n += encode_hdr(major, num.into(), w)? + encode_addnl(num, w)?;
+                    depth += 1;
+                    match tag.as_content() {
+                        Cbor::Major6(_, _) if depth > RECURSION_LIMIT => {
+                            err_at!(FailCbor, msg: "encode recursion limit exceeded")?
+                        }
+                        Cbor::Major6(_, ttag) => tag = ttag,
+                        val => break n + val.do_encode_canonical(w, depth)?,
+                    }
+                }
+            }
+            Cbor::Major7(_, sval) => {
+                let sval = match sval {
+                    SimpleValue::F16(val) if f16_is_nan(*val) => SimpleValue::F16(0x7e00),
+                    SimpleValue::F32(val) => canonical_float((*val).into()),
+                    SimpleValue::F64(val) => canonical_float(*val),
+                    sval => *sval,
+                };
+                match sval.into_cbor()? {
+                    Cbor::Major7(info, sval) => {
+                        encode_hdr(major, info, w)? + SimpleValue::encode(&sval, w)?
+                    }
+                    _ => unreachable!(),
+                }
+            }
+            Cbor::Binary(data) => decode_exact(data)?.do_encode_canonical(w, depth)?,
+        };
+
+        Ok(n)
+    }
+
+    fn do_encode<W>(&self, w: &mut W, depth: u32) -> Result<usize>
+    where
+        W: io::Write,
+    {
+        if depth > RECURSION_LIMIT {
+            return err_at!(FailCbor, msg: "encode recursion limit exceeded");
+        }
+
+        let major = self.to_major_val();
+        let n = match self {
+            Cbor::Major0(info, num) => encode_num(major, *info, *num, w)?,
+            Cbor::Major1(info, num) => encode_num(major, *info, *num, w)?,
+            Cbor::Major2(Info::Indefinite, byts) => {
+                // chunk boundaries are not preserved, re-emit as single chunk.
+                let n = encode_hdr(major, Info::Indefinite, w)?;
+                let len = err_at!(FailConvert, u64::try_from(byts.len()))?;
+                let m = encode_num(major, len.into(), len, w)?;
+                write_w!(w, byts);
+                write_w!(w, &[BREAK]);
+                n + m + byts.len() + 1
             }
             Cbor::Major2(info, byts) => {
-                let n = encode_hdr(major, *info, w)?;
-                let m =
-                    encode_addnl(err_at!(FailConvert, u64::try_from(byts.len()))?, w)?;
+                let len = err_at!(FailConvert, u64::try_from(byts.len()))?;
+                let n = encode_num(major, *info, len, w)?;
                 write_w!(w, byts);
-                n + m + byts.len()
+                n + byts.len()
+            }
+            Cbor::Major3(Info::Indefinite, text) => {
+                let n = encode_hdr(major, Info::Indefinite, w)?;
+                let len = err_at!(FailConvert, u64::try_from(text.len()))?;
+                let m = encode_num(major, len.into(), len, w)?;
+                write_w!(w, text);
+                write_w!(w, &[BREAK]);
+                n + m + text.len() + 1
             }
             Cbor::Major3(info, text) => {
-                let n = encode_hdr(major, *info, w)?;
-                let m = encode_addnl(err_at!(FailCbor, u64::try_from(text.len()))?, w)?;
+                let len = err_at!(FailCbor, u64::try_from(text.len()))?;
+                let n = encode_num(major, *info, len, w)?;
                 write_w!(w, text);
-                n + m + text.len()
+                n + text.len()
             }
             Cbor::Major4(info, list) => {
-                let n = encode_hdr(major, *info, w)?;
-                let m =
-                    encode_addnl(err_at!(FailConvert, u64::try_from(list.len()))?, w)?;
+                let n = match info {
+                    Info::Indefinite => encode_hdr(major, *info, w)?,
+                    _ => {
+                        let len = err_at!(FailConvert, u64::try_from(list.len()))?;
+                        encode_num(major, *info, len, w)?
+                    }
+                };
                 let mut acc = 0;
                 for x in list.iter() {
                     acc += x.do_encode(w, depth + 1)?;
                 }
-                n + m + acc
+                if let Info::Indefinite = info {
+                    write_w!(w, &[BREAK]);
+                    acc += 1;
+                }
+                n + acc
             }
-            Cbor::Major5(info, map) => {
-                let n = encode_hdr(major, *info, w)?;
-                let m = encode_addnl(err_at!(FailConvert, u64::try_from(map.len()))?, w)?;
+            Cbor::Major5(info, map, _) => {
+                let n = match info {
+                    Info::Indefinite => encode_hdr(major, *info, w)?,
+                    _ => {
+                        let len = err_at!(FailConvert, u64::try_from(map.len()))?;
+                        encode_num(major, *info, len, w)?
+                    }
+                };
                 let mut acc = 0;
                 for (key, val) in map.iter() {
                     let key = key.clone().into_cbor()?;
                     acc += key.do_encode(w, depth + 1)?;
                     acc += val.do_encode(w, depth + 1)?;
                 }
-                n + m + acc
-            }
-            Cbor::Major6(info, tag) => {
-                let n = encode_hdr(major, *info, w)?;
-                let m = Tag::encode(tag, w)?;
-                n + m
+                if let Info::Indefinite = info {
+                    write_w!(w, &[BREAK]);
+                    acc += 1;
+                }
+                n + acc
             }
+            Cbor::Major6(info, tag) => Tag::encode(*info, tag, w, depth)?,
             Cbor::Major7(info, sval) => {
                 let n = encode_hdr(major, *info, w)?;
                 let m = SimpleValue::encode(sval, w)?;
@@ -230,18 +440,102 @@ impl Cbor {
     where
         R: io::Read,
     {
-        Cbor::do_decode(r, 1)
+        Cbor::decode_with(r, &DecodeConfig::default())
     }
 
-    fn do_decode<R>(reader: &mut R, depth: u32) -> Result<(Cbor, usize)>
+    /// Same as [Cbor::decode], with configuration to control the decoding.
+    pub fn decode_with<R>(r: &mut R, config: &DecodeConfig) -> Result<(Cbor, usize)>
     where
         R: io::Read,
     {
-        if depth > RECURSION_LIMIT {
-            return err_at!(FailCbor, msg: "decode recursion limt exceeded");
+        Cbor::decode_root(r, config, &mut DecodeState::default())
+    }
+
+    /// Decode a cbor value from `buf`, advancing `buf` past the value.
+    ///
+    /// This is a convenience wrapper over [Cbor::decode], byte-strings and
+    /// text-strings are copied into the decoded value. To decode without
+    /// copying, use [CborRef::decode][crate::CborRef::decode] on the `Bytes`
+    /// and [CborRef::to_shared_bytes][crate::CborRef::to_shared_bytes] to get
+    /// strings as `Bytes` slices of it, where strings of indefinite length
+    /// are still copied while joining their chunks.
+    ///
+    /// When the remaining data is contiguous, as with `Bytes` and `BytesMut`,
+    /// `buf` is advanced only if decoding succeeds. Otherwise, like with
+    /// chained buffers, the bytes read before failing are consumed.
+    #[cfg(feature = "bytes")]
+    pub fn decode_bytes<B: bytes::Buf>(buf: &mut B) -> Result<Cbor> {
+        if buf.chunk().len() == buf.remaining() {
+            let (val, n) = Cbor::decode(&mut buf.chunk())?;
+            buf.advance(n);
+            Ok(val)
+        } else {
+            Ok(Cbor::decode(&mut bytes::Buf::reader(buf))?.0)
+        }
+    }
+
+    /// Same as [Cbor::decode], additionally return metrics on the shape of
+    /// the decoded value, refer to [DecodeMetrics].
+    pub fn decode_with_metrics<R>(r: &mut R) -> Result<(Cbor, DecodeMetrics)>
+    where
+        R: io::Read,
+    {
+        let mut state = DecodeState::default();
+        let (val, n) = Cbor::decode_root(r, &DecodeConfig::default(), &mut state)?;
+        state.metrics.bytes_consumed = n;
+        Ok((val, state.metrics))
+    }
+
+    // Decode a top-level value. Values embedded under tag 24 are decoded
+    // after the enclosing value, one after the other, so that a chain of
+    // embedded values does not recurse for every embedding.
+    pub(crate) fn decode_root<R>(
+        r: &mut R,
+        config: &DecodeConfig,
+        state: &mut DecodeState,
+    ) -> Result<(Cbor, usize)>
+    where
+        R: io::Read,
+    {
+        let res = Cbor::do_decode(r, 1, 0, config, state)?;
+        while let Some((data, depth, off)) = state.embedded.pop() {
+            decode_embedded(&data, depth, off, config, state)?;
+            state.pool.put_bytes(data);
         }
+        Ok(res)
+    }
+
+    // `off` is the offset, from beginning of the top-level value, of the value
+    // being decoded.
+    pub(crate) fn do_decode<R>(
+        reader: &mut R,
+        depth: u32,
+        off: usize,
+        config: &DecodeConfig,
+        state: &mut DecodeState,
+    ) -> Result<(Cbor, usize)>
+    where
+        R: io::Read,
+    {
+        check_depth(depth, config)?;
+        let hdr = decode_hdr(reader)?;
+        Cbor::decode_item(hdr, reader, depth, off, config, state)
+    }
 
-        let (major, info, n) = decode_hdr(reader)?;
+    // Decode the value whose header, `hdr`, is already read from `reader`,
+    // refer to [Cbor::do_decode].
+    fn decode_item<R>(
+        hdr: (u8, Info, usize),
+        reader: &mut R,
+        depth: u32,
+        off: usize,
+        config: &DecodeConfig,
+        state: &mut DecodeState,
+    ) -> Result<(Cbor, usize)>
+    where
+        R: io::Read,
+    {
+        let (major, info, n) = hdr;
 
         let (val, m) = match (major, info) {
             (0, info) => {
@@ -253,121 +547,169 @@ impl Cbor {
                 (Cbor::Major1(info, val), m)
             }
             (2, Info::Indefinite) => {
-                let mut data: Vec<u8> = Vec::default();
+                let mut data: Vec<u8> = state.pool.bytes(0);
                 let mut m = 0_usize;
                 loop {
-                    let (val, k) = Cbor::do_decode(reader, depth + 1)?;
+                    let (val, k) =
+                        Cbor::do_decode(reader, depth + 1, off + n + m, config, state)?;
+                    m += k;
                     match val {
-                        Cbor::Major2(_, chunk) => data.extend_from_slice(&chunk),
+                        Cbor::Major2(_, chunk) => {
+                            data.extend_from_slice(&chunk);
+                            state.pool.put_bytes(chunk);
+                        }
                         Cbor::Major7(_, SimpleValue::Break) => break,
                         _ => err_at!(FailConvert, msg: "expected byte chunk")?,
                     }
-                    m += k;
+                    check_string_len(data.len(), off, config)?;
                 }
                 (Cbor::Major2(info, data), m)
             }
             (2, info) => {
                 let (val, m) = decode_addnl(info, reader)?;
                 let len: usize = err_at!(FailConvert, val.try_into())?;
-                let mut data = vec![0; len];
+                check_string_len(len, off, config)?;
+                let mut data = state.pool.bytes(len);
                 read_r!(reader, &mut data);
                 (Cbor::Major2(info, data), m + len)
             }
             (3, Info::Indefinite) => {
-                let mut text: Vec<u8> = Vec::default();
+                // chunks are decoded as is, lossy_text applies to the joined
+                // text, except for strict validation of each chunk.
+                let unchecked;
+                let cf = match config.lossy_text {
+                    LossyText::Unchecked => config,
+                    _ => {
+                        let lossy_text = LossyText::Unchecked;
+                        unchecked = DecodeConfig { lossy_text, ..config.clone() };
+                        &unchecked
+                    }
+                };
+                let mut text: Vec<u8> = state.pool.bytes(0);
                 let mut m = 0_usize;
                 loop {
-                    let (val, k) = Cbor::do_decode(reader, depth + 1)?;
+                    let coff = off + n + m;
+                    let (val, k) = Cbor::do_decode(reader, depth + 1, coff, cf, state)?;
+                    m += k;
                     match val {
-                        Cbor::Major3(_, chunk) => text.extend_from_slice(&chunk),
+                        Cbor::Major3(_, chunk) => {
+                            if config.lossy_text == LossyText::Strict
+                                && std::str::from_utf8(&chunk).is_err()
+                            {
+                                err_at!(FailCbor, msg: "invalid utf8 text at offset {}", coff)?
+                            }
+                            text.extend_from_slice(&chunk);
+                            state.pool.put_bytes(chunk);
+                        }
                         Cbor::Major7(_, SimpleValue::Break) => break,
-                        _ => err_at!(FailConvert, msg: "expected byte chunk")?,
+                        _ => err_at!(FailConvert, msg: "expected text chunk")?,
                     }
-                    m += k;
+                    check_string_len(text.len(), off, config)?;
                 }
-                (Cbor::Major3(info, text), m)
+                (lossy_text(info, text, off, config)?, m)
             }
             (3, info) => {
                 let (val, m) = decode_addnl(info, reader)?;
                 let len: usize = err_at!(FailConvert, val.try_into())?;
-                let mut text = vec![0; len];
+                check_string_len(len, off, config)?;
+                let mut text = state.pool.bytes(len);
                 read_r!(reader, &mut text);
-                (Cbor::Major3(info, text), m + len)
+                (lossy_text(info, text, off, config)?, m + len)
             }
             (4, Info::Indefinite) => {
-                let mut list: Vec<Cbor> = vec![];
+                let mut list: Vec<Cbor> = state.pool.list();
                 let mut m = 0_usize;
                 loop {
-                    let (val, k) = Cbor::do_decode(reader, depth + 1)?;
+                    let (val, k) =
+                        Cbor::do_decode(reader, depth + 1, off + n + m, config, state)?;
+                    m += k;
                     match val {
                         Cbor::Major7(_, SimpleValue::Break) => break,
                         item => list.push(item),
                     }
-                    m += k;
                 }
                 (Cbor::Major4(info, list), m)
             }
             (4, info) => {
-                let mut list: Vec<Cbor> = vec![];
+                let mut list: Vec<Cbor> = state.pool.list();
                 let (len, mut m) = decode_addnl(info, reader)?;
                 for _ in 0..len {
-                    let (val, k) = Cbor::do_decode(reader, depth + 1)?;
+                    let (val, k) =
+                        Cbor::do_decode(reader, depth + 1, off + n + m, config, state)?;
                     list.push(val);
                     m += k;
                 }
                 (Cbor::Major4(info, list), m)
             }
             (5, Info::Indefinite) => {
-                let mut map: Vec<(Key, Cbor)> = Vec::default();
+                let mut map: Vec<(Key, Cbor)> = state.pool.map();
                 let mut m = 0_usize;
                 loop {
-                    let (key, j) = Cbor::do_decode(reader, depth + 1)?;
-                    let (val, k) = Cbor::do_decode(reader, depth + 1)?;
-                    let val = match val {
+                    let (key, j) =
+                        Cbor::do_decode(reader, depth + 1, off + n + m, config, state)?;
+                    m += j;
+                    let key = match key {
                         Cbor::Major7(_, SimpleValue::Break) => break,
-                        val => val,
+                        key => key,
                     };
+                    let (val, k) =
+                        Cbor::do_decode(reader, depth + 1, off + n + m, config, state)?;
                     map.push((Key::from_cbor(key)?, val));
-                    m += j + k;
+                    m += k;
                 }
-                (Cbor::Major5(info, map), m)
+                (decoded_map(info, map, off, config)?, m)
             }
             (5, info) => {
-                let mut map: Vec<(Key, Cbor)> = Vec::default();
+                let mut map: Vec<(Key, Cbor)> = state.pool.map();
                 let (len, mut m) = decode_addnl(info, reader)?;
                 for _ in 0..len {
-                    let (key, j) = Cbor::do_decode(reader, depth + 1)?;
-                    let (val, k) = Cbor::do_decode(reader, depth + 1)?;
+                    let (key, j) =
+                        Cbor::do_decode(reader, depth + 1, off + n + m, config, state)?;
+                    let (val, k) = Cbor::do_decode(
+                        reader,
+                        depth + 1,
+                        off + n + m + j,
+                        config,
+                        state,
+                    )?;
                     map.push((Key::from_cbor(key)?, val));
                     m += j + k;
                 }
-                (Cbor::Major5(info, map), m)
-            }
-            (6, info) => {
-                let (tag, m) = Tag::decode(info, reader)?;
-                (Cbor::Major6(info, tag), m)
+                (decoded_map(info, map, off, config)?, m)
             }
+            (6, info) => Tag::decode(info, reader, depth, off, n, config, state)?,
             (7, info) => {
                 let (sval, m) = SimpleValue::decode(info, reader)?;
-                (Cbor::Major7(info, sval), m)
+                if config.reject_nonfinite_floats && !sval.is_finite() {
+                    err_at!(FailCbor, msg: "non-finite float at offset {}", off)?
+                }
+                let val = match sval {
+                    SimpleValue::True if config.legacy_bools => Cbor::bool(false),
+                    SimpleValue::False if config.legacy_bools => Cbor::bool(true),
+                    sval => Cbor::Major7(info, sval),
+                };
+                (val, m)
             }
             _ => unreachable!(),
         };
 
+        count_item(&val, depth, config, state)?;
+
         Ok((val, (m + n)))
     }
 
-    fn to_major_val(&self) -> u8 {
+    // Empty Binary value holds no item, its major type is taken as 0.
+    pub(crate) fn to_major_val(&self) -> u8 {
         match self {
             Cbor::Major0(_, _) => 0,
             Cbor::Major1(_, _) => 1,
             Cbor::Major2(_, _) => 2,
             Cbor::Major3(_, _) => 3,
             Cbor::Major4(_, _) => 4,
-            Cbor::Major5(_, _) => 5,
+            Cbor::Major5(_, _, _) => 5,
             Cbor::Major6(_, _) => 6,
             Cbor::Major7(_, _) => 7,
-            Cbor::Binary(data) => (data[0] & 0xe0) >> 5,
+            Cbor::Binary(data) => data.first().map(|b| (b & 0xe0) >> 5).unwrap_or(0),
         }
     }
 
@@ -383,6 +725,22 @@ impl Cbor {
         Ok(Cbor::Major2(n.into(), val))
     }
 
+    /// Convert a slice into Cbor major type-4 value, cloning each item,
+    /// without first collecting the slice into a `Vec<T>`. A blanket
+    /// `IntoCbor for &[T]` is not provided since `&[u8]` already converts
+    /// into Cbor bytes (Major type-2), refer to [Cbor::from_bytes].
+    pub fn from_slice<T>(val: &[T]) -> Result<Self>
+    where
+        T: Clone + IntoCbor,
+    {
+        let n = err_at!(FailConvert, u64::try_from(val.len()))?;
+        let mut arr = Vec::with_capacity(val.len());
+        for item in val.iter() {
+            arr.push(item.clone().into_cbor()?)
+        }
+        Ok(Cbor::Major4(n.into(), arr))
+    }
+
     /// This is converse of [Cbor::from_bytes].
     pub fn into_bytes(self) -> Result<Vec<u8>> {
         match self {
@@ -390,142 +748,1053 @@ impl Cbor {
             _ => err_at!(FailConvert, msg: "not bytes"),
         }
     }
-}
 
-/// 5-bit value for additional info. Refer to Cbor [spec] for details.
-///
-/// [spec]: https://tools.ietf.org/html/rfc7049
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
-pub enum Info {
-    /// additional info is in-lined.
-    Tiny(u8), // 0..=23
-    /// additional info of 8-bit unsigned integer.
-    U8,
-    /// additional info of 16-bit unsigned integer.
-    U16,
-    /// additional info of 32-bit unsigned integer.
-    U32,
-    /// additional info of 64-bit unsigned integer.
-    U64,
-    /// Reserved.
-    Reserved28,
-    /// Reserved.
-    Reserved29,
-    /// Reserved.
-    Reserved30,
-    /// Indefinite encoding.
-    Indefinite,
-}
+    /// Construct boolean simple-value.
+    pub fn bool(val: bool) -> Cbor {
+        match val {
+            false => Cbor::Major7(Info::Tiny(20), SimpleValue::False),
+            true => Cbor::Major7(Info::Tiny(21), SimpleValue::True),
+        }
+    }
 
-#[cfg(any(feature = "arbitrary", test))]
-impl<'a> Arbitrary<'a> for Info {
-    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
-        let tn = u.arbitrary::<u8>()? % 24;
-        Ok(*u.choose(&[
-            Info::Tiny(tn),
-            Info::U8,
-            Info::U16,
-            Info::U32,
-            Info::U64,
-            Info::Indefinite,
-        ])?)
+    /// Construct null simple-value.
+    pub fn null() -> Cbor {
+        Cbor::Major7(Info::Tiny(22), SimpleValue::Null)
     }
-}
 
-impl TryFrom<u8> for Info {
-    type Error = Error;
+    /// Construct undefined simple-value.
+    pub fn undefined() -> Cbor {
+        Cbor::Major7(Info::Tiny(23), SimpleValue::Undefined)
+    }
 
-    fn try_from(b: u8) -> Result<Info> {
-        let val = match b {
-            0..=23 => Info::Tiny(b),
-            24 => Info::U8,
-            25 => Info::U16,
-            26 => Info::U32,
-            27 => Info::U64,
-            28 => Info::Reserved28,
-            29 => Info::Reserved29,
-            30 => Info::Reserved30,
-            31 => Info::Indefinite,
-            _ => err_at!(Fatal, msg: "unreachable")?,
+    /// Construct simple-value `n`. Values 24..=31 are not valid simple values,
+    /// they are either reserved or used by floating point and break encoding,
+    /// fail with [Error::FailCbor].
+    pub fn simple(n: u8) -> Result<Cbor> {
+        let val = match n {
+            0..=19 => Cbor::Major7(Info::Tiny(n), SimpleValue::Unassigned),
+            20 => Cbor::bool(false),
+            21 => Cbor::bool(true),
+            22 => Cbor::null(),
+            23 => Cbor::undefined(),
+            24..=31 => err_at!(FailCbor, msg: "invalid simple-value {}", n)?,
+            32..=255 => Cbor::Major7(Info::U8, SimpleValue::Reserved24(n)),
         };
-
         Ok(val)
     }
-}
 
-impl From<u64> for Info {
-    fn from(num: u64) -> Info {
-        match num {
-            0..=23 => Info::Tiny(num as u8),
-            n if n <= (u8::MAX as u64) => Info::U8,
-            n if n <= (u16::MAX as u64) => Info::U16,
-            n if n <= (u32::MAX as u64) => Info::U32,
-            _ => Info::U64,
+    /// Construct Cbor major type-5 value from list of key, value pairs,
+    /// preserving the order of pairs. Keys shall be convertible to [Key].
+    pub fn map_from(pairs: Vec<(Cbor, Cbor)>) -> Result<Self> {
+        Cbor::map_from_with(pairs, &MapConfig::default())
+    }
+
+    /// Same as [Cbor::map_from], with configuration to validate and order
+    /// the keys.
+    pub fn map_from_with(pairs: Vec<(Cbor, Cbor)>, config: &MapConfig) -> Result<Self> {
+        let mut map: Vec<(Key, Cbor)> = Vec::with_capacity(pairs.len());
+        for (key, val) in pairs.into_iter() {
+            map.push((Key::from_cbor(key)?, val))
+        }
+
+        if config.unique_keys {
+            if let Some(key) = find_duplicate_key(&map, config.key_equality) {
+                err_at!(FailCbor, msg: "duplicate map key {:?}", key)?
+            }
         }
+        if config.sort_keys {
+            let mut entries: Vec<(Vec<u8>, (Key, Cbor))> = Vec::with_capacity(map.len());
+            for (key, val) in map.into_iter() {
+                let mut buf = vec![];
+                key.clone().into_cbor()?.do_encode_canonical(&mut buf, 1)?;
+                entries.push((buf, (key, val)));
+            }
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+            map = entries.into_iter().map(|(_, entry)| entry).collect();
+        }
+
+        let n = err_at!(FailConvert, u64::try_from(map.len()))?;
+        let order = MapOrder::of(&map);
+        Ok(Cbor::Major5(n.into(), map, order))
     }
-}
 
-impl TryFrom<usize> for Info {
-    type Error = Error;
+    /// Return true if value is null, simple-value 22.
+    ///
+    /// Note that `is_*` predicates return false for [Cbor::Binary] values,
+    /// which are not decoded.
+    pub fn is_null(&self) -> bool {
+        matches!(self, Cbor::Major7(_, SimpleValue::Null))
+    }
 
-    fn try_from(num: usize) -> Result<Info> {
-        Ok(err_at!(FailConvert, u64::try_from(num))?.into())
+    /// Return true if value is undefined, simple-value 23.
+    pub fn is_undefined(&self) -> bool {
+        matches!(self, Cbor::Major7(_, SimpleValue::Undefined))
     }
-}
 
-impl Info {
-    fn pretty_print(&self) -> Result<String> {
-        let s = match self {
-            Info::Tiny(val) => format!("Tiny(0x{:x})", val),
-            Info::U8 => "U8".to_string(),
-            Info::U16 => "U16".to_string(),
-            Info::U32 => "U32".to_string(),
-            Info::U64 => "U64".to_string(),
-            Info::Reserved28 => "Reserved28".to_string(),
-            Info::Reserved29 => "Reserved29".to_string(),
-            Info::Reserved30 => "Reserved30".to_string(),
-            Info::Indefinite => "Indefinite".to_string(),
-        };
+    /// Return true if value is boolean, true or false.
+    pub fn is_bool(&self) -> bool {
+        matches!(self, Cbor::Major7(_, SimpleValue::True | SimpleValue::False))
+    }
 
-        Ok(s)
+    /// Return true if value is an integer, a big-num or a floating point.
+    pub fn is_number(&self) -> bool {
+        matches!(
+            self,
+            Cbor::Major0(_, _)
+                | Cbor::Major1(_, _)
+                | Cbor::Major6(_, Tag::UBigNum(_) | Tag::SBigNum(_))
+                | Cbor::Major7(
+                    _,
+                    SimpleValue::F16(_) | SimpleValue::F32(_) | SimpleValue::F64(_)
+                )
+        )
     }
-}
 
-fn encode_hdr<W>(major: u8, info: Info, w: &mut W) -> Result<usize>
-where
-    W: io::Write,
-{
-    let info = match info {
-        Info::Tiny(val) if val <= 23 => val,
-        Info::Tiny(val) => err_at!(FailCbor, msg: "{} > 23", val)?,
-        Info::U8 => 24,
-        Info::U16 => 25,
-        Info::U32 => 26,
-        Info::U64 => 27,
-        Info::Reserved28 => 28,
-        Info::Reserved29 => 29,
-        Info::Reserved30 => 30,
-        Info::Indefinite => 31,
-    };
-    write_w!(w, &[(major as u8) << 5 | info]);
-    Ok(1)
-}
+    /// Return true if value is a text-string.
+    pub fn is_text(&self) -> bool {
+        matches!(self, Cbor::Major3(_, _))
+    }
 
-fn decode_hdr<R>(r: &mut R) -> Result<(u8, Info, usize)>
-where
-    R: io::Read,
-{
-    let mut scratch = [0_u8; 8];
-    read_r!(r, &mut scratch[..1]);
+    /// Return true if value is a byte-string.
+    pub fn is_bytes(&self) -> bool {
+        matches!(self, Cbor::Major2(_, _))
+    }
 
-    let b = scratch[0];
+    /// Return true if value is an array.
+    pub fn is_array(&self) -> bool {
+        matches!(self, Cbor::Major4(_, _))
+    }
 
-    let major = (b & 0xe0) >> 5;
-    let info = b & 0x1f;
-    Ok((major, info.try_into()?, 1 /* only 1-byte read */))
-}
+    /// Return true if value is a map.
+    pub fn is_map(&self) -> bool {
+        matches!(self, Cbor::Major5(_, _, _))
+    }
 
-fn encode_addnl<W>(num: u64, w: &mut W) -> Result<usize>
+    /// Return true if value is an indefinite length byte-string, text,
+    /// list or map. Such values are re-encoded with indefinite length
+    /// framing, though chunked strings are re-encoded as single chunk, and
+    /// an empty string, like `5f ff`, re-encodes with an empty chunk, like
+    /// `5f 40 ff`.
+    pub fn is_indefinite(&self) -> bool {
+        matches!(
+            self,
+            Cbor::Major2(Info::Indefinite, _)
+                | Cbor::Major3(Info::Indefinite, _)
+                | Cbor::Major4(Info::Indefinite, _)
+                | Cbor::Major5(Info::Indefinite, _, _)
+        )
+    }
+
+    /// Return true if value is tagged, including big-nums.
+    pub fn is_tag(&self) -> bool {
+        matches!(self, Cbor::Major6(_, _))
+    }
+
+    /// Return true if value is an integer, or big-num, outside the range
+    /// `-(2^53 - 1) ..= 2^53 - 1`, that can't be represented exactly as a
+    /// JavaScript number, say while bridging to JSON. Nested items are not
+    /// inspected.
+    pub fn exceeds_js_safe_integer(&self) -> bool {
+        const MAX_SAFE: u128 = (1 << 53) - 1;
+
+        // magnitude of big-num, None if it exceeds u128.
+        let magnitude = |val: &Cbor| match val {
+            Cbor::Major2(_, bytes) => {
+                let bytes = match bytes.iter().position(|b| *b != 0) {
+                    Some(off) => &bytes[off..],
+                    None => &bytes[..0],
+                };
+                match bytes.len() {
+                    0..=16 => Some(
+                        bytes.iter().fold(0_u128, |acc, b| (acc << 8) | (*b as u128)),
+                    ),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+
+        match self {
+            Cbor::Major0(_, val) => u128::from(*val) > MAX_SAFE,
+            // holds -1 - n.
+            Cbor::Major1(_, val) => u128::from(*val) + 1 > MAX_SAFE,
+            Cbor::Major6(_, Tag::UBigNum(val)) => match magnitude(val) {
+                Some(val) => val > MAX_SAFE,
+                None => true,
+            },
+            Cbor::Major6(_, Tag::SBigNum(val)) => match magnitude(val) {
+                Some(val) => val + 1 > MAX_SAFE,
+                None => true,
+            },
+            _ => false,
+        }
+    }
+
+    /// Return the tag number and the tagged value, if value is tagged.
+    pub fn get_tag(&self) -> Option<(u64, &Cbor)> {
+        match self {
+            Cbor::Major6(_, tag) => Some((tag.to_tag_value(), tag.as_content())),
+            _ => None,
+        }
+    }
+
+    /// Strip the outer tag and return the tagged value, return the value as
+    /// is if not tagged. Nested tags, if any, are not stripped.
+    pub fn untag(self) -> Cbor {
+        match self {
+            Cbor::Major6(_, tag) => tag.into_content(),
+            val => val,
+        }
+    }
+
+    /// Return the expected text encoding hinted by tags 21, 22 and 23,
+    /// for byte-strings nested under the tagged value. Tags are preserved
+    /// as is, no conversion is done while encoding or decoding.
+    pub fn as_expected_encoding(&self) -> Option<ExpectedEncoding> {
+        match self {
+            Cbor::Major6(_, Tag::Expected(enc, _)) => Some(*enc),
+            _ => None,
+        }
+    }
+
+    /// Compare values for equality, after stripping tags numbered in `tags`
+    /// from both sides, at any level of nesting. Say, to ignore tag 55799,
+    /// self-described cbor, or tag 24 wrapping, in which case the wrapped
+    /// byte-string is compared as is, without decoding it. Rest of the tags
+    /// must match by their number and content.
+    pub fn eq_ignoring_tags(&self, other: &Cbor, tags: &[u64]) -> bool {
+        match (strip_tags(self, tags), strip_tags(other, tags)) {
+            (Cbor::Major4(a, xs), Cbor::Major4(b, ys)) => {
+                a == b
+                    && xs.len() == ys.len()
+                    && xs.iter().zip(ys.iter()).all(|(x, y)| x.eq_ignoring_tags(y, tags))
+            }
+            (Cbor::Major5(a, xs, _), Cbor::Major5(b, ys, _)) => {
+                a == b
+                    && xs.len() == ys.len()
+                    && xs
+                        .iter()
+                        .zip(ys.iter())
+                        .all(|((kx, x), (ky, y))| kx == ky && x.eq_ignoring_tags(y, tags))
+            }
+            (Cbor::Major6(a, x), Cbor::Major6(b, y)) => {
+                a == b
+                    && x.to_tag_value() == y.to_tag_value()
+                    && x.as_content().eq_ignoring_tags(y.as_content(), tags)
+            }
+            (x, y) => x == y,
+        }
+    }
+
+    /// Compare byte-string values in constant time, to avoid leaking
+    /// the position of first mismatching byte via timing, say while
+    /// verifying MACs and signatures.
+    ///
+    /// Return None if either of the values is not a byte-string. Byte-strings
+    /// of different length compare as unequal, length is not treated as
+    /// secret.
+    #[cfg(feature = "subtle")]
+    pub fn ct_eq_bytes(&self, other: &Cbor) -> Option<bool> {
+        use subtle::ConstantTimeEq;
+
+        match (self, other) {
+            (Cbor::Major2(_, a), Cbor::Major2(_, b)) => Some(a.ct_eq(b).into()),
+            (_, _) => None,
+        }
+    }
+
+    /// Gather structural statistics for this value, refer to [CborStats].
+    ///
+    /// Traverse the value once, using an explicit stack, without cloning
+    /// any of its items. [Cbor::Binary] values are counted under their
+    /// major type and are not decoded, empty ones are not counted.
+    pub fn stats(&self) -> CborStats {
+        let mut stats = CborStats::default();
+
+        let mut stack: Vec<(&Cbor, u32)> = vec![(self, 1)];
+        while let Some((val, depth)) = stack.pop() {
+            if let Cbor::Binary(data) = val {
+                if data.is_empty() {
+                    continue;
+                }
+            }
+            stats.majors[usize::from(val.to_major_val())] += 1;
+            stats.n_items += 1;
+            stats.max_depth = cmp::max(stats.max_depth, depth);
+
+            match val {
+                Cbor::Major2(_, byts) => stats.n_bytes += byts.len(),
+                Cbor::Major4(_, list) => {
+                    stack.extend(list.iter().rev().map(|x| (x, depth + 1)))
+                }
+                Cbor::Major5(_, map, _) => {
+                    for (key, val) in map.iter().rev() {
+                        stats.add_key(key, depth + 1);
+                        stack.push((val, depth + 1));
+                    }
+                }
+                Cbor::Major6(_, tag) => stack.push((tag.as_content(), depth + 1)),
+                _ => (),
+            }
+        }
+
+        stats
+    }
+
+    /// Retain only the map entries for which `f` returns true, in place.
+    /// No-op if value is not a map. Length of definite length map is
+    /// updated to the retained number of entries.
+    pub fn retain_map<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&Key, &Cbor) -> bool,
+    {
+        if let Cbor::Major5(info, map, _) = self {
+            map.retain(|(key, val)| f(key, val));
+            if !matches!(info, Info::Indefinite) {
+                *info = (map.len() as u64).into();
+            }
+        }
+    }
+
+    /// Retain only the array items for which `f` returns true, in place.
+    /// No-op if value is not an array. Length of definite length array is
+    /// updated to the retained number of items.
+    pub fn retain_array<F>(&mut self, f: F)
+    where
+        F: FnMut(&Cbor) -> bool,
+    {
+        if let Cbor::Major4(info, list) = self {
+            list.retain(f);
+            if !matches!(info, Info::Indefinite) {
+                *info = (list.len() as u64).into();
+            }
+        }
+    }
+
+    /// Append the items of array `other` to this array. Fail with
+    /// [Error::FailConvert] if either value is not an array. Length of
+    /// definite length array is updated to the new number of items.
+    pub fn extend_array(&mut self, other: Cbor) -> Result<()> {
+        match (self, other) {
+            (Cbor::Major4(info, list), Cbor::Major4(_, items)) => {
+                list.extend(items);
+                if !matches!(info, Info::Indefinite) {
+                    *info = err_at!(FailConvert, u64::try_from(list.len()))?.into();
+                }
+                Ok(())
+            }
+            (Cbor::Major4(_, _), _) => err_at!(FailConvert, msg: "extend with non-array"),
+            _ => err_at!(FailConvert, msg: "extend non-array"),
+        }
+    }
+
+    /// Merge the entries of map `other` into this map. Entries whose key is
+    /// already present, as per [Key]'s `Eq` implementation, replace the
+    /// existing value in its position, that is last write wins, and other
+    /// entries are appended in order. Fail with [Error::FailConvert] if
+    /// either value is not a map. Length of definite length map is updated
+    /// to the new number of entries, and its [MapOrder] is re-computed.
+    pub fn extend_map(&mut self, other: Cbor) -> Result<()> {
+        match (self, other) {
+            (Cbor::Major5(info, map, order), Cbor::Major5(_, entries, _)) => {
+                for (key, val) in entries.into_iter() {
+                    match map.iter_mut().find(|(k, _)| *k == key) {
+                        Some(entry) => entry.1 = val,
+                        None => map.push((key, val)),
+                    }
+                }
+                if !matches!(info, Info::Indefinite) {
+                    *info = err_at!(FailConvert, u64::try_from(map.len()))?.into();
+                }
+                *order = MapOrder::of(map);
+                Ok(())
+            }
+            (Cbor::Major5(_, _, _), _) => {
+                err_at!(FailConvert, msg: "extend with non-map")
+            }
+            _ => err_at!(FailConvert, msg: "extend non-map"),
+        }
+    }
+
+    /// Lookup `key` in a map, return None if the key is not found or if
+    /// value is not a map.
+    ///
+    /// Maps are looked up using binary search. Maps with [MapOrder::Sorted],
+    /// as with [DecodeConfig]'s `sort_keys`, or built using
+    /// [Cbor::map_from_with] and [Cbor::map_insert] on sorted entries, are
+    /// looked up using binary search alone. Other maps are not known to be
+    /// sorted, hence a key not found by binary search is looked up by
+    /// scanning the entries, which makes their misses linear. If the key
+    /// appears more than once, any one of the matching values may be
+    /// returned.
+    pub fn map_get(&self, key: &Key) -> Option<&Cbor> {
+        match self {
+            Cbor::Major5(_, map, order) => match map_search(map, key, *order) {
+                Ok(off) => Some(&map[off].1),
+                Err(_) => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Insert `key` and `val` into a map sorted by key, preserving the
+    /// order and its [MapOrder], refer to [Cbor::map_get]. If the key is
+    /// already present, its value is replaced and the old value is returned,
+    /// even if the map is not sorted. Fail with [Error::FailConvert] if value is not a map.
+    /// Length of definite length map is updated to the new number of
+    /// entries.
+    pub fn map_insert(&mut self, key: Key, val: Cbor) -> Result<Option<Cbor>> {
+        match self {
+            Cbor::Major5(info, map, order) => match map_search(map, &key, *order) {
+                Ok(off) => Ok(Some(mem::replace(&mut map[off].1, val))),
+                Err(off) => {
+                    map.insert(off, (key, val));
+                    if !matches!(info, Info::Indefinite) {
+                        *info = err_at!(FailConvert, u64::try_from(map.len()))?.into();
+                    }
+                    Ok(None)
+                }
+            },
+            _ => err_at!(FailConvert, msg: "insert into non-map"),
+        }
+    }
+
+    /// Return the in-memory footprint of this value, in bytes. That is the
+    /// size of the value itself, plus the heap memory held by its strings,
+    /// byte-strings, arrays, maps and tagged values, nested at any depth.
+    ///
+    /// Heap memory is counted by capacity, not by length, and allocator
+    /// overheads are not accounted for.
+    pub fn deep_size(&self) -> usize {
+        use std::mem::size_of;
+
+        let mut size = size_of::<Cbor>();
+
+        let mut stack: Vec<&Cbor> = vec![self];
+        while let Some(val) = stack.pop() {
+            match val {
+                Cbor::Major2(_, data) | Cbor::Major3(_, data) => size += data.capacity(),
+                Cbor::Binary(data) => size += data.capacity(),
+                Cbor::Major4(_, list) => {
+                    size += list.capacity() * size_of::<Cbor>();
+                    stack.extend(list.iter());
+                }
+                Cbor::Major5(_, map, _) => {
+                    size += map.capacity() * size_of::<(Key, Cbor)>();
+                    for (key, val) in map.iter() {
+                        size += match key {
+                            Key::Bytes(data) => data.capacity(),
+                            Key::Text(text) => text.capacity(),
+                            _ => 0,
+                        };
+                        stack.push(val);
+                    }
+                }
+                Cbor::Major6(_, tag) => {
+                    size += size_of::<Cbor>();
+                    stack.push(tag.as_content());
+                }
+                _ => (),
+            }
+        }
+
+        size
+    }
+
+    /// Copy this value up to `max` levels of nesting, replacing items nested
+    /// deeper with [SimpleValue::Undefined]. Top level value is at depth 1,
+    /// and items of arrays, values of maps and content of tags are one level
+    /// deeper than their parent. Say, to preview huge documents.
+    pub fn truncate_depth(&self, max: usize) -> Cbor {
+        self.truncate_depth_with(max, &Cbor::undefined())
+    }
+
+    /// Same as [Cbor::truncate_depth], replacing items nested deeper than
+    /// `max` with `sentinel`.
+    ///
+    /// Traverse the value using an explicit stack, hence adversarially deep
+    /// values don't overflow the call stack.
+    pub fn truncate_depth_with(&self, max: usize, sentinel: &Cbor) -> Cbor {
+        enum Op<'a> {
+            Visit(&'a Cbor, usize),
+            List(Info, usize),
+            Map(Info, &'a [(Key, Cbor)], MapOrder),
+            Tag(Info, u64),
+        }
+
+        let mut out: Vec<Cbor> = vec![];
+        let mut stack: Vec<Op> = vec![Op::Visit(self, 1)];
+        while let Some(op) = stack.pop() {
+            match op {
+                Op::Visit(_, depth) if depth > max => out.push(sentinel.clone()),
+                Op::Visit(Cbor::Major4(info, list), depth) => {
+                    stack.push(Op::List(*info, list.len()));
+                    stack.extend(list.iter().rev().map(|x| Op::Visit(x, depth + 1)));
+                }
+                Op::Visit(Cbor::Major5(info, map, order), depth) => {
+                    stack.push(Op::Map(*info, map, *order));
+                    stack.extend(map.iter().rev().map(|(_, x)| Op::Visit(x, depth + 1)));
+                }
+                Op::Visit(Cbor::Major6(info, tag), depth) => {
+                    stack.push(Op::Tag(*info, tag.to_tag_value()));
+                    stack.push(Op::Visit(tag.as_content(), depth + 1));
+                }
+                Op::Visit(val, _) => out.push(val.clone()),
+                Op::List(info, n) => {
+                    let list = out.split_off(out.len() - n);
+                    out.push(Cbor::Major4(info, list));
+                }
+                Op::Map(info, map, order) => {
+                    let vals = out.split_off(out.len() - map.len());
+                    let keys = map.iter().map(|(key, _)| key.clone());
+                    out.push(Cbor::Major5(info, keys.zip(vals).collect(), order));
+                }
+                Op::Tag(info, num) => {
+                    let val = out.pop().unwrap();
+                    out.push(Cbor::Major6(info, Tag::from_num(num, val)));
+                }
+            }
+        }
+
+        out.pop().unwrap()
+    }
+
+    /// Return a copy of this value in normal form, such that semantically
+    /// equal values, encoded differently, normalize to equal values:
+    ///
+    /// * Integers, lengths and tag numbers use the shortest [Info].
+    /// * Indefinite length items are converted to definite length.
+    /// * Big-nums are stripped of leading zeros, and converted to integers
+    ///   if they fit in major type 0 or 1.
+    /// * Floats are converted to the shortest width that preserves the
+    ///   value, and NaN is normalized to `0x7e00`, same as
+    ///   [Cbor::encode_canonical]. Float keys are converted to [Key::F64].
+    /// * Map entries are sorted by their canonically encoded keys, entries
+    ///   with duplicate keys are retained in their original order.
+    /// * Self-describe tag 55799 is stripped.
+    /// * [Cbor::Binary] values are decoded, and retained as is if they
+    ///   are not well-formed.
+    pub fn normalize(&self) -> Cbor {
+        match self {
+            Cbor::Major0(_, num) => Cbor::Major0((*num).into(), *num),
+            Cbor::Major1(_, num) => Cbor::Major1((*num).into(), *num),
+            Cbor::Major2(_, data) => {
+                Cbor::Major2((data.len() as u64).into(), data.clone())
+            }
+            Cbor::Major3(_, text) => {
+                Cbor::Major3((text.len() as u64).into(), text.clone())
+            }
+            Cbor::Major4(_, list) => {
+                let list: Vec<Cbor> = list.iter().map(|x| x.normalize()).collect();
+                Cbor::Major4((list.len() as u64).into(), list)
+            }
+            Cbor::Major5(_, map, _) => {
+                let mut items: Vec<(Vec<u8>, Key, Cbor)> = map
+                    .iter()
+                    .map(|(key, val)| {
+                        let key = match key {
+                            Key::F32(val) if val.is_nan() => Key::F64(f64::NAN),
+                            Key::F32(val) => Key::F64((*val).into()),
+                            Key::F64(val) if val.is_nan() => Key::F64(f64::NAN),
+                            key => key.clone(),
+                        };
+                        let mut buf = vec![];
+                        if let Ok(k) = key.clone().into_cbor() {
+                            k.encode_canonical(&mut buf).ok();
+                        }
+                        (buf, key, val.normalize())
+                    })
+                    .collect();
+                items.sort_by(|a, b| a.0.cmp(&b.0));
+                let map: Vec<(Key, Cbor)> =
+                    items.into_iter().map(|(_, key, val)| (key, val)).collect();
+                let order = MapOrder::of(&map);
+                Cbor::Major5((map.len() as u64).into(), map, order)
+            }
+            Cbor::Major6(_, Tag::SelfDescribed(val)) => val.normalize(),
+            Cbor::Major6(_, Tag::UBigNum(val)) => match normalize_bignum(val) {
+                Ok(num) => Cbor::Major0(num.into(), num),
+                Err(val) => Tag::UBigNum(Box::new(val)).into(),
+            },
+            Cbor::Major6(_, Tag::SBigNum(val)) => match normalize_bignum(val) {
+                Ok(num) => Cbor::Major1(num.into(), num),
+                Err(val) => Tag::SBigNum(Box::new(val)).into(),
+            },
+            Cbor::Major6(_, tag) => {
+                Tag::from_num(tag.to_tag_value(), tag.as_content().normalize()).into()
+            }
+            Cbor::Major7(_, sval) => {
+                let sval = match sval {
+                    SimpleValue::F16(val) if f16_is_nan(*val) => SimpleValue::F16(0x7e00),
+                    SimpleValue::F32(val) => canonical_float((*val).into()),
+                    SimpleValue::F64(val) => canonical_float(*val),
+                    sval => *sval,
+                };
+                match sval {
+                    SimpleValue::Unassigned => self.clone(),
+                    sval => sval.into_cbor().unwrap_or_else(|_| self.clone()),
+                }
+            }
+            Cbor::Binary(data) => match decode_exact(data) {
+                Ok(val) => val.normalize(),
+                Err(_) => self.clone(),
+            },
+        }
+    }
+}
+
+// Strip leading zeros from big-num's byte-string, return the number if it
+// fits in u64, else the normalized byte-string.
+fn normalize_bignum(val: &Cbor) -> result::Result<u64, Cbor> {
+    let data = match val {
+        Cbor::Major2(_, data) => data,
+        val => return Err(val.normalize()),
+    };
+    let off = data.iter().position(|b| *b != 0).unwrap_or(data.len());
+    let data = &data[off..];
+    match data.len() {
+        0..=8 => Ok(data.iter().fold(0_u64, |acc, b| (acc << 8) | u64::from(*b))),
+        n => Err(Cbor::Major2((n as u64).into(), data.to_vec())),
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for Cbor {
+    type Error = Error;
+
+    /// Decode exactly one cbor value from `buf`, trailing bytes are treated
+    /// as error. Use [Cbor::decode] to learn the number of bytes consumed.
+    fn try_from(buf: &'a [u8]) -> Result<Cbor> {
+        decode_exact(buf)
+    }
+}
+
+impl TryFrom<Vec<u8>> for Cbor {
+    type Error = Error;
+
+    /// Same as `TryFrom<&[u8]>`.
+    fn try_from(buf: Vec<u8>) -> Result<Cbor> {
+        decode_exact(&buf)
+    }
+}
+
+/// Structural summary of a [Cbor] value, returned by [Cbor::stats].
+///
+/// Map keys are counted as items, under their respective major type.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct CborStats {
+    /// Number of items for each major type, indexed by major type.
+    pub majors: [usize; 8],
+    /// Maximum nesting depth, top-level value is at depth 1. Same scale
+    /// as [RECURSION_LIMIT].
+    pub max_depth: u32,
+    /// Total number of items in the value, including the top-level value.
+    pub n_items: usize,
+    /// Total number of bytes held by byte-strings, excluding headers.
+    pub n_bytes: usize,
+}
+
+impl CborStats {
+    fn add_key(&mut self, key: &Key, depth: u32) {
+        let major = match key {
+            Key::U64(_) => 0,
+            Key::N64(_) => 1,
+            Key::Bytes(val) => {
+                self.n_bytes += val.len();
+                2
+            }
+            Key::Text(_) => 3,
+            Key::Bool(_) | Key::F32(_) | Key::F64(_) => 7,
+        };
+        self.majors[major] += 1;
+        self.n_items += 1;
+        self.max_depth = cmp::max(self.max_depth, depth);
+    }
+}
+
+/// Metrics gathered while decoding a value, returned by
+/// [Cbor::decode_with_metrics].
+///
+/// Break codes, terminating indefinite-length items, are not counted as
+/// items. Values embedded under tag-24 are decoded as items nested under
+/// the byte-string, and are part of these metrics.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct DecodeMetrics {
+    /// Maximum nesting depth observed, top-level value is at depth 1. Same
+    /// scale as [RECURSION_LIMIT].
+    pub max_depth: u32,
+    /// Number of items decoded, including map keys, tagged values and
+    /// chunks of indefinite-length strings.
+    pub item_count: usize,
+    /// Number of bytes consumed to decode the value.
+    pub bytes_consumed: usize,
+}
+
+/// Configuration for decoding cbor values, refer to [Cbor::decode_with].
+///
+/// Default configuration is used by [Cbor::decode]. Limits on depth, items
+/// and bytes guard against untrusted input, failing with
+/// [Error::LimitExceeded].
+///
+/// Decoded text-strings, including map keys, own their storage, repeated
+/// text is not interned. For documents with repetitive keys, use
+/// [CborRef][crate::CborRef] to borrow text from the input, or
+/// [CborBuffer][crate::CborBuffer] to reuse storage across decoded values.
+#[derive(Debug, Clone, Default)]
+pub struct DecodeConfig {
+    /// Fail with [Error::FailCbor] when a decoded floating point value,
+    /// half, single or double precision, is NaN or infinite. Default is false.
+    pub reject_nonfinite_floats: bool,
+    /// Policy for map keys that appear more than once in the same map,
+    /// compared as per `key_equality`. Default is to allow them.
+    pub duplicate_keys: DuplicateKeyPolicy,
+    /// Semantics for comparing map keys, refer to [KeyEquality].
+    pub key_equality: KeyEquality,
+    /// Convert text keys holding a decimal integer, like `"42"` or `"-7"`,
+    /// into integer-keyed maps, like `HashMap<u64, V>`. Meant for documents
+    /// originating from JSON, where keys are always text. Applies only to
+    /// [FromCbor::from_cbor_with], decoded values keep their text keys, and
+    /// only to map keys that fail to convert as text. Other text keys shall
+    /// fail with [Error::FailConvert]. Only the canonical form is coerced,
+    /// `"042"` and `"+42"` are not. Default is false.
+    pub coerce_map_keys: bool,
+    /// Convert integers 0 and 1 into `bool` false and true, for encoders that
+    /// do not use the simple values. Applies only to
+    /// [FromCbor::from_cbor_with], decoded values keep their integers.
+    /// Default is false.
+    pub coerce_bool: bool,
+    /// Handling of text-strings that are not valid utf8, refer to
+    /// [LossyText].
+    pub lossy_text: LossyText,
+    /// Handling of tags not recognized by the application, refer to
+    /// [UnknownTagPolicy].
+    pub unknown_tag_policy: UnknownTagPolicy,
+    /// Tag numbers recognized by the application, in addition to the tags
+    /// with a dedicated [Tag] variant, which are always recognized. Default
+    /// is empty.
+    pub known_tags: Vec<u64>,
+    /// Maximum nesting depth, top-level value is at depth 1, same scale as
+    /// [DecodeMetrics]. Can only tighten [RECURSION_LIMIT], which applies
+    /// when None. Default is None.
+    pub max_depth: Option<u32>,
+    /// Maximum number of items, counted as per [DecodeMetrics]. Default is
+    /// None, no limit.
+    pub max_items: Option<usize>,
+    /// Maximum length, in bytes, of a byte-string or text-string, checked
+    /// before allocating its storage. For indefinite-length strings, limit
+    /// applies to each chunk and to the concatenated string. Default is
+    /// None, no limit.
+    pub max_bytes: Option<usize>,
+    /// Sort map entries by key, in the order defined by [Key]'s `Ord`
+    /// implementation, for lookups using [Cbor::map_get]. Applied after
+    /// `duplicate_keys` policy. Decoded maps shall no longer re-encode to
+    /// the input bytes, unless already sorted. Default is false.
+    pub sort_keys: bool,
+    /// Decode `0xf4` as true and `0xf5` as false, the swapped booleans
+    /// written by cbordata 0.6 and earlier. Decoded booleans re-encode as
+    /// per RFC-8949, that is, `0xf5` for true. Default is false.
+    pub legacy_bools: bool,
+}
+
+/// Handling of unrecognized tags while decoding, that is, tags without a
+/// dedicated [Tag] variant and not listed in [DecodeConfig]'s `known_tags`.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub enum UnknownTagPolicy {
+    /// Decode them as [Tag::Value].
+    #[default]
+    Keep,
+    /// Fail with [Error::FailCbor].
+    Reject,
+    /// Drop the tag and decode the tagged value in its place.
+    Unwrap,
+}
+
+/// Handling of invalid utf8 text-strings while decoding, refer to
+/// [DecodeConfig].
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub enum LossyText {
+    /// Text-strings are not validated while decoding, converting them
+    /// into `String` shall fail with [Error::FailConvert].
+    #[default]
+    Unchecked,
+    /// Fail with [Error::FailCbor]. For indefinite-length text-strings, each
+    /// chunk shall be valid utf8 by itself.
+    Strict,
+    /// Replace invalid sequences with `U+FFFD`, the replacement character.
+    /// Length of the text-string is updated for the replaced text.
+    Replace,
+    /// Decode invalid text-strings as byte-strings, preserving the data.
+    Bytes,
+}
+
+/// Policy for duplicate keys in a map, refer to [DecodeConfig].
+///
+/// When entries are dropped from a definite length map, its length is
+/// updated to the remaining number of entries.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub enum DuplicateKeyPolicy {
+    /// Keep all the entries as they appear.
+    #[default]
+    Allow,
+    /// Fail with [Error::FailCbor].
+    Reject,
+    /// Keep the first entry for each key, dropping the later ones.
+    KeepFirst,
+    /// Keep the last entry for each key, dropping the earlier ones, that is
+    /// last write wins, same as [Cbor::extend_map]. The entry is retained in
+    /// the position of last occurrence.
+    KeepLast,
+}
+
+/// Semantics for comparing map keys while detecting duplicates.
+///
+/// CBOR's generic data model treats integer `1` and floating point `1.0` as
+/// distinct values, and so does this crate by default. Consumers that map
+/// cbor keys to a single numeric type, like JavaScript, would see them as
+/// the same key, use [KeyEquality::Numeric] to detect such collisions.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub enum KeyEquality {
+    /// Keys are equal only if they are of the same type and value, as per
+    /// [Key]'s `Eq` implementation.
+    #[default]
+    Structural,
+    /// Integer and floating point keys are equal if they have the same
+    /// numeric value, likewise single and double precision keys.
+    Numeric,
+}
+
+// State carried across the items of a value being decoded.
+#[derive(Default)]
+pub(crate) struct DecodeState {
+    pub(crate) metrics: DecodeMetrics,
+    pub(crate) pool: Pool,
+    // values embedded under tag 24, pending decode, with depth and offset.
+    pub(crate) embedded: Vec<(Vec<u8>, u32, usize)>,
+}
+
+/// Configuration for constructing map values, refer to [Cbor::map_from_with].
+///
+/// Default configuration is used by [Cbor::map_from].
+#[derive(Debug, Clone, Default)]
+pub struct MapConfig {
+    /// Fail with [Error::FailCbor] if the same key appears more than once.
+    /// Default is false.
+    pub unique_keys: bool,
+    /// Sort the pairs by the bytewise lexicographic order of their
+    /// canonically encoded keys, as with [Cbor::encode_canonical]. Note that
+    /// this is not the same as [Key]'s `Ord` implementation, refer to
+    /// [encode_canonical_map]. Default is false, preserving the input order.
+    pub sort_keys: bool,
+    /// Semantics for comparing keys with `unique_keys`, refer to [KeyEquality].
+    pub key_equality: KeyEquality,
+}
+
+/// Order of entries in a map, refer to [Cbor::map_get].
+///
+/// Maps built by this crate compute their order, maps built by applications
+/// can use [MapOrder::of]. Entries mutated in place, other than by [Cbor]'s
+/// methods, may leave a map with [MapOrder::Sorted] unsorted, in which case
+/// lookups can miss keys present in the map. Values of `MapOrder` are always
+/// equal, so that maps compare equal irrespective of their order.
+#[derive(Debug, Copy, Clone)]
+pub enum MapOrder {
+    /// Entries are sorted in the order defined by [Key]'s `Ord`.
+    Sorted,
+    /// Entries are not known to be sorted.
+    Unknown,
+}
+
+impl MapOrder {
+    /// Return [MapOrder::Sorted] if entries in `map` are sorted by key.
+    pub fn of(map: &[(Key, Cbor)]) -> MapOrder {
+        match map.windows(2).all(|w| w[0].0 <= w[1].0) {
+            true => MapOrder::Sorted,
+            false => MapOrder::Unknown,
+        }
+    }
+
+    /// Return true if entries are known to be sorted.
+    pub fn is_sorted(&self) -> bool {
+        matches!(self, MapOrder::Sorted)
+    }
+}
+
+impl PartialEq for MapOrder {
+    fn eq(&self, _other: &MapOrder) -> bool {
+        true
+    }
+}
+
+impl Eq for MapOrder {}
+
+/// 5-bit value for additional info. Refer to Cbor [spec] for details.
+///
+/// [spec]: https://tools.ietf.org/html/rfc7049
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Info {
+    /// additional info is in-lined.
+    Tiny(u8), // 0..=23
+    /// additional info of 8-bit unsigned integer.
+    U8,
+    /// additional info of 16-bit unsigned integer.
+    U16,
+    /// additional info of 32-bit unsigned integer.
+    U32,
+    /// additional info of 64-bit unsigned integer.
+    U64,
+    /// Reserved.
+    Reserved28,
+    /// Reserved.
+    Reserved29,
+    /// Reserved.
+    Reserved30,
+    /// Indefinite encoding.
+    Indefinite,
+}
+
+#[cfg(any(feature = "arbitrary", test))]
+impl<'a> Arbitrary<'a> for Info {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        let tn = u.arbitrary::<u8>()? % 24;
+        Ok(*u.choose(&[
+            Info::Tiny(tn),
+            Info::U8,
+            Info::U16,
+            Info::U32,
+            Info::U64,
+            Info::Indefinite,
+        ])?)
+    }
+}
+
+impl TryFrom<u8> for Info {
+    type Error = Error;
+
+    fn try_from(b: u8) -> Result<Info> {
+        let val = match b {
+            0..=23 => Info::Tiny(b),
+            24 => Info::U8,
+            25 => Info::U16,
+            26 => Info::U32,
+            27 => Info::U64,
+            28 => Info::Reserved28,
+            29 => Info::Reserved29,
+            30 => Info::Reserved30,
+            31 => Info::Indefinite,
+            _ => err_at!(Fatal, msg: "unreachable")?,
+        };
+
+        Ok(val)
+    }
+}
+
+impl From<u64> for Info {
+    fn from(num: u64) -> Info {
+        match num {
+            0..=23 => Info::Tiny(num as u8),
+            n if n <= (u8::MAX as u64) => Info::U8,
+            n if n <= (u16::MAX as u64) => Info::U16,
+            n if n <= (u32::MAX as u64) => Info::U32,
+            _ => Info::U64,
+        }
+    }
+}
+
+impl TryFrom<usize> for Info {
+    type Error = Error;
+
+    fn try_from(num: usize) -> Result<Info> {
+        Ok(err_at!(FailConvert, u64::try_from(num))?.into())
+    }
+}
+
+impl Info {
+    fn pretty_print(&self) -> Result<String> {
+        let s = match self {
+            Info::Tiny(val) => format!("Tiny(0x{:x})", val),
+            Info::U8 => "U8".to_string(),
+            Info::U16 => "U16".to_string(),
+            Info::U32 => "U32".to_string(),
+            Info::U64 => "U64".to_string(),
+            Info::Reserved28 => "Reserved28".to_string(),
+            Info::Reserved29 => "Reserved29".to_string(),
+            Info::Reserved30 => "Reserved30".to_string(),
+            Info::Indefinite => "Indefinite".to_string(),
+        };
+
+        Ok(s)
+    }
+}
+
+fn encode_hdr<W>(major: u8, info: Info, w: &mut W) -> Result<usize>
+where
+    W: io::Write,
+{
+    let info = match info {
+        Info::Tiny(val) if val <= 23 => val,
+        Info::Tiny(val) => err_at!(FailCbor, msg: "{} > 23", val)?,
+        Info::U8 => 24,
+        Info::U16 => 25,
+        Info::U32 => 26,
+        Info::U64 => 27,
+        Info::Reserved28 => 28,
+        Info::Reserved29 => 29,
+        Info::Reserved30 => 30,
+        Info::Indefinite => 31,
+    };
+    write_w!(w, &[major << 5 | info]);
+    Ok(1)
+}
+
+pub(crate) fn decode_hdr<R>(r: &mut R) -> Result<(u8, Info, usize)>
+where
+    R: io::Read,
+{
+    let mut scratch = [0_u8; 8];
+    read_r!(r, &mut scratch[..1]);
+
+    let b = scratch[0];
+
+    let major = (b & 0xe0) >> 5;
+    let info = b & 0x1f;
+    Ok((major, info.try_into()?, 1 /* only 1-byte read */))
+}
+
+// Encode header and additional value `num` in the width of `info`, as
+// recorded while decoding, so that values are re-encoded as they were
+// received. Fall back to the shortest form when `num` does not fit `info`.
+fn encode_num<W>(major: u8, info: Info, num: u64, w: &mut W) -> Result<usize>
+where
+    W: io::Write,
+{
+    let hdr = major << 5;
+    match info {
+        Info::Tiny(val) if u64::from(val) == num || val > 23 => {
+            encode_hdr(major, info, w)
+        }
+        Info::U8 if num <= u64::from(u8::MAX) => {
+            write_w!(w, &[hdr | 24, num as u8]);
+            Ok(2)
+        }
+        Info::U16 if num <= u64::from(u16::MAX) => {
+            write_w!(w, &[hdr | 25]);
+            write_w!(w, &(num as u16).to_be_bytes());
+            Ok(3)
+        }
+        Info::U32 if num <= u64::from(u32::MAX) => {
+            write_w!(w, &[hdr | 26]);
+            write_w!(w, &(num as u32).to_be_bytes());
+            Ok(5)
+        }
+        Info::U64 => {
+            write_w!(w, &[hdr | 27]);
+            write_w!(w, &num.to_be_bytes());
+            Ok(9)
+        }
+        _ => Ok(encode_hdr(major, num.into(), w)? + encode_addnl(num, w)?),
+    }
+}
+
+fn encode_addnl<W>(num: u64, w: &mut W) -> Result<usize>
 where
     W: io::Write,
 {
@@ -553,7 +1822,7 @@ where
     Ok(n)
 }
 
-fn decode_addnl<R>(info: Info, r: &mut R) -> Result<(u64, usize)>
+pub(crate) fn decode_addnl<R>(info: Info, r: &mut R) -> Result<(u64, usize)>
 where
     R: io::Read,
 {
@@ -574,7 +1843,7 @@ where
         }
         Info::U64 => {
             read_r!(r, &mut scratch[..8]);
-            (u64::from_be_bytes(scratch[..8].try_into().unwrap()) as u64, 8)
+            (u64::from_be_bytes(scratch[..8].try_into().unwrap()), 8)
         }
         Info::Indefinite => (0, 0),
         _ => err_at!(FailCbor, msg: "no additional value")?,
@@ -587,17 +1856,17 @@ where
 /// [spec]: https://tools.ietf.org/html/rfc7049
 #[derive(Debug, Copy, Clone)]
 pub enum SimpleValue {
-    /// 0..=19 and 28..=30 and 32..=255 are unassigned.
+    /// 0..=19 are unassigned, value is carried by the [Info::Tiny] header.
     Unassigned,
     /// Boolean type, value true.
-    True, // 20, tiny simple-value
+    True, // 21, tiny simple-value
     /// Boolean type, value false.
-    False, // 21, tiny simple-value
+    False, // 20, tiny simple-value
     /// Null unitary type, can be used in place of optional types.
     Null, // 22, tiny simple-value
     /// Undefined unitary type.
     Undefined, // 23, tiny simple-value
-    /// Reserved.
+    /// Simple value 32..=255, encoded with one-byte additional info.
     Reserved24(u8), // 24, one-byte simple-value
     /// 16-bit floating point.
     F16(u16), // 25, not-implemented
@@ -672,12 +1941,13 @@ impl IntoCbor for SimpleValue {
 
         let val = match self {
             Unassigned => err_at!(FailConvert, msg: "simple-value-unassigned")?,
-            val @ True => Cbor::Major7(Info::Tiny(20), val),
-            val @ False => Cbor::Major7(Info::Tiny(21), val),
+            val @ False => Cbor::Major7(Info::Tiny(20), val),
+            val @ True => Cbor::Major7(Info::Tiny(21), val),
             val @ Null => Cbor::Major7(Info::Tiny(22), val),
-            Undefined => err_at!(FailConvert, msg: "simple-value-undefined")?,
-            Reserved24(_) => err_at!(FailConvert, msg: "simple-value-unassigned1")?,
-            F16(_) => err_at!(FailConvert, msg: "simple-value-f16")?,
+            val @ Undefined => Cbor::Major7(Info::Tiny(23), val),
+            val @ Reserved24(32..=255) => Cbor::Major7(Info::U8, val),
+            Reserved24(_) => err_at!(FailConvert, msg: "simple-value-reserved")?,
+            val @ F16(_) => Cbor::Major7(Info::U16, val),
             val @ F32(_) => Cbor::Major7(Info::U32, val),
             val @ F64(_) => Cbor::Major7(Info::U64, val),
             val @ Break => Cbor::Major7(Info::Indefinite, val),
@@ -705,6 +1975,16 @@ impl SimpleValue {
         }
     }
 
+    /// Return false for NaN and infinite floating point values, true otherwise.
+    pub fn is_finite(&self) -> bool {
+        match self {
+            SimpleValue::F16(val) => (val & 0x7c00) != 0x7c00,
+            SimpleValue::F32(val) => val.is_finite(),
+            SimpleValue::F64(val) => val.is_finite(),
+            _ => true,
+        }
+    }
+
     fn encode<W>(sval: &SimpleValue, w: &mut W) -> Result<usize>
     where
         W: io::Write,
@@ -735,19 +2015,30 @@ impl SimpleValue {
         Ok(n)
     }
 
-    fn decode<R>(info: Info, r: &mut R) -> Result<(SimpleValue, usize)>
+    pub(crate) fn decode<R>(info: Info, r: &mut R) -> Result<(SimpleValue, usize)>
     where
         R: io::Read,
     {
         let mut scratch = [0_u8; 8];
         let (val, n) = match info {
-            Info::Tiny(20) => (SimpleValue::True, 0),
-            Info::Tiny(21) => (SimpleValue::False, 0),
+            Info::Tiny(0..=19) => (SimpleValue::Unassigned, 0),
+            Info::Tiny(20) => (SimpleValue::False, 0),
+            Info::Tiny(21) => (SimpleValue::True, 0),
             Info::Tiny(22) => (SimpleValue::Null, 0),
-            Info::Tiny(23) => err_at!(FailCbor, msg: "simple-value-undefined")?,
-            Info::Tiny(_) => err_at!(FailCbor, msg: "simple-value-unassigned")?,
-            Info::U8 => err_at!(FailCbor, msg: "simple-value-unassigned1")?,
-            Info::U16 => err_at!(FailCbor, msg: "simple-value-f16")?,
+            Info::Tiny(23) => (SimpleValue::Undefined, 0),
+            Info::Tiny(_) => err_at!(FailCbor, msg: "simple-value-invalid")?,
+            Info::U8 => {
+                read_r!(r, &mut scratch[..1]);
+                match scratch[0] {
+                    val @ 32..=255 => (SimpleValue::Reserved24(val), 1),
+                    val => err_at!(FailCbor, msg: "simple-value {} in two bytes", val)?,
+                }
+            }
+            Info::U16 => {
+                read_r!(r, &mut scratch[..2]);
+                let val = u16::from_be_bytes(scratch[..2].try_into().unwrap());
+                (SimpleValue::F16(val), 2)
+            }
             Info::U32 => {
                 read_r!(r, &mut scratch[..4]);
                 let val = f32::from_be_bytes(scratch[..4].try_into().unwrap());
@@ -769,18 +2060,38 @@ impl SimpleValue {
 
 #[derive(Copy, Clone)]
 enum TagNum {
+    DateTime = 0,
+    Epoch = 1,
     UBigNum = 2,
     SBigNum = 3,
+    DecimalFraction = 4,
+    BigFloat = 5,
+    Base64Url = 21,
+    Base64 = 22,
+    Base16 = 23,
+    EncodedCbor = 24,
+    Uri = 32,
     Identifier = 39,
+    SelfDescribed = 55799,
     Any = 65535, // always invalid
 }
 
 impl From<u64> for TagNum {
     fn from(num: u64) -> TagNum {
         match num {
+            0 => TagNum::DateTime,
+            1 => TagNum::Epoch,
             2 => TagNum::UBigNum,
             3 => TagNum::SBigNum,
+            4 => TagNum::DecimalFraction,
+            5 => TagNum::BigFloat,
+            21 => TagNum::Base64Url,
+            22 => TagNum::Base64,
+            23 => TagNum::Base16,
+            24 => TagNum::EncodedCbor,
+            32 => TagNum::Uri,
             39 => TagNum::Identifier,
+            55799 => TagNum::SelfDescribed,
             _ => TagNum::Any,
         }
     }
@@ -788,29 +2099,95 @@ impl From<u64> for TagNum {
 
 /// Major type 6, Tag values. Refer to Cbor [spec] for details.
 ///
+/// Standard tags have a dedicated variant, whose content is validated while
+/// decoding, failing with [Error::FailCbor] on mismatch. Rest of the tags,
+/// unknown to this implementation, are decoded as [Tag::Value] and encoded
+/// back as is.
+///
 /// [spec]: https://tools.ietf.org/html/rfc7049
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum Tag {
+    /// Tag 0, RFC3339 date/time, text-string like `"2013-03-21T20:04:00Z"`.
+    /// Refer to [Tag::from_datetime].
+    DateTime(Box<Cbor>),
+    /// Tag 1, epoch-based date/time, integer or floating-point seconds
+    /// relative to 1970-01-01T00:00Z. Refer to [Tag::from_epoch].
+    Epoch(Box<Cbor>),
     /// Tag 2, arbitrarily sized positive integers, byte-string in network byte order.
     UBigNum(Box<Cbor>),
-    /// Tag 3, arbitrarily sized signed integers, byte-string in network byte order.
+    /// Tag 3, arbitrarily sized negative integers, byte-string holding `-1 - n`
+    /// in network byte order, for negative value `n`.
     SBigNum(Box<Cbor>),
+    /// Tag 4, decimal fraction, list of integer exponent and integer or
+    /// big-num mantissa, for value `mantissa * 10^exponent`.
+    DecimalFraction(Box<Cbor>),
+    /// Tag 5, big-float, list of integer exponent and integer or big-num
+    /// mantissa, for value `mantissa * 2^exponent`.
+    BigFloat(Box<Cbor>),
+    /// Tags 21, 22 and 23, expected conversion of byte-strings nested under
+    /// the tagged value, refer to [Cbor::as_expected_encoding].
+    Expected(ExpectedEncoding, Box<Cbor>),
+    /// Tag 24, encoded cbor data item, byte-string holding a well-formed
+    /// cbor value. Refer to [Embedded] for transparently converting the
+    /// nested value.
+    EncodedCbor(Box<Cbor>),
+    /// Tag 32, URI, text-string as defined by RFC3986.
+    Uri(Box<Cbor>),
     /// Tag 39, used as identifier marker. This implementation shall
     /// treat them as literal values. Used by `Cborize` procedural
     /// macro to match values with types.
     Identifier(Box<Cbor>),
-    /// Catch all tag-value, follows the generic Tag specification
-    /// for Cbor.
-    Value(u64),
+    /// Tag 55799, self-described cbor, marks the data as cbor and carries
+    /// no other meaning.
+    SelfDescribed(Box<Cbor>),
+    /// Catch all tag-value, tag number along with the tagged value, for
+    /// tags that don't have a dedicated variant.
+    Value(u64, Box<Cbor>),
 }
 
 #[cfg(any(feature = "arbitrary", test))]
 impl<'a> Arbitrary<'a> for Tag {
     fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
         let tag = *u
-            .choose(&[TagNum::UBigNum, TagNum::SBigNum, TagNum::Identifier, TagNum::Any])
+            .choose(&[
+                TagNum::DateTime,
+                TagNum::Epoch,
+                TagNum::UBigNum,
+                TagNum::SBigNum,
+                TagNum::DecimalFraction,
+                TagNum::BigFloat,
+                TagNum::Base64Url,
+                TagNum::Base64,
+                TagNum::Base16,
+                TagNum::EncodedCbor,
+                TagNum::Uri,
+                TagNum::Identifier,
+                TagNum::SelfDescribed,
+                TagNum::Any,
+            ])
             .unwrap();
         match tag {
+            TagNum::DateTime | TagNum::Uri => {
+                let val = u.arbitrary::<String>()?.into_cbor().unwrap();
+                Ok(Tag::from_num(tag as u64, val))
+            }
+            TagNum::Epoch => {
+                let val = u.arbitrary::<i64>()?.into_cbor().unwrap();
+                Ok(Tag::Epoch(Box::new(val)))
+            }
+            TagNum::DecimalFraction | TagNum::BigFloat => {
+                let (exp, mantissa) = (u.arbitrary::<i64>()?, u.arbitrary::<i128>()?);
+                let val = vec![exp.into_cbor().unwrap(), mantissa.into_cbor().unwrap()];
+                Ok(Tag::from_num(tag as u64, val.into_cbor().unwrap()))
+            }
+            TagNum::Base64Url | TagNum::Base64 | TagNum::Base16 => {
+                let val: Cbor = u.arbitrary()?;
+                Ok(Tag::from_num(tag as u64, val))
+            }
+            TagNum::SelfDescribed => {
+                let val: Cbor = u.arbitrary()?;
+                Ok(Tag::SelfDescribed(Box::new(val)))
+            }
             TagNum::UBigNum | TagNum::SBigNum => {
                 let val: BigInt = u.arbitrary()?;
                 let (sign, bytes) = val.to_bytes_be();
@@ -820,13 +2197,22 @@ impl<'a> Arbitrary<'a> for Tag {
                     Sign::Minus => Ok(Tag::SBigNum(val)),
                 }
             }
+            TagNum::EncodedCbor => {
+                let val: Cbor = u.arbitrary()?;
+                Ok(Tag::from_encoded_cbor(val).unwrap())
+            }
             TagNum::Identifier => {
                 let val: Cbor = u.arbitrary()?;
                 Ok(Tag::Identifier(Box::new(val)))
             }
             TagNum::Any => {
                 let num: u64 = u.arbitrary()?;
-                Ok(Tag::Value(num))
+                let num = match TagNum::from(num) {
+                    TagNum::Any => num,
+                    _ => num + 1000,
+                };
+                let val: Cbor = u.arbitrary()?;
+                Ok(Tag::Value(num, Box::new(val)))
             }
         }
     }
@@ -840,88 +2226,281 @@ impl From<Tag> for Cbor {
 }
 
 impl Tag {
-    /// Construct a Tag value from u64 type.
-    pub fn from_value(value: u64) -> Tag {
-        Tag::Value(value)
+    /// Construct a Tag value from tag number and the tagged value, using the
+    /// dedicated variant for standard tags. Content is not validated.
+    pub fn from_value(value: u64, content: Cbor) -> Tag {
+        Tag::from_num(value, content)
+    }
+
+    // Wrap value with tag number `num`, with dedicated variant if any.
+    pub(crate) fn from_num(num: u64, val: Cbor) -> Tag {
+        let val = Box::new(val);
+        match TagNum::from(num) {
+            TagNum::DateTime => Tag::DateTime(val),
+            TagNum::Epoch => Tag::Epoch(val),
+            TagNum::UBigNum => Tag::UBigNum(val),
+            TagNum::SBigNum => Tag::SBigNum(val),
+            TagNum::DecimalFraction => Tag::DecimalFraction(val),
+            TagNum::BigFloat => Tag::BigFloat(val),
+            TagNum::Base64Url => Tag::Expected(ExpectedEncoding::Base64Url, val),
+            TagNum::Base64 => Tag::Expected(ExpectedEncoding::Base64, val),
+            TagNum::Base16 => Tag::Expected(ExpectedEncoding::Base16, val),
+            TagNum::EncodedCbor => Tag::EncodedCbor(val),
+            TagNum::Uri => Tag::Uri(val),
+            TagNum::Identifier => Tag::Identifier(val),
+            TagNum::SelfDescribed => Tag::SelfDescribed(val),
+            TagNum::Any => Tag::Value(num, val),
+        }
+    }
+
+    /// Wrap value with Identifier tag.
+    pub fn from_identifier(value: Cbor) -> Tag {
+        Tag::Identifier(Box::new(value))
+    }
+
+    /// Encode value and wrap the encoded bytes with tag 24, encoded cbor
+    /// data item.
+    pub fn from_encoded_cbor(value: Cbor) -> Result<Tag> {
+        let mut buf: Vec<u8> = Vec::default();
+        value.encode(&mut buf)?;
+        Ok(Tag::EncodedCbor(Box::new(Cbor::from_bytes(buf)?)))
+    }
+
+    /// Return the tagged value.
+    pub fn as_content(&self) -> &Cbor {
+        match self {
+            Tag::DateTime(val)
+            | Tag::Epoch(val)
+            | Tag::UBigNum(val)
+            | Tag::SBigNum(val)
+            | Tag::DecimalFraction(val)
+            | Tag::BigFloat(val)
+            | Tag::Expected(_, val)
+            | Tag::EncodedCbor(val)
+            | Tag::Uri(val)
+            | Tag::Identifier(val)
+            | Tag::SelfDescribed(val)
+            | Tag::Value(_, val) => val,
+        }
+    }
+
+    // Return the tagged value, that can be modified in place.
+    pub(crate) fn as_content_mut(&mut self) -> &mut Cbor {
+        match self {
+            Tag::DateTime(val)
+            | Tag::Epoch(val)
+            | Tag::UBigNum(val)
+            | Tag::SBigNum(val)
+            | Tag::DecimalFraction(val)
+            | Tag::BigFloat(val)
+            | Tag::Expected(_, val)
+            | Tag::EncodedCbor(val)
+            | Tag::Uri(val)
+            | Tag::Identifier(val)
+            | Tag::SelfDescribed(val)
+            | Tag::Value(_, val) => val,
+        }
     }
 
-    /// Wrap value with Identifier tag.
-    pub fn from_identifier(value: Cbor) -> Tag {
-        Tag::Identifier(Box::new(value))
+    /// Strip the tag and return the tagged value.
+    pub fn into_content(self) -> Cbor {
+        match self {
+            Tag::DateTime(val)
+            | Tag::Epoch(val)
+            | Tag::UBigNum(val)
+            | Tag::SBigNum(val)
+            | Tag::DecimalFraction(val)
+            | Tag::BigFloat(val)
+            | Tag::Expected(_, val)
+            | Tag::EncodedCbor(val)
+            | Tag::Uri(val)
+            | Tag::Identifier(val)
+            | Tag::SelfDescribed(val)
+            | Tag::Value(_, val) => *val,
+        }
     }
 
     /// Fetch the u64 type value for tag.
     pub fn to_tag_value(&self) -> u64 {
         match self {
+            Tag::DateTime(_) => TagNum::DateTime as u64,
+            Tag::Epoch(_) => TagNum::Epoch as u64,
             Tag::UBigNum(_) => TagNum::UBigNum as u64,
             Tag::SBigNum(_) => TagNum::SBigNum as u64,
+            Tag::DecimalFraction(_) => TagNum::DecimalFraction as u64,
+            Tag::BigFloat(_) => TagNum::BigFloat as u64,
+            Tag::Expected(ExpectedEncoding::Base64Url, _) => TagNum::Base64Url as u64,
+            Tag::Expected(ExpectedEncoding::Base64, _) => TagNum::Base64 as u64,
+            Tag::Expected(ExpectedEncoding::Base16, _) => TagNum::Base16 as u64,
+            Tag::EncodedCbor(_) => TagNum::EncodedCbor as u64,
+            Tag::Uri(_) => TagNum::Uri as u64,
             Tag::Identifier(_) => TagNum::Identifier as u64,
-            Tag::Value(val) => *val,
+            Tag::SelfDescribed(_) => TagNum::SelfDescribed as u64,
+            Tag::Value(val, _) => *val,
         }
     }
 
-    fn encode<W>(tag: &Tag, w: &mut W) -> Result<usize>
+    // Encode tag, with header in the width of `info`, and the value it tags,
+    // `depth` is the depth of the tag item. Like Tag::decode, chain of tags
+    // is encoded in a loop.
+    fn encode<W>(info: Info, tag: &Tag, w: &mut W, depth: u32) -> Result<usize>
     where
         W: io::Write,
     {
-        let num = tag.to_tag_value();
-        let mut n = encode_addnl(num, w)?;
-        n += match tag {
-            Tag::UBigNum(val) => val.encode(w)?,
-            Tag::SBigNum(val) => val.encode(w)?,
-            Tag::Identifier(val) => val.encode(w)?,
-            Tag::Value(_) => 0,
-        };
-
-        Ok(n)
+        let (mut info, mut tag, mut depth, mut n) = (info, tag, depth, 0);
+        loop {
+            n += encode_num(6, info, tag.to_tag_value(), w)?;
+            depth += 1;
+            match tag.as_content() {
+                Cbor::Major6(_, _) if depth > RECURSION_LIMIT => {
+                    err_at!(FailCbor, msg: "encode recursion limit exceeded")?
+                }
+                Cbor::Major6(tinfo, ttag) => {
+                    info = *tinfo;
+                    tag = ttag;
+                }
+                val => break Ok(n + val.do_encode(w, depth)?),
+            }
+        }
     }
 
-    fn decode<R>(info: Info, r: &mut R) -> Result<(Tag, usize)>
+    // Decode tag, whose header of `n` bytes is read from offset `off`, and
+    // the value it tags, `depth` is the depth of the tag item. Tags tagging
+    // other tags are decoded in a loop, so that a chain of tags does not
+    // recurse for every tag, while every tag is counted against the limits.
+    fn decode<R>(
+        info: Info,
+        r: &mut R,
+        depth: u32,
+        off: usize,
+        n: usize,
+        config: &DecodeConfig,
+        state: &mut DecodeState,
+    ) -> Result<(Cbor, usize)>
     where
         R: io::Read,
     {
-        let (tag, n) = decode_addnl(info, r)?;
-        let (tag, m) = match TagNum::from(tag) {
-            TagNum::UBigNum => {
-                let (val, m) = Cbor::decode(r)?;
-                (Tag::UBigNum(Box::new(val)), m)
+        let mut chain = vec![]; // (info, num, offset), outermost tag first.
+        let (mut info, mut tag_off, mut vdepth, mut m) = (info, off, depth, 0);
+        let (mut val, k) = loop {
+            let (num, k) = decode_addnl(info, r)?;
+            m += k;
+            chain.push((info, num, tag_off));
+            vdepth += 1;
+            check_depth(vdepth, config)?;
+            match decode_hdr(r)? {
+                (6, tinfo, k) => {
+                    tag_off = off + n + m;
+                    info = tinfo;
+                    m += k;
+                }
+                hdr => {
+                    break Cbor::decode_item(hdr, r, vdepth, off + n + m, config, state)?
+                }
             }
-            TagNum::SBigNum => {
-                let (val, m) = Cbor::decode(r)?;
-                (Tag::SBigNum(Box::new(val)), m)
+        };
+        m += k;
+
+        // wrap the tagged value from the innermost tag outwards.
+        for (info, num, tag_off) in chain.into_iter().rev() {
+            vdepth -= 1;
+            let tag = Tag::from_num(num, val);
+            tag.validate()?;
+            // embedded value is nested under the byte-string, refer to
+            // Cbor::decode_root.
+            if let Tag::EncodedCbor(val) = &tag {
+                if let Cbor::Major2(_, data) = val.as_ref() {
+                    let off = off + n + m - data.len();
+                    state.embedded.push((data.clone(), vdepth + 2, off));
+                }
             }
-            TagNum::Identifier => {
-                let (val, m) = Cbor::decode(r)?;
-                (Tag::Identifier(Box::new(val)), m)
+            val = match tag {
+                Tag::Value(num, val) if !config.known_tags.contains(&num) => match config
+                    .unknown_tag_policy
+                {
+                    UnknownTagPolicy::Keep => Cbor::Major6(info, Tag::Value(num, val)),
+                    UnknownTagPolicy::Reject => {
+                        err_at!(FailCbor, msg: "unknown tag {} at offset {}", num, tag_off)?
+                    }
+                    UnknownTagPolicy::Unwrap => *val,
+                },
+                tag => Cbor::Major6(info, tag),
+            };
+            // outermost tag is counted by the caller, like any other item.
+            if vdepth > depth {
+                count_item(&val, vdepth, config, state)?;
             }
-            _ => (Tag::Value(tag as u64), 0),
-        };
-        Ok((tag, m + n))
+        }
+
+        Ok((val, m))
     }
 
-    fn pretty_print(&self, p: &str) -> Result<String> {
+    fn pretty_print(&self, p: &str, indent: &str) -> Result<String> {
         let s = match self {
             Tag::UBigNum(val) => {
                 let val = BigInt::from_bytes_be(Sign::Plus, &val.clone().into_bytes()?);
                 format!("Tag::UBigNum(0x{:x})", val)
             }
             Tag::SBigNum(val) => {
-                let val = BigInt::from_bytes_be(Sign::Minus, &val.clone().into_bytes()?);
+                let val = BigInt::from_bytes_be(Sign::Plus, &val.clone().into_bytes()?);
+                let val = -1 - val;
                 format!("Tag::SBigNum(0x{:x})", val)
             }
+            Tag::EncodedCbor(val) => {
+                let mut ss = vec!["Tag::EncodedCbor".to_string()];
+                let p = p.to_owned() + indent;
+                let data = val.clone().into_bytes()?;
+                ss.push(decode_exact(&data)?.pretty_print(&p, indent)?);
+                ss.join("\n")
+            }
             Tag::Identifier(val) => {
                 let mut ss = vec!["Tag::Identifier".to_string()];
-                let p = p.to_owned() + "  ";
-                ss.push(val.pretty_print(&p)?);
+                let p = p.to_owned() + indent;
+                ss.push(val.pretty_print(&p, indent)?);
+                ss.join("\n")
+            }
+            tag => {
+                let name = match tag {
+                    Tag::DateTime(_) => "Tag::DateTime".to_string(),
+                    Tag::Epoch(_) => "Tag::Epoch".to_string(),
+                    Tag::DecimalFraction(_) => "Tag::DecimalFraction".to_string(),
+                    Tag::BigFloat(_) => "Tag::BigFloat".to_string(),
+                    Tag::Expected(enc, _) => format!("Tag::Expected({:?})", enc),
+                    Tag::Uri(_) => "Tag::Uri".to_string(),
+                    Tag::SelfDescribed(_) => "Tag::SelfDescribed".to_string(),
+                    tag => format!("Tag::Value(0x{:x})", tag.to_tag_value()),
+                };
+                let mut ss = vec![name];
+                let p = p.to_owned() + indent;
+                ss.push(tag.as_content().pretty_print(&p, indent)?);
                 ss.join("\n")
             }
-            Tag::Value(val) => format!("Tag::Value(0x{:x})", val),
         };
 
         Ok(s)
     }
 }
 
+/// Expected conversion of byte-strings into text, refer to
+/// [Cbor::as_expected_encoding].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ExpectedEncoding {
+    /// Tag 21, base64url encoding without padding.
+    Base64Url,
+    /// Tag 22, base64 encoding with padding.
+    Base64,
+    /// Tag 23, base16 encoding.
+    Base16,
+}
+
+/// Wrapper type to nest a value as tag 24, encoded cbor data item.
+///
+/// [IntoCbor] shall encode the wrapped value and embed the encoded bytes
+/// as tagged byte-string, while [FromCbor] shall validate and decode the
+/// embedded bytes back into the wrapped type.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Embedded<T>(pub T);
+
 /// Possible types that can be used as a key in cbor-map.
 #[derive(Debug, Clone)]
 pub enum Key {
@@ -1041,11 +2620,798 @@ impl PartialOrd for Key {
     }
 }
 
+// Binary search for `key` in `map`, falling back to a scan of the entries,
+// unless the map is known to be sorted. On miss, return the offset at which
+// `key` can be inserted, keeping a sorted map sorted.
+fn map_search(
+    map: &[(Key, Cbor)],
+    key: &Key,
+    order: MapOrder,
+) -> result::Result<usize, usize> {
+    match map.binary_search_by(|(k, _)| k.cmp(key)) {
+        Ok(off) => Ok(off),
+        Err(off) if order.is_sorted() => Err(off),
+        Err(off) => {
+            match map.iter().position(|(k, _)| k.cmp(key) == cmp::Ordering::Equal) {
+                Some(off) => Ok(off),
+                None => Err(off),
+            }
+        }
+    }
+}
+
+fn lossy_text(info: Info, text: Vec<u8>, off: usize, cf: &DecodeConfig) -> Result<Cbor> {
+    let text = match cf.lossy_text {
+        LossyText::Unchecked => return Ok(Cbor::Major3(info, text)),
+        _ => match String::from_utf8(text) {
+            Ok(text) => return Ok(Cbor::Major3(info, text.into_bytes())),
+            Err(err) => err.into_bytes(),
+        },
+    };
+    let val = match cf.lossy_text {
+        LossyText::Strict => {
+            err_at!(FailCbor, msg: "invalid utf8 text at offset {}", off)?
+        }
+        LossyText::Replace => {
+            let text = String::from_utf8_lossy(&text).into_owned().into_bytes();
+            let info = match info {
+                Info::Indefinite => info,
+                _ => (text.len() as u64).into(),
+            };
+            Cbor::Major3(info, text)
+        }
+        _ => Cbor::Major2(info, text),
+    };
+    Ok(val)
+}
+
+fn check_depth(depth: u32, cf: &DecodeConfig) -> Result<()> {
+    let max_depth = match cf.max_depth {
+        Some(max_depth) => cmp::min(max_depth, RECURSION_LIMIT),
+        None => RECURSION_LIMIT,
+    };
+    if depth > max_depth {
+        err_at!(LimitExceeded, msg: "decode recursion limit {} exceeded", max_depth)?
+    }
+    Ok(())
+}
+
+// Update decode metrics with `val` decoded at `depth`, items are counted
+// against DecodeConfig::max_items.
+fn count_item(
+    val: &Cbor,
+    depth: u32,
+    cf: &DecodeConfig,
+    state: &mut DecodeState,
+) -> Result<()> {
+    if !matches!(val, Cbor::Major7(_, SimpleValue::Break)) {
+        state.metrics.max_depth = cmp::max(state.metrics.max_depth, depth);
+        state.metrics.item_count += 1;
+        match cf.max_items {
+            Some(max_items) if state.metrics.item_count > max_items => {
+                err_at!(LimitExceeded, msg: "item limit {} exceeded", max_items)?
+            }
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+fn check_string_len(len: usize, off: usize, cf: &DecodeConfig) -> Result<()> {
+    match cf.max_bytes {
+        Some(max_bytes) if len > max_bytes => err_at!(
+            LimitExceeded, msg: "string of {} bytes at offset {} exceeds limit {}",
+            len, off, max_bytes
+        ),
+        _ => Ok(()),
+    }
+}
+
+// Apply duplicate key policy and key ordering, from `cf`, on decoded map.
+fn decoded_map(
+    info: Info,
+    mut map: Vec<(Key, Cbor)>,
+    off: usize,
+    cf: &DecodeConfig,
+) -> Result<Cbor> {
+    let n = map.len();
+    match cf.duplicate_keys {
+        DuplicateKeyPolicy::Allow => (),
+        DuplicateKeyPolicy::Reject => {
+            if let Some(key) = find_duplicate_key(&map, cf.key_equality) {
+                err_at!(FailCbor, msg: "duplicate map key {:?} at offset {}", key, off)?
+            }
+        }
+        policy => {
+            // normalized key -> index of the entry to keep.
+            let mut keys: BTreeMap<Key, usize> = BTreeMap::new();
+            for (i, (key, _)) in map.iter().enumerate() {
+                let key = match cf.key_equality {
+                    KeyEquality::Structural => key.clone(),
+                    KeyEquality::Numeric => numeric_key(key),
+                };
+                match keys.get_mut(&key) {
+                    Some(j) if policy == DuplicateKeyPolicy::KeepLast => *j = i,
+                    Some(_) => (),
+                    None => {
+                        keys.insert(key, i);
+                    }
+                }
+            }
+            if keys.len() < map.len() {
+                let mut keep = vec![false; map.len()];
+                keys.values().for_each(|i| keep[*i] = true);
+                let mut iter = keep.into_iter();
+                map.retain(|_| iter.next().unwrap_or(false));
+            }
+        }
+    }
+    let order = match cf.sort_keys {
+        true => {
+            map.sort_by(|a, b| a.0.cmp(&b.0));
+            MapOrder::Sorted
+        }
+        false => MapOrder::of(&map),
+    };
+
+    let info = match info {
+        Info::Indefinite => info,
+        info if map.len() == n => info,
+        _ => (map.len() as u64).into(),
+    };
+    Ok(Cbor::Major5(info, map, order))
+}
+
+fn find_duplicate_key(map: &[(Key, Cbor)], eq: KeyEquality) -> Option<Key> {
+    let mut keys: Vec<Key> = match eq {
+        KeyEquality::Structural => map.iter().map(|(k, _)| k.clone()).collect(),
+        KeyEquality::Numeric => map.iter().map(|(k, _)| numeric_key(k)).collect(),
+    };
+    keys.sort();
+    keys.windows(2).find(|w| w[0] == w[1]).map(|w| w[0].clone())
+}
+
+// Normalize numeric keys, floats with integral value are converted to
+// integer keys and single precision is widened to double precision.
+fn numeric_key(key: &Key) -> Key {
+    let val = match key {
+        Key::F32(val) => *val as f64,
+        Key::F64(val) => *val,
+        key => return key.clone(),
+    };
+    match val {
+        val if val.fract() != 0.0 => Key::F64(val),
+        val if (0.0..18446744073709551616.0).contains(&val) => Key::U64(val as u64),
+        val if (-9223372036854775808.0..0.0).contains(&val) => Key::N64(val as i64),
+        val => Key::F64(val),
+    }
+}
+
+// Decode `data`, embedded under tag 24, as exactly one cbor value at
+// `depth`, the decoded value is discarded.
+fn decode_embedded(
+    data: &[u8],
+    depth: u32,
+    off: usize,
+    config: &DecodeConfig,
+    state: &mut DecodeState,
+) -> Result<()> {
+    // running out of embedded bytes is malformed input, not an io error.
+    let n = match Cbor::do_decode(&mut &data[..], depth, off, config, state) {
+        Ok((_, n)) => n,
+        Err(Error::IOError(_, msg)) => err_at!(FailCbor, msg: "tag 24 {}", msg)?,
+        Err(err) => return Err(err),
+    };
+    if n != data.len() {
+        err_at!(FailCbor, msg: "trailing bytes {} after tag 24 value", data.len() - n)?
+    }
+    Ok(())
+}
+
+/// Decode `buf` as exactly one cbor value, trailing bytes are treated
+/// as malformed input.
+pub(crate) fn decode_exact(buf: &[u8]) -> Result<Cbor> {
+    decode_exact_with(buf, &DecodeConfig::default())
+}
+
+/// Same as [decode_exact], with `config` to control the decoding.
+pub(crate) fn decode_exact_with(buf: &[u8], config: &DecodeConfig) -> Result<Cbor> {
+    let (val, n) = match Cbor::decode_with(&mut &buf[..], config) {
+        Ok(res) => res,
+        Err(err @ Error::LimitExceeded(_, _)) => return Err(err),
+        Err(err) => err_at!(FailCbor, msg: "{}", err)?,
+    };
+    if n != buf.len() {
+        err_at!(FailCbor, msg: "trailing bytes {} after cbor value", buf.len() - n)?
+    }
+    Ok(val)
+}
+
+/// Scan a single cbor item from the beginning of `buf` without decoding it,
+/// return the item's length in bytes. Return None if `buf` is truncated
+/// before the end of the item.
+pub(crate) fn scan_item(buf: &[u8]) -> Result<Option<usize>> {
+    scan_value(buf, 1)
+}
+
+/// Resumable version of [scan_item], for bytes that arrive incrementally.
+///
+/// Scan progress is retained across calls to [Scanner::scan], which shall
+/// be called with the same bytes, extended with newly received bytes, if
+/// any. That way bytes are scanned only once.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Scanner {
+    // bytes scanned so far, from the beginning of the item.
+    off: usize,
+    // enclosing items, yet to complete, innermost last. Number of pending
+    // items for definite-length items, and None for indefinite-length.
+    stack: Vec<Option<u64>>,
+}
+
+impl Scanner {
+    /// Continue scanning `buf`, return the item's length in bytes once the
+    /// item is complete, and reset the scanner for the next item. Return
+    /// None, if `buf` is truncated before the end of the item.
+    pub(crate) fn scan(&mut self, buf: &[u8]) -> Result<Option<usize>> {
+        Ok(self.scan_need(buf)?.ok())
+    }
+
+    /// Same as [Scanner::scan], but when `buf` is truncated, return the
+    /// minimum number of bytes, beyond `buf`, that are part of the item.
+    pub(crate) fn scan_need(
+        &mut self,
+        buf: &[u8],
+    ) -> Result<result::Result<usize, usize>> {
+        let res = self.do_scan(buf);
+        if !matches!(res, Ok(Err(_))) {
+            *self = Scanner::default();
+        }
+        res
+    }
+
+    fn do_scan(&mut self, buf: &[u8]) -> Result<result::Result<usize, usize>> {
+        loop {
+            let b = match buf.get(self.off) {
+                Some(b) => *b,
+                None => return Ok(Err(1)),
+            };
+            if (self.stack.len() as u32) >= RECURSION_LIMIT {
+                return err_at!(LimitExceeded, msg: "scan recursion limit exceeded");
+            }
+
+            if b == BREAK && self.stack.last() == Some(&None) {
+                self.off += 1;
+                self.stack.pop();
+                if let Some(n) = self.complete() {
+                    return Ok(Ok(n));
+                }
+                continue;
+            }
+
+            let (major, info) = ((b & 0xe0) >> 5, Info::try_from(b & 0x1f)?);
+            if let Info::Reserved28 | Info::Reserved29 | Info::Reserved30 = info {
+                err_at!(FailCbor, msg: "reserved additional info {:?}", info)?
+            }
+            let (num, n) = match scan_addnl(info, &buf[(self.off + 1)..]) {
+                Some(val) => val,
+                None => return Ok(Err((self.off + 1 + addnl_len(info)) - buf.len())),
+            };
+
+            let hdr = 1 + n;
+            match (major, info) {
+                (2, Info::Indefinite)
+                | (3, Info::Indefinite)
+                | (4, Info::Indefinite)
+                | (5, Info::Indefinite) => {
+                    self.off += hdr;
+                    self.stack.push(None);
+                    continue;
+                }
+                (2, _) | (3, _) => {
+                    let len: usize = err_at!(FailConvert, num.try_into())?;
+                    let end = self.off.saturating_add(hdr).saturating_add(len);
+                    if buf.len() < end {
+                        return Ok(Err(end - buf.len()));
+                    }
+                    self.off = end;
+                }
+                (4, _) | (5, _) if num > 0 => {
+                    self.off += hdr;
+                    let num = if major == 5 { num.saturating_mul(2) } else { num };
+                    self.stack.push(Some(num));
+                    continue;
+                }
+                (6, _) => {
+                    self.off += hdr;
+                    self.stack.push(Some(1));
+                    continue;
+                }
+                (_, _) => self.off += hdr,
+            }
+
+            if let Some(n) = self.complete() {
+                return Ok(Ok(n));
+            }
+        }
+    }
+
+    // An item at `self.off` is complete, return the length of the top-level
+    // item, if that completes as well.
+    fn complete(&mut self) -> Option<usize> {
+        loop {
+            match self.stack.last_mut() {
+                None => break Some(self.off),
+                Some(Some(1)) => {
+                    self.stack.pop();
+                }
+                Some(Some(num)) => {
+                    *num -= 1;
+                    break None;
+                }
+                Some(None) => break None,
+            }
+        }
+    }
+}
+
+fn addnl_len(info: Info) -> usize {
+    match info {
+        Info::U8 => 1,
+        Info::U16 => 2,
+        Info::U32 => 4,
+        Info::U64 => 8,
+        _ => 0,
+    }
+}
+
+fn scan_value(buf: &[u8], depth: u32) -> Result<Option<usize>> {
+    macro_rules! scan_child {
+        ($off:ident) => {
+            match scan_value(&buf[$off..], depth + 1)? {
+                Some(k) => $off += k,
+                None => return Ok(None),
+            }
+        };
+    }
+
+    if depth > RECURSION_LIMIT {
+        return err_at!(LimitExceeded, msg: "scan recursion limit exceeded");
+    }
+
+    let (major, info) = match buf.first() {
+        Some(b) => ((b & 0xe0) >> 5, Info::try_from(b & 0x1f)?),
+        None => return Ok(None),
+    };
+    let (num, n) = match scan_addnl(info, &buf[1..]) {
+        Some(val) => val,
+        None => return Ok(None),
+    };
+
+    let mut off = 1 + n;
+    match (major, info) {
+        (_, Info::Reserved28) | (_, Info::Reserved29) | (_, Info::Reserved30) => {
+            err_at!(FailCbor, msg: "reserved additional info {:?}", info)?
+        }
+        (2, Info::Indefinite)
+        | (3, Info::Indefinite)
+        | (4, Info::Indefinite)
+        | (5, Info::Indefinite) => loop {
+            match buf.get(off) {
+                Some(0xff) => {
+                    off += 1;
+                    break;
+                }
+                Some(_) if major == 5 => {
+                    scan_child!(off);
+                    scan_child!(off);
+                }
+                Some(_) => scan_child!(off),
+                None => return Ok(None),
+            }
+        },
+        (2, _) | (3, _) => {
+            let len: usize = err_at!(FailConvert, num.try_into())?;
+            if (buf.len() - off) < len {
+                return Ok(None);
+            }
+            off += len;
+        }
+        (4, _) => {
+            for _ in 0..num {
+                scan_child!(off);
+            }
+        }
+        (5, _) => {
+            for _ in 0..num {
+                scan_child!(off);
+                scan_child!(off);
+            }
+        }
+        (6, _) => {
+            // skip the chain of tags in a loop, refer to Tag::decode.
+            let mut depth = depth + 1;
+            while let Some(b) = buf.get(off).filter(|b| (*b >> 5) == 6) {
+                let info = Info::try_from(b & 0x1f)?;
+                if matches!(info, Info::Reserved28 | Info::Reserved29 | Info::Reserved30)
+                {
+                    break;
+                } else if depth > RECURSION_LIMIT {
+                    err_at!(LimitExceeded, msg: "scan recursion limit exceeded")?
+                }
+                match scan_addnl(info, &buf[(off + 1)..]) {
+                    Some((_, k)) => off += 1 + k,
+                    None => return Ok(None),
+                }
+                depth += 1;
+            }
+            match scan_value(&buf[off..], depth)? {
+                Some(k) => off += k,
+                None => return Ok(None),
+            }
+        }
+        (_, _) => (),
+    }
+
+    Ok(Some(off))
+}
+
+// Strip outer tags numbered in `tags`.
+fn strip_tags<'a>(mut val: &'a Cbor, tags: &[u64]) -> &'a Cbor {
+    while let Cbor::Major6(_, tag) = val {
+        match tags.contains(&tag.to_tag_value()) {
+            true => val = tag.as_content(),
+            false => break,
+        }
+    }
+    val
+}
+
+/// Serialize a map of `len` entries in canonical form, refer to
+/// [Cbor::encode_canonical], streaming the entries from `pairs` instead of
+/// buffering and sorting them in memory.
+///
+/// Precondition: entries must be sorted by the bytewise lexicographic order
+/// of their canonically encoded keys, without duplicates. Note that this is
+/// not the same as [Key]'s `Ord` implementation, in particular shorter
+/// text keys sort before longer ones, for example `"b"` before `"aa"`, and
+/// all integer keys sort before all text keys.
+///
+/// Fail with [Error::FailCbor] when a key is out of order or repeated, and
+/// when `pairs` yield more or less than `len` entries. Only the previous
+/// key is held in memory for validation. On failure, bytes written so far
+/// are left in `w`, which shall not be treated as a valid cbor value.
+pub fn encode_canonical_map<W, I>(w: &mut W, len: usize, pairs: I) -> Result<usize>
+where
+    W: io::Write,
+    I: IntoIterator<Item = (Key, Cbor)>,
+{
+    let num = err_at!(FailConvert, u64::try_from(len))?;
+    let mut n = encode_hdr(5, num.into(), w)? + encode_addnl(num, w)?;
+
+    let (mut prev, mut key_buf) = (Vec::<u8>::default(), Vec::<u8>::default());
+    let mut count = 0;
+    for (key, val) in pairs.into_iter() {
+        if count == len {
+            err_at!(FailCbor, msg: "more than {} map entries", len)?
+        }
+        key_buf.clear();
+        key.into_cbor()?.do_encode_canonical(&mut key_buf, 2)?;
+        if count > 0 && key_buf <= prev {
+            err_at!(FailCbor, msg: "map key {:?} is not sorted or repeated", key_buf)?
+        }
+        write_w!(w, &key_buf);
+        n += key_buf.len() + val.do_encode_canonical(w, 2)?;
+        mem::swap(&mut prev, &mut key_buf);
+        count += 1;
+    }
+    if count < len {
+        err_at!(FailCbor, msg: "expected {} map entries, got {}", len, count)?
+    }
+    Ok(n)
+}
+
+/// Serialize an indefinite length array, streaming the items from `items`,
+/// for writers that do not know the number of items upfront. Same as
+/// encoding a `Cbor::Major4(Info::Indefinite, ..)` value, without buffering
+/// the items in memory.
+pub fn encode_indefinite_array<W, I>(w: &mut W, items: I) -> Result<usize>
+where
+    W: io::Write,
+    I: IntoIterator<Item = Cbor>,
+{
+    let mut n = encode_hdr(4, Info::Indefinite, w)?;
+    for item in items.into_iter() {
+        n += item.do_encode(w, 2)?;
+    }
+    write_w!(w, &[BREAK]);
+    Ok(n + 1)
+}
+
+/// Serialize an indefinite length map, streaming the entries from `pairs`,
+/// refer to [encode_indefinite_array]. Entries are written in the order
+/// yielded by `pairs`, and repeated keys are not detected.
+pub fn encode_indefinite_map<W, I>(w: &mut W, pairs: I) -> Result<usize>
+where
+    W: io::Write,
+    I: IntoIterator<Item = (Key, Cbor)>,
+{
+    let mut n = encode_hdr(5, Info::Indefinite, w)?;
+    for (key, val) in pairs.into_iter() {
+        n += key.into_cbor()?.do_encode(w, 2)?;
+        n += val.do_encode(w, 2)?;
+    }
+    write_w!(w, &[BREAK]);
+    Ok(n + 1)
+}
+
+/// Check whether `buf` holds exactly one cbor value in deterministic, canonical,
+/// form, as per RFC-8949 section 4.2.1. Return false at the first violation
+/// of the following rules:
+///
+/// * Integers, lengths and tag numbers are encoded in the shortest form.
+/// * Strings, arrays and maps are of definite length.
+/// * Map keys are sorted by the bytewise lexicographic order of their
+///   encoding, without duplicates.
+/// * Floats are encoded in the shortest width that preserves the value,
+///   and NaN is encoded as `0xf97e00`.
+///
+/// Malformed and truncated input, and trailing bytes after the value,
+/// shall return an error.
+pub fn is_canonical(buf: &[u8]) -> Result<bool> {
+    match canonical_value(buf, 1)? {
+        Some(n) if n == buf.len() => Ok(true),
+        Some(n) => {
+            err_at!(FailCbor, msg: "trailing bytes {} after cbor value", buf.len() - n)
+        }
+        None => Ok(false),
+    }
+}
+
+// Return the item's length in bytes, None if the item is not canonical.
+fn canonical_value(buf: &[u8], depth: u32) -> Result<Option<usize>> {
+    macro_rules! canonical_child {
+        ($off:ident) => {
+            match canonical_value(&buf[$off..], depth + 1)? {
+                Some(k) => $off += k,
+                None => return Ok(None),
+            }
+        };
+    }
+
+    if depth > RECURSION_LIMIT {
+        return err_at!(LimitExceeded, msg: "scan recursion limit exceeded");
+    }
+
+    let (major, info) = match buf.first() {
+        Some(b) => ((b & 0xe0) >> 5, Info::try_from(b & 0x1f)?),
+        None => return err_at!(FailCbor, msg: "truncated cbor"),
+    };
+    let (num, n) = match scan_addnl(info, &buf[1..]) {
+        Some(val) => val,
+        None => return err_at!(FailCbor, msg: "truncated cbor"),
+    };
+
+    let shortest = match info {
+        Info::Tiny(_) => true,
+        Info::U8 => num >= 24,
+        Info::U16 => num > 0xff,
+        Info::U32 => num > 0xffff,
+        Info::U64 => num > 0xffff_ffff,
+        Info::Indefinite => false,
+        _ => err_at!(FailCbor, msg: "reserved additional info {:?}", info)?,
+    };
+
+    let mut off = 1 + n;
+    match major {
+        7 => {
+            let ok = match info {
+                Info::Indefinite => err_at!(FailCbor, msg: "unexpected break")?,
+                Info::Tiny(_) => true,
+                Info::U8 => num >= 32,
+                Info::U16 => !f16_is_nan(num as u16) || num == 0x7e00,
+                Info::U32 => {
+                    is_canonical_float(f64::from(f32::from_bits(num as u32)), 10)
+                }
+                Info::U64 => is_canonical_float(f64::from_bits(num), 23),
+                _ => false,
+            };
+            if !ok {
+                return Ok(None);
+            }
+        }
+        _ if !shortest => return Ok(None),
+        0 | 1 => (),
+        2 | 3 => {
+            let len: usize = err_at!(FailConvert, num.try_into())?;
+            if (buf.len() - off) < len {
+                return err_at!(FailCbor, msg: "truncated cbor");
+            }
+            off += len;
+        }
+        4 => {
+            for _ in 0..num {
+                canonical_child!(off);
+            }
+        }
+        5 => {
+            let mut prev: Option<&[u8]> = None;
+            for _ in 0..num {
+                let start = off;
+                canonical_child!(off);
+                let key = &buf[start..off];
+                match prev {
+                    Some(prev) if prev >= key => return Ok(None),
+                    _ => prev = Some(key),
+                }
+                canonical_child!(off);
+            }
+        }
+        6 => canonical_child!(off),
+        _ => unreachable!(),
+    }
+
+    Ok(Some(off))
+}
+
+fn f16_is_nan(bits: u16) -> bool {
+    (bits & 0x7c00) == 0x7c00 && (bits & 0x03ff) != 0
+}
+
+// Check that `val` cannot be encoded in the narrower float width, having
+// `mantissa` bits of precision, that is 10 for half and 23 for single precision.
+fn is_canonical_float(val: f64, mantissa: i32) -> bool {
+    // zero, infinity and NaN are always encoded as half precision.
+    !(val.is_nan() || fits_float(val, mantissa))
+}
+
+// Check that `val` can be encoded, without loss, in the float width having
+// `mantissa` bits of precision, that is 10 for half and 23 for single
+// precision.
+fn fits_float(val: f64, mantissa: i32) -> bool {
+    let (min_exp, max_exp) = match mantissa {
+        10 => (-14, 15),
+        _ => (-126, 127),
+    };
+    if val == 0.0 || val.is_infinite() {
+        return true;
+    }
+
+    let bits = val.to_bits();
+    let (exp, frac) = (((bits >> 52) & 0x7ff) as i32, bits & 0x000f_ffff_ffff_ffff);
+    if exp == 0 {
+        // double precision subnormals are too small for narrower widths.
+        return false;
+    }
+    let exp = exp - 1023;
+    // number of significant bits after the binary point.
+    let width = match frac {
+        0 => 0,
+        frac => 52 - frac.trailing_zeros() as i32,
+    };
+    exp <= max_exp && width <= mantissa && (exp - width) >= (min_exp - mantissa)
+}
+
+// Widen half precision `bits` into double precision, exactly.
+pub(crate) fn f16_to_f64(bits: u16) -> f64 {
+    let sign = if (bits >> 15) == 1 { -1.0 } else { 1.0 };
+    let (exp, frac) = (i32::from((bits >> 10) & 0x1f), f64::from(bits & 0x3ff));
+    match exp {
+        // signed zero and subnormals, in units of 2^-24.
+        0 => sign * frac * 2_f64.powi(-24),
+        0x1f if frac == 0.0 => sign * f64::INFINITY,
+        0x1f => f64::NAN,
+        exp => sign * (1024.0 + frac) * 2_f64.powi(exp - 25),
+    }
+}
+
+// Shortest float that preserves `val`, bit for bit, including the sign of
+// zero. NaN is normalized to `0x7e00`.
+fn canonical_float(val: f64) -> SimpleValue {
+    match canonical_f16(val) {
+        Some(bits) if f16_to_f64(bits).to_bits() == val.to_bits() => {
+            SimpleValue::F16(bits)
+        }
+        Some(bits) if val.is_nan() => SimpleValue::F16(bits),
+        _ if f64::from(val as f32).to_bits() == val.to_bits() => {
+            SimpleValue::F32(val as f32)
+        }
+        _ => SimpleValue::F64(val),
+    }
+}
+
+// Narrow `val` into half precision, if it fits without loss.
+fn canonical_f16(val: f64) -> Option<u16> {
+    if val.is_nan() {
+        Some(0x7e00)
+    } else if fits_float(val, 10) {
+        let bits = val.to_bits();
+        let sign = ((bits >> 63) as u16) << 15;
+        let (exp, frac) =
+            (((bits >> 52) & 0x7ff) as i32 - 1023, bits & 0x000f_ffff_ffff_ffff);
+        let bits = if val == 0.0 {
+            sign
+        } else if val.is_infinite() {
+            sign | 0x7c00
+        } else if exp >= -14 {
+            sign | (((exp + 15) as u16) << 10) | ((frac >> 42) as u16)
+        } else {
+            // subnormal half precision, in units of 2^-24.
+            sign | ((((1_u64 << 52) | frac) >> (52 - (exp + 24))) as u16)
+        };
+        Some(bits)
+    } else {
+        None
+    }
+}
+
+// Return the additional value and its width, None if `buf` is truncated.
+fn scan_addnl(info: Info, buf: &[u8]) -> Option<(u64, usize)> {
+    let n = match info {
+        Info::Tiny(num) => return Some((num as u64, 0)),
+        Info::U8 => 1,
+        Info::U16 => 2,
+        Info::U32 => 4,
+        Info::U64 => 8,
+        _ => return Some((0, 0)),
+    };
+    let bytes = buf.get(..n)?;
+    let num = bytes.iter().fold(0_u64, |acc, b| (acc << 8) | (*b as u64));
+    Some((num, n))
+}
+
 /// Return pretty formated string representing `val`.
 ///
 /// Can be printed on terminal or log-file for eye-ball verification.
 pub fn pretty_print(val: &Cbor) -> Result<String> {
-    val.pretty_print("")
+    pretty_print_with(val, &PrintConfig::default())
+}
+
+/// Same as [pretty_print], with configuration to control the output.
+pub fn pretty_print_with(val: &Cbor, config: &PrintConfig) -> Result<String> {
+    let s = val.pretty_print("", &" ".repeat(config.indent))?;
+    match config.color {
+        true => Ok(colorize(&s)),
+        false => Ok(s),
+    }
+}
+
+/// Configuration for [pretty_print_with].
+///
+/// Default configuration is used by [pretty_print].
+#[derive(Debug, Clone)]
+pub struct PrintConfig {
+    /// Wrap items with ANSI color codes, distinguishing keys, strings,
+    /// numbers and tags. Default is false.
+    pub color: bool,
+    /// Number of spaces to indent nested items. Default is 2.
+    pub indent: usize,
+}
+
+impl Default for PrintConfig {
+    fn default() -> PrintConfig {
+        PrintConfig { color: false, indent: 2 }
+    }
+}
+
+// Color each line of pretty printed text, going by the item's prefix.
+fn colorize(s: &str) -> String {
+    let lines: Vec<String> = s
+        .lines()
+        .map(|line| {
+            let item = line.trim_start();
+            let code = match item.get(..4).unwrap_or("") {
+                "Key(" => "34",
+                "Text" | "Byts" => "32",
+                "Maj0" | "Maj1" => "33",
+                "Maj7" => "36",
+                "Tag:" => "35",
+                _ => return line.to_string(),
+            };
+            let p = &line[..(line.len() - item.len())];
+            format!("{}\x1b[{}m{}\x1b[0m", p, code, item)
+        })
+        .collect();
+    lines.join("\n")
 }
 
 /// Stub trait until `total_cmp` is stabilized implementations taken from, TODO
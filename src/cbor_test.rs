@@ -40,7 +40,6 @@ fn test_simple_value() {
             (Unassigned, Err(_)) => continue,
             (Undefined, Err(_)) => continue,
             (Reserved24(_), Err(_)) => continue,
-            (F16(_), Err(_)) => continue,
             (Break, Err(_)) => continue,
             (_, val) => val.unwrap(),
         };
@@ -119,3 +118,1966 @@ fn test_bigint() {
         }
     }
 }
+
+#[test]
+fn test_stats() {
+    let val: Cbor = vec![
+        (Key::from("name"), "hello".into_cbor().unwrap()),
+        (Key::from(10_u64), Cbor::from_bytes(vec![1, 2, 3]).unwrap()),
+        (
+            Key::from(vec![4_u8, 5]),
+            vec![1_u64.into_cbor().unwrap(), (-1_i64).into_cbor().unwrap()]
+                .into_cbor()
+                .unwrap(),
+        ),
+    ]
+    .into_cbor()
+    .unwrap();
+
+    let stats = val.stats();
+    assert_eq!(stats.majors, [2, 1, 2, 2, 1, 1, 0, 0]);
+    assert_eq!(stats.max_depth, 3);
+    assert_eq!(stats.n_items, 9);
+    assert_eq!(stats.n_bytes, 5);
+
+    let val: Cbor = 10_u128.pow(30).into_cbor().unwrap();
+    let stats = val.stats();
+    assert_eq!(stats.majors, [0, 0, 1, 0, 0, 0, 1, 0]);
+    assert_eq!(stats.max_depth, 2);
+    assert_eq!(stats.n_items, 2);
+
+    let val: Cbor =
+        vec![Cbor::Binary(vec![]), Cbor::Binary(vec![0x41, 0x01])].into_cbor().unwrap();
+    let stats = val.stats();
+    assert_eq!(stats.majors, [0, 0, 1, 0, 1, 0, 0, 0]);
+    assert_eq!(stats.n_items, 2);
+}
+
+#[test]
+fn test_retain() {
+    let mut map: Cbor = vec![
+        (Key::from("user"), "alice".into_cbor().unwrap()),
+        (Key::from("password"), "secret".into_cbor().unwrap()),
+        (Key::from(10_u64), 10.into_cbor().unwrap()),
+    ]
+    .into_cbor()
+    .unwrap();
+    map.retain_map(|key, _| key != &Key::from("password"));
+    let refmap: Cbor = vec![
+        (Key::from("user"), "alice".into_cbor().unwrap()),
+        (Key::from(10_u64), 10.into_cbor().unwrap()),
+    ]
+    .into_cbor()
+    .unwrap();
+    assert_eq!(map, refmap);
+    map.retain_map(|_, _| false);
+    assert_eq!(map, Vec::<(Key, Cbor)>::new().into_cbor().unwrap());
+    let mut buf = vec![];
+    map.encode(&mut buf).unwrap();
+    assert_eq!(buf, [0xa0]);
+
+    let mut list = vec![1_u64, 2, 3, 4].into_cbor().unwrap();
+    list.retain_array(|item| u64::from_cbor(item.clone()).unwrap() % 2 == 0);
+    assert_eq!(list, vec![2_u64, 4].into_cbor().unwrap());
+    list.retain_array(|_| false);
+    assert_eq!(list, Vec::<u64>::new().into_cbor().unwrap());
+    let mut buf = vec![];
+    list.encode(&mut buf).unwrap();
+    assert_eq!(buf, [0x80]);
+
+    // indefinite length is preserved.
+    let buf = [0x9f, 0x01, 0x02, 0xff];
+    let (mut list, _) = Cbor::decode(&mut &buf[..]).unwrap();
+    list.retain_array(|item| item != &1.into_cbor().unwrap());
+    let mut out = vec![];
+    list.encode(&mut out).unwrap();
+    assert_eq!(out, [0x9f, 0x02, 0xff]);
+
+    // no-op for other values.
+    let mut val = "text".into_cbor().unwrap();
+    val.retain_array(|_| false);
+    val.retain_map(|_, _| false);
+    assert_eq!(val, "text".into_cbor().unwrap());
+}
+
+#[test]
+fn test_extend() {
+    let mut list = vec![1_u64, 2].into_cbor().unwrap();
+    list.extend_array(vec![3_u64].into_cbor().unwrap()).unwrap();
+    list.extend_array(Vec::<u64>::new().into_cbor().unwrap()).unwrap();
+    assert_eq!(list, vec![1_u64, 2, 3].into_cbor().unwrap());
+    // length grows beyond the tiny info.
+    let items: Vec<u64> = (4..=30).collect();
+    list.extend_array(items.into_cbor().unwrap()).unwrap();
+    let mut buf = vec![];
+    list.encode(&mut buf).unwrap();
+    assert_eq!(buf[..2], [0x98, 30]);
+    let (val, _) = Cbor::decode(&mut buf.as_slice()).unwrap();
+    assert_eq!(Vec::<u64>::from_cbor(val).unwrap(), (1..=30).collect::<Vec<u64>>());
+
+    let mut map: Cbor = vec![
+        (Key::from("user"), "alice".into_cbor().unwrap()),
+        (Key::from(10_u64), 10.into_cbor().unwrap()),
+    ]
+    .into_cbor()
+    .unwrap();
+    // duplicate key replaces the value in its position.
+    let other: Cbor = vec![
+        (Key::from("role"), "admin".into_cbor().unwrap()),
+        (Key::from("user"), "bob".into_cbor().unwrap()),
+    ]
+    .into_cbor()
+    .unwrap();
+    map.extend_map(other).unwrap();
+    let refmap: Cbor = vec![
+        (Key::from("user"), "bob".into_cbor().unwrap()),
+        (Key::from(10_u64), 10.into_cbor().unwrap()),
+        (Key::from("role"), "admin".into_cbor().unwrap()),
+    ]
+    .into_cbor()
+    .unwrap();
+    assert_eq!(map, refmap);
+
+    // type mismatch.
+    let text = "text".into_cbor().unwrap();
+    let testcases = [
+        list.clone().extend_array(refmap.clone()),
+        text.clone().extend_array(list.clone()),
+        map.clone().extend_map(list.clone()),
+        list.clone().extend_map(map.clone()),
+    ];
+    for res in testcases.iter() {
+        match res {
+            Err(Error::FailConvert(_, _)) => (),
+            res => panic!("{:?}", res),
+        }
+    }
+    assert_eq!(map, refmap);
+}
+
+#[test]
+fn test_deep_size() {
+    use std::mem::size_of;
+
+    let base = size_of::<Cbor>();
+    assert_eq!(10.into_cbor().unwrap().deep_size(), base);
+
+    let text = || Cbor::Major3(3.into(), Vec::with_capacity(3));
+    assert_eq!(text().deep_size(), base + 3);
+
+    let list = Cbor::Major4(2.into(), vec![text(), 1.into_cbor().unwrap()]);
+    assert_eq!(list.deep_size(), base + (2 * base) + 3);
+
+    let size = list.deep_size();
+    let tag = Cbor::Major6(39.into(), Tag::from_identifier(list));
+    assert_eq!(tag.deep_size(), base + size);
+
+    let map = Cbor::Major5(
+        1.into(),
+        vec![(Key::Text(String::with_capacity(4)), text())],
+        MapOrder::Sorted,
+    );
+    let refsize = base + size_of::<(Key, Cbor)>() + 4 + 3;
+    assert_eq!(map.deep_size(), refsize);
+
+    // deeply nested values do not overflow the stack.
+    let mut val = Cbor::Major4(0.into(), vec![]);
+    for _ in 0..100_000 {
+        val = Cbor::Major4(1.into(), vec![val]);
+    }
+    assert_eq!(val.deep_size(), base * 100_001);
+    // dropping the value recurses, leak it instead.
+    std::mem::forget(val);
+}
+
+#[test]
+fn test_decode_with_metrics() {
+    let vals: Vec<Cbor> = vec![
+        vec![
+            (Key::from("name"), "hello".into_cbor().unwrap()),
+            (Key::from(10_u64), vec![1_u64, 2].into_cbor().unwrap()),
+        ]
+        .into_cbor()
+        .unwrap(),
+        10_u128.pow(30).into_cbor().unwrap(),
+        10.into_cbor().unwrap(),
+    ];
+    for val in vals.into_iter() {
+        let mut buf: Vec<u8> = vec![];
+        let n = val.encode(&mut buf).unwrap();
+        let (out, metrics) = Cbor::decode_with_metrics(&mut buf.as_slice()).unwrap();
+        assert_eq!(out, val);
+
+        let stats = val.stats();
+        assert_eq!(metrics.max_depth, stats.max_depth);
+        assert_eq!(metrics.item_count, stats.n_items);
+        assert_eq!(metrics.bytes_consumed, n);
+    }
+
+    // break codes are not counted.
+    let buf = [0x9f, 0x01, 0x9f, 0xff, 0xff];
+    let (_, metrics) = Cbor::decode_with_metrics(&mut &buf[..]).unwrap();
+    let refm = DecodeMetrics { max_depth: 2, item_count: 3, bytes_consumed: 5 };
+    assert_eq!(metrics, refm);
+}
+
+#[test]
+fn test_tag_chain_depth() {
+    // tag(39, tag(39, ... 1)), 100k deep.
+    let mut buf: Vec<u8> = [0xd8, 0x27].repeat(100_000);
+    buf.push(0x01);
+    match Cbor::decode(&mut buf.as_slice()) {
+        Err(Error::LimitExceeded(_, msg)) => {
+            assert!(msg.contains("recursion"), "{}", msg)
+        }
+        res => panic!("{:?}", res),
+    }
+    let mut decoder = crate::Decoder::new();
+    decoder.feed(&buf);
+    assert!(decoder.pull().is_err());
+
+    let chain = |n: u32, val: Cbor| {
+        let mut val = val;
+        for _ in 0..n {
+            val = Cbor::Major6(39.into(), Tag::from_identifier(val));
+        }
+        val
+    };
+    let val = chain(RECURSION_LIMIT - 1, 1.into_cbor().unwrap());
+    let mut buf: Vec<u8> = vec![];
+    val.encode(&mut buf).unwrap();
+    assert_eq!(Cbor::decode(&mut buf.as_slice()).unwrap().0, val);
+    let mut cbuf: Vec<u8> = vec![];
+    val.encode_canonical(&mut cbuf).unwrap();
+    assert_eq!(cbuf, buf);
+
+    let val = chain(RECURSION_LIMIT, 1.into_cbor().unwrap());
+    match val.encode(&mut vec![]) {
+        Err(Error::FailCbor(_, msg)) => assert!(msg.contains("recursion"), "{}", msg),
+        res => panic!("{:?}", res),
+    }
+    match val.encode_canonical(&mut vec![]) {
+        Err(Error::FailCbor(_, msg)) => assert!(msg.contains("recursion"), "{}", msg),
+        res => panic!("{:?}", res),
+    }
+
+    // tag(24, h'tag(24, ... 1')'), 100k deep.
+    let buf = encoded_chain(100_000);
+    match Cbor::decode(&mut buf.as_slice()) {
+        Err(Error::LimitExceeded(_, msg)) => {
+            assert!(msg.contains("recursion"), "{}", msg)
+        }
+        res => panic!("{:?}", res),
+    }
+    let mut decoder = crate::Decoder::new();
+    decoder.feed(&buf);
+    assert!(decoder.pull().is_err());
+
+    // tag chain in a binary value, tagged by another chain.
+    let inner = chain(600, 1.into_cbor().unwrap());
+    let mut buf: Vec<u8> = vec![];
+    inner.encode(&mut buf).unwrap();
+    let val = chain(600, Cbor::Binary(buf));
+    let mut buf: Vec<u8> = vec![];
+    val.encode(&mut buf).unwrap();
+    match Cbor::decode(&mut buf.as_slice()) {
+        Err(Error::LimitExceeded(_, msg)) => {
+            assert!(msg.contains("recursion"), "{}", msg)
+        }
+        res => panic!("{:?}", res),
+    }
+    match val.encode_canonical(&mut vec![]) {
+        Err(Error::FailCbor(_, msg)) => assert!(msg.contains("recursion"), "{}", msg),
+        res => panic!("{:?}", res),
+    }
+}
+
+#[test]
+fn test_encoded_cbor() {
+    let inner: Vec<u64> = vec![1, 2, 3];
+    let val = Embedded(inner.clone()).into_cbor().unwrap();
+    match &val {
+        Cbor::Major6(_, Tag::EncodedCbor(data)) => match data.as_ref() {
+            Cbor::Major2(_, data) => assert_eq!(data, &[0x83, 1, 2, 3]),
+            data => panic!("{:?}", data),
+        },
+        val => panic!("{:?}", val),
+    }
+
+    let mut buf: Vec<u8> = vec![];
+    let n = val.encode(&mut buf).unwrap();
+    assert_eq!(buf, [0xd8, 24, 0x44, 0x83, 1, 2, 3]);
+    let (nval, m) = Cbor::decode(&mut buf.as_slice()).unwrap();
+    assert_eq!(n, m);
+    assert_eq!(val, nval);
+    assert_eq!(Embedded::<Vec<u64>>::from_cbor(nval).unwrap(), Embedded(inner));
+
+    // truncated inner item.
+    let buf = [0xd8, 24, 0x43, 0x83, 1, 2];
+    match Cbor::decode(&mut &buf[..]) {
+        Err(Error::FailCbor(_, _)) => (),
+        res => panic!("{:?}", res),
+    }
+    // trailing bytes after inner item.
+    let buf = [0xd8, 24, 0x42, 1, 2];
+    match Cbor::decode(&mut &buf[..]) {
+        Err(Error::FailCbor(_, _)) => (),
+        res => panic!("{:?}", res),
+    }
+    // inner item is not a byte-string.
+    let buf = [0xd8, 24, 0x01];
+    match Cbor::decode(&mut &buf[..]) {
+        Err(Error::FailCbor(_, _)) => (),
+        res => panic!("{:?}", res),
+    }
+
+    // embedded items are nested under the byte-string, and are subject to
+    // the caller's limits.
+    let buf = encoded_chain(20);
+    let (val, n) = Cbor::decode(&mut buf.as_slice()).unwrap();
+    assert_eq!(n, buf.len());
+    let cf = DecodeConfig { max_depth: Some(41), ..DecodeConfig::default() };
+    assert_eq!(Cbor::decode_with(&mut buf.as_slice(), &cf).unwrap().0, val);
+    let cf = DecodeConfig { max_depth: Some(40), ..DecodeConfig::default() };
+    match Cbor::decode_with(&mut buf.as_slice(), &cf) {
+        Err(Error::LimitExceeded(_, msg)) => assert!(msg.contains("limit 40"), "{}", msg),
+        res => panic!("{:?}", res),
+    }
+    let cf = DecodeConfig { max_items: Some(40), ..DecodeConfig::default() };
+    match Cbor::decode_with(&mut buf.as_slice(), &cf) {
+        Err(Error::LimitExceeded(_, msg)) => {
+            assert!(msg.contains("item limit"), "{}", msg)
+        }
+        res => panic!("{:?}", res),
+    }
+    let (_, metrics) = Cbor::decode_with_metrics(&mut buf.as_slice()).unwrap();
+    assert_eq!(metrics.max_depth, 41);
+    assert_eq!(metrics.item_count, 41);
+
+    // embedded values are decoded one after the other, not recursively.
+    let buf = encoded_chain(100_000);
+    match Cbor::decode(&mut buf.as_slice()) {
+        Err(Error::LimitExceeded(_, msg)) => {
+            assert!(msg.contains("recursion"), "{}", msg)
+        }
+        res => panic!("{:?}", res),
+    }
+}
+
+// tag(24, h'tag(24, h'... 1')'), `n` deep.
+fn encoded_chain(n: usize) -> Vec<u8> {
+    let mut prefixes = vec![];
+    let mut len = 1_u64;
+    for _ in 0..n {
+        let mut prefix = vec![0xd8, 24];
+        encode_num(2, len.into(), len, &mut prefix).unwrap();
+        len += prefix.len() as u64;
+        prefixes.push(prefix);
+    }
+    let mut buf: Vec<u8> = prefixes.into_iter().rev().flatten().collect();
+    buf.push(0x01);
+    buf
+}
+
+#[test]
+fn test_empty_values() {
+    let vals = vec![
+        (Cbor::from_bytes(vec![]).unwrap(), 0x40),
+        ("".into_cbor().unwrap(), 0x60),
+        (Vec::<Cbor>::new().into_cbor().unwrap(), 0x80),
+        (Vec::<(Key, Cbor)>::new().into_cbor().unwrap(), 0xa0),
+    ];
+    for (val, byt) in vals.into_iter() {
+        let mut buf = vec![];
+        assert_eq!(val.encode(&mut buf).unwrap(), 1);
+        assert_eq!(buf, vec![byt], "{:?}", val);
+
+        let (dval, n) = Cbor::decode(&mut buf.as_slice()).unwrap();
+        assert_eq!(n, 1);
+        assert_eq!(dval, val);
+        match &dval {
+            Cbor::Major2(info, v) => assert!(*info == Info::Tiny(0) && v.is_empty()),
+            Cbor::Major3(info, v) => assert!(*info == Info::Tiny(0) && v.is_empty()),
+            Cbor::Major4(info, v) => assert!(*info == Info::Tiny(0) && v.is_empty()),
+            Cbor::Major5(info, v, _) => assert!(*info == Info::Tiny(0) && v.is_empty()),
+            _ => panic!("{:?}", dval),
+        }
+    }
+
+    // empty values nested in array and map.
+    let val = vec![
+        Cbor::from_bytes(vec![]).unwrap(),
+        "".into_cbor().unwrap(),
+        Vec::<Cbor>::new().into_cbor().unwrap(),
+        Vec::<(Key, Cbor)>::new().into_cbor().unwrap(),
+    ]
+    .into_cbor()
+    .unwrap();
+    let mut buf = vec![];
+    val.encode(&mut buf).unwrap();
+    assert_eq!(buf, vec![0x84, 0x40, 0x60, 0x80, 0xa0]);
+    assert_eq!(Cbor::decode(&mut buf.as_slice()).unwrap(), (val, 5));
+
+    // indefinite empty values decode as empty.
+    for buf in [[0x5f, 0xff], [0x7f, 0xff], [0x9f, 0xff], [0xbf, 0xff]].iter() {
+        let (val, n) = Cbor::decode(&mut &buf[..]).unwrap();
+        assert_eq!(n, 2);
+        match val {
+            Cbor::Major2(Info::Indefinite, v) => assert!(v.is_empty()),
+            Cbor::Major3(Info::Indefinite, v) => assert!(v.is_empty()),
+            Cbor::Major4(Info::Indefinite, v) => assert!(v.is_empty()),
+            Cbor::Major5(Info::Indefinite, v, _) => assert!(v.is_empty()),
+            val => panic!("{:?}", val),
+        }
+    }
+}
+
+#[test]
+fn test_preserve_encoding() {
+    // non-shortest integers, lengths, tag numbers and floats.
+    let bufs: Vec<Vec<u8>> = vec![
+        vec![0x19, 0x00, 0x05],
+        vec![0x1b, 0, 0, 0, 0, 0, 0, 0, 0x05],
+        vec![0x38, 0x04],
+        vec![0x3a, 0, 0, 0x01, 0x00],
+        vec![0x59, 0x00, 0x01, 0xff],
+        vec![0x78, 0x01, 0x61],
+        vec![0x99, 0x00, 0x02, 0x18, 0x01, 0x1a, 0, 0, 0, 0x02],
+        vec![0xb8, 0x01, 0x01, 0x19, 0x00, 0x01],
+        vec![0xd8, 0x02, 0x41, 0x01],
+        vec![0xda, 0, 0, 0x03, 0xe8, 0x00],
+        vec![0xfa, 0x3f, 0xc0, 0x00, 0x00],
+        vec![0xfb, 0x3f, 0xf8, 0, 0, 0, 0, 0, 0],
+        vec![0x9f, 0x18, 0x05, 0xff],
+    ];
+    for buf in bufs.iter() {
+        let (val, n) = Cbor::decode(&mut buf.as_slice()).unwrap();
+        assert_eq!(n, buf.len());
+        let mut out = vec![];
+        assert_eq!(val.encode(&mut out).unwrap(), buf.len());
+        assert_eq!(&out, buf);
+        assert_eq!(val.encoded_len().unwrap(), buf.len());
+
+        // canonical mode ignores the original width.
+        let mut out = vec![];
+        val.encode_canonical(&mut out).unwrap();
+        assert!(out.len() < buf.len() || buf[0] == 0x9f, "{:x?}", buf);
+        assert!(is_canonical(&out).unwrap());
+    }
+
+    // width too narrow for the value falls back to the shortest form.
+    let mut out = vec![];
+    Cbor::Major0(Info::U8, 1000).encode(&mut out).unwrap();
+    assert_eq!(out, [0x19, 0x03, 0xe8]);
+    let mut out = vec![];
+    Cbor::Major0(Info::Tiny(0), 5).encode(&mut out).unwrap();
+    assert_eq!(out, [0x05]);
+
+    // map keys are always encoded in the shortest form.
+    let buf = [0xa1, 0x19, 0x00, 0x01, 0x02];
+    let (val, _) = Cbor::decode(&mut &buf[..]).unwrap();
+    let mut out = vec![];
+    val.encode(&mut out).unwrap();
+    assert_eq!(out, [0xa1, 0x01, 0x02]);
+}
+
+#[test]
+fn test_encode_canonical() {
+    let refs: Vec<(Cbor, Vec<u8>)> = vec![
+        // integers and lengths in the shortest form.
+        (Cbor::Major0(Info::U64, 10), vec![0x0a]),
+        (Cbor::Major1(Info::U32, 24), vec![0x38, 0x18]),
+        (Cbor::Major2(Info::U16, vec![1]), vec![0x41, 0x01]),
+        (Cbor::Major3(Info::Indefinite, b"ab".to_vec()), vec![0x62, 0x61, 0x62]),
+        (
+            Cbor::Major4(Info::Indefinite, vec![Cbor::Major0(Info::U8, 1)]),
+            vec![0x81, 0x01],
+        ),
+        (
+            Tag::from_value(24 * 1000, Cbor::Major0(Info::U8, 0)).into(),
+            vec![0xd9, 0x5d, 0xc0, 0x00],
+        ),
+        // floats in the shortest width.
+        (1.0_f64.into_cbor().unwrap(), vec![0xf9, 0x3c, 0x00]),
+        (1.5_f32.into_cbor().unwrap(), vec![0xf9, 0x3e, 0x00]),
+        ((-0.0_f64).into_cbor().unwrap(), vec![0xf9, 0x80, 0x00]),
+        (65504.0_f64.into_cbor().unwrap(), vec![0xf9, 0x7b, 0xff]),
+        (5.960464477539063e-8_f64.into_cbor().unwrap(), vec![0xf9, 0x00, 0x01]),
+        (f64::NEG_INFINITY.into_cbor().unwrap(), vec![0xf9, 0xfc, 0x00]),
+        (f32::NAN.into_cbor().unwrap(), vec![0xf9, 0x7e, 0x00]),
+        (SimpleValue::F16(0x7e01).into_cbor().unwrap(), vec![0xf9, 0x7e, 0x00]),
+        (100000.0_f64.into_cbor().unwrap(), vec![0xfa, 0x47, 0xc3, 0x50, 0x00]),
+        (
+            1.1_f64.into_cbor().unwrap(),
+            vec![0xfb, 0x3f, 0xf1, 0x99, 0x99, 0x99, 0x99, 0x99, 0x9a],
+        ),
+        // map keys sorted by their encoding.
+        (
+            vec![
+                (Key::from("a"), 1.into_cbor().unwrap()),
+                (Key::from(-1_i64), 2.into_cbor().unwrap()),
+                (Key::from(24_u64), 3.into_cbor().unwrap()),
+                (Key::from(10_u64), 4.into_cbor().unwrap()),
+            ]
+            .into_cbor()
+            .unwrap(),
+            vec![0xa4, 0x0a, 0x04, 0x18, 0x18, 0x03, 0x20, 0x02, 0x61, 0x61, 0x01],
+        ),
+    ];
+    for (val, refbuf) in refs.into_iter() {
+        let mut buf = vec![];
+        assert_eq!(val.encode_canonical(&mut buf).unwrap(), refbuf.len());
+        assert_eq!(buf, refbuf, "{:?}", val);
+        assert!(is_canonical(&buf).unwrap());
+    }
+
+    // lazy values are re-encoded.
+    let val = Cbor::Binary(vec![0x9f, 0x19, 0x00, 0x01, 0xff]);
+    let mut buf = vec![];
+    val.encode_canonical(&mut buf).unwrap();
+    assert_eq!(buf, vec![0x81, 0x01]);
+
+    // duplicate keys.
+    let val = Cbor::Major5(
+        Info::Tiny(2),
+        vec![(Key::U64(1), Cbor::null()), (Key::U64(1), Cbor::null())],
+        MapOrder::Sorted,
+    );
+    match val.encode_canonical(&mut vec![]) {
+        Err(Error::FailCbor(_, msg)) => assert!(msg.contains("duplicate"), "{}", msg),
+        res => panic!("{:?}", res),
+    }
+
+    let seed: u128 = random();
+    println!("test_encode_canonical seed:{}", seed);
+    let mut rng = {
+        let mut rng_seed = [0; 32];
+        rng_seed[0..16].copy_from_slice(&seed.to_le_bytes());
+        StdRng::from_seed(rng_seed)
+    };
+    for _i in 0..1000 {
+        let val: Cbor = {
+            let bytes: Vec<u8> =
+                (0..100).flat_map(|_| rng.gen::<[u8; 32]>().to_vec()).collect();
+            let mut uns = Unstructured::new(&bytes);
+            uns.arbitrary().unwrap()
+        };
+        let mut buf = vec![];
+        match val.encode_canonical(&mut buf) {
+            Ok(n) => assert_eq!(n, buf.len()),
+            Err(Error::FailCbor(_, msg)) if msg.contains("duplicate") => continue,
+            Err(err) => panic!("{}", err),
+        }
+        assert!(is_canonical(&buf).unwrap(), "{:?}", val);
+    }
+}
+
+#[test]
+fn test_canonical_f16() {
+    let mut n_subnormal = 0;
+    for bits in 0..=u16::MAX {
+        let val = f16_to_f64(bits);
+        if f16_is_nan(bits) {
+            assert!(val.is_nan());
+            assert_eq!(canonical_float(val), SimpleValue::F16(0x7e00));
+            continue;
+        }
+        // every half precision value, including signed zeros and
+        // subnormals, narrows back to the same bits.
+        assert_eq!(canonical_float(val), SimpleValue::F16(bits), "{:x}", bits);
+        if (bits & 0x7c00) == 0 && (bits & 0x3ff) != 0 {
+            n_subnormal += 1;
+        }
+
+        // neighbouring values are not narrowed to half precision, and the
+        // wider value preserves them exactly.
+        if !val.is_finite() {
+            continue;
+        }
+        let wider = [
+            f64::from_bits(val.to_bits() + 1),
+            f64::from_bits(val.to_bits() ^ (1 << 28)),
+            val + val.signum() * 2_f64.powi(-30),
+        ];
+        for w in wider.iter() {
+            match canonical_float(*w) {
+                SimpleValue::F16(x) => panic!("{:x} narrowed {} to {:x}", bits, w, x),
+                SimpleValue::F32(x) => assert_eq!(f64::from(x).to_bits(), w.to_bits()),
+                SimpleValue::F64(x) => assert_eq!(x.to_bits(), w.to_bits()),
+                sval => panic!("{:?}", sval),
+            }
+        }
+    }
+    assert_eq!(n_subnormal, 2 * 1023);
+
+    let zero = canonical_float(0.0);
+    assert_eq!(zero, SimpleValue::F16(0x0000));
+    assert_eq!(canonical_float(-0.0), SimpleValue::F16(0x8000));
+    assert_ne!(canonical_float(-0.0), zero);
+    // smallest half subnormal, and half of it that needs single precision.
+    assert_eq!(canonical_float(2_f64.powi(-24)), SimpleValue::F16(0x0001));
+    assert_eq!(canonical_float(-(2_f64.powi(-24))), SimpleValue::F16(0x8001));
+    assert_eq!(canonical_float(2_f64.powi(-25)), SimpleValue::F32(2_f32.powi(-25)));
+    // double precision subnormal.
+    let tiny = f64::from_bits(1);
+    assert_eq!(canonical_float(tiny), SimpleValue::F64(tiny));
+    assert_eq!(canonical_float(-tiny), SimpleValue::F64(-tiny));
+}
+
+#[test]
+fn test_encoded_len_canonical() {
+    let seed: u128 = random();
+    println!("test_encoded_len_canonical seed:{}", seed);
+    let mut rng = {
+        let mut rng_seed = [0; 32];
+        rng_seed[0..16].copy_from_slice(&seed.to_le_bytes());
+        StdRng::from_seed(rng_seed)
+    };
+
+    for _i in 0..1000 {
+        let val: Cbor = {
+            let bytes: Vec<u8> =
+                (0..100).flat_map(|_| rng.gen::<[u8; 32]>().to_vec()).collect();
+            let mut uns = Unstructured::new(&bytes);
+            uns.arbitrary().unwrap()
+        };
+        let mut buf = vec![];
+        match val.encode_canonical(&mut buf) {
+            Ok(n) => {
+                assert_eq!(val.encoded_len_canonical().unwrap(), n);
+                assert_eq!(n, buf.len());
+            }
+            Err(_) => assert!(val.encoded_len_canonical().is_err()),
+        }
+    }
+
+    // float shrinking changes the length, key sorting doesn't.
+    let val: Cbor = vec![
+        (Key::from(2_u64), 1.0_f64.into_cbor().unwrap()),
+        (Key::from(1_u64), 1.5_f32.into_cbor().unwrap()),
+    ]
+    .into_cbor()
+    .unwrap();
+    assert_eq!(val.encoded_len().unwrap(), 1 + (1 + 9) + (1 + 5));
+    assert_eq!(val.encoded_len_canonical().unwrap(), 1 + (1 + 3) + (1 + 3));
+}
+
+#[test]
+fn test_is_canonical() {
+    let canonical: Vec<Vec<u8>> = vec![
+        vec![0x00],
+        vec![0x17],
+        vec![0x18, 0x18],
+        vec![0x19, 0x01, 0x00],
+        vec![0x1a, 0x00, 0x01, 0x00, 0x00],
+        vec![0x1b, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00],
+        vec![0x38, 0x18],
+        vec![0x43, 0x01, 0x02, 0x03],
+        vec![0x82, 0x61, 0x61, 0xc2, 0x41, 0x01],
+        // keys 10, 24, "a", -1, sorted bytewise, not length first.
+        vec![0xa4, 0x0a, 0x00, 0x18, 0x18, 0x00, 0x20, 0x00, 0x61, 0x61, 0x00],
+        vec![0xf9, 0x3c, 0x00],             // 1.0
+        vec![0xf9, 0x7e, 0x00],             // NaN
+        vec![0xf9, 0x7c, 0x00],             // infinity
+        vec![0xfa, 0x47, 0xc3, 0x50, 0x00], // 100000.0
+        vec![0xfa, 0x33, 0x00, 0x00, 0x00], // 2^-25
+        vec![0xfb, 0x3f, 0xf1, 0x99, 0x99, 0x99, 0x99, 0x99, 0x9a], // 1.1
+        vec![0xf5],
+        vec![0xf8, 0x20],
+    ];
+    for buf in canonical.iter() {
+        assert!(is_canonical(buf).unwrap(), "{:x?}", buf);
+    }
+
+    let non_canonical: Vec<Vec<u8>> = vec![
+        // integers, lengths and tag numbers not in shortest form.
+        vec![0x18, 0x17],
+        vec![0x19, 0x00, 0xff],
+        vec![0x1a, 0x00, 0x00, 0xff, 0xff],
+        vec![0x1b, 0x00, 0x00, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff],
+        vec![0x38, 0x00],
+        vec![0x58, 0x01, 0x00],
+        vec![0x98, 0x01, 0x00],
+        vec![0xd8, 0x02, 0x41, 0x01],
+        vec![0x81, 0x18, 0x01],
+        // indefinite lengths.
+        vec![0x5f, 0xff],
+        vec![0x7f, 0x61, 0x61, 0xff],
+        vec![0x9f, 0x01, 0xff],
+        vec![0xbf, 0x01, 0x00, 0xff],
+        // unsorted and duplicate keys.
+        vec![0xa2, 0x02, 0x00, 0x01, 0x00],
+        vec![0xa2, 0x01, 0x00, 0x01, 0x00],
+        vec![0xa2, 0x61, 0x61, 0x00, 0x18, 0x18, 0x00],
+        vec![0x81, 0xa2, 0x02, 0x00, 0x01, 0x00],
+        // floats in wider than needed width, and non-canonical NaN.
+        vec![0xfa, 0x3f, 0x80, 0x00, 0x00],
+        vec![0xfb, 0x3f, 0xf0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+        vec![0xfb, 0x40, 0xf8, 0x6a, 0x00, 0x00, 0x00, 0x00, 0x00],
+        vec![0xfa, 0x33, 0x80, 0x00, 0x00],
+        vec![0xfa, 0x00, 0x00, 0x00, 0x00],
+        vec![0xfa, 0x7f, 0xc0, 0x00, 0x00],
+        vec![0xfb, 0x7f, 0xf0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+        vec![0xf9, 0x7e, 0x01],
+        // simple value in two bytes.
+        vec![0xf8, 0x10],
+    ];
+    for buf in non_canonical.iter() {
+        assert!(!is_canonical(buf).unwrap(), "{:x?}", buf);
+    }
+
+    // decoded values preserve the encoding, except for map keys, while
+    // two byte simple values fail to decode.
+    for buf in canonical.iter() {
+        let (val, _) = Cbor::decode(&mut buf.as_slice()).unwrap();
+        assert!(val.is_canonical().unwrap(), "{:x?}", buf);
+    }
+    for buf in non_canonical.iter().filter(|buf| buf[0] != 0xa2 && buf[0] != 0xf8) {
+        let (val, _) = Cbor::decode(&mut buf.as_slice()).unwrap();
+        assert!(!val.is_canonical().unwrap(), "{:x?}", buf);
+        let mut out = vec![];
+        val.encode_canonical(&mut out).unwrap();
+        let (val, _) = Cbor::decode(&mut out.as_slice()).unwrap();
+        assert!(val.is_canonical().unwrap(), "{:x?}", buf);
+    }
+    // width of map keys is lost on decode, {1: 1} with a 2-byte key.
+    let buf = [0xa1, 0x18, 0x01, 0x01];
+    assert!(!is_canonical(&buf).unwrap());
+    let (val, _) = Cbor::decode(&mut buf.as_ref()).unwrap();
+    assert!(val.is_canonical().unwrap());
+
+    let malformed: Vec<Vec<u8>> = vec![
+        vec![],
+        vec![0x19, 0x01],
+        vec![0x43, 0x01],
+        vec![0x82, 0x01],
+        vec![0x00, 0x00],
+        vec![0x1c],
+        vec![0xff],
+    ];
+    for buf in malformed.iter() {
+        match is_canonical(buf) {
+            Err(Error::FailCbor(_, _)) => (),
+            res => panic!("{:x?} {:?}", buf, res),
+        }
+    }
+
+    // values built with big numbers and sorted keys encode canonically.
+    let val: Cbor = vec![
+        (Key::U64(1), 1.1_f64.into_cbor().unwrap()),
+        (Key::U64(2), (u64::MAX as u128 + 1).into_cbor().unwrap()),
+        (Key::Text("a".to_string()), vec![1_u64, 1000].into_cbor().unwrap()),
+    ]
+    .into_cbor()
+    .unwrap();
+    let mut buf = vec![];
+    val.encode(&mut buf).unwrap();
+    assert!(is_canonical(&buf).unwrap());
+}
+
+#[test]
+fn test_encode_canonical_map() {
+    let pairs = || -> Vec<(Key, Cbor)> {
+        vec![
+            (Key::U64(1), 1.5_f64.into_cbor().unwrap()),
+            (Key::N64(-1), vec![1_u64, 2].into_cbor().unwrap()),
+            (Key::Text("b".to_string()), true.into_cbor().unwrap()),
+            (Key::Text("aa".to_string()), "x".into_cbor().unwrap()),
+        ]
+    };
+
+    let mut buf = vec![];
+    let n = encode_canonical_map(&mut buf, 4, pairs()).unwrap();
+    assert_eq!(n, buf.len());
+    assert!(is_canonical(&buf).unwrap());
+    let mut ref_buf = vec![];
+    pairs().into_cbor().unwrap().encode_canonical(&mut ref_buf).unwrap();
+    assert_eq!(buf, ref_buf);
+
+    // out of order, Key's Ord puts "aa" before "b".
+    let mut sorted = pairs();
+    sorted.swap(2, 3);
+    match encode_canonical_map(&mut vec![], 4, sorted) {
+        Err(Error::FailCbor(_, msg)) => assert!(msg.contains("not sorted"), "{}", msg),
+        res => panic!("{:?}", res),
+    }
+    let mut dups = pairs();
+    dups[1].0 = Key::U64(1);
+    assert!(encode_canonical_map(&mut vec![], 4, dups).is_err());
+
+    for len in [3, 5].iter() {
+        match encode_canonical_map(&mut vec![], *len, pairs()) {
+            Err(Error::FailCbor(_, msg)) => assert!(msg.contains("entries"), "{}", msg),
+            res => panic!("{:?}", res),
+        }
+    }
+
+    let mut buf = vec![];
+    encode_canonical_map(&mut buf, 0, vec![]).unwrap();
+    assert_eq!(buf, [0xa0]);
+}
+
+#[test]
+fn test_encode_indefinite() {
+    let items = || -> Vec<Cbor> {
+        vec![
+            1_u64.into_cbor().unwrap(),
+            "a".into_cbor().unwrap(),
+            vec![2_u64, 3].into_cbor().unwrap(),
+        ]
+    };
+    let mut buf = vec![];
+    let n = encode_indefinite_array(&mut buf, items()).unwrap();
+    assert_eq!(n, buf.len());
+    assert_eq!(buf, [0x9f, 0x01, 0x61, 0x61, 0x82, 0x02, 0x03, 0xff]);
+    let (val, m) = Cbor::decode(&mut buf.as_slice()).unwrap();
+    assert_eq!((val.clone(), m), (Cbor::Major4(Info::Indefinite, items()), n));
+    let mut ref_buf = vec![];
+    val.encode(&mut ref_buf).unwrap();
+    assert_eq!(buf, ref_buf);
+
+    let pairs = vec![
+        (Key::Text("b".to_string()), 1_u64.into_cbor().unwrap()),
+        (Key::U64(1), Cbor::null()),
+    ];
+    let mut buf = vec![];
+    let n = encode_indefinite_map(&mut buf, pairs.clone()).unwrap();
+    assert_eq!(n, buf.len());
+    assert_eq!(buf, [0xbf, 0x61, 0x62, 0x01, 0x01, 0xf6, 0xff]);
+    let (val, _) = Cbor::decode(&mut buf.as_slice()).unwrap();
+    assert_eq!(val, Cbor::Major5(Info::Indefinite, pairs, MapOrder::Unknown));
+
+    let mut buf = vec![];
+    encode_indefinite_array(&mut buf, vec![]).unwrap();
+    assert_eq!(buf, [0x9f, 0xff]);
+
+    // chunked strings, as emitted by other producers, are joined.
+    let buf = [0x7f, 0x62, 0x61, 0x62, 0x60, 0x61, 0x63, 0xff];
+    let (val, n) = Cbor::decode(&mut &buf[..]).unwrap();
+    assert_eq!((val, n), (Cbor::Major3(Info::Indefinite, b"abc".to_vec()), 8));
+    let buf = [0x5f, 0x41, 0x01, 0x42, 0x02, 0x03, 0xff];
+    let (val, n) = Cbor::decode(&mut &buf[..]).unwrap();
+    assert_eq!((val, n), (Cbor::Major2(Info::Indefinite, vec![1, 2, 3]), 7));
+}
+
+#[test]
+fn test_get_tag_untag() {
+    let content = vec![1_u64, 2].into_cbor().unwrap();
+    for num in [0_u64, 1, 2, 3, 21, 24, 39, 55799, u64::MAX].iter() {
+        let val: Cbor = Tag::from_num(*num, content.clone()).into();
+        assert_eq!(val.get_tag(), Some((*num, &content)));
+        assert_eq!(val.untag(), content);
+    }
+
+    // only the outer tag is stripped.
+    let inner: Cbor = Tag::from_value(7, content.clone()).into();
+    let val: Cbor = Tag::from_value(55799, inner.clone()).into();
+    assert_eq!(val.get_tag(), Some((55799, &inner)));
+    assert_eq!(val.untag().untag(), content);
+
+    // untagged values.
+    assert_eq!(content.get_tag(), None);
+    assert_eq!(content.clone().untag(), content);
+    let val = "hello".into_cbor().unwrap();
+    assert_eq!(val.clone().untag(), val);
+}
+
+#[test]
+fn test_eq_ignoring_tags() {
+    let bare: Cbor = vec![
+        (Key::from("name"), "hello".into_cbor().unwrap()),
+        (Key::from(10_u64), vec![1_u64, 2].into_cbor().unwrap()),
+    ]
+    .into_cbor()
+    .unwrap();
+    let wrapped: Cbor = Tag::from_value(55799, bare.clone()).into();
+    assert_ne!(wrapped, bare);
+    assert!(wrapped.eq_ignoring_tags(&bare, &[55799]));
+    assert!(bare.eq_ignoring_tags(&wrapped, &[55799]));
+    assert!(!wrapped.eq_ignoring_tags(&bare, &[]));
+    assert!(!wrapped.eq_ignoring_tags(&bare, &[24]));
+
+    // decoded from self-described cbor, 0xd9d9f7 prefix.
+    let mut buf = vec![0xd9, 0xd9, 0xf7];
+    bare.encode(&mut buf).unwrap();
+    let (val, _) = Cbor::decode(&mut buf.as_slice()).unwrap();
+    assert!(val.eq_ignoring_tags(&bare, &[55799]));
+
+    // nested, and repeated, tags.
+    let inner: Cbor = Tag::from_value(55799, 2_u64.into_cbor().unwrap()).into();
+    let other: Cbor =
+        vec![1_u64.into_cbor().unwrap(), Tag::from_value(55799, inner).into()]
+            .into_cbor()
+            .unwrap();
+    let refval = vec![1_u64, 2].into_cbor().unwrap();
+    assert!(other.eq_ignoring_tags(&refval, &[55799]));
+    assert!(!other.eq_ignoring_tags(&vec![1_u64, 3].into_cbor().unwrap(), &[55799]));
+
+    // tags not listed must match.
+    let x: Cbor = Tag::from_value(7, bare.clone()).into();
+    let y: Cbor = Tag::from_value(7, wrapped.clone()).into();
+    let z: Cbor = Tag::from_value(8, bare.clone()).into();
+    assert!(x.eq_ignoring_tags(&y, &[55799]));
+    assert!(!x.eq_ignoring_tags(&z, &[55799]));
+
+    // tag 24 is stripped, without decoding the wrapped bytes.
+    let encoded: Cbor = Tag::from_encoded_cbor(bare.clone()).unwrap().into();
+    let mut buf = vec![];
+    bare.encode(&mut buf).unwrap();
+    assert!(encoded.eq_ignoring_tags(&Cbor::from_bytes(buf).unwrap(), &[24]));
+    assert!(!encoded.eq_ignoring_tags(&bare, &[24]));
+}
+
+#[test]
+fn test_exceeds_js_safe_integer() {
+    let max_safe: i128 = (1 << 53) - 1;
+
+    let safe: Vec<i128> = vec![0, 1, -1, max_safe, -max_safe];
+    for val in safe.into_iter() {
+        assert!(!val.into_cbor().unwrap().exceeds_js_safe_integer(), "{}", val);
+    }
+    let unsafe_: Vec<i128> =
+        vec![max_safe + 1, -max_safe - 1, i128::from(u64::MAX), i128::MIN, i128::MAX];
+    for val in unsafe_.into_iter() {
+        assert!(val.into_cbor().unwrap().exceeds_js_safe_integer(), "{}", val);
+    }
+
+    // big-nums within the safe range, and beyond 128-bit.
+    let small: Cbor =
+        Tag::UBigNum(Box::new(Cbor::from_bytes(vec![0, 0, 1]).unwrap())).into();
+    assert!(!small.exceeds_js_safe_integer());
+    let small: Cbor = Tag::SBigNum(Box::new(Cbor::from_bytes(vec![]).unwrap())).into();
+    assert!(!small.exceeds_js_safe_integer());
+    let big: BigInt = BigInt::from(u128::MAX) * 2;
+    assert!(big.clone().into_cbor().unwrap().exceeds_js_safe_integer());
+    assert!((-big).into_cbor().unwrap().exceeds_js_safe_integer());
+
+    // floats and other types.
+    assert!(!1e300_f64.into_cbor().unwrap().exceeds_js_safe_integer());
+    assert!(!"9007199254740993".into_cbor().unwrap().exceeds_js_safe_integer());
+    assert!(!vec![u64::MAX].into_cbor().unwrap().exceeds_js_safe_integer());
+}
+
+#[test]
+fn test_truncate_depth() {
+    // {"a": [1, [2, [3]]], 10: tag(7, [4])}
+    let inner = vec![3_u64].into_cbor().unwrap();
+    let list = vec![2_u64.into_cbor().unwrap(), inner].into_cbor().unwrap();
+    let tagged: Cbor = Tag::from_value(7, vec![4_u64].into_cbor().unwrap()).into();
+    let val: Cbor = vec![
+        (Key::from("a"), vec![1_u64.into_cbor().unwrap(), list].into_cbor().unwrap()),
+        (Key::from(10_u64), tagged),
+    ]
+    .into_cbor()
+    .unwrap();
+
+    let u = Cbor::undefined;
+    assert_eq!(val.truncate_depth(0), u());
+    assert_eq!(
+        val.truncate_depth(1),
+        vec![(Key::from("a"), u()), (Key::from(10_u64), u())].into_cbor().unwrap()
+    );
+
+    let refval: Cbor = vec![
+        (
+            Key::from("a"),
+            vec![1_u64.into_cbor().unwrap(), vec![u(), u()].into_cbor().unwrap()]
+                .into_cbor()
+                .unwrap(),
+        ),
+        (Key::from(10_u64), Tag::from_value(7, vec![u()].into_cbor().unwrap()).into()),
+    ]
+    .into_cbor()
+    .unwrap();
+    assert_eq!(val.truncate_depth(3), refval);
+    assert_eq!(val.truncate_depth(5), val);
+    assert_eq!(val.truncate_depth(100), val);
+    assert_eq!(val.truncate_depth(3).stats().max_depth, 4);
+
+    let sentinel = "...".into_cbor().unwrap();
+    let refval: Cbor = vec![
+        (Key::from("a"), vec![sentinel.clone(), sentinel.clone()].into_cbor().unwrap()),
+        (Key::from(10_u64), Tag::from_value(7, sentinel.clone()).into()),
+    ]
+    .into_cbor()
+    .unwrap();
+    assert_eq!(val.truncate_depth_with(2, &sentinel), refval);
+
+    // adversarially deep value.
+    let mut deep = 0_u64.into_cbor().unwrap();
+    for _ in 0..100_000 {
+        deep = Cbor::Major4(Info::Tiny(1), vec![deep]);
+    }
+    let val = deep.truncate_depth(2);
+    assert_eq!(
+        val,
+        Cbor::Major4(Info::Tiny(1), vec![Cbor::Major4(Info::Tiny(1), vec![u()])])
+    );
+    let copy = deep.truncate_depth(usize::MAX);
+    assert_eq!(copy.stats().max_depth, 100_001);
+    std::mem::forget(copy);
+    std::mem::forget(deep);
+}
+
+#[test]
+fn test_normalize() {
+    let equivalents: Vec<(&[u8], &[u8])> = vec![
+        (&[0x19, 0x00, 0x05], &[0x05]),
+        (&[0x3a, 0x00, 0x00, 0x00, 0x04], &[0x24]),
+        (&[0x9f, 0x01, 0x18, 0x02, 0xff], &[0x82, 0x01, 0x02]),
+        (&[0x5f, 0x41, 0x01, 0x41, 0x02, 0xff], &[0x42, 0x01, 0x02]),
+        (&[0x7f, 0x61, 0x61, 0x61, 0x62, 0xff], &[0x62, 0x61, 0x62]),
+        // map entries in different order.
+        (
+            &[0xbf, 0x61, 0x61, 0x19, 0x00, 0x01, 0x02, 0x03, 0xff],
+            &[0xa2, 0x02, 0x03, 0x61, 0x61, 0x01],
+        ),
+        // floats.
+        (&[0xfb, 0x3f, 0xf8, 0, 0, 0, 0, 0, 0], &[0xf9, 0x3e, 0x00]),
+        (&[0xfa, 0x47, 0xc3, 0x50, 0x00], &[0xfb, 0x40, 0xf8, 0x6a, 0, 0, 0, 0, 0]),
+        (&[0xfa, 0x7f, 0xc0, 0x00, 0x01], &[0xf9, 0x7e, 0x00]),
+        (&[0xfb, 0xff, 0xf8, 0, 0, 0, 0, 0, 0x01], &[0xf9, 0x7e, 0x01]),
+        // self-describe tag.
+        (&[0xd9, 0xd9, 0xf7, 0x01], &[0x01]),
+        (&[0x81, 0xd9, 0xd9, 0xf7, 0x80], &[0x81, 0x80]),
+        // big-nums and tag numbers.
+        (&[0xc2, 0x43, 0x00, 0x00, 0x05], &[0x05]),
+        (&[0xc3, 0x41, 0x00], &[0x20]),
+        (
+            &[0xc2, 0x4a, 0x00, 0x01, 0, 0, 0, 0, 0, 0, 0, 0],
+            &[0xc2, 0x49, 0x01, 0, 0, 0, 0, 0, 0, 0, 0],
+        ),
+        (&[0xd9, 0x00, 0x20, 0x61, 0x61], &[0xd8, 0x20, 0x61, 0x61]),
+    ];
+    for (a, b) in equivalents.into_iter() {
+        let (x, _) = Cbor::decode(&mut &a[..]).unwrap();
+        let (y, _) = Cbor::decode(&mut &b[..]).unwrap();
+        assert_ne!(x, y, "{:x?} {:x?}", a, b);
+        assert_eq!(x.normalize(), y.normalize(), "{:x?} {:x?}", a, b);
+    }
+
+    let distinct: Vec<(&[u8], &[u8])> = vec![
+        (&[0x01], &[0xf9, 0x3c, 0x00]),
+        (&[0xf9, 0x00, 0x00], &[0xf9, 0x80, 0x00]),
+        (&[0x41, 0x61], &[0x61, 0x61]),
+        (&[0xc1, 0x01], &[0x01]),
+        (&[0xa1, 0x01, 0x02], &[0xa1, 0x02, 0x01]),
+    ];
+    for (a, b) in distinct.into_iter() {
+        let (x, _) = Cbor::decode(&mut &a[..]).unwrap();
+        let (y, _) = Cbor::decode(&mut &b[..]).unwrap();
+        assert_ne!(x.normalize(), y.normalize(), "{:x?} {:x?}", a, b);
+    }
+
+    let val = vec![1_u64, 2].into_cbor().unwrap();
+    assert_eq!(Cbor::Binary(vec![0x9f, 0x01, 0x02, 0xff]).normalize(), val);
+    let bad = Cbor::Binary(vec![0x82, 0x01]);
+    assert_eq!(bad.normalize(), bad);
+
+    // float keys, narrowed to half precision by canonical encoding.
+    let val = Cbor::Major5(
+        Info::Tiny(1),
+        vec![(Key::F32(1.5), Cbor::null()), (Key::F64(1.0e300), Cbor::null())],
+        MapOrder::Unknown,
+    )
+    .normalize();
+    let mut buf = vec![];
+    val.encode_canonical(&mut buf).unwrap();
+    assert_eq!(buf[1..4], [0xf9, 0x3e, 0x00]);
+    assert_eq!(Cbor::decode(&mut buf.as_slice()).unwrap().0.normalize(), val);
+
+    // normalized values are idempotent, and encode in canonical form.
+    let seed: u128 = random();
+    println!("test_normalize seed:{}", seed);
+    let mut rng = {
+        let mut rng_seed = [0; 32];
+        rng_seed[0..16].copy_from_slice(&seed.to_le_bytes());
+        StdRng::from_seed(rng_seed)
+    };
+    for _ in 0..1000 {
+        let val: Cbor = {
+            let bytes: Vec<u8> =
+                (0..100).flat_map(|_| rng.gen::<[u8; 32]>().to_vec()).collect();
+            let mut uns = Unstructured::new(&bytes);
+            uns.arbitrary().unwrap()
+        };
+        let norm = val.normalize();
+        assert_eq!(norm.normalize(), norm);
+        // canonical encoding fails for duplicate keys.
+        let mut buf = vec![];
+        if norm.encode_canonical(&mut buf).is_ok() {
+            let (canon, _) = Cbor::decode(&mut buf.as_slice()).unwrap();
+            assert_eq!(canon.normalize(), norm);
+        }
+    }
+}
+
+#[cfg(feature = "bytes")]
+#[test]
+fn test_decode_bytes() {
+    use bytes::Buf;
+
+    let vals = [10.into_cbor().unwrap(), vec!["a", "b"].into_cbor().unwrap()];
+    let mut data = vec![];
+    for val in vals.iter() {
+        val.encode(&mut data).unwrap();
+    }
+    let n = data.len();
+
+    let mut buf = bytes::Bytes::from(data.clone());
+    for val in vals.iter() {
+        assert_eq!(&Cbor::decode_bytes(&mut buf).unwrap(), val);
+    }
+    assert!(!buf.has_remaining());
+
+    // truncated value is not consumed from contiguous buffer.
+    let mut buf = bytes::Bytes::from(data[..n - 1].to_vec());
+    assert_eq!(Cbor::decode_bytes(&mut buf).unwrap(), vals[0]);
+    let remaining = buf.remaining();
+    assert!(Cbor::decode_bytes(&mut buf).is_err());
+    assert_eq!(buf.remaining(), remaining);
+
+    // chained buffers, split within the second value.
+    let mut buf = (&data[..3]).chain(&data[3..]);
+    for val in vals.iter() {
+        assert_eq!(&Cbor::decode_bytes(&mut buf).unwrap(), val);
+    }
+    assert!(!buf.has_remaining());
+}
+
+#[test]
+fn test_from_slice() {
+    let items: Vec<u64> = vec![0, 23, 24, 1000, u64::MAX];
+    let val = Cbor::from_slice(&items[1..]).unwrap();
+    assert_eq!(val, items[1..].to_vec().into_cbor().unwrap());
+
+    let (mut buf, mut refbuf) = (vec![], vec![]);
+    val.encode(&mut buf).unwrap();
+    items[1..].to_vec().into_cbor().unwrap().encode(&mut refbuf).unwrap();
+    assert_eq!(buf, refbuf);
+
+    let empty: &[u64] = &[];
+    assert_eq!(Cbor::from_slice(empty).unwrap(), Vec::<u64>::new().into_cbor().unwrap());
+}
+
+#[test]
+fn test_try_from_bytes() {
+    let val: Cbor = vec![10_u64, 2000].into_cbor().unwrap();
+    let mut buf: Vec<u8> = vec![];
+    val.encode(&mut buf).unwrap();
+
+    assert_eq!(Cbor::try_from(buf.as_slice()).unwrap(), val);
+    assert_eq!(Cbor::try_from(buf.clone()).unwrap(), val);
+
+    buf.push(0);
+    match Cbor::try_from(buf.as_slice()) {
+        Err(Error::FailCbor(_, _)) => (),
+        res => panic!("{:?}", res),
+    }
+    buf.truncate(buf.len() - 2);
+    match Cbor::try_from(buf) {
+        Err(Error::FailCbor(_, _)) => (),
+        res => panic!("{:?}", res),
+    }
+}
+
+#[test]
+fn test_expected_encoding() {
+    let refs = [
+        (21, ExpectedEncoding::Base64Url),
+        (22, ExpectedEncoding::Base64),
+        (23, ExpectedEncoding::Base16),
+    ];
+    for (num, enc) in refs.iter() {
+        let buf = vec![0xc0 | num, 0x43, 0x01, 0x02, 0x03];
+        let (val, n) = Cbor::decode(&mut buf.as_slice()).unwrap();
+        assert_eq!(n, buf.len());
+        assert_eq!(val.as_expected_encoding(), Some(*enc));
+        match &val {
+            Cbor::Major6(_, Tag::Expected(_, data)) => {
+                assert_eq!(data.as_ref(), &Cbor::from_bytes(vec![1, 2, 3]).unwrap())
+            }
+            val => panic!("{:?}", val),
+        }
+
+        let mut out = vec![];
+        val.encode(&mut out).unwrap();
+        assert_eq!(out, buf);
+    }
+
+    let val = Cbor::Major6(24.into(), Tag::from_value(24, 1.into_cbor().unwrap()));
+    assert_eq!(val.as_expected_encoding(), None);
+    assert_eq!(1.into_cbor().unwrap().as_expected_encoding(), None);
+}
+
+#[cfg(feature = "ciborium")]
+#[test]
+fn test_ciborium() {
+    // both implementations agree on the encoding of these values, floats
+    // and bignums are re-encoded by ciborium in its own preferred form.
+    fn is_comparable(val: &Cbor) -> bool {
+        match val {
+            Cbor::Major4(_, list) => list.iter().all(is_comparable),
+            Cbor::Major5(_, map, _) => map.iter().all(|(key, val)| {
+                !matches!(key, Key::F32(_) | Key::F64(_)) && is_comparable(val)
+            }),
+            Cbor::Major6(_, Tag::EncodedCbor(_)) => true,
+            Cbor::Major6(_, Tag::UBigNum(_) | Tag::SBigNum(_)) => false,
+            Cbor::Major6(_, tag) => is_comparable(tag.as_content()),
+            Cbor::Major7(_, SimpleValue::F32(_)) => false,
+            Cbor::Major7(_, SimpleValue::F64(_)) => false,
+            _ => true,
+        }
+    }
+
+    let seed: u128 = random();
+    println!("test_ciborium seed:{}", seed);
+    let mut rng = {
+        let mut rng_seed = [0; 32];
+        rng_seed[0..16].copy_from_slice(&seed.to_le_bytes());
+        StdRng::from_seed(rng_seed)
+    };
+
+    let mut n_compared = 0;
+    for _i in 0..10000 {
+        let val: Cbor = {
+            let bytes: Vec<u8> =
+                (0..100).flat_map(|_| rng.gen::<[u8; 32]>().to_vec()).collect();
+            let mut uns = Unstructured::new(&bytes);
+            uns.arbitrary().unwrap()
+        };
+        if !is_comparable(&val) {
+            continue;
+        }
+
+        let mut buf: Vec<u8> = vec![];
+        val.encode(&mut buf).unwrap();
+
+        let value: ciborium::value::Value = match ciborium::de::from_reader(&buf[..]) {
+            Ok(value) => value,
+            Err(err) => panic!("ciborium decode {} for {:?}", err, val),
+        };
+        let mut rbuf: Vec<u8> = vec![];
+        ciborium::ser::into_writer(&value, &mut rbuf).unwrap();
+        assert_eq!(buf, rbuf, "diverging value {:?}", val);
+        n_compared += 1;
+    }
+    // most of the generated values are free of floats and bignums.
+    assert!(n_compared > 5000, "compared only {} values", n_compared);
+}
+
+#[test]
+fn test_predicates() {
+    let null = SimpleValue::Null.into_cbor().unwrap();
+    let undefined = Cbor::Major7(Info::Tiny(23), SimpleValue::Undefined);
+    let flag = true.into_cbor().unwrap();
+    let num = 10_u64.into_cbor().unwrap();
+    let neg = (-10_i64).into_cbor().unwrap();
+    let float = 10.5_f64.into_cbor().unwrap();
+    let big = BigInt::from(u128::MAX).into_cbor().unwrap();
+    let text = "hello".into_cbor().unwrap();
+    let byts = Cbor::from_bytes(vec![1, 2]).unwrap();
+    let list = vec![1_u64].into_cbor().unwrap();
+    let map = vec![(Key::from(1_u64), num.clone())].into_cbor().unwrap();
+    let tag: Cbor = Tag::from_identifier(num.clone()).into();
+
+    assert!(null.is_null() && !undefined.is_null() && !num.is_null());
+    assert!(undefined.is_undefined() && !null.is_undefined());
+    assert!(flag.is_bool() && !null.is_bool() && !num.is_bool());
+    for val in [&num, &neg, &float, &big].iter() {
+        assert!(val.is_number(), "{:?}", val);
+    }
+    assert!(!text.is_number() && !tag.is_number() && !flag.is_number());
+    assert!(text.is_text() && !byts.is_text());
+    assert!(byts.is_bytes() && !text.is_bytes() && !list.is_bytes());
+    assert!(list.is_array() && !map.is_array() && !byts.is_array());
+    assert!(map.is_map() && !list.is_map());
+    assert!(tag.is_tag() && big.is_tag() && !num.is_tag());
+
+    let mut buf = vec![];
+    list.encode(&mut buf).unwrap();
+    assert!(!Cbor::Binary(buf).is_array());
+}
+
+#[test]
+fn test_lossy_text() {
+    let config = |lossy_text| DecodeConfig { lossy_text, ..DecodeConfig::default() };
+
+    // "a\xffb", and indefinite ["a\xff", "b"]
+    let bufs: [&[u8]; 2] =
+        [&[0x63, 0x61, 0xff, 0x62], &[0x7f, 0x62, 0x61, 0xff, 0x61, 0x62, 0xff]];
+    for buf in bufs.iter() {
+        let (val, n) = Cbor::decode(&mut &buf[..]).unwrap();
+        assert_eq!(n, buf.len());
+        assert!(val.is_text());
+        assert!(String::from_cbor(val).is_err());
+
+        let cf = config(LossyText::Replace);
+        let (val, n) = Cbor::decode_with(&mut &buf[..], &cf).unwrap();
+        assert_eq!(n, buf.len());
+        assert_eq!(String::from_cbor(val.clone()).unwrap(), "a\u{fffd}b");
+        if buf[0] == 0x63 {
+            assert_eq!(val, "a\u{fffd}b".into_cbor().unwrap());
+        }
+
+        let cf = config(LossyText::Bytes);
+        let (val, n) = Cbor::decode_with(&mut &buf[..], &cf).unwrap();
+        assert_eq!(n, buf.len());
+        assert_eq!(val.into_bytes().unwrap(), vec![0x61, 0xff, 0x62]);
+
+        match Cbor::decode_with(&mut &buf[..], &config(LossyText::Strict)) {
+            Err(Error::FailCbor(_, msg)) => assert!(msg.contains("utf8"), "{}", msg),
+            res => panic!("{:?}", res),
+        }
+    }
+
+    // byte-string chunk is invalid inside text, even when invalid text is
+    // decoded as bytes.
+    let buf = [0x7f, 0x62, 0x61, 0xff, 0x41, 0x62, 0xff];
+    assert!(Cbor::decode_with(&mut &buf[..], &config(LossyText::Bytes)).is_err());
+
+    // chunks are validated by themselves, "\u{e9}" split across chunks.
+    let buf = [0x7f, 0x61, 0xc3, 0x61, 0xa9, 0xff];
+    assert!(Cbor::decode_with(&mut &buf[..], &config(LossyText::Strict)).is_err());
+    let buf = [0x7f, 0x62, 0xc3, 0xa9, 0xff];
+    let (val, _) = Cbor::decode_with(&mut &buf[..], &config(LossyText::Strict)).unwrap();
+    assert_eq!(String::from_cbor(val).unwrap(), "\u{e9}");
+
+    // valid text is not affected.
+    let mut buf = vec![];
+    "hello".into_cbor().unwrap().encode(&mut buf).unwrap();
+    let lossys =
+        [LossyText::Unchecked, LossyText::Strict, LossyText::Replace, LossyText::Bytes];
+    for lossy in lossys.iter() {
+        let (val, _) = Cbor::decode_with(&mut buf.as_slice(), &config(*lossy)).unwrap();
+        assert_eq!(val, "hello".into_cbor().unwrap());
+    }
+}
+
+#[test]
+fn test_unknown_tag_policy() {
+    let config = |unknown_tag_policy, known_tags| DecodeConfig {
+        unknown_tag_policy,
+        known_tags,
+        ..DecodeConfig::default()
+    };
+
+    // [1000("x"), 2(h'01')]
+    let inner: Cbor = Tag::from_value(1000, "x".into_cbor().unwrap()).into();
+    let bignum: Cbor = Tag::UBigNum(Box::new(Cbor::from_bytes(vec![1]).unwrap())).into();
+    let mut buf = vec![];
+    let n = vec![inner, bignum].into_cbor().unwrap().encode(&mut buf).unwrap();
+
+    let (val, m) = Cbor::decode(&mut buf.as_slice()).unwrap();
+    assert_eq!(m, n);
+    let items = Vec::<Cbor>::from_cbor(val.clone()).unwrap();
+    assert_eq!(items[0].get_tag().unwrap().0, 1000);
+
+    let cf = config(UnknownTagPolicy::Keep, vec![]);
+    let (keep, _) = Cbor::decode_with(&mut buf.as_slice(), &cf).unwrap();
+    assert_eq!(keep, val);
+
+    let cf = config(UnknownTagPolicy::Reject, vec![]);
+    match Cbor::decode_with(&mut buf.as_slice(), &cf) {
+        Err(Error::FailCbor(_, msg)) => {
+            assert!(msg.contains("unknown tag 1000"), "{}", msg)
+        }
+        res => panic!("{:?}", res),
+    }
+    let cf = config(UnknownTagPolicy::Reject, vec![1000]);
+    let (known, _) = Cbor::decode_with(&mut buf.as_slice(), &cf).unwrap();
+    assert_eq!(known, val);
+
+    let cf = config(UnknownTagPolicy::Unwrap, vec![]);
+    let (unwrap, m) = Cbor::decode_with(&mut buf.as_slice(), &cf).unwrap();
+    assert_eq!(m, n);
+    let items = Vec::<Cbor>::from_cbor(unwrap).unwrap();
+    assert_eq!(items[0], "x".into_cbor().unwrap());
+    assert!(matches!(items[1], Cbor::Major6(_, Tag::UBigNum(_))));
+}
+
+#[test]
+fn test_reject_nonfinite_floats() {
+    let config = DecodeConfig {
+        reject_nonfinite_floats: true,
+        ..DecodeConfig::default()
+    };
+
+    let testcases: Vec<Vec<u8>> = vec![
+        vec![0xf9, 0x7c, 0x00],             // f16 +inf
+        vec![0xf9, 0xfc, 0x00],             // f16 -inf
+        vec![0xf9, 0x7e, 0x00],             // f16 nan
+        vec![0xfa, 0x7f, 0x80, 0x00, 0x00], // f32 +inf
+        vec![0xfa, 0xff, 0x80, 0x00, 0x00], // f32 -inf
+        vec![0xfa, 0x7f, 0xc0, 0x00, 0x00], // f32 nan
+        vec![0xfb, 0x7f, 0xf0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // f64 +inf
+        vec![0xfb, 0xff, 0xf0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // f64 -inf
+        vec![0xfb, 0x7f, 0xf8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // f64 nan
+    ];
+    for buf in testcases.into_iter() {
+        let (val, n) = Cbor::decode(&mut buf.as_slice()).unwrap();
+        assert_eq!(n, buf.len());
+        match val {
+            Cbor::Major7(_, sval) => assert!(!sval.is_finite(), "{:?}", sval),
+            val => panic!("{:?}", val),
+        }
+        match Cbor::decode_with(&mut buf.as_slice(), &config) {
+            Err(Error::FailCbor(_, msg)) => assert!(msg.contains("offset 0"), "{}", msg),
+            res => panic!("{:?}", res),
+        }
+    }
+
+    // offset shall point to the nested float.
+    let buf = [0x82, 0x01, 0xf9, 0x7e, 0x00];
+    match Cbor::decode_with(&mut buf.as_ref(), &config) {
+        Err(Error::FailCbor(_, msg)) => assert!(msg.contains("offset 2"), "{}", msg),
+        res => panic!("{:?}", res),
+    }
+
+    // finite floats are accepted.
+    let buf = [0x83, 0xf9, 0x3c, 0x00, 0xfa, 0x3f, 0x80, 0x00, 0x00, 0x01];
+    let (val, _) = Cbor::decode_with(&mut buf.as_ref(), &config).unwrap();
+    assert!(val.is_array());
+}
+
+#[cfg(feature = "subtle")]
+#[test]
+fn test_ct_eq_bytes() {
+    let a = Cbor::from_bytes(vec![1, 2, 3, 4]).unwrap();
+    let b = Cbor::from_bytes(vec![1, 2, 3, 5]).unwrap();
+    let c = Cbor::from_bytes(vec![1, 2, 3]).unwrap();
+    let d = vec![1_u64, 2, 3, 4].into_cbor().unwrap();
+
+    assert_eq!(a.ct_eq_bytes(&a.clone()), Some(true));
+    assert_eq!(a.ct_eq_bytes(&b), Some(false));
+    assert_eq!(a.ct_eq_bytes(&c), Some(false));
+    assert_eq!(a.ct_eq_bytes(&d), None);
+    assert_eq!(d.ct_eq_bytes(&a), None);
+}
+
+#[test]
+fn test_map_from() {
+    let pairs = vec![
+        ("b".into_cbor().unwrap(), 1.into_cbor().unwrap()),
+        (10.into_cbor().unwrap(), 2.into_cbor().unwrap()),
+        ("a".into_cbor().unwrap(), 3.into_cbor().unwrap()),
+    ];
+
+    let val = Cbor::map_from(pairs.clone()).unwrap();
+    let keys: Vec<Key> = match val {
+        Cbor::Major5(Info::Tiny(3), map, _) => map.into_iter().map(|(k, _)| k).collect(),
+        val => panic!("{:?}", val),
+    };
+    let refs = vec![Key::Text("b".to_string()), Key::U64(10), Key::Text("a".to_string())];
+    assert_eq!(keys, refs);
+
+    let config = MapConfig {
+        unique_keys: true,
+        sort_keys: true,
+        ..MapConfig::default()
+    };
+    let val = Cbor::map_from_with(pairs.clone(), &config).unwrap();
+    let keys: Vec<Key> = match val {
+        Cbor::Major5(_, map, _) => map.into_iter().map(|(k, _)| k).collect(),
+        val => panic!("{:?}", val),
+    };
+    let refs = vec![Key::U64(10), Key::Text("a".to_string()), Key::Text("b".to_string())];
+    assert_eq!(keys, refs);
+
+    // sorted by canonical encoding, shorter keys first, unlike Key's Ord.
+    let keyed = vec![
+        ("aa".into_cbor().unwrap(), 1.into_cbor().unwrap()),
+        ("b".into_cbor().unwrap(), 2.into_cbor().unwrap()),
+        ((-1).into_cbor().unwrap(), 3.into_cbor().unwrap()),
+        (100.into_cbor().unwrap(), 4.into_cbor().unwrap()),
+        (1.into_cbor().unwrap(), 5.into_cbor().unwrap()),
+    ];
+    let val = Cbor::map_from_with(keyed, &config).unwrap();
+    let mut buf = vec![];
+    val.encode(&mut buf).unwrap();
+    let mut canonical = vec![];
+    val.encode_canonical(&mut canonical).unwrap();
+    assert_eq!(buf, canonical);
+    assert!(is_canonical(&buf).unwrap());
+
+    let mut dups = pairs;
+    dups.push(("b".into_cbor().unwrap(), 4.into_cbor().unwrap()));
+    assert!(Cbor::map_from(dups.clone()).is_ok());
+    match Cbor::map_from_with(dups, &config) {
+        Err(Error::FailCbor(_, _)) => (),
+        res => panic!("{:?}", res),
+    }
+
+    let pairs = vec![(vec![1_u64].into_cbor().unwrap(), 1.into_cbor().unwrap())];
+    assert!(Cbor::map_from(pairs).is_err());
+}
+
+#[test]
+fn test_float_width() {
+    let refs: Vec<Vec<u8>> = vec![
+        vec![0xf9, 0x3c, 0x00],
+        vec![0xf9, 0x7e, 0x00],
+        vec![0xfa, 0x3f, 0x80, 0x00, 0x00],
+        vec![0xfa, 0x7f, 0x80, 0x00, 0x00],
+        vec![0xfb, 0x3f, 0xf0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+        vec![0xfb, 0x3f, 0xf1, 0x99, 0x99, 0x99, 0x99, 0x99, 0x9a],
+    ];
+    for refbuf in refs.into_iter() {
+        let (val, n) = Cbor::decode(&mut refbuf.as_slice()).unwrap();
+        assert_eq!(n, refbuf.len());
+        let mut buf = vec![];
+        assert_eq!(val.encode(&mut buf).unwrap(), refbuf.len());
+        assert_eq!(buf, refbuf);
+    }
+
+    let vals = vec![
+        (SimpleValue::F16(0x3c00).into_cbor().unwrap(), 3),
+        (1.0_f32.into_cbor().unwrap(), 5),
+        (1.0_f64.into_cbor().unwrap(), 9),
+    ];
+    for (val, n) in vals.into_iter() {
+        let mut buf = vec![];
+        assert_eq!(val.encode(&mut buf).unwrap(), n);
+        let (nval, m) = Cbor::decode(&mut buf.as_slice()).unwrap();
+        assert_eq!((nval, m), (val, n));
+    }
+    assert_eq!(f32::from_cbor(1.0_f32.into_cbor().unwrap()).unwrap(), 1.0);
+    assert!(f64::from_cbor(1.0_f32.into_cbor().unwrap()).is_err());
+}
+
+#[test]
+fn test_pretty_print_color() {
+    let val = Cbor::map_from(vec![
+        ("list".into_cbor().unwrap(), vec![1_u64, 2].into_cbor().unwrap()),
+        (10.into_cbor().unwrap(), 1.5_f64.into_cbor().unwrap()),
+        ("big".into_cbor().unwrap(), u128::MAX.into_cbor().unwrap()),
+    ])
+    .unwrap();
+
+    let plain = pretty_print(&val).unwrap();
+    let config = PrintConfig { color: false, indent: 2 };
+    assert_eq!(pretty_print_with(&val, &config).unwrap(), plain);
+    assert_eq!(
+        plain,
+        concat!(
+            "Dict(3)\n",
+            "Key(T:\"list\")\n",
+            "  List(2)\n",
+            "    Maj0(Tiny(0x1),0x1)\n",
+            "    Maj0(Tiny(0x2),0x2)\n",
+            "Key(P:0xa)\n",
+            "  Maj7(U64,F64(1.5))\n",
+            "Key(T:\"big\")\n",
+            "  Tag::UBigNum(0xffffffffffffffffffffffffffffffff)",
+        )
+    );
+
+    let config = PrintConfig { color: true, indent: 2 };
+    let color = pretty_print_with(&val, &config).unwrap();
+    assert!(color.contains("\x1b[34mKey(T:\"list\")\x1b[0m"));
+    assert!(color.contains("    \x1b[33mMaj0(Tiny(0x1),0x1)\x1b[0m"));
+    assert!(color.contains("  \x1b[35mTag::UBigNum("));
+    let mut stripped = String::default();
+    let mut iter = color.chars();
+    while let Some(ch) = iter.next() {
+        match ch {
+            '\x1b' => while iter.next() != Some('m') {},
+            ch => stripped.push(ch),
+        }
+    }
+    assert_eq!(stripped, plain);
+
+    let config = PrintConfig { color: false, indent: 4 };
+    let wide = pretty_print_with(&val, &config).unwrap();
+    assert_eq!(wide, plain.replace("  ", "    "));
+}
+
+#[test]
+fn test_encode_into() {
+    let vals = [
+        10.into_cbor().unwrap(),
+        "hello".into_cbor().unwrap(),
+        vec![1_u64, 2, 3].into_cbor().unwrap(),
+    ];
+
+    let mut buf: Vec<u8> = vec![0xff];
+    let mut refbuf: Vec<u8> = vec![0xff];
+    for val in vals.iter() {
+        let n = val.encode_into(&mut buf).unwrap();
+        assert_eq!(n, val.encode(&mut refbuf).unwrap());
+    }
+    assert_eq!(buf, refbuf);
+
+    let bad = Cbor::Major4(
+        3_u64.into(),
+        vec![1.into_cbor().unwrap(), Cbor::Major0(Info::Tiny(24), 0)],
+    );
+    assert!(bad.encode_into(&mut buf).is_err());
+    assert_eq!(buf, refbuf);
+}
+
+#[test]
+fn test_key_collision() {
+    // {1: "a", 1.0: "b"}, {1.0: "a", 1.0_f64: "b"} and {1: "a", 1: "b"}
+    let int_float = [0xa2, 0x01, 0x61, 0x61, 0xfa, 0x3f, 0x80, 0, 0, 0x61, 0x62];
+    let float_double = [
+        0xa2, 0xfa, 0x3f, 0x80, 0, 0, 0x61, 0x61, 0xfb, 0x3f, 0xf0, 0, 0, 0, 0, 0, 0,
+        0x61, 0x62,
+    ];
+    let int_int = [0xbf, 0x01, 0x61, 0x61, 0x01, 0x61, 0x62, 0xff];
+
+    let mut config = DecodeConfig::default();
+    for buf in [&int_float[..], &float_double[..], &int_int[..]].iter() {
+        let (val, _) = Cbor::decode_with(&mut &buf[..], &config).unwrap();
+        assert_eq!(val.stats().n_items, 5);
+    }
+
+    config.duplicate_keys = DuplicateKeyPolicy::Reject;
+    assert!(Cbor::decode_with(&mut &int_float[..], &config).is_ok());
+    assert!(Cbor::decode_with(&mut &float_double[..], &config).is_ok());
+    match Cbor::decode_with(&mut &int_int[..], &config) {
+        Err(Error::FailCbor(_, msg)) => assert!(msg.contains("offset 0"), "{}", msg),
+        res => panic!("{:?}", res),
+    }
+
+    config.key_equality = KeyEquality::Numeric;
+    for buf in [&int_float[..], &float_double[..], &int_int[..]].iter() {
+        match Cbor::decode_with(&mut &buf[..], &config) {
+            Err(Error::FailCbor(_, _)) => (),
+            res => panic!("{:?}", res),
+        }
+    }
+    // fractional and out of range values don't collide with integers.
+    let val = Cbor::map_from(vec![
+        (1.into_cbor().unwrap(), 1.into_cbor().unwrap()),
+        (1.5_f64.into_cbor().unwrap(), 1.into_cbor().unwrap()),
+        (u64::MAX.into_cbor().unwrap(), 1.into_cbor().unwrap()),
+        (18446744073709551616.0_f64.into_cbor().unwrap(), 1.into_cbor().unwrap()),
+        ((-1).into_cbor().unwrap(), 1.into_cbor().unwrap()),
+        ((-1.0_f32).into_cbor().unwrap(), 1.into_cbor().unwrap()),
+    ])
+    .unwrap();
+    let mut buf = vec![];
+    val.encode(&mut buf).unwrap();
+    match Cbor::decode_with(&mut buf.as_slice(), &config) {
+        Err(Error::FailCbor(_, msg)) => assert!(msg.contains("N64(-1)"), "{}", msg),
+        res => panic!("{:?}", res),
+    }
+
+    // map_from follows the same semantics.
+    let pairs = vec![
+        (1.into_cbor().unwrap(), 1.into_cbor().unwrap()),
+        (1.0_f64.into_cbor().unwrap(), 2.into_cbor().unwrap()),
+    ];
+    let mut config = MapConfig { unique_keys: true, ..MapConfig::default() };
+    assert!(Cbor::map_from_with(pairs.clone(), &config).is_ok());
+    config.key_equality = KeyEquality::Numeric;
+    assert!(Cbor::map_from_with(pairs, &config).is_err());
+}
+
+#[test]
+fn test_simple_constructors() {
+    let refs = vec![
+        (Cbor::bool(false), vec![0xf4]),
+        (Cbor::bool(true), vec![0xf5]),
+        (Cbor::null(), vec![0xf6]),
+        (Cbor::undefined(), vec![0xf7]),
+        (Cbor::simple(0).unwrap(), vec![0xe0]),
+        (Cbor::simple(19).unwrap(), vec![0xf3]),
+        (Cbor::simple(32).unwrap(), vec![0xf8, 0x20]),
+        (Cbor::simple(255).unwrap(), vec![0xf8, 0xff]),
+    ];
+    for (val, refbuf) in refs.into_iter() {
+        let mut buf = vec![];
+        val.encode(&mut buf).unwrap();
+        assert_eq!(buf, refbuf, "{:?}", val);
+        assert_eq!(Cbor::decode(&mut buf.as_slice()).unwrap(), (val, buf.len()));
+    }
+
+    assert_eq!(Cbor::bool(true), true.into_cbor().unwrap());
+
+    // booleans written by 0.6 and earlier.
+    let config = DecodeConfig { legacy_bools: true, ..DecodeConfig::default() };
+    let buf = [0x82, 0xf4, 0xf5];
+    let (val, _) = Cbor::decode_with(&mut buf.as_ref(), &config).unwrap();
+    assert_eq!(val, vec![true, false].into_cbor().unwrap());
+    let mut out = vec![];
+    val.encode(&mut out).unwrap();
+    assert_eq!(out, [0x82, 0xf5, 0xf4]);
+    assert_eq!(Cbor::simple(20).unwrap(), Cbor::bool(false));
+    assert_eq!(Cbor::simple(23).unwrap(), Cbor::undefined());
+    assert_ne!(Cbor::simple(1).unwrap(), Cbor::simple(2).unwrap());
+    for n in 24..=31 {
+        match Cbor::simple(n) {
+            Err(Error::FailCbor(_, _)) => (),
+            res => panic!("{} {:?}", n, res),
+        }
+    }
+    assert!(Cbor::decode(&mut [0xf8, 0x10].as_ref()).is_err());
+}
+
+#[test]
+fn test_encode_slice() {
+    let val = Cbor::map_from(vec![
+        ("list".into_cbor().unwrap(), vec![1_u64, 1000, 100000].into_cbor().unwrap()),
+        ("big".into_cbor().unwrap(), u128::MAX.into_cbor().unwrap()),
+    ])
+    .unwrap();
+    let mut refbuf = vec![];
+    let n = val.encode(&mut refbuf).unwrap();
+    assert_eq!(val.encoded_len().unwrap(), n);
+
+    let mut out = [0xaa_u8; 64];
+    assert_eq!(val.encode_slice(&mut out).unwrap(), n);
+    assert_eq!(&out[..n], refbuf.as_slice());
+    assert!(out[n..].iter().all(|b| *b == 0xaa));
+
+    let mut out = vec![0_u8; n];
+    assert_eq!(val.encode_slice(&mut out).unwrap(), n);
+    assert_eq!(out, refbuf);
+
+    let mut out = vec![0_u8; n - 1];
+    match val.encode_slice(&mut out) {
+        Err(Error::FailCbor(_, msg)) => {
+            assert!(msg.contains("buffer too small"), "{}", msg)
+        }
+        res => panic!("{:?}", res),
+    }
+}
+
+#[test]
+fn test_indefinite_roundtrip() {
+    // {_ "a": [_ 1, 2], "b": [3]}
+    let buf =
+        vec![0xbf, 0x61, 0x61, 0x9f, 0x01, 0x02, 0xff, 0x61, 0x62, 0x81, 0x03, 0xff];
+    let (val, n) = Cbor::decode(&mut buf.as_slice()).unwrap();
+    assert_eq!(n, buf.len());
+    assert!(val.is_indefinite());
+    match &val {
+        Cbor::Major5(_, map, _) => {
+            assert!(map[0].1.is_indefinite());
+            assert!(!map[1].1.is_indefinite());
+        }
+        val => panic!("{:?}", val),
+    }
+
+    let mut out = vec![];
+    assert_eq!(val.encode(&mut out).unwrap(), buf.len());
+    assert_eq!(out, buf);
+    assert_eq!(val.encoded_len().unwrap(), buf.len());
+
+    // chunked strings are re-emitted as single chunk.
+    let buf = vec![0x7f, 0x62, 0x61, 0x62, 0x61, 0x63, 0xff, 0x5f, 0x41, 0x01, 0xff];
+    let (text, n) = Cbor::decode(&mut buf.as_slice()).unwrap();
+    assert_eq!(n, 7);
+    assert!(text.is_indefinite());
+    let (byts, _) = Cbor::decode(&mut &buf[n..]).unwrap();
+    assert!(byts.is_indefinite());
+
+    let mut out = vec![];
+    text.encode(&mut out).unwrap();
+    assert_eq!(out, vec![0x7f, 0x63, 0x61, 0x62, 0x63, 0xff]);
+    let mut out = vec![];
+    byts.encode(&mut out).unwrap();
+    assert_eq!(out, &buf[n..]);
+    assert!(!"abc".into_cbor().unwrap().is_indefinite());
+
+    // empty strings are re-emitted with an empty chunk.
+    let (byts, _) = Cbor::decode(&mut [0x5f, 0xff].as_ref()).unwrap();
+    let mut out = vec![];
+    assert_eq!(byts.encode(&mut out).unwrap(), 3);
+    assert_eq!(out, vec![0x5f, 0x40, 0xff]);
+}
+
+#[test]
+fn test_decode_limits() {
+    let config = |max_depth, max_items, max_bytes| DecodeConfig {
+        max_depth,
+        max_items,
+        max_bytes,
+        ..DecodeConfig::default()
+    };
+    let is_limit = |res: Result<(Cbor, usize)>| match res {
+        Err(Error::LimitExceeded(_, msg)) => msg,
+        res => panic!("{:?}", res),
+    };
+
+    // [[1, 2], "abc", h'0102']
+    let buf = [0x83, 0x82, 0x01, 0x02, 0x63, 0x61, 0x62, 0x63, 0x42, 0x01, 0x02];
+    let cf = config(Some(3), Some(6), Some(3));
+    let (val, n) = Cbor::decode_with(&mut buf.as_ref(), &cf).unwrap();
+    assert_eq!(n, buf.len());
+    assert_eq!(val, Cbor::decode(&mut buf.as_ref()).unwrap().0);
+
+    let msg =
+        is_limit(Cbor::decode_with(&mut buf.as_ref(), &config(Some(2), None, None)));
+    assert!(msg.contains("recursion limit 2"), "{}", msg);
+    let msg =
+        is_limit(Cbor::decode_with(&mut buf.as_ref(), &config(None, Some(5), None)));
+    assert!(msg.contains("item limit 5"), "{}", msg);
+    let msg =
+        is_limit(Cbor::decode_with(&mut buf.as_ref(), &config(None, None, Some(2))));
+    assert!(msg.contains("offset 4"), "{}", msg);
+
+    // max_depth cannot relax RECURSION_LIMIT.
+    let cf = config(Some(u32::MAX), None, None);
+    let handle =
+        std::thread::Builder::new().stack_size(16 * 1024 * 1024).spawn(move || {
+            let mut buf: Vec<u8> = [0x81].repeat(RECURSION_LIMIT as usize);
+            buf.push(0x01);
+            let msg = is_limit(Cbor::decode_with(&mut buf.as_slice(), &cf));
+            assert!(msg.contains("recursion"), "{}", msg);
+        });
+    handle.unwrap().join().unwrap();
+
+    // string length is checked before reading its content.
+    let buf = [0x5a, 0xff, 0xff, 0xff, 0xff];
+    is_limit(Cbor::decode_with(&mut buf.as_ref(), &config(None, None, Some(1024))));
+
+    // indefinite strings, limit applies to the concatenated string.
+    let buf = [0x5f, 0x42, 0x01, 0x02, 0x42, 0x03, 0x04, 0xff];
+    let cf = config(None, None, Some(4));
+    assert_eq!(Cbor::decode_with(&mut buf.as_ref(), &cf).unwrap().1, buf.len());
+    is_limit(Cbor::decode_with(&mut buf.as_ref(), &config(None, None, Some(3))));
+    let buf = [0x7f, 0x62, 0x61, 0x62, 0x62, 0x63, 0x64, 0xff];
+    is_limit(Cbor::decode_with(&mut buf.as_ref(), &config(None, None, Some(3))));
+
+    // limits apply to items pulled from a stream.
+    let mut decoder = crate::Decoder::with_config(config(None, Some(2), None));
+    decoder.feed(&[0x81, 0x01, 0x82, 0x01, 0x02]);
+    assert!(decoder.pull().unwrap().is_some());
+    match decoder.pull() {
+        Err(Error::LimitExceeded(_, _)) => (),
+        res => panic!("{:?}", res),
+    }
+}
+
+#[test]
+fn test_duplicate_keys_keep() {
+    // {1: "a", 2: "b", 1: "c"} and {_ 1: "a", 2: "b", 1.0: "c"}
+    let buf = [0xa3, 0x01, 0x61, 0x61, 0x02, 0x61, 0x62, 0x01, 0x61, 0x63];
+    let indef =
+        [0xbf, 0x01, 0x61, 0x61, 0x02, 0x61, 0x62, 0xf9, 0x3c, 0x00, 0x61, 0x63, 0xff];
+    let text = |s: &str| s.into_cbor().unwrap();
+
+    let cf = |duplicate_keys, key_equality| DecodeConfig {
+        duplicate_keys,
+        key_equality,
+        ..DecodeConfig::default()
+    };
+    let refs = vec![
+        (&buf[..], KeyEquality::Structural, DuplicateKeyPolicy::KeepFirst, 0, "a"),
+        (&buf[..], KeyEquality::Structural, DuplicateKeyPolicy::KeepLast, 1, "c"),
+        (&indef[..], KeyEquality::Numeric, DuplicateKeyPolicy::KeepFirst, 0, "a"),
+        (&indef[..], KeyEquality::Numeric, DuplicateKeyPolicy::KeepLast, 1, "c"),
+    ];
+    // off is the position of the retained entry, after dropping duplicates.
+    for (buf, eq, policy, off, refv) in refs.into_iter() {
+        let (val, n) = Cbor::decode_with(&mut &buf[..], &cf(policy, eq)).unwrap();
+        assert_eq!(n, buf.len());
+        let map = match &val {
+            Cbor::Major5(_, map, _) => map.clone(),
+            val => panic!("{:?}", val),
+        };
+        assert_eq!(map.len(), 2, "{:?}", policy);
+        assert_eq!(map[off].1, text(refv), "{:?}", policy);
+        assert_eq!(map[1 - off].1, text("b"), "{:?}", policy);
+
+        // length of definite length map is updated.
+        let mut out = vec![];
+        val.encode(&mut out).unwrap();
+        assert_eq!(Cbor::decode(&mut out.as_slice()).unwrap().0, val);
+        assert_eq!(out[0] == 0xbf, buf[0] == 0xbf);
+    }
+
+    // structural equality, 1 and 1.0 are distinct.
+    let config = cf(DuplicateKeyPolicy::KeepFirst, KeyEquality::Structural);
+    let (val, _) = Cbor::decode_with(&mut &indef[..], &config).unwrap();
+    assert_eq!(val.stats().n_items, 7);
+}
+
+#[test]
+fn test_map_get_insert() {
+    // {3: "c", 1: "a", 2: "b"}
+    let buf = [0xa3, 0x03, 0x61, 0x63, 0x01, 0x61, 0x61, 0x02, 0x61, 0x62];
+    let text = |s: &str| s.into_cbor().unwrap();
+    let config = DecodeConfig { sort_keys: true, ..DecodeConfig::default() };
+    let (mut val, _) = Cbor::decode_with(&mut buf.as_ref(), &config).unwrap();
+    match &val {
+        Cbor::Major5(_, map, order) => {
+            let keys: Vec<Key> = map.iter().map(|(k, _)| k.clone()).collect();
+            assert_eq!(keys, vec![Key::U64(1), Key::U64(2), Key::U64(3)]);
+            assert!(order.is_sorted());
+        }
+        val => panic!("{:?}", val),
+    }
+    assert_eq!(val.map_get(&Key::U64(1)), Some(&text("a")));
+    assert_eq!(val.map_get(&Key::U64(3)), Some(&text("c")));
+    assert_eq!(val.map_get(&Key::U64(4)), None);
+    assert_eq!(val.map_get(&Key::Text("a".to_string())), None);
+
+    assert_eq!(val.map_insert(Key::U64(2), text("x")).unwrap(), Some(text("b")));
+    assert_eq!(val.map_insert(Key::N64(-1), text("y")).unwrap(), None);
+    assert_eq!(val.map_insert(Key::Text("z".to_string()), text("z")).unwrap(), None);
+    assert_eq!(val.map_get(&Key::U64(2)), Some(&text("x")));
+    assert_eq!(val.map_get(&Key::N64(-1)), Some(&text("y")));
+    match &val {
+        Cbor::Major5(info, map, order) => {
+            assert_eq!(*info, Info::Tiny(5));
+            assert!(map.windows(2).all(|w| w[0].0 < w[1].0), "{:?}", map);
+            assert!(order.is_sorted());
+        }
+        val => panic!("{:?}", val),
+    }
+    let mut out = vec![];
+    val.encode(&mut out).unwrap();
+    assert_eq!(Cbor::decode(&mut out.as_slice()).unwrap().0, val);
+
+    let mut val = Cbor::map_from(vec![]).unwrap();
+    for n in [5_u64, 1, 4, 2, 3].iter() {
+        val.map_insert(Key::U64(*n), n.into_cbor().unwrap()).unwrap();
+    }
+    for n in 1_u64..=5 {
+        assert_eq!(val.map_get(&Key::U64(n)), Some(&n.into_cbor().unwrap()));
+    }
+
+    // sorted map is looked up without scanning the entries.
+    let entries = vec![
+        (Key::U64(2), text("b")),
+        (Key::U64(3), text("c")),
+        (Key::U64(1), text("a")),
+    ];
+    let val = Cbor::Major5(Info::Tiny(3), entries.clone(), MapOrder::Sorted);
+    assert_eq!(val.map_get(&Key::U64(1)), None);
+    let val = Cbor::Major5(Info::Tiny(3), entries.clone(), MapOrder::Unknown);
+    assert_eq!(val.map_get(&Key::U64(1)), Some(&text("a")));
+    assert!(!MapOrder::of(&entries).is_sorted());
+    assert!(MapOrder::of(&entries[..1]).is_sorted());
+
+    // unsorted map, as decoded.
+    let (mut val, _) = Cbor::decode(&mut buf.as_ref()).unwrap();
+    match &val {
+        Cbor::Major5(_, _, order) => assert!(!order.is_sorted()),
+        val => panic!("{:?}", val),
+    }
+    for (n, s) in [(1, "a"), (2, "b"), (3, "c")].iter() {
+        assert_eq!(val.map_get(&Key::U64(*n)), Some(&text(s)), "{}", n);
+    }
+    assert_eq!(val.map_get(&Key::U64(4)), None);
+    assert_eq!(val.map_insert(Key::U64(3), text("x")).unwrap(), Some(text("c")));
+    assert_eq!(val.map_insert(Key::U64(4), text("d")).unwrap(), None);
+    match &val {
+        Cbor::Major5(info, map, _) => {
+            assert_eq!(*info, Info::Tiny(4));
+            assert_eq!(map.iter().filter(|(k, _)| *k == Key::U64(3)).count(), 1);
+        }
+        val => panic!("{:?}", val),
+    }
+
+    let mut val = text("abc");
+    assert_eq!(val.map_get(&Key::U64(1)), None);
+    match val.map_insert(Key::U64(1), text("a")) {
+        Err(Error::FailConvert(_, _)) => (),
+        res => panic!("{:?}", res),
+    }
+}
@@ -0,0 +1,271 @@
+//! Module implement serde's Deserializer, converting Cbor into serde types.
+
+use serde::de::{self, DeserializeOwned, DeserializeSeed, Visitor};
+
+use std::{convert::TryFrom, fmt, vec};
+
+use crate::{
+    cbor::{decode_exact, f16_to_f64},
+    Cbor, Error, FromCbor, IntoCbor, Key, Result, SimpleValue, Tag,
+};
+
+/// Convert [Cbor] value into type implementing `serde::Deserialize`, refer
+/// to [crate::to_cbor] on how types are mapped on to cbor.
+///
+/// Integers convert to any of the number types that can hold the value, and
+/// big-nums to `i128` and `u128`. Tags other than big-num are stripped, such
+/// that the tagged value is converted. Text that is not valid utf8 shall
+/// fail with [Error::FailConvert].
+pub fn from_cbor<T>(val: Cbor) -> Result<T>
+where
+    T: DeserializeOwned,
+{
+    T::deserialize(val)
+}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Error {
+        Error::FailConvert(format!("{}:{}", file!(), line!()), msg.to_string())
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Cbor {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Cbor::Major0(_, num) => visitor.visit_u64(num),
+            Cbor::Major1(_, num) => match i64::try_from(num) {
+                Ok(num) => visitor.visit_i64(-1 - num),
+                Err(_) => visitor.visit_i128(-1 - i128::from(num)),
+            },
+            Cbor::Major2(_, byts) => visitor.visit_byte_buf(byts),
+            Cbor::Major3(_, text) => match String::from_utf8(text) {
+                Ok(text) => visitor.visit_string(text),
+                Err(err) => err_at!(FailConvert, msg: "{}", err),
+            },
+            Cbor::Major4(_, items) => {
+                let mut seq = Seq { iter: items.into_iter() };
+                let val = visitor.visit_seq(&mut seq)?;
+                match seq.iter.len() {
+                    0 => Ok(val),
+                    n => err_at!(FailConvert, msg: "{} trailing items in array", n),
+                }
+            }
+            Cbor::Major5(_, entries, _) => {
+                let mut map = Map { iter: entries.into_iter(), val: None };
+                let val = visitor.visit_map(&mut map)?;
+                match map.iter.len() {
+                    0 => Ok(val),
+                    n => err_at!(FailConvert, msg: "{} trailing entries in map", n),
+                }
+            }
+            val @ Cbor::Major6(_, Tag::UBigNum(_)) => {
+                visitor.visit_u128(u128::from_cbor(val)?)
+            }
+            val @ Cbor::Major6(_, Tag::SBigNum(_)) => {
+                visitor.visit_i128(i128::from_cbor(val)?)
+            }
+            val @ Cbor::Major6(_, _) => val.untag().deserialize_any(visitor),
+            Cbor::Major7(_, sval) => match sval {
+                SimpleValue::True => visitor.visit_bool(true),
+                SimpleValue::False => visitor.visit_bool(false),
+                SimpleValue::Null | SimpleValue::Undefined => visitor.visit_unit(),
+                SimpleValue::F16(bits) => visitor.visit_f64(f16_to_f64(bits)),
+                SimpleValue::F32(val) => visitor.visit_f32(val),
+                SimpleValue::F64(val) => visitor.visit_f64(val),
+                sval => {
+                    err_at!(FailConvert, msg: "simple-value {:?} not supported", sval)
+                }
+            },
+            Cbor::Binary(data) => decode_exact(&data)?.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Cbor::Major7(_, SimpleValue::Null)
+            | Cbor::Major7(_, SimpleValue::Undefined) => visitor.visit_none(),
+            Cbor::Binary(data) => decode_exact(&data)?.deserialize_option(visitor),
+            val => visitor.visit_some(val),
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            val @ Cbor::Major3(_, _) => {
+                visitor.visit_enum(Enum { variant: val, val: None })
+            }
+            Cbor::Major5(_, mut entries, _) if entries.len() == 1 => {
+                let (key, val) = entries.remove(0);
+                let variant = key.into_cbor()?;
+                visitor.visit_enum(Enum { variant, val: Some(val) })
+            }
+            Cbor::Binary(data) => {
+                decode_exact(&data)?.deserialize_enum(name, variants, visitor)
+            }
+            _ => err_at!(FailConvert, msg: "expected variant of enum {}", name),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+struct Seq {
+    iter: vec::IntoIter<Cbor>,
+}
+
+impl<'de> de::SeqAccess<'de> for Seq {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        match self.iter.next() {
+            Some(item) => Ok(Some(seed.deserialize(item)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+struct Map {
+    iter: vec::IntoIter<(Key, Cbor)>,
+    val: Option<Cbor>,
+}
+
+impl<'de> de::MapAccess<'de> for Map {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        match self.iter.next() {
+            Some((key, val)) => {
+                self.val = Some(val);
+                Ok(Some(seed.deserialize(key.into_cbor()?)?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        match self.val.take() {
+            Some(val) => seed.deserialize(val),
+            None => err_at!(Fatal, msg: "next_value before next_key"),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+// Enum value, variant name and the variant's value if any.
+struct Enum {
+    variant: Cbor,
+    val: Option<Cbor>,
+}
+
+impl<'de> de::EnumAccess<'de> for Enum {
+    type Error = Error;
+    type Variant = Variant;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Variant)>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let variant = seed.deserialize(self.variant)?;
+        Ok((variant, Variant { val: self.val }))
+    }
+}
+
+struct Variant {
+    val: Option<Cbor>,
+}
+
+impl<'de> de::VariantAccess<'de> for Variant {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        match self.val {
+            Some(val) => de::Deserialize::deserialize(val),
+            None => Ok(()),
+        }
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        match self.val {
+            Some(val) => seed.deserialize(val),
+            None => err_at!(FailConvert, msg: "expected newtype variant"),
+        }
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.val {
+            Some(val) => de::Deserializer::deserialize_seq(val, visitor),
+            None => err_at!(FailConvert, msg: "expected tuple variant"),
+        }
+    }
+
+    fn struct_variant<V>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.val {
+            Some(val) => de::Deserializer::deserialize_map(val, visitor),
+            None => err_at!(FailConvert, msg: "expected struct variant"),
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "de_test.rs"]
+mod de_test;
@@ -0,0 +1,102 @@
+use serde::{Deserialize, Serialize};
+
+use std::collections::{BTreeMap, HashMap};
+
+use super::*;
+use crate::{to_cbor, Info};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+enum Shape {
+    Empty,
+    Circle(u32),
+    Rect(u32, u32),
+    Line { len: u32 },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Record {
+    name: String,
+    age: Option<u8>,
+    score: f64,
+    big: i128,
+    tags: Vec<String>,
+    attrs: BTreeMap<String, i64>,
+    index: HashMap<u32, (bool, char)>,
+    shapes: Vec<Shape>,
+    unit: (),
+}
+
+#[test]
+fn test_from_cbor() {
+    let mut attrs = BTreeMap::new();
+    attrs.insert("a".to_string(), -10);
+    attrs.insert("b".to_string(), i64::MIN);
+    let mut index = HashMap::new();
+    index.insert(1, (true, 'x'));
+    let val = Record {
+        name: "rec".to_string(),
+        age: Some(30),
+        score: 1.5,
+        big: i128::MIN,
+        tags: vec!["x".to_string(), "y".to_string()],
+        attrs,
+        index,
+        shapes: vec![
+            Shape::Empty,
+            Shape::Circle(3),
+            Shape::Rect(1, 2),
+            Shape::Line { len: 4 },
+        ],
+        unit: (),
+    };
+
+    // round trip through bytes.
+    let mut buf = vec![];
+    to_cbor(&val).unwrap().encode(&mut buf).unwrap();
+    let (cbor_val, _) = Cbor::decode(&mut buf.as_slice()).unwrap();
+    assert_eq!(from_cbor::<Record>(cbor_val).unwrap(), val);
+
+    let mut val = val;
+    val.age = None;
+    val.big = i128::from(u64::MAX) + 1;
+    let out: Record = from_cbor(to_cbor(&val).unwrap()).unwrap();
+    assert_eq!(out, val);
+
+    // values not produced by serde.
+    let val: u8 = from_cbor(Cbor::Binary(vec![0x18, 0x64])).unwrap();
+    assert_eq!(val, 100);
+    let val: f64 = from_cbor(Cbor::Major7(Info::U16, SimpleValue::F16(0x3e00))).unwrap();
+    assert_eq!(val, 1.5);
+    let val: i64 =
+        from_cbor(Tag::from_identifier((-3_i64).into_cbor().unwrap()).into()).unwrap();
+    assert_eq!(val, -3);
+    let val: Vec<u64> =
+        from_cbor(Cbor::Major4(Info::Indefinite, vec![1_u64.into_cbor().unwrap()]))
+            .unwrap();
+    assert_eq!(val, vec![1]);
+}
+
+#[test]
+fn test_from_cbor_fail() {
+    match from_cbor::<u8>(300_u64.into_cbor().unwrap()) {
+        Err(Error::FailConvert(_, _)) => (),
+        res => panic!("{:?}", res),
+    }
+    match from_cbor::<String>(Cbor::Major3(1.into(), vec![0xff])) {
+        Err(Error::FailConvert(_, _)) => (),
+        res => panic!("{:?}", res),
+    }
+    match from_cbor::<(u8, u8)>(vec![1_u64, 2, 3].into_cbor().unwrap()) {
+        Err(Error::FailConvert(_, msg)) => assert!(msg.contains("trailing"), "{}", msg),
+        res => panic!("{:?}", res),
+    }
+    match from_cbor::<Shape>("Square".into_cbor().unwrap()) {
+        Err(Error::FailConvert(_, msg)) => assert!(msg.contains("Square"), "{}", msg),
+        res => panic!("{:?}", res),
+    }
+    match from_cbor::<Shape>(1_u64.into_cbor().unwrap()) {
+        Err(Error::FailConvert(_, msg)) => assert!(msg.contains("Shape"), "{}", msg),
+        res => panic!("{:?}", res),
+    }
+    assert!(from_cbor::<Shape>("Circle".into_cbor().unwrap()).is_err());
+}
@@ -0,0 +1,158 @@
+//! Module implement incremental decoding of cbor sequence.
+
+use std::io::{self, Read};
+
+use crate::{
+    cbor::{decode_exact_with, scan_item, DecodeConfig, Scanner},
+    Cbor, Error, Result,
+};
+
+/// Decode a sequence of cbor items, from bytes that arrive incrementally.
+///
+/// Feed bytes as and when they are received, say from a network connection,
+/// and call [Decoder::pull] to get the next complete item. Partially received
+/// item is buffered across feeds.
+///
+/// ```
+/// use cbordata::{Decoder, IntoCbor};
+///
+/// let val = "hello world".into_cbor().unwrap();
+/// let mut buf = vec![];
+/// val.encode(&mut buf).unwrap();
+///
+/// let mut decoder = Decoder::new();
+/// decoder.feed(&buf[..4]);
+/// assert!(decoder.pull().unwrap().is_none());
+/// decoder.feed(&buf[4..]);
+/// assert_eq!(decoder.pull().unwrap(), Some(val));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Decoder {
+    buf: Vec<u8>,
+    // beginning of the next item in `buf`, pulled items are yet to be drained.
+    start: usize,
+    // scan progress of the next item, retained across feeds.
+    scanner: Scanner,
+    config: DecodeConfig,
+}
+
+impl Decoder {
+    /// Create a new decoder, with default [DecodeConfig].
+    pub fn new() -> Decoder {
+        Decoder::default()
+    }
+
+    /// Create a new decoder, with `config` applied to every decoded item.
+    pub fn with_config(config: DecodeConfig) -> Decoder {
+        Decoder { config, ..Decoder::default() }
+    }
+
+    /// Append bytes received from the stream.
+    pub fn feed(&mut self, data: &[u8]) {
+        // drain pulled items, once they outweigh the buffered bytes.
+        if self.start > (self.buf.len() - self.start) {
+            self.buf.drain(..self.start);
+            self.start = 0;
+        }
+        self.buf.extend_from_slice(data);
+    }
+
+    /// Return the next complete item from the stream. Return None if more
+    /// bytes are needed to complete the next item.
+    ///
+    /// A malformed item is discarded before returning the error, to the
+    /// extent its length can be determined. When it can't, the error
+    /// shall repeat on every pull, and framing of the stream is lost.
+    pub fn pull(&mut self) -> Result<Option<Cbor>> {
+        let buf = &self.buf[self.start..];
+        let n = match self.scanner.scan(buf)? {
+            Some(n) => n,
+            None => return Ok(None),
+        };
+        let res = Cbor::decode_with(&mut &buf[..n], &self.config);
+        self.start += n;
+        if self.start == self.buf.len() {
+            self.buf.clear();
+            self.start = 0;
+        }
+        Ok(Some(res?.0))
+    }
+
+    /// Return the number of bytes buffered, that are yet to be pulled.
+    pub fn buffered(&self) -> usize {
+        self.buf.len() - self.start
+    }
+}
+
+/// Decode a sequence of cbor items from `buf`, for as many complete items
+/// as possible. Return the decoded items, along with the error encountered,
+/// if any, after the last decoded item. Say, to recover records from a log
+/// file whose last record is partially written.
+pub fn decode_all_lenient(buf: &[u8]) -> (Vec<Cbor>, Option<Error>) {
+    decode_all_lenient_with(buf, &DecodeConfig::default())
+}
+
+/// Same as [decode_all_lenient], with `config` applied to every item.
+pub fn decode_all_lenient_with(
+    buf: &[u8],
+    config: &DecodeConfig,
+) -> (Vec<Cbor>, Option<Error>) {
+    let mut items = vec![];
+    let mut off = 0;
+    while off < buf.len() {
+        let res = match scan_item(&buf[off..]) {
+            Ok(Some(n)) => {
+                decode_exact_with(&buf[off..(off + n)], config).map(|item| (item, n))
+            }
+            Ok(None) => err_at!(FailCbor, msg: "truncated cbor at offset {}", off),
+            Err(err) => Err(err),
+        };
+        match res {
+            Ok((item, n)) => {
+                items.push(item);
+                off += n;
+            }
+            Err(err) => return (items, Some(err)),
+        }
+    }
+    (items, None)
+}
+
+/// Read a frame from `r`, a 4-byte big-endian length followed by as many
+/// bytes of cbor value, and decode the value. Return None on clean EOF,
+/// before the first byte of the frame. EOF anywhere within the frame shall
+/// return [Error::IOError].
+pub fn read_frame<R: io::Read>(r: &mut R) -> Result<Option<Cbor>> {
+    read_frame_with(r, &DecodeConfig::default())
+}
+
+/// Same as [read_frame], with `config` applied to the value.
+pub fn read_frame_with<R: io::Read>(
+    r: &mut R,
+    config: &DecodeConfig,
+) -> Result<Option<Cbor>> {
+    let mut scratch = [0_u8; 4];
+    let mut m = 0;
+    while m < scratch.len() {
+        match r.read(&mut scratch[m..]) {
+            Ok(0) if m == 0 => return Ok(None),
+            Ok(0) => err_at!(IOError, msg: "truncated frame length, {} bytes", m)?,
+            Ok(k) => m += k,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
+            Err(err) => err_at!(IOError, Err(err))?,
+        }
+    }
+
+    // don't trust the length for pre-allocation.
+    let len = u32::from_be_bytes(scratch);
+    let mut body = vec![];
+    err_at!(IOError, r.take(len.into()).read_to_end(&mut body))?;
+    if body.len() != len as usize {
+        err_at!(IOError, msg: "truncated frame, {}/{} bytes", body.len(), len)?
+    }
+    Ok(Some(decode_exact_with(&body, config)?))
+}
+
+#[cfg(test)]
+#[path = "decoder_test.rs"]
+mod decoder_test;
@@ -0,0 +1,203 @@
+use arbitrary::Unstructured;
+use rand::{prelude::random, rngs::StdRng, Rng, SeedableRng};
+
+use super::*;
+use crate::{Info, IntoCbor, Key};
+
+#[test]
+fn test_decoder() {
+    let seed: u128 = random();
+    println!("test_decoder seed:{}", seed);
+    let mut rng = {
+        let mut rng_seed = [0; 32];
+        rng_seed[0..16].copy_from_slice(&seed.to_le_bytes());
+        StdRng::from_seed(rng_seed)
+    };
+
+    for _i in 0..100 {
+        let mut vals: Vec<Cbor> = vec![];
+        let mut buf: Vec<u8> = vec![];
+        for _j in 0..rng.gen_range(0..20) {
+            let val: Cbor = {
+                let bytes: Vec<u8> =
+                    (0..100).flat_map(|_| rng.gen::<[u8; 32]>().to_vec()).collect();
+                let mut uns = Unstructured::new(&bytes);
+                uns.arbitrary().unwrap()
+            };
+            let n = val.encode(&mut buf).unwrap();
+            assert_eq!(scan_item(&buf[buf.len() - n..]).unwrap(), Some(n));
+            let item = &buf[buf.len() - n..];
+            let mut scanner = Scanner::default();
+            for k in 0..n {
+                assert_eq!(scanner.scan(&item[..k]).unwrap(), None);
+            }
+            assert_eq!(scanner.scan(item).unwrap(), Some(n));
+            vals.push(val);
+        }
+
+        let mut decoder = Decoder::new();
+        let mut items: Vec<Cbor> = vec![];
+        let mut off = 0;
+        while off < buf.len() {
+            let n = rng.gen_range(1..32).min(buf.len() - off);
+            decoder.feed(&buf[off..(off + n)]);
+            off += n;
+            while let Some(item) = decoder.pull().unwrap() {
+                items.push(item);
+            }
+        }
+        assert_eq!(decoder.buffered(), 0);
+        assert_eq!(items, vals);
+    }
+}
+
+#[test]
+fn test_decoder_indefinite() {
+    // [_ 1, 2], {_ "a": 1}, followed by a truncated array.
+    let buf = [0x9f, 0x01, 0x02, 0xff, 0xbf, 0x61, 0x61, 0x01, 0xff, 0x82, 0x01];
+
+    let mut decoder = Decoder::new();
+    for b in buf.iter() {
+        decoder.feed(&[*b]);
+        match decoder.pull().unwrap() {
+            Some(Cbor::Major4(Info::Indefinite, items)) => {
+                assert_eq!(items, vec![1.into_cbor().unwrap(), 2.into_cbor().unwrap()])
+            }
+            Some(Cbor::Major5(Info::Indefinite, items, _)) => {
+                let key = Key::Text("a".to_string());
+                assert_eq!(items, vec![(key, 1.into_cbor().unwrap())])
+            }
+            Some(item) => panic!("unexpected {:?}", item),
+            None => (),
+        }
+    }
+    assert_eq!(decoder.buffered(), 2);
+
+    let (_, n) = Cbor::decode(&mut &buf[..4]).unwrap();
+    assert_eq!(n, 4);
+    let (_, n) = Cbor::decode(&mut &buf[4..9]).unwrap();
+    assert_eq!(n, 5);
+}
+
+#[test]
+fn test_decoder_malformed() {
+    let mut decoder = Decoder::new();
+    decoder.feed(&[0x1c, 0x01]);
+    assert!(decoder.pull().is_err());
+    assert_eq!(decoder.buffered(), 2);
+
+    // tag-24 wrapping malformed cbor is discarded.
+    let mut decoder = Decoder::with_config(DecodeConfig::default());
+    decoder.feed(&[0xd8, 0x18, 0x41, 0x1c, 0x01]);
+    assert!(decoder.pull().is_err());
+    assert_eq!(decoder.pull().unwrap(), Some(1.into_cbor().unwrap()));
+}
+
+#[test]
+fn test_decode_all_lenient() {
+    let vals = vec![
+        10.into_cbor().unwrap(),
+        "hello".into_cbor().unwrap(),
+        vec!["a", "b"].into_cbor().unwrap(),
+    ];
+    let mut buf: Vec<u8> = vec![];
+    for val in vals.iter() {
+        val.encode(&mut buf).unwrap();
+    }
+
+    let (items, err) = decode_all_lenient(&buf);
+    assert_eq!(items, vals);
+    assert!(err.is_none());
+    let (items, err) = decode_all_lenient(&[]);
+    assert!(items.is_empty() && err.is_none());
+
+    // half written record.
+    let mut partial = buf.clone();
+    vec!["hello", "world"].into_cbor().unwrap().encode(&mut partial).unwrap();
+    partial.truncate(partial.len() - 3);
+    match decode_all_lenient(&partial) {
+        (items, Some(Error::FailCbor(_, msg))) => {
+            assert_eq!(items, vals);
+            assert!(msg.contains(&format!("offset {}", buf.len())), "{}", msg);
+        }
+        res => panic!("{:?}", res),
+    }
+
+    // malformed record.
+    let mut bad = buf;
+    bad.extend_from_slice(&[0x1c]);
+    let (items, err) = decode_all_lenient(&bad);
+    assert_eq!(items, vals);
+    assert!(err.is_some());
+}
+
+#[test]
+fn test_read_frame() {
+    let vals = [
+        10.into_cbor().unwrap(),
+        "hello".into_cbor().unwrap(),
+        vec!["a", "b"].into_cbor().unwrap(),
+    ];
+    let mut buf: Vec<u8> = vec![];
+    for val in vals.iter() {
+        let mut body = vec![];
+        val.encode(&mut body).unwrap();
+        buf.extend_from_slice(&(body.len() as u32).to_be_bytes());
+        buf.extend_from_slice(&body);
+    }
+
+    let mut r = buf.as_slice();
+    for val in vals.iter() {
+        assert_eq!(read_frame(&mut r).unwrap().as_ref(), Some(val));
+    }
+    assert!(read_frame(&mut r).unwrap().is_none());
+    assert!(read_frame(&mut &[][..]).unwrap().is_none());
+
+    // truncated within length prefix, and within body.
+    for n in [2, 4, buf.len() - 1].iter() {
+        let mut r = &buf[..*n];
+        loop {
+            match read_frame(&mut r) {
+                Ok(Some(_)) => (),
+                Err(Error::IOError(_, _)) => break,
+                res => panic!("{} {:?}", n, res),
+            }
+        }
+    }
+
+    // length doesn't match the cbor value.
+    let frame = [0, 0, 0, 2, 0x0a, 0x0a];
+    match read_frame(&mut &frame[..]) {
+        Err(Error::FailCbor(_, msg)) => assert!(msg.contains("trailing"), "{}", msg),
+        res => panic!("{:?}", res),
+    }
+}
+
+#[test]
+fn test_decode_with_config() {
+    let cf = DecodeConfig { max_depth: Some(2), ..DecodeConfig::default() };
+    let vals =
+        [vec![1_u64, 2].into_cbor().unwrap(), vec![vec![1_u64]].into_cbor().unwrap()];
+    let mut buf: Vec<u8> = vec![];
+    for val in vals.iter() {
+        val.encode(&mut buf).unwrap();
+    }
+    match decode_all_lenient_with(&buf, &cf) {
+        (items, Some(Error::LimitExceeded(_, _))) => assert_eq!(&items, &vals[..1]),
+        res => panic!("{:?}", res),
+    }
+
+    let mut frames: Vec<u8> = vec![];
+    for val in vals.iter() {
+        let mut body = vec![];
+        val.encode(&mut body).unwrap();
+        frames.extend_from_slice(&(body.len() as u32).to_be_bytes());
+        frames.extend_from_slice(&body);
+    }
+    let mut r = frames.as_slice();
+    assert_eq!(read_frame_with(&mut r, &cf).unwrap().as_ref(), Some(&vals[0]));
+    match read_frame_with(&mut r, &cf) {
+        Err(Error::LimitExceeded(_, _)) => (),
+        res => panic!("{:?}", res),
+    }
+}
@@ -0,0 +1,1010 @@
+use super::*;
+
+#[test]
+fn test_schema_hash() {
+    #[derive(LocalCborize)]
+    struct Record {
+        name: String,
+        age: u32,
+    }
+    impl Record {
+        const ID: u32 = 1;
+    }
+
+    #[derive(LocalCborize)]
+    struct Renamed {
+        name: String,
+        age: u32,
+    }
+    impl Renamed {
+        const ID: u32 = 2;
+    }
+
+    #[derive(LocalCborize)]
+    struct Reordered {
+        age: u32,
+        name: String,
+    }
+    impl Reordered {
+        const ID: u32 = 1;
+    }
+
+    #[derive(LocalCborize)]
+    struct Retyped {
+        name: String,
+        age: u64,
+    }
+    impl Retyped {
+        const ID: u32 = 1;
+    }
+
+    #[derive(LocalCborize)]
+    struct Defaulted {
+        name: String,
+        #[cbor(default)]
+        age: u32,
+    }
+    impl Defaulted {
+        const ID: u32 = 1;
+    }
+
+    #[allow(dead_code)]
+    #[derive(LocalCborize)]
+    struct Skipped {
+        name: String,
+        #[cbor(skip)]
+        age: u32,
+    }
+    impl Skipped {
+        const ID: u32 = 1;
+    }
+
+    #[derive(LocalCborize)]
+    #[cbor(repr = "named_map")]
+    struct Named {
+        name: String,
+        age: u32,
+    }
+
+    #[derive(LocalCborize)]
+    #[cbor(repr = "named_map")]
+    struct Short {
+        #[cbor(rename = "n")]
+        name: String,
+        age: u32,
+    }
+
+    #[derive(LocalCborize)]
+    struct Alias {
+        name: String,
+        age: u32,
+    }
+    impl Alias {
+        const ID: u32 = 1;
+    }
+
+    assert_eq!(Record::schema_hash().unwrap(), Alias::schema_hash().unwrap());
+    // same layout, but different ID.
+    assert_ne!(Record::schema_hash().unwrap(), Renamed::schema_hash().unwrap());
+    assert_ne!(Record::schema_hash().unwrap(), Reordered::schema_hash().unwrap());
+    assert_ne!(Record::schema_hash().unwrap(), Retyped::schema_hash().unwrap());
+    assert_ne!(Record::schema_hash().unwrap(), Defaulted::schema_hash().unwrap());
+    assert_ne!(Record::schema_hash().unwrap(), Skipped::schema_hash().unwrap());
+    assert_ne!(Defaulted::schema_hash().unwrap(), Skipped::schema_hash().unwrap());
+    assert_ne!(Named::schema_hash().unwrap(), Short::schema_hash().unwrap());
+    // fingerprint must remain stable across releases.
+    assert_eq!(Record::schema_hash().unwrap(), 0xa8190f63b229bb1c);
+}
+
+#[test]
+fn test_flatten_rest() {
+    #[derive(Debug, Clone, PartialEq, LocalCborize)]
+    struct Older {
+        name: String,
+        #[cbor(flatten_rest)]
+        rest: Vec<Cbor>,
+        age: u32,
+    }
+    impl Older {
+        const ID: &'static str = "record";
+    }
+
+    #[derive(Debug, Clone, PartialEq, LocalCborize)]
+    struct Newer {
+        name: String,
+        age: u32,
+        email: String,
+        retired: bool,
+    }
+    impl Newer {
+        const ID: &'static str = "record";
+    }
+
+    let newer = Newer {
+        name: "alice".to_string(),
+        age: 30,
+        email: "alice@example.com".to_string(),
+        retired: false,
+    };
+    let val = newer.clone().into_cbor().unwrap();
+
+    let older = Older::from_cbor(val.clone()).unwrap();
+    assert_eq!(older.name, "alice");
+    assert_eq!(older.age, 30);
+    assert_eq!(
+        older.rest,
+        vec!["alice@example.com".into_cbor().unwrap(), false.into_cbor().unwrap()]
+    );
+
+    let fwd = older.into_cbor().unwrap();
+    assert_eq!(fwd, val);
+    assert_eq!(Newer::from_cbor(fwd).unwrap(), newer);
+
+    let older = Older { name: "bob".to_string(), rest: vec![], age: 10 };
+    let val = older.clone().into_cbor().unwrap();
+    assert_eq!(Older::from_cbor(val).unwrap(), older);
+}
+
+#[test]
+fn test_enum_discriminant() {
+    #[derive(Debug, Clone, PartialEq, LocalCborize)]
+    enum Shape {
+        Empty,
+        #[cbor(n = 10)]
+        Circle {
+            radius: u32,
+        },
+        #[cbor(n = 20)]
+        Rect(u32, u32),
+        #[cbor(n = 30)]
+        Point,
+        Line(u32),
+    }
+    impl Shape {
+        const ID: u32 = 100;
+    }
+
+    let shapes = vec![
+        Shape::Empty,
+        Shape::Circle { radius: 5 },
+        Shape::Rect(3, 4),
+        Shape::Point,
+        Shape::Line(7),
+    ];
+    let discriminants = vec![
+        "Empty".into_cbor().unwrap(),
+        10_u64.into_cbor().unwrap(),
+        20_u64.into_cbor().unwrap(),
+        30_u64.into_cbor().unwrap(),
+        "Line".into_cbor().unwrap(),
+    ];
+    for (shape, disc) in shapes.into_iter().zip(discriminants) {
+        let val = shape.clone().into_cbor().unwrap();
+        match &val {
+            Cbor::Major4(_, items) => assert_eq!(items[1], disc),
+            val => panic!("{:?}", val),
+        }
+        assert_eq!(Shape::from_cbor(val).unwrap(), shape);
+    }
+
+    let id: Cbor = Tag::from_identifier(100_u32.into_cbor().unwrap()).into();
+    let testcases = vec![
+        vec![id.clone(), 40_u64.into_cbor().unwrap()],
+        vec![id.clone(), "Point".into_cbor().unwrap()],
+        vec![id, "Square".into_cbor().unwrap()],
+    ];
+    for items in testcases.into_iter() {
+        match Shape::from_cbor(items.into_cbor().unwrap()) {
+            Err(Error::FailCbor(_, _)) => (),
+            res => panic!("{:?}", res),
+        }
+    }
+}
+
+#[test]
+fn test_enum_tag() {
+    #[derive(Debug, Clone, PartialEq, LocalCborize)]
+    enum MsgV1 {
+        #[cbor(tag = 1)]
+        Ping,
+        #[cbor(tag = 2)]
+        Data(String, u64),
+        #[cbor(tag = 3)]
+        Ack { seqno: u64, ok: bool },
+    }
+    impl MsgV1 {
+        const ID: u32 = 300;
+    }
+
+    // same type, with variants re-ordered.
+    #[derive(Debug, Clone, PartialEq, LocalCborize)]
+    enum MsgV2 {
+        #[cbor(tag = 3)]
+        Ack { seqno: u64, ok: bool },
+        #[cbor(tag = 1)]
+        Ping,
+        #[cbor(tag = 2)]
+        Data(String, u64),
+    }
+    impl MsgV2 {
+        const ID: u32 = 300;
+    }
+
+    let refs = vec![
+        (MsgV1::Ping, MsgV2::Ping, 1_u64),
+        (MsgV1::Data("abc".to_string(), 7), MsgV2::Data("abc".to_string(), 7), 2),
+        (MsgV1::Ack { seqno: 9, ok: true }, MsgV2::Ack { seqno: 9, ok: true }, 3),
+    ];
+    for (v1, v2, tag) in refs.into_iter() {
+        let val = v1.clone().into_cbor().unwrap();
+        match &val {
+            Cbor::Major4(_, items) => assert_eq!(items[1], tag.into_cbor().unwrap()),
+            val => panic!("{:?}", val),
+        }
+        assert_eq!(val, v2.clone().into_cbor().unwrap());
+        assert_eq!(MsgV2::from_cbor(val).unwrap(), v2);
+        assert_eq!(MsgV1::from_cbor(v2.into_cbor().unwrap()).unwrap(), v1);
+    }
+}
+
+#[test]
+fn test_enum_unknown() {
+    #[derive(Debug, Clone, PartialEq, LocalCborize)]
+    enum EventV2 {
+        Start,
+        #[cbor(n = 10)]
+        Stop(u32),
+        Pause {
+            secs: u32,
+        },
+        #[cbor(n = 20)]
+        Resume(String, u64),
+    }
+    impl EventV2 {
+        const ID: u32 = 200;
+    }
+
+    #[derive(Debug, Clone, PartialEq, LocalCborize)]
+    #[cbor(unknown = Unknown)]
+    enum EventV1 {
+        Start,
+        #[cbor(n = 10)]
+        Stop(u32),
+        Unknown(Cbor),
+    }
+    impl EventV1 {
+        const ID: u32 = 200;
+    }
+
+    let events = vec![EventV2::Pause { secs: 5 }, EventV2::Resume("job".to_string(), 42)];
+    for event in events.into_iter() {
+        let mut refb = vec![];
+        event.clone().into_cbor().unwrap().encode(&mut refb).unwrap();
+
+        let val = EventV1::from_cbor(event.clone().into_cbor().unwrap()).unwrap();
+        assert!(matches!(val, EventV1::Unknown(_)), "{:?}", val);
+
+        let mut buf = vec![];
+        val.into_cbor().unwrap().encode(&mut buf).unwrap();
+        assert_eq!(buf, refb);
+        assert_eq!(
+            EventV2::from_cbor(Cbor::decode(&mut &buf[..]).unwrap().0).unwrap(),
+            event
+        );
+    }
+
+    // known variants are not captured.
+    let val = EventV2::Stop(3).into_cbor().unwrap();
+    assert_eq!(EventV1::from_cbor(val).unwrap(), EventV1::Stop(3));
+    let val = EventV2::Start.into_cbor().unwrap();
+    assert_eq!(EventV1::from_cbor(val).unwrap(), EventV1::Start);
+
+    // the catch-all variant is not identified by its name.
+    let id: Cbor = Tag::from_identifier(200_u32.into_cbor().unwrap()).into();
+    let items = vec![id, "Unknown".into_cbor().unwrap()];
+    let val = EventV1::from_cbor(items.clone().into_cbor().unwrap()).unwrap();
+    assert_eq!(val, EventV1::Unknown(items[1..].to_vec().into_cbor().unwrap()));
+}
+
+#[test]
+fn test_field_overflow() {
+    #[derive(LocalCborize)]
+    struct Wide {
+        name: String,
+        level: u16,
+    }
+    impl Wide {
+        const ID: u32 = 1;
+    }
+
+    #[derive(Debug, LocalCborize)]
+    struct Narrow {
+        name: String,
+        level: u8,
+    }
+    impl Narrow {
+        const ID: u32 = 1;
+    }
+
+    #[derive(Debug, LocalCborize)]
+    enum Level {
+        Wide(u16),
+        Narrow(u8),
+    }
+    impl Level {
+        const ID: u32 = 2;
+    }
+
+    let val = Wide { name: "x".to_string(), level: 300 }.into_cbor().unwrap();
+    match Narrow::from_cbor(val) {
+        Err(Error::FailConvert(_, msg)) => {
+            assert_eq!(msg, "value 300 exceeds u8 at path .level")
+        }
+        res => panic!("{:?}", res),
+    }
+
+    let mut val = Level::Wide(300).into_cbor().unwrap();
+    if let Cbor::Major4(_, items) = &mut val {
+        items[1] = "Narrow".into_cbor().unwrap();
+    }
+    match Level::from_cbor(val) {
+        Err(Error::FailConvert(_, msg)) => {
+            assert_eq!(msg, "value 300 exceeds u8 at path .0")
+        }
+        res => panic!("{:?}", res),
+    }
+}
+
+#[test]
+fn test_tuple_struct() {
+    #[derive(Debug, Clone, PartialEq, LocalCborize)]
+    struct Point(f64, f64);
+    impl Point {
+        const ID: &'static str = "point";
+    }
+
+    #[derive(Debug, Clone, PartialEq, LocalCborize)]
+    struct Blob(String, Vec<u8>, Option<u32>);
+    impl Blob {
+        const ID: u32 = 10;
+    }
+
+    let point = Point(1.5, -2.5);
+    let val = point.clone().into_cbor().unwrap();
+    assert_eq!(
+        get_cborize_id(&val),
+        Some(Tag::from_identifier("point".into_cbor().unwrap()).into())
+    );
+    match &val {
+        Cbor::Major4(_, items) => {
+            assert_eq!(items.len(), 3);
+            assert_eq!(items[1], 1.5_f64.into_cbor().unwrap());
+            assert_eq!(items[2], (-2.5_f64).into_cbor().unwrap());
+        }
+        val => panic!("{:?}", val),
+    }
+    assert_eq!(Point::from_cbor(val).unwrap(), point);
+
+    let blob = Blob("x".to_string(), vec![1, 2, 3], None);
+    let val = blob.clone().into_cbor().unwrap();
+    match &val {
+        Cbor::Major4(_, items) => {
+            assert_eq!(items[2], Cbor::from_bytes(vec![1, 2, 3]).unwrap())
+        }
+        val => panic!("{:?}", val),
+    }
+    assert_eq!(Blob::from_cbor(val).unwrap(), blob);
+
+    let val = point.into_cbor().unwrap();
+    match Blob::from_cbor(val) {
+        Err(Error::FailConvert(_, msg)) => assert!(msg.contains("bad id"), "{}", msg),
+        res => panic!("{:?}", res),
+    }
+}
+
+#[test]
+fn test_transparent() {
+    #[derive(Debug, Clone, PartialEq, LocalCborize)]
+    #[cbor(transparent)]
+    struct Meters(f64);
+
+    #[derive(Debug, Clone, PartialEq, LocalCborize)]
+    #[cbor(transparent)]
+    struct Digest {
+        data: Vec<u8>,
+    }
+
+    #[derive(Debug, Clone, PartialEq, LocalCborize)]
+    struct Route {
+        name: String,
+        length: Meters,
+    }
+    impl Route {
+        const ID: u32 = 1;
+    }
+
+    let val = Meters(10.5).into_cbor().unwrap();
+    assert_eq!(val, 10.5_f64.into_cbor().unwrap());
+    assert_eq!(Meters::from_cbor(val).unwrap(), Meters(10.5));
+
+    let val = Digest { data: vec![1, 2] }.into_cbor().unwrap();
+    assert_eq!(val, Cbor::from_bytes(vec![1, 2]).unwrap());
+    assert_eq!(Digest::from_cbor(val).unwrap(), Digest { data: vec![1, 2] });
+
+    let route = Route { name: "a".to_string(), length: Meters(1.0) };
+    let val = route.clone().into_cbor().unwrap();
+    match &val {
+        Cbor::Major4(_, items) => assert_eq!(items[2], 1.0_f64.into_cbor().unwrap()),
+        val => panic!("{:?}", val),
+    }
+    assert_eq!(Route::from_cbor(val).unwrap(), route);
+
+    match Meters::from_cbor("x".into_cbor().unwrap()) {
+        Err(Error::FailConvert(_, msg)) => {
+            assert!(msg.ends_with(" at path .0"), "{}", msg)
+        }
+        res => panic!("{:?}", res),
+    }
+    assert_ne!(Meters::schema_hash().unwrap(), 0);
+}
+
+#[test]
+fn test_named_map() {
+    let text = |key: &str| Key::Text(key.to_string());
+
+    #[derive(Debug, Clone, PartialEq, LocalCborize)]
+    #[cbor(repr = "named_map")]
+    struct Config {
+        name: String,
+        port: u16,
+        digest: Vec<u8>,
+    }
+
+    #[derive(Debug, Clone, PartialEq, LocalCborize)]
+    #[cbor(repr = "named_map")]
+    struct Partial {
+        port: u16,
+        #[cbor(flatten_rest)]
+        rest: Vec<(Key, Cbor)>,
+    }
+
+    let config = Config {
+        name: "db".to_string(),
+        port: 8080,
+        digest: vec![1, 2],
+    };
+    let val = config.clone().into_cbor().unwrap();
+    let keys: Vec<Key> = match &val {
+        Cbor::Major5(_, items, _) => items.iter().map(|(key, _)| key.clone()).collect(),
+        val => panic!("{:?}", val),
+    };
+    let refkeys = ["name", "port", "digest"];
+    assert_eq!(keys, refkeys.iter().map(|k| text(k)).collect::<Vec<Key>>());
+    assert_eq!(Config::from_cbor(val.clone()).unwrap(), config);
+
+    // order of keys does not matter, unknown keys are ignored.
+    let val = vec![
+        (text("extra"), true.into_cbor().unwrap()),
+        (text("digest"), Cbor::from_bytes(vec![1, 2]).unwrap()),
+        (text("port"), 8080.into_cbor().unwrap()),
+        (text("name"), "db".into_cbor().unwrap()),
+    ]
+    .into_cbor()
+    .unwrap();
+    assert_eq!(Config::from_cbor(val.clone()).unwrap(), config);
+
+    let partial = Partial::from_cbor(val.clone()).unwrap();
+    assert_eq!(partial.port, 8080);
+    assert_eq!(partial.rest.len(), 3);
+    assert_eq!(Config::from_cbor(partial.into_cbor().unwrap()).unwrap(), config);
+
+    let val = vec![(text("port"), 1.into_cbor().unwrap())].into_cbor().unwrap();
+    match Config::from_cbor(val) {
+        Err(Error::FailConvert(_, msg)) => assert!(msg.contains("name"), "{}", msg),
+        res => panic!("{:?}", res),
+    }
+    assert_ne!(Config::schema_hash().unwrap(), Partial::schema_hash().unwrap());
+}
+
+#[test]
+fn test_empty_struct() {
+    #[derive(Debug, Clone, PartialEq, LocalCborize)]
+    struct Unit;
+    impl Unit {
+        const ID: u32 = 1;
+    }
+
+    #[derive(Debug, Clone, PartialEq, LocalCborize)]
+    struct Named {}
+    impl Named {
+        const ID: u32 = 2;
+    }
+
+    #[derive(Debug, Clone, PartialEq, LocalCborize)]
+    struct Tuple();
+    impl Tuple {
+        const ID: u32 = 3;
+    }
+
+    let id = |n: u32| {
+        let id: Cbor = Tag::from_identifier(n.into_cbor().unwrap()).into();
+        vec![id].into_cbor().unwrap()
+    };
+
+    let val = Unit.into_cbor().unwrap();
+    assert_eq!(val, id(1));
+    assert_eq!(Unit::from_cbor(val).unwrap(), Unit);
+
+    let val = Named {}.into_cbor().unwrap();
+    assert_eq!(val, id(2));
+    assert_eq!(Named::from_cbor(val).unwrap(), Named {});
+
+    let val = Tuple().into_cbor().unwrap();
+    assert_eq!(val, id(3));
+    assert_eq!(Tuple::from_cbor(val).unwrap(), Tuple());
+
+    // only the ID tells them apart.
+    assert!(Unit::from_cbor(id(2)).is_err());
+    assert!(Named::from_cbor(id(3)).is_err());
+    let mut val = id(3);
+    if let Cbor::Major4(_, items) = &mut val {
+        items.push(4.into_cbor().unwrap())
+    }
+    assert!(Tuple::from_cbor(val).is_err());
+}
+
+#[test]
+fn test_from_cbor_ref() {
+    #[derive(Debug, Clone, PartialEq, LocalCborize)]
+    struct Owned {
+        id: u64,
+        name: String,
+        tags: Vec<String>,
+        digest: Vec<u8>,
+        note: Option<String>,
+    }
+    impl Owned {
+        const ID: &'static str = "msg";
+    }
+
+    #[derive(Debug, Clone, PartialEq, LocalFromCborRef)]
+    struct Msg<'a> {
+        id: u64,
+        name: &'a str,
+        tags: Vec<&'a str>,
+        digest: Vec<u8>,
+        note: Option<String>,
+    }
+    impl<'a> Msg<'a> {
+        const ID: &'static str = "msg";
+    }
+
+    #[derive(Debug, Clone, PartialEq, LocalFromCborRef)]
+    struct Other(u64, String);
+    impl Other {
+        const ID: &'static str = "other";
+    }
+
+    #[derive(Debug, Clone, PartialEq, LocalFromCborRef)]
+    #[cbor(transparent)]
+    struct Name<'a>(&'a str);
+
+    let owned = Owned {
+        id: 10,
+        name: "hello".to_string(),
+        tags: vec!["a".to_string(), "b".to_string()],
+        digest: vec![1, 2, 3],
+        note: None,
+    };
+    let mut buf: Vec<u8> = vec![];
+    owned.into_cbor().unwrap().encode(&mut buf).unwrap();
+
+    let (val, _) = CborRef::decode(&buf).unwrap();
+    let msg = Msg::from_cbor_ref(&val).unwrap();
+    assert_eq!(msg.id, 10);
+    assert_eq!(msg.name, "hello");
+    assert!(buf.as_ptr_range().contains(&msg.name.as_ptr()));
+    assert_eq!(msg.tags, vec!["a", "b"]);
+    assert_eq!(msg.digest, vec![1, 2, 3]);
+    assert_eq!(msg.note, None);
+
+    match Other::from_cbor_ref(&val) {
+        Err(Error::FailConvert(_, msg)) => assert!(msg.contains("bad id"), "{}", msg),
+        res => panic!("{:?}", res),
+    }
+
+    let mut buf: Vec<u8> = vec![];
+    "xyz".into_cbor().unwrap().encode(&mut buf).unwrap();
+    let (val, _) = CborRef::decode(&buf).unwrap();
+    assert_eq!(Name::from_cbor_ref(&val).unwrap(), Name("xyz"));
+    match Msg::from_cbor_ref(&val) {
+        Err(Error::FailConvert(_, msg)) => assert!(msg.contains("not a list"), "{}", msg),
+        res => panic!("{:?}", res),
+    }
+}
+
+#[test]
+fn test_error_path() {
+    #[derive(Debug, Clone, LocalCborize)]
+    struct Wide {
+        name: String,
+        age: u16,
+    }
+    impl Wide {
+        const ID: u32 = 1;
+    }
+
+    #[derive(Debug, Clone, LocalCborize)]
+    struct Narrow {
+        name: String,
+        age: u8,
+    }
+    impl Narrow {
+        const ID: u32 = 1;
+    }
+
+    #[derive(Debug, Clone, LocalCborize)]
+    struct Users<T> {
+        users: Vec<T>,
+    }
+    impl<T> Users<T> {
+        const ID: u32 = 2;
+    }
+
+    let users: Vec<Wide> = (0..5)
+        .map(|i| Wide {
+            name: i.to_string(),
+            age: if i == 3 { 300 } else { 30 },
+        })
+        .collect();
+    let val = Users { users }.into_cbor().unwrap();
+    match Users::<Narrow>::from_cbor(val) {
+        Err(Error::FailConvert(_, msg)) => {
+            assert_eq!(msg, "value 300 exceeds u8 at path .users[3].age")
+        }
+        res => panic!("{:?}", res),
+    }
+}
+
+#[test]
+fn test_id_match_prefix() {
+    #[derive(Debug, Clone, PartialEq, LocalCborize)]
+    struct RecordV1 {
+        name: String,
+        age: u32,
+    }
+    impl RecordV1 {
+        const ID: [u64; 2] = [7, 1];
+    }
+
+    #[derive(Debug, Clone, PartialEq, LocalCborize)]
+    #[cbor(id_match = "prefix")]
+    struct RecordV2 {
+        name: String,
+        age: u32,
+        #[cbor(flatten_rest)]
+        rest: Vec<Cbor>,
+    }
+    impl RecordV2 {
+        const ID: [u64; 2] = [7, 2];
+    }
+
+    #[derive(Debug, Clone, PartialEq, LocalCborize)]
+    struct Other {
+        name: String,
+        age: u32,
+    }
+    impl Other {
+        const ID: [u64; 2] = [8, 1];
+    }
+
+    let v1 = RecordV1 { name: "alice".to_string(), age: 30 };
+    let mut buf = vec![];
+    v1.clone().into_cbor().unwrap().encode(&mut buf).unwrap();
+
+    let (val, _) = Cbor::decode(&mut buf.as_slice()).unwrap();
+    let (v2, id) = RecordV2::from_cbor_with_id(val).unwrap();
+    assert_eq!(id, [7_u64, 1].into_cbor().unwrap());
+    assert_eq!(v2, RecordV2 { name: "alice".to_string(), age: 30, rest: vec![] });
+
+    let v2 = RecordV2 {
+        name: "bob".to_string(),
+        age: 10,
+        rest: vec!["bob@example.com".into_cbor().unwrap()],
+    };
+    let val = v2.clone().into_cbor().unwrap();
+    assert_eq!(RecordV2::from_cbor(val.clone()).unwrap(), v2);
+    let (_, id) = RecordV2::from_cbor_with_id(val.clone()).unwrap();
+    assert_eq!(id, [7_u64, 2].into_cbor().unwrap());
+    // exact match, by default.
+    assert!(RecordV1::from_cbor(val).is_err());
+
+    let other = Other { name: "carol".to_string(), age: 20 }.into_cbor().unwrap();
+    match RecordV2::from_cbor(other) {
+        Err(Error::FailConvert(_, msg)) => assert!(msg.contains("bad id"), "{}", msg),
+        res => panic!("{:?}", res),
+    }
+}
+
+#[test]
+fn test_cborize_lifetime() {
+    #[derive(Debug, Clone, PartialEq, LocalCborize)]
+    struct Msg<'a> {
+        id: u64,
+        name: &'a str,
+        digest: &'a [u8],
+        tags: Vec<&'a str>,
+        note: Option<String>,
+    }
+    impl<'a> Msg<'a> {
+        const ID: &'static str = "msg";
+    }
+
+    #[derive(Debug, Clone, PartialEq, LocalCborize)]
+    #[cbor(transparent)]
+    struct Name<'a>(&'a str);
+
+    let digest = vec![1_u8, 2, 3];
+    let msg = Msg {
+        id: 10,
+        name: "hello",
+        digest: &digest,
+        tags: vec!["a", "b"],
+        note: Some("note".to_string()),
+    };
+    let mut buf: Vec<u8> = vec![];
+    msg.clone().into_cbor().unwrap().encode(&mut buf).unwrap();
+
+    let (val, _) = CborRef::decode(&buf).unwrap();
+    let out = Msg::from_cbor_ref(&val).unwrap();
+    assert_eq!(out, msg);
+    assert!(buf.as_ptr_range().contains(&out.name.as_ptr()));
+    assert!(buf.as_ptr_range().contains(&out.digest.as_ptr()));
+
+    let mut buf: Vec<u8> = vec![];
+    Name("xyz").into_cbor().unwrap().encode(&mut buf).unwrap();
+    assert_eq!(buf, [0x63, b'x', b'y', b'z']);
+    let (val, _) = CborRef::decode(&buf).unwrap();
+    assert_eq!(Name::from_cbor_ref(&val).unwrap(), Name("xyz"));
+    assert_ne!(Msg::schema_hash().unwrap(), 0);
+}
+
+#[test]
+fn test_cborize_generics() {
+    use std::borrow::Cow;
+
+    #[derive(Debug, Clone, PartialEq, LocalCborize)]
+    struct Entry<K: Ord, V = u64>
+    where
+        V: Clone,
+    {
+        key: K,
+        value: V,
+    }
+    impl<K: Ord, V: Clone> Entry<K, V> {
+        const ID: u32 = 10;
+    }
+
+    #[derive(Debug, Clone, PartialEq, LocalCborize)]
+    struct Block<const N: usize> {
+        seqno: u64,
+        data: [u8; N],
+    }
+    impl<const N: usize> Block<N> {
+        const ID: u32 = 11;
+    }
+
+    #[derive(Debug, Clone, PartialEq, LocalCborize)]
+    enum Either<L, R> {
+        Left(L),
+        Right { val: R },
+    }
+    impl<L, R> Either<L, R> {
+        const ID: u32 = 12;
+    }
+
+    #[derive(Debug, Clone, PartialEq, LocalCborize)]
+    enum Label<'a> {
+        Name(Cow<'a, str>),
+    }
+    impl<'a> Label<'a> {
+        const ID: u32 = 13;
+    }
+
+    #[derive(Debug, Clone, PartialEq, LocalCborize)]
+    #[cbor(repr = "named_map")]
+    struct Pair<T> {
+        a: T,
+        b: T,
+    }
+
+    #[derive(Debug, Clone, PartialEq, LocalCborize)]
+    #[cbor(transparent)]
+    struct Wrap<T>(T);
+
+    let val: Entry<String> = Entry { key: "k".to_string(), value: 10 };
+    assert_eq!(Entry::from_cbor(val.clone().into_cbor().unwrap()).unwrap(), val);
+
+    let val = Block { seqno: 1, data: [1_u8, 2, 3] };
+    assert_eq!(Block::<3>::from_cbor(val.clone().into_cbor().unwrap()).unwrap(), val);
+    assert!(Block::<4>::from_cbor(val.into_cbor().unwrap()).is_err());
+
+    let vals: Vec<Either<u8, String>> =
+        vec![Either::Left(1), Either::Right { val: "r".to_string() }];
+    for val in vals.into_iter() {
+        assert_eq!(Either::from_cbor(val.clone().into_cbor().unwrap()).unwrap(), val);
+    }
+
+    let val = Label::Name(Cow::Borrowed("x"));
+    assert_eq!(Label::from_cbor(val.clone().into_cbor().unwrap()).unwrap(), val);
+
+    let val = Pair { a: 1_u32, b: 2 };
+    assert_eq!(Pair::from_cbor(val.clone().into_cbor().unwrap()).unwrap(), val);
+
+    let val = Wrap(vec![1_u64, 2]);
+    assert_eq!(val.clone().into_cbor().unwrap(), vec![1_u64, 2].into_cbor().unwrap());
+    assert_eq!(Wrap::from_cbor(val.clone().into_cbor().unwrap()).unwrap(), val);
+
+    // schema hash is same across instantiations of the type.
+    assert_eq!(Block::<3>::schema_hash().unwrap(), Block::<4>::schema_hash().unwrap());
+}
+
+#[test]
+fn test_field_attrs() {
+    #[derive(Debug, Clone, PartialEq, LocalCborize)]
+    struct ConfigV1 {
+        name: String,
+        #[cbor(skip)]
+        cache: Vec<u8>,
+    }
+    impl ConfigV1 {
+        const ID: u32 = 21;
+    }
+
+    #[derive(Debug, Clone, PartialEq, LocalCborize)]
+    struct ConfigV2 {
+        name: String,
+        #[cbor(skip)]
+        cache: Vec<u8>,
+        #[cbor(default)]
+        port: u16,
+    }
+    impl ConfigV2 {
+        const ID: u32 = 21;
+    }
+
+    let v1 = ConfigV1 { name: "db".to_string(), cache: vec![1, 2] };
+    let val = v1.clone().into_cbor().unwrap();
+    match &val {
+        Cbor::Major4(_, items) => assert_eq!(items.len(), 2),
+        _ => unreachable!(),
+    }
+    let v1 = ConfigV1 { cache: vec![], ..v1 };
+    assert_eq!(ConfigV1::from_cbor(val.clone()).unwrap(), v1);
+
+    let v2 = ConfigV2::from_cbor(val).unwrap();
+    assert_eq!(v2, ConfigV2 { name: "db".to_string(), cache: vec![], port: 0 });
+
+    let v2 = ConfigV2 { port: 8080, ..v2 };
+    let val = v2.clone().into_cbor().unwrap();
+    assert_eq!(ConfigV2::from_cbor(val.clone()).unwrap(), v2);
+    assert!(ConfigV1::from_cbor(val).is_err());
+
+    #[derive(Debug, Clone, PartialEq, LocalCborize)]
+    #[cbor(repr = "named_map")]
+    struct Server {
+        #[cbor(rename = "h")]
+        host: String,
+        #[cbor(default)]
+        port: u16,
+        #[cbor(skip)]
+        conns: usize,
+    }
+
+    let server = Server { host: "localhost".to_string(), port: 80, conns: 10 };
+    let val = server.into_cbor().unwrap();
+    let keys: Vec<Key> = match &val {
+        Cbor::Major5(_, entries, _) => entries.iter().map(|(k, _)| k.clone()).collect(),
+        _ => unreachable!(),
+    };
+    assert_eq!(keys, vec![Key::Text("h".to_string()), Key::Text("port".to_string())]);
+    let server = Server::from_cbor(val).unwrap();
+    assert_eq!(server, Server { host: "localhost".to_string(), port: 80, conns: 0 });
+
+    let val = vec![(Key::Text("h".to_string()), "remote".into_cbor().unwrap())];
+    let server = Server::from_cbor(val.into_cbor().unwrap()).unwrap();
+    assert_eq!(server, Server { host: "remote".to_string(), port: 0, conns: 0 });
+
+    let val = vec![(Key::Text("host".to_string()), "remote".into_cbor().unwrap())];
+    assert!(Server::from_cbor(val.into_cbor().unwrap()).is_err());
+}
+
+#[test]
+fn test_enum_field_attrs() {
+    #[derive(Debug, Clone, PartialEq, LocalCborize)]
+    enum EventV1 {
+        Open { path: String },
+        Read(u64),
+    }
+    impl EventV1 {
+        const ID: u32 = 22;
+    }
+
+    #[derive(Debug, Clone, PartialEq, LocalCborize)]
+    enum EventV2 {
+        Open {
+            path: String,
+            #[cbor(skip)]
+            handle: Option<u64>,
+            #[cbor(default)]
+            mode: u32,
+        },
+        Read(u64, #[cbor(skip)] Vec<u8>, #[cbor(default)] u32),
+    }
+    impl EventV2 {
+        const ID: u32 = 22;
+    }
+
+    let open = EventV2::Open {
+        path: "/x".to_string(),
+        handle: Some(3),
+        mode: 0o644,
+    };
+    let val = open.into_cbor().unwrap();
+    match &val {
+        Cbor::Major4(_, items) => assert_eq!(items.len(), 4),
+        _ => unreachable!(),
+    }
+    let open = EventV2::Open { path: "/x".to_string(), handle: None, mode: 0o644 };
+    assert_eq!(EventV2::from_cbor(val.clone()).unwrap(), open);
+    assert!(EventV1::from_cbor(val).is_err());
+
+    let read = EventV2::Read(10, vec![1, 2], 7);
+    let val = read.into_cbor().unwrap();
+    assert_eq!(EventV2::from_cbor(val).unwrap(), EventV2::Read(10, vec![], 7));
+
+    // values written by the older version.
+    let val = EventV1::Open { path: "/y".to_string() }.into_cbor().unwrap();
+    let open = EventV2::Open { path: "/y".to_string(), handle: None, mode: 0 };
+    assert_eq!(EventV2::from_cbor(val).unwrap(), open);
+    let val = EventV1::Read(20).into_cbor().unwrap();
+    assert_eq!(EventV2::from_cbor(val).unwrap(), EventV2::Read(20, vec![], 0));
+}
+
+#[test]
+fn test_from_cbor_with() {
+    use std::collections::BTreeMap;
+
+    #[derive(Debug, Clone, PartialEq, LocalCborize)]
+    struct Flags {
+        enabled: bool,
+        ports: BTreeMap<u16, bool>,
+    }
+    impl Flags {
+        const ID: u32 = 1;
+    }
+
+    #[derive(Debug, Clone, PartialEq, LocalCborize)]
+    #[cbor(repr = "named_map")]
+    struct Named {
+        flags: Option<Flags>,
+    }
+
+    let config = DecodeConfig {
+        coerce_bool: true,
+        coerce_map_keys: true,
+        ..DecodeConfig::default()
+    };
+    let ports = vec![(Key::from("80"), 1.into_cbor().unwrap())].into_cbor().unwrap();
+    let val = vec![
+        Tag::from_identifier(Flags::ID.into_cbor().unwrap()).into(),
+        0.into_cbor().unwrap(),
+        ports,
+    ];
+    let val = vec![(Key::from("flags"), val.into_cbor().unwrap())].into_cbor().unwrap();
+    assert!(Named::from_cbor(val.clone()).is_err());
+
+    let named = Named::from_cbor_with(val, &config).unwrap();
+    let ports = vec![(80, true)].into_iter().collect();
+    assert_eq!(named, Named { flags: Some(Flags { enabled: false, ports }) });
+}
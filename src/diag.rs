@@ -0,0 +1,734 @@
+//! Module implement diagnostic notation for cbor values.
+
+use std::{char, convert::TryFrom, io};
+
+use crate::{
+    cbor::{decode_exact, f16_to_f64},
+    decode_all_lenient, Cbor, Error, ExpectedEncoding, FromCbor, Info, IntoCbor, Key,
+    MapOrder, Result, SimpleValue, Tag, RECURSION_LIMIT,
+};
+
+/// Return the diagnostic notation of `val`, as per RFC-8949 section 8.
+///
+/// Integers are printed as decimal, byte-strings as `h'..'`, text-strings
+/// as JSON strings, tags as `N(..)` and floats with a decimal point, like
+/// `1.5`, `NaN` and `-Infinity`. Indefinite length items are marked with
+/// `_`, like `[_ 1, 2]`, while chunks of indefinite length strings are not
+/// preserved by decoding, hence printed as a single chunk. Text-strings
+/// that are not valid utf8 are printed with `U+FFFD` replacing the invalid
+/// sequences.
+///
+/// ```
+/// use cbordata::{diagnostic, Cbor};
+///
+/// let buf = [0x82, 0x01, 0xa1, 0x61, 0x61, 0xf9, 0x3e, 0x00];
+/// let (val, _) = Cbor::decode(&mut &buf[..]).unwrap();
+/// assert_eq!(diagnostic(&val).unwrap(), r#"[1, {"a": 1.5}]"#);
+/// ```
+pub fn diagnostic(val: &Cbor) -> Result<String> {
+    let mut s = String::new();
+    diag_value(val, &mut s)?;
+    Ok(s)
+}
+
+/// Decode a sequence of cbor items from `buf`, and write the diagnostic
+/// notation of each item to `w`, one item per line, refer to [diagnostic].
+///
+/// Items preceding the first malformed, or truncated, item are written,
+/// after which [Error::FailCbor] is returned, identifying the malformed
+/// item by its position in the sequence.
+pub fn dump<W>(buf: &[u8], w: &mut W) -> Result<()>
+where
+    W: io::Write,
+{
+    let (items, err) = decode_all_lenient(buf);
+    for item in items.iter() {
+        err_at!(IOError, writeln!(w, "{}", diagnostic(item)?))?;
+    }
+    match err {
+        Some(err) => err_at!(FailCbor, msg: "malformed item {}, {}", items.len(), err),
+        None => Ok(()),
+    }
+}
+
+/// Parse diagnostic notation, as generated by [diagnostic], back into cbor
+/// value.
+///
+/// Integers are parsed into their shortest encoding, and floats, including
+/// `NaN` and `Infinity`, as double precision. Tagged values are validated
+/// the same way as while decoding, and indefinite length strings, like
+/// `(_ h'01', h'02')`, are parsed as a single chunk. Encoding indicators,
+/// like `1.5_1`, and comments are not supported, fail with
+/// [Error::FailConvert].
+///
+/// ```
+/// use cbordata::{diagnostic, from_diagnostic};
+///
+/// let val = from_diagnostic(r#"[1, {"a": h'0102'}, 0("2013-03-21T20:04:00Z")]"#).unwrap();
+/// assert_eq!(diagnostic(&val).unwrap(), r#"[1, {"a": h'0102'}, 0("2013-03-21T20:04:00Z")]"#);
+/// ```
+pub fn from_diagnostic(text: &str) -> Result<Cbor> {
+    Parser::new(text, false).parse()
+}
+
+impl Cbor {
+    /// Convert cbor value into JSON text, as per RFC-8949 section 6.1. The
+    /// conversion is lossy:
+    ///
+    /// * Byte-strings are converted into base64url text, without padding,
+    ///   unless nested under tags 21, 22 or 23, hinting the expected
+    ///   encoding, refer to [Cbor::as_expected_encoding].
+    /// * Big-nums are converted into base64url text, with `~` prefix for
+    ///   negative big-nums, while rest of the tags are stripped.
+    /// * Map keys are converted into text, non-finite floats, `undefined`
+    ///   and other simple values into `null`.
+    /// * Integers are converted into numbers, even beyond the range that
+    ///   JavaScript can represent exactly, refer to [JsonConfig].
+    pub fn to_json(&self) -> Result<String> {
+        self.to_json_with(&JsonConfig::default())
+    }
+
+    /// Same as [Cbor::to_json], with configuration for converting integers
+    /// that exceed the JavaScript safe range.
+    pub fn to_json_with(&self, config: &JsonConfig) -> Result<String> {
+        let mut s = String::new();
+        json_value(self, ExpectedEncoding::Base64Url, config, &mut s)?;
+        Ok(s)
+    }
+
+    /// Convert JSON text into cbor value, as per RFC-8949 section 6.2.
+    /// Numbers without fraction and exponent are converted into integers
+    /// when they fit within 64-bit, otherwise into double precision floats.
+    pub fn from_json(text: &str) -> Result<Cbor> {
+        Parser::new(text, true).parse()
+    }
+}
+
+/// Configuration for converting cbor values into JSON, refer to
+/// [Cbor::to_json_with].
+#[derive(Debug, Clone, Default)]
+pub struct JsonConfig {
+    /// Handling of integers that [Cbor::exceeds_js_safe_integer], refer to
+    /// [UnsafeIntegers]. Big-nums are always converted into text.
+    pub unsafe_integers: UnsafeIntegers,
+}
+
+/// Handling of integers outside `-(2^53 - 1) ..= 2^53 - 1` while converting
+/// into JSON, refer to [JsonConfig].
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub enum UnsafeIntegers {
+    /// Convert into JSON numbers, JavaScript consumers may lose precision.
+    #[default]
+    Number,
+    /// Convert into JSON text holding the decimal integer, like
+    /// `"9007199254740993"`.
+    Text,
+    /// Fail with [Error::FailConvert].
+    Reject,
+}
+
+fn diag_value(val: &Cbor, s: &mut String) -> Result<()> {
+    match val {
+        Cbor::Major0(_, num) => s.push_str(&num.to_string()),
+        Cbor::Major1(_, num) => s.push_str(&(-1 - i128::from(*num)).to_string()),
+        Cbor::Major2(info, data) => {
+            let hex: String = data.iter().map(|b| format!("{:02x}", b)).collect();
+            match info {
+                Info::Indefinite => s.push_str(&format!("(_ h'{}')", hex)),
+                _ => s.push_str(&format!("h'{}'", hex)),
+            }
+        }
+        Cbor::Major3(info, text) => {
+            let text = escape_text(&String::from_utf8_lossy(text));
+            match info {
+                Info::Indefinite => s.push_str(&format!("(_ {})", text)),
+                _ => s.push_str(&text),
+            }
+        }
+        Cbor::Major4(info, list) => {
+            s.push_str(indefinite(*info, "["));
+            for (i, item) in list.iter().enumerate() {
+                if i > 0 {
+                    s.push_str(", ");
+                }
+                diag_value(item, s)?;
+            }
+            s.push(']');
+        }
+        Cbor::Major5(info, map, _) => {
+            s.push_str(indefinite(*info, "{"));
+            for (i, (key, val)) in map.iter().enumerate() {
+                if i > 0 {
+                    s.push_str(", ");
+                }
+                diag_value(&key.clone().into_cbor()?, s)?;
+                s.push_str(": ");
+                diag_value(val, s)?;
+            }
+            s.push('}');
+        }
+        Cbor::Major6(_, tag) => {
+            s.push_str(&format!("{}(", tag.to_tag_value()));
+            diag_value(tag.as_content(), s)?;
+            s.push(')');
+        }
+        Cbor::Major7(info, sval) => s.push_str(&diag_simple(*info, sval)?),
+        Cbor::Binary(data) => diag_value(&decode_exact(data)?, s)?,
+    }
+    Ok(())
+}
+
+fn indefinite(info: Info, open: &'static str) -> &'static str {
+    match (info, open) {
+        (Info::Indefinite, "[") => "[_ ",
+        (Info::Indefinite, _) => "{_ ",
+        (_, open) => open,
+    }
+}
+
+fn diag_simple(info: Info, sval: &SimpleValue) -> Result<String> {
+    let s = match sval {
+        SimpleValue::Unassigned => match info {
+            Info::Tiny(n) => format!("simple({})", n),
+            _ => err_at!(FailCbor, msg: "unassigned simple-value {:?}", info)?,
+        },
+        SimpleValue::True => "true".to_string(),
+        SimpleValue::False => "false".to_string(),
+        SimpleValue::Null => "null".to_string(),
+        SimpleValue::Undefined => "undefined".to_string(),
+        SimpleValue::Reserved24(n) => format!("simple({})", n),
+        SimpleValue::F16(bits) => {
+            let val = f16_to_f64(*bits);
+            diag_float(val, format!("{:?}", val))
+        }
+        SimpleValue::F32(val) => diag_float(f64::from(*val), format!("{:?}", val)),
+        SimpleValue::F64(val) => diag_float(*val, format!("{:?}", val)),
+        SimpleValue::Break => err_at!(FailCbor, msg: "unexpected break")?,
+    };
+    Ok(s)
+}
+
+// Format finite floats with a decimal point, and exponent if any, like
+// `1.0e+300`, going by `s`, the shortest representation of the value.
+fn diag_float(val: f64, s: String) -> String {
+    if val.is_nan() {
+        return "NaN".to_string();
+    } else if val.is_infinite() {
+        return if val > 0.0 { "Infinity" } else { "-Infinity" }.to_string();
+    }
+
+    let (mantissa, exp) = match s.find('e') {
+        Some(off) => (&s[..off], Some(&s[off + 1..])),
+        None => (s.as_str(), None),
+    };
+    let mut out = mantissa.to_string();
+    if !out.contains('.') {
+        out.push_str(".0");
+    }
+    match exp {
+        Some(exp) if exp.starts_with('-') => out.push_str(&format!("e{}", exp)),
+        Some(exp) => out.push_str(&format!("e+{}", exp)),
+        None => (),
+    }
+    out
+}
+
+fn escape_text(text: &str) -> String {
+    let mut s = String::with_capacity(text.len() + 2);
+    s.push('"');
+    for ch in text.chars() {
+        match ch {
+            '"' => s.push_str("\\\""),
+            '\\' => s.push_str("\\\\"),
+            '\n' => s.push_str("\\n"),
+            '\r' => s.push_str("\\r"),
+            '\t' => s.push_str("\\t"),
+            ch if (ch as u32) < 0x20 => s.push_str(&format!("\\u{:04x}", ch as u32)),
+            ch => s.push(ch),
+        }
+    }
+    s.push('"');
+    s
+}
+
+fn json_value(
+    val: &Cbor,
+    enc: ExpectedEncoding,
+    cf: &JsonConfig,
+    s: &mut String,
+) -> Result<()> {
+    match val {
+        Cbor::Major0(_, num) => json_integer(val, i128::from(*num), cf, s)?,
+        Cbor::Major1(_, num) => json_integer(val, -1 - i128::from(*num), cf, s)?,
+        Cbor::Major2(_, data) => {
+            s.push('"');
+            s.push_str(&encode_bytes(data, enc));
+            s.push('"');
+        }
+        Cbor::Major3(_, text) => s.push_str(&escape_text(&String::from_utf8_lossy(text))),
+        Cbor::Major4(_, list) => {
+            s.push('[');
+            for (i, item) in list.iter().enumerate() {
+                if i > 0 {
+                    s.push(',');
+                }
+                json_value(item, enc, cf, s)?;
+            }
+            s.push(']');
+        }
+        Cbor::Major5(_, map, _) => {
+            s.push('{');
+            for (i, (key, val)) in map.iter().enumerate() {
+                if i > 0 {
+                    s.push(',');
+                }
+                let key = match key {
+                    Key::Bool(val) => val.to_string(),
+                    Key::N64(val) => val.to_string(),
+                    Key::U64(val) => val.to_string(),
+                    Key::F32(val) => diag_float(f64::from(*val), format!("{:?}", val)),
+                    Key::F64(val) => diag_float(*val, format!("{:?}", val)),
+                    Key::Bytes(data) => encode_bytes(data, enc),
+                    Key::Text(text) => text.clone(),
+                };
+                s.push_str(&escape_text(&key));
+                s.push(':');
+                json_value(val, enc, cf, s)?;
+            }
+            s.push('}');
+        }
+        Cbor::Major6(_, Tag::UBigNum(val)) => {
+            let data = val.as_ref().clone().into_bytes()?;
+            s.push_str(&format!(
+                "\"{}\"",
+                encode_bytes(&data, ExpectedEncoding::Base64Url)
+            ));
+        }
+        Cbor::Major6(_, Tag::SBigNum(val)) => {
+            let data = val.as_ref().clone().into_bytes()?;
+            s.push_str(&format!(
+                "\"~{}\"",
+                encode_bytes(&data, ExpectedEncoding::Base64Url)
+            ));
+        }
+        Cbor::Major6(_, Tag::Expected(enc, val)) => json_value(val, *enc, cf, s)?,
+        Cbor::Major6(_, tag) => json_value(tag.as_content(), enc, cf, s)?,
+        Cbor::Major7(_, SimpleValue::True) => s.push_str("true"),
+        Cbor::Major7(_, SimpleValue::False) => s.push_str("false"),
+        Cbor::Major7(info, sval @ SimpleValue::F16(_))
+        | Cbor::Major7(info, sval @ SimpleValue::F32(_))
+        | Cbor::Major7(info, sval @ SimpleValue::F64(_)) => {
+            match diag_simple(*info, sval)?.as_str() {
+                "NaN" | "Infinity" | "-Infinity" => s.push_str("null"),
+                val => s.push_str(val),
+            }
+        }
+        Cbor::Major7(_, _) => s.push_str("null"),
+        Cbor::Binary(data) => json_value(&decode_exact(data)?, enc, cf, s)?,
+    }
+    Ok(())
+}
+
+fn json_integer(val: &Cbor, num: i128, cf: &JsonConfig, s: &mut String) -> Result<()> {
+    match cf.unsafe_integers {
+        _ if !val.exceeds_js_safe_integer() => s.push_str(&num.to_string()),
+        UnsafeIntegers::Number => s.push_str(&num.to_string()),
+        UnsafeIntegers::Text => s.push_str(&format!("\"{}\"", num)),
+        UnsafeIntegers::Reject => {
+            err_at!(FailConvert, msg: "integer {} exceeds JavaScript safe range", num)?
+        }
+    }
+    Ok(())
+}
+
+fn encode_bytes(data: &[u8], enc: ExpectedEncoding) -> String {
+    const BASE64: &[u8; 64] =
+        b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    const BASE64URL: &[u8; 64] =
+        b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+    let (alphabet, pad) = match enc {
+        ExpectedEncoding::Base16 => {
+            return data.iter().map(|b| format!("{:02x}", b)).collect();
+        }
+        ExpectedEncoding::Base64 => (BASE64, true),
+        ExpectedEncoding::Base64Url => (BASE64URL, false),
+    };
+
+    let mut s = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let n = chunk.iter().fold(0_u32, |acc, b| (acc << 8) | u32::from(*b));
+        let n = n << (8 * (3 - chunk.len()));
+        for i in 0..=chunk.len() {
+            s.push(alphabet[((n >> (18 - 6 * i)) & 0x3f) as usize] as char);
+        }
+        if pad {
+            s.push_str(&"=="[..3 - chunk.len()]);
+        }
+    }
+    s
+}
+
+// Recursive descent parser for diagnostic notation, and for JSON text, which
+// is a subset of diagnostic notation.
+struct Parser<'a> {
+    text: &'a str,
+    off: usize,
+    json: bool,
+}
+
+impl<'a> Parser<'a> {
+    fn new(text: &'a str, json: bool) -> Parser<'a> {
+        Parser { text, off: 0, json }
+    }
+
+    fn parse(mut self) -> Result<Cbor> {
+        let val = self.value(1)?;
+        self.skip_ws();
+        match self.peek() {
+            Some(ch) => self.fail(&format!("unexpected {:?}", ch)),
+            None => Ok(val),
+        }
+    }
+
+    fn value(&mut self, depth: u32) -> Result<Cbor> {
+        if depth > RECURSION_LIMIT {
+            return self.fail("recursion limit exceeded");
+        }
+
+        self.skip_ws();
+        let rest = &self.text[self.off..];
+        match self.peek() {
+            Some('[') => self.array(depth),
+            Some('{') => self.map(depth),
+            Some('"') => Ok(self.text_string()?.as_str().into_cbor()?),
+            Some('h') if !self.json && rest.starts_with("h'") => {
+                Cbor::from_bytes(self.byte_string()?)
+            }
+            Some('(') if !self.json => self.chunks(),
+            Some(ch) if ch == '-' || ch.is_ascii_digit() => self.number(depth),
+            Some(ch) if ch.is_ascii_alphabetic() => self.word(),
+            Some(ch) => self.fail(&format!("unexpected {:?}", ch)),
+            None => self.fail("unexpected end of text"),
+        }
+    }
+
+    fn array(&mut self, depth: u32) -> Result<Cbor> {
+        self.expect('[')?;
+        let indefinite = self.indefinite();
+        let mut list = vec![];
+        while self.more(']', list.is_empty())? {
+            list.push(self.value(depth + 1)?);
+        }
+        match indefinite {
+            true => Ok(Cbor::Major4(Info::Indefinite, list)),
+            false => list.into_cbor(),
+        }
+    }
+
+    fn map(&mut self, depth: u32) -> Result<Cbor> {
+        self.expect('{')?;
+        let indefinite = self.indefinite();
+        let mut map = vec![];
+        while self.more('}', map.is_empty())? {
+            let key = match self.value(depth + 1)? {
+                key @ Cbor::Major3(_, _) => Key::from_cbor(key)?,
+                _ if self.json => self.fail("expected text key")?,
+                key => Key::from_cbor(key)?,
+            };
+            self.skip_ws();
+            self.expect(':')?;
+            map.push((key, self.value(depth + 1)?));
+        }
+        match indefinite {
+            true => {
+                let order = MapOrder::of(&map);
+                Ok(Cbor::Major5(Info::Indefinite, map, order))
+            }
+            false => map.into_cbor(),
+        }
+    }
+
+    // Return whether there are more items in array or map before `close`,
+    // consuming the separating comma, or the closing `close`.
+    fn more(&mut self, close: char, first: bool) -> Result<bool> {
+        self.skip_ws();
+        match self.peek() {
+            Some(ch) if ch == close => {
+                self.off += 1;
+                Ok(false)
+            }
+            Some(',') if !first => {
+                self.off += 1;
+                Ok(true)
+            }
+            _ if first => Ok(true),
+            _ => self.fail(&format!("expected ',' or {:?}", close)),
+        }
+    }
+
+    // Indefinite length string, like `(_ "a", "b")`, chunks are joined.
+    fn chunks(&mut self) -> Result<Cbor> {
+        self.expect('(')?;
+        if !self.indefinite() {
+            return self.fail("expected indefinite length string");
+        }
+        self.skip_ws();
+        let is_text = match self.peek() {
+            Some('"') => true,
+            Some('h') => false,
+            _ => return self.fail("expected string chunk"),
+        };
+
+        let mut data = vec![];
+        loop {
+            self.skip_ws();
+            match is_text {
+                true => data.extend_from_slice(self.text_string()?.as_bytes()),
+                false => data.extend_from_slice(&self.byte_string()?),
+            }
+            self.skip_ws();
+            match self.next() {
+                Some(',') => (),
+                Some(')') => break,
+                _ => return self.fail("expected ',' or ')'"),
+            }
+        }
+        match is_text {
+            true => Ok(Cbor::Major3(Info::Indefinite, data)),
+            false => Ok(Cbor::Major2(Info::Indefinite, data)),
+        }
+    }
+
+    fn number(&mut self, depth: u32) -> Result<Cbor> {
+        let start = self.off;
+        if self.peek() == Some('-') {
+            self.off += 1;
+            if !self.json && self.text[self.off..].starts_with("Infinity") {
+                self.off += 8;
+                return f64::NEG_INFINITY.into_cbor();
+            }
+        }
+        self.digits()?;
+        let mut is_float = false;
+        if self.peek() == Some('.') {
+            self.off += 1;
+            self.digits()?;
+            is_float = true;
+        }
+        if let Some('e') | Some('E') = self.peek() {
+            self.off += 1;
+            if let Some('+') | Some('-') = self.peek() {
+                self.off += 1;
+            }
+            self.digits()?;
+            is_float = true;
+        }
+        let num = &self.text[start..self.off];
+
+        if !is_float && !self.json && self.peek() == Some('(') {
+            let tag = match num.parse::<u64>() {
+                Ok(tag) => tag,
+                Err(_) => return self.fail(&format!("bad tag number {}", num)),
+            };
+            self.off += 1;
+            let val = self.value(depth + 1)?;
+            self.skip_ws();
+            self.expect(')')?;
+            let tag = Tag::from_value(tag, val);
+            tag.validate()?;
+            if let Tag::EncodedCbor(val) = &tag {
+                if let Cbor::Major2(_, data) = val.as_ref() {
+                    decode_exact(data)?;
+                }
+            }
+            return Ok(tag.into());
+        }
+
+        match num.parse::<i128>() {
+            Ok(num) if !is_float => {
+                if let Ok(num) = u64::try_from(num) {
+                    return Ok(Cbor::Major0(num.into(), num));
+                } else if let Ok(num) = u64::try_from(-1 - num) {
+                    return Ok(Cbor::Major1(num.into(), num));
+                }
+            }
+            _ => (),
+        }
+        match num.parse::<f64>() {
+            Ok(val) if is_float || self.json => val.into_cbor(),
+            _ => self.fail(&format!("integer {} out of range", num)),
+        }
+    }
+
+    fn word(&mut self) -> Result<Cbor> {
+        let start = self.off;
+        while let Some(ch) = self.peek() {
+            if !ch.is_ascii_alphanumeric() {
+                break;
+            }
+            self.off += 1;
+        }
+        let val = match (&self.text[start..self.off], self.json) {
+            ("true", _) => true.into_cbor()?,
+            ("false", _) => false.into_cbor()?,
+            ("null", _) => Cbor::null(),
+            ("undefined", false) => Cbor::undefined(),
+            ("NaN", false) => f64::NAN.into_cbor()?,
+            ("Infinity", false) => f64::INFINITY.into_cbor()?,
+            ("simple", false) => {
+                self.expect('(')?;
+                let start = self.off;
+                self.digits()?;
+                let n = match self.text[start..self.off].parse::<u8>() {
+                    Ok(n) => n,
+                    Err(_) => self.fail("bad simple value")?,
+                };
+                self.expect(')')?;
+                Cbor::simple(n)?
+            }
+            (word, _) => self.fail(&format!("unexpected {:?}", word))?,
+        };
+        Ok(val)
+    }
+
+    fn text_string(&mut self) -> Result<String> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            match self.next() {
+                Some('"') => break Ok(s),
+                Some('\\') => match self.next() {
+                    Some('"') => s.push('"'),
+                    Some('\\') => s.push('\\'),
+                    Some('/') => s.push('/'),
+                    Some('b') => s.push('\u{8}'),
+                    Some('f') => s.push('\u{c}'),
+                    Some('n') => s.push('\n'),
+                    Some('r') => s.push('\r'),
+                    Some('t') => s.push('\t'),
+                    Some('u') => s.push(self.unicode_escape()?),
+                    _ => self.fail("bad escape sequence")?,
+                },
+                Some(ch) if (ch as u32) < 0x20 => {
+                    self.fail("control character in text")?
+                }
+                Some(ch) => s.push(ch),
+                None => self.fail("unterminated text")?,
+            }
+        }
+    }
+
+    // Parse `XXXX` following `\u`, along with the low surrogate if any.
+    fn unicode_escape(&mut self) -> Result<char> {
+        let high = self.hex4()?;
+        let code = match high {
+            0xd800..=0xdbff if self.text[self.off..].starts_with("\\u") => {
+                self.off += 2;
+                match self.hex4()? {
+                    low @ 0xdc00..=0xdfff => {
+                        0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+                    }
+                    _ => self.fail("bad surrogate pair")?,
+                }
+            }
+            code => code,
+        };
+        match char::from_u32(code) {
+            Some(ch) => Ok(ch),
+            None => self.fail("bad unicode escape"),
+        }
+    }
+
+    fn hex4(&mut self) -> Result<u32> {
+        match self.text.get(self.off..self.off + 4).map(|s| u32::from_str_radix(s, 16)) {
+            Some(Ok(code)) => {
+                self.off += 4;
+                Ok(code)
+            }
+            _ => self.fail("bad unicode escape"),
+        }
+    }
+
+    fn byte_string(&mut self) -> Result<Vec<u8>> {
+        self.expect('h')?;
+        self.expect('\'')?;
+        let mut hex = String::new();
+        loop {
+            match self.next() {
+                Some('\'') => break,
+                Some(ch) if ch.is_ascii_hexdigit() => hex.push(ch),
+                Some(ch) if ch.is_whitespace() => (),
+                _ => self.fail("bad byte string")?,
+            }
+        }
+        if hex.len() % 2 == 1 {
+            self.fail("odd number of hex digits")?
+        }
+        let data = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect();
+        Ok(data)
+    }
+
+    fn digits(&mut self) -> Result<()> {
+        let start = self.off;
+        while let Some(ch) = self.peek() {
+            if !ch.is_ascii_digit() {
+                break;
+            }
+            self.off += 1;
+        }
+        match self.off > start {
+            true => Ok(()),
+            false => self.fail("expected digits"),
+        }
+    }
+
+    // Consume the `_` marker of indefinite length items, if any.
+    fn indefinite(&mut self) -> bool {
+        self.skip_ws();
+        match self.peek() {
+            Some('_') if !self.json => {
+                self.off += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn skip_ws(&mut self) {
+        while let Some(ch) = self.peek() {
+            if !ch.is_whitespace() {
+                break;
+            }
+            self.off += ch.len_utf8();
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.off..].chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let ch = self.peek()?;
+        self.off += ch.len_utf8();
+        Some(ch)
+    }
+
+    fn expect(&mut self, want: char) -> Result<()> {
+        match self.next() {
+            Some(ch) if ch == want => Ok(()),
+            _ => self.fail(&format!("expected {:?}", want)),
+        }
+    }
+
+    fn fail<T>(&self, msg: &str) -> Result<T> {
+        err_at!(FailConvert, msg: "{} at offset {}", msg, self.off)
+    }
+}
+
+#[cfg(test)]
+#[path = "diag_test.rs"]
+mod diag_test;
@@ -0,0 +1,242 @@
+use super::*;
+
+#[test]
+fn test_diagnostic() {
+    // examples from RFC-8949 appendix A.
+    let testcases: Vec<(&[u8], &str)> = vec![
+        (&[0x00], "0"),
+        (&[0x18, 0x64], "100"),
+        (&[0x1b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff], "18446744073709551615"),
+        (
+            &[0x3b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff],
+            "-18446744073709551616",
+        ),
+        (&[0x38, 0x63], "-100"),
+        (&[0xf9, 0x80, 0x00], "-0.0"),
+        (&[0xf9, 0x3e, 0x00], "1.5"),
+        (&[0xf9, 0x7b, 0xff], "65504.0"),
+        (&[0xfa, 0x47, 0xc3, 0x50, 0x00], "100000.0"),
+        (&[0xfb, 0x7e, 0x37, 0xe4, 0x3c, 0x88, 0x00, 0x75, 0x9c], "1.0e+300"),
+        (&[0xf9, 0x00, 0x01], "5.960464477539063e-8"),
+        (&[0xfb, 0xc0, 0x10, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66], "-4.1"),
+        (&[0xf9, 0x7c, 0x00], "Infinity"),
+        (&[0xf9, 0x7e, 0x00], "NaN"),
+        (&[0xfa, 0xff, 0x80, 0x00, 0x00], "-Infinity"),
+        (&[0xf4], "false"),
+        (&[0xf5], "true"),
+        (&[0xf6], "null"),
+        (&[0xf7], "undefined"),
+        (&[0xf0], "simple(16)"),
+        (&[0xf8, 0xff], "simple(255)"),
+        (&[0xc1, 0x1a, 0x51, 0x4b, 0x67, 0xb0], "1(1363896240)"),
+        (&[0xc2, 0x42, 0x01, 0x00], "2(h'0100')"),
+        (&[0x40], "h''"),
+        (&[0x44, 0x01, 0x02, 0x03, 0x04], "h'01020304'"),
+        (&[0x62, 0x22, 0x5c], r#""\"\\""#),
+        (&[0x63, 0xe6, 0xb0, 0xb4], "\"\u{6c34}\""),
+        (&[0x61, 0x0a], r#""\n""#),
+        (&[0x61, 0x01], r#""\u0001""#),
+        (&[0x80], "[]"),
+        (&[0x83, 0x01, 0x82, 0x02, 0x03, 0x82, 0x04, 0x05], "[1, [2, 3], [4, 5]]"),
+        (&[0xa2, 0x01, 0x02, 0x03, 0x04], "{1: 2, 3: 4}"),
+        (
+            &[0xa2, 0x61, 0x61, 0x01, 0x61, 0x62, 0x82, 0x02, 0x03],
+            r#"{"a": 1, "b": [2, 3]}"#,
+        ),
+        (&[0x5f, 0x42, 0x01, 0x02, 0x43, 0x03, 0x04, 0x05, 0xff], "(_ h'0102030405')"),
+        (
+            &[
+                0x7f, 0x65, 0x73, 0x74, 0x72, 0x65, 0x61, 0x64, 0x6d, 0x69, 0x6e, 0x67,
+                0xff,
+            ],
+            r#"(_ "streaming")"#,
+        ),
+        (&[0x9f, 0x01, 0x82, 0x02, 0x03, 0xff], "[_ 1, [2, 3]]"),
+        (&[0xbf, 0x61, 0x61, 0x01, 0xff], r#"{_ "a": 1}"#),
+    ];
+    for (buf, refs) in testcases.into_iter() {
+        let (val, _) = Cbor::decode(&mut &buf[..]).unwrap();
+        assert_eq!(diagnostic(&val).unwrap(), refs, "{:x?}", buf);
+    }
+
+    // lazily decoded values.
+    let val = Cbor::Binary(vec![0x82, 0x01, 0x02]);
+    assert_eq!(diagnostic(&val).unwrap(), "[1, 2]");
+}
+
+#[test]
+fn test_dump() {
+    let mut buf = vec![];
+    1_u64.into_cbor().unwrap().encode(&mut buf).unwrap();
+    vec!["a", "b"].into_cbor().unwrap().encode(&mut buf).unwrap();
+    (-2.5_f64).into_cbor().unwrap().encode(&mut buf).unwrap();
+
+    let mut out: Vec<u8> = vec![];
+    dump(&buf, &mut out).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "1\n[\"a\", \"b\"]\n-2.5\n");
+
+    // stop at the first malformed, here truncated, item.
+    let mut out: Vec<u8> = vec![];
+    buf.extend_from_slice(&[0x82, 0x01]);
+    match dump(&buf, &mut out) {
+        Err(Error::FailCbor(_, msg)) => {
+            assert!(msg.contains("malformed item 3"), "{}", msg)
+        }
+        res => panic!("{:?}", res),
+    }
+    assert_eq!(String::from_utf8(out).unwrap(), "1\n[\"a\", \"b\"]\n-2.5\n");
+
+    let mut out: Vec<u8> = vec![];
+    dump(&[], &mut out).unwrap();
+    assert!(out.is_empty());
+}
+
+#[test]
+fn test_from_diagnostic() {
+    let texts = [
+        "0",
+        "18446744073709551615",
+        "-18446744073709551616",
+        "-0.0",
+        "1.0e+300",
+        "5.960464477539063e-8",
+        "NaN",
+        "-Infinity",
+        "Infinity",
+        "false",
+        "true",
+        "null",
+        "undefined",
+        "simple(16)",
+        "simple(255)",
+        "1(1363896240)",
+        "2(h'0100')",
+        "h''",
+        "h'01020304'",
+        r#""\"\\""#,
+        "\"\u{6c34}\"",
+        r#""\n\u0001""#,
+        "[]",
+        "[1, [2, 3], [4, 5]]",
+        "{1: 2, 3: 4}",
+        r#"{"a": 1, "b": [2, 3]}"#,
+        "(_ h'0102030405')",
+        r#"(_ "streaming")"#,
+        "[_ 1, [2, 3]]",
+        r#"{_ "a": 1}"#,
+        "1000(-1)",
+        r#"21({h'01': 22(h'ff')})"#,
+    ];
+    for text in texts.iter() {
+        let val = from_diagnostic(text).unwrap();
+        assert_eq!(diagnostic(&val).unwrap(), *text);
+
+        let mut buf = vec![];
+        val.encode(&mut buf).unwrap();
+        let (out, _) = Cbor::decode(&mut buf.as_slice()).unwrap();
+        assert_eq!(diagnostic(&out).unwrap(), *text);
+    }
+
+    let val =
+        from_diagnostic(r#" [ 1 ,{ "a" :h'01 02' } , (_ h'01', h'02'), (_ "a", "b") ] "#);
+    let refs = vec![
+        1_u64.into_cbor().unwrap(),
+        vec![(Key::Text("a".to_string()), Cbor::from_bytes(vec![1, 2]).unwrap())]
+            .into_cbor()
+            .unwrap(),
+        Cbor::Major2(Info::Indefinite, vec![1, 2]),
+        Cbor::Major3(Info::Indefinite, b"ab".to_vec()),
+    ];
+    assert_eq!(val.unwrap(), refs.into_cbor().unwrap());
+
+    let val = from_diagnostic(r#""𝄞\/""#).unwrap();
+    assert_eq!(val, "\u{1d11e}/".into_cbor().unwrap());
+
+    let texts = [
+        "",
+        "[1, 2",
+        "[1 2]",
+        "[1,]",
+        "{1}",
+        "h'0'",
+        "\"abc",
+        r#""\x""#,
+        r#""\ud834""#,
+        "simple(24)",
+        "1(\"x\")",
+        "18446744073709551616",
+        "(_ )",
+        "1 2",
+        "nil",
+    ];
+    for text in texts.iter() {
+        assert!(from_diagnostic(text).is_err(), "{}", text);
+    }
+}
+
+#[test]
+fn test_json() {
+    let testcases = [
+        ("[1, -2, 1.5, true, null]", "[1,-2,1.5,true,null]"),
+        (r#"{"a": {"b": "c\n"}}"#, r#"{"a":{"b":"c\n"}}"#),
+        ("h'fbff'", r#""-_8""#),
+        ("h'010203'", r#""AQID""#),
+        ("22(h'fbff')", r#""+/8=""#),
+        ("23([h'0102', 21(h'fbff')])", r#"["0102","-_8"]"#),
+        ("2(h'010000000000000000')", r#""AQAAAAAAAAAA""#),
+        ("3(h'010000000000000000')", r#""~AQAAAAAAAAAA""#),
+        ("1(1363896240)", "1363896240"),
+        (r#"{1: 2, true: h'01', -1.5: "x"}"#, r#"{"1":2,"true":"AQ","-1.5":"x"}"#),
+        ("[NaN, Infinity, undefined, simple(16)]", "[null,null,null,null]"),
+        ("[_ 1, (_ \"a\", \"b\")]", r#"[1,"ab"]"#),
+    ];
+    for (text, json) in testcases.iter() {
+        let val = from_diagnostic(text).unwrap();
+        assert_eq!(val.to_json().unwrap(), *json, "{}", text);
+    }
+
+    // integers at the JavaScript safe range boundary.
+    let config = |unsafe_integers| JsonConfig { unsafe_integers };
+    let val = from_diagnostic(
+        "[9007199254740991, 9007199254740992, -9007199254740991, -9007199254740992]",
+    )
+    .unwrap();
+    let json = val.to_json().unwrap();
+    assert_eq!(
+        json,
+        "[9007199254740991,9007199254740992,-9007199254740991,-9007199254740992]"
+    );
+    let json = val.to_json_with(&config(UnsafeIntegers::Text)).unwrap();
+    assert_eq!(
+        json,
+        r#"[9007199254740991,"9007199254740992",-9007199254740991,"-9007199254740992"]"#
+    );
+    match val.to_json_with(&config(UnsafeIntegers::Reject)) {
+        Err(Error::FailConvert(_, msg)) => {
+            assert!(msg.contains("9007199254740992"), "{}", msg)
+        }
+        res => panic!("{:?}", res),
+    }
+    let val = from_diagnostic("{18446744073709551615: 1}").unwrap();
+    let json = val.to_json_with(&config(UnsafeIntegers::Reject)).unwrap();
+    assert_eq!(json, r#"{"18446744073709551615":1}"#);
+
+    let testcases = [
+        (
+            "[1, -2, 1.5, 1e2, true, false, null]",
+            "[1, -2, 1.5, 100.0, true, false, null]",
+        ),
+        (r#" { "a" : [ ] , "b" : { } } "#, r#"{"a": [], "b": {}}"#),
+        ("18446744073709551616", "1.8446744073709552e+19"),
+        (r#""é""#, "\"\u{e9}\""),
+    ];
+    for (json, text) in testcases.iter() {
+        let val = Cbor::from_json(json).unwrap();
+        assert_eq!(diagnostic(&val).unwrap(), *text, "{}", json);
+    }
+
+    let jsons = ["h'01'", "[_ 1]", "{1: 2}", "NaN", "undefined", "1(1)", "-Infinity"];
+    for json in jsons.iter() {
+        assert!(Cbor::from_json(json).is_err(), "{}", json);
+    }
+}
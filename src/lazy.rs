@@ -0,0 +1,270 @@
+//! Module implement lazy view into encoded cbor documents.
+
+use std::{collections::BTreeMap, convert::TryFrom, ops::Range};
+
+use crate::{
+    cbor::{decode_addnl, decode_exact, decode_hdr, scan_item},
+    Cbor, Error, FromCbor, Info, Key, Result,
+};
+
+/// Lazy view into an encoded cbor array or map, decoding its items on demand.
+///
+/// Constructing the view scans the top-level items once, recording the
+/// byte-range of each array item, or each map value along with its decoded
+/// key. Scanning walks the headers of every nested item without building
+/// values, hence the one-time indexing cost is proportional to the size of
+/// the document, while each [LazyCbor::get] decodes only the requested
+/// sub-tree.
+///
+/// ```
+/// use cbordata::{Cbor, IntoCbor, Key, LazyCbor};
+///
+/// let val = Cbor::map_from(vec![
+///     ("name".into_cbor().unwrap(), "cbor".into_cbor().unwrap()),
+///     ("size".into_cbor().unwrap(), 100.into_cbor().unwrap()),
+/// ])
+/// .unwrap();
+/// let mut buf = vec![];
+/// val.encode(&mut buf).unwrap();
+///
+/// let doc = LazyCbor::new(&buf).unwrap();
+/// let key = Key::Text("size".to_string());
+/// assert_eq!(doc.get(&key).unwrap(), Some(100.into_cbor().unwrap()));
+/// ```
+#[derive(Debug, Clone)]
+pub struct LazyCbor<'a> {
+    buf: &'a [u8],
+    index: Index,
+}
+
+#[derive(Debug, Clone)]
+enum Index {
+    Array(Vec<Range<usize>>),
+    Map(BTreeMap<Key, Range<usize>>),
+}
+
+impl<'a> LazyCbor<'a> {
+    /// Index `buf`, which shall contain exactly one encoded array or map.
+    /// When a map has duplicate keys, the first occurence is indexed.
+    pub fn new(buf: &'a [u8]) -> Result<LazyCbor<'a>> {
+        let mut r = buf;
+        let (major, info, n) = decode_hdr(&mut r)?;
+        let (len, m) = decode_addnl(info, &mut r)?;
+        let mut off = n + m;
+
+        let index = match major {
+            4 => {
+                let mut items = vec![];
+                while LazyCbor::more(buf, &mut off, info, items.len(), len)? {
+                    items.push(LazyCbor::skip(buf, &mut off)?);
+                }
+                Index::Array(items)
+            }
+            5 => {
+                let (mut items, mut n_items) = (BTreeMap::new(), 0);
+                while LazyCbor::more(buf, &mut off, info, n_items, len)? {
+                    let key = LazyCbor::skip(buf, &mut off)?;
+                    let key = Key::from_cbor(decode_exact(&buf[key])?)?;
+                    let val = LazyCbor::skip(buf, &mut off)?;
+                    items.entry(key).or_insert(val);
+                    n_items += 1;
+                }
+                Index::Map(items)
+            }
+            major => err_at!(FailCbor, msg: "expected array or map, major {}", major)?,
+        };
+
+        if off != buf.len() {
+            err_at!(FailCbor, msg: "trailing bytes {} after cbor value", buf.len() - off)?
+        }
+
+        Ok(LazyCbor { buf, index })
+    }
+
+    // Return whether more items follow at `off`, consuming the break
+    // byte for indefinite length collections.
+    fn more(buf: &[u8], off: &mut usize, info: Info, n: usize, len: u64) -> Result<bool> {
+        match info {
+            Info::Indefinite => match buf.get(*off) {
+                Some(0xff) => {
+                    *off += 1;
+                    Ok(false)
+                }
+                Some(_) => Ok(true),
+                None => err_at!(FailCbor, msg: "truncated cbor at offset {}", off),
+            },
+            _ => Ok(err_at!(FailConvert, u64::try_from(n))? < len),
+        }
+    }
+
+    fn skip(buf: &[u8], off: &mut usize) -> Result<Range<usize>> {
+        match scan_item(&buf[*off..])? {
+            Some(n) => {
+                let range = *off..(*off + n);
+                *off += n;
+                Ok(range)
+            }
+            None => err_at!(FailCbor, msg: "truncated cbor at offset {}", off),
+        }
+    }
+
+    /// Decode the value for `key`. For arrays, key shall be `Key::U64(index)`.
+    /// Return None if there is no such key or index.
+    pub fn get(&self, key: &Key) -> Result<Option<Cbor>> {
+        match self.get_bytes(key) {
+            Some(data) => Ok(Some(decode_exact(data)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Same as [LazyCbor::get], but return the encoded bytes of the value
+    /// without decoding them.
+    pub fn get_bytes(&self, key: &Key) -> Option<&'a [u8]> {
+        let range = match (&self.index, key) {
+            (Index::Array(items), Key::U64(n)) => {
+                items.get(usize::try_from(*n).ok()?)?.clone()
+            }
+            (Index::Array(_), _) => return None,
+            (Index::Map(items), key) => items.get(key)?.clone(),
+        };
+        Some(&self.buf[range])
+    }
+
+    /// Return the keys of the indexed map, in sort order. Return None
+    /// if the document is an array.
+    pub fn keys(&self) -> Option<Vec<&Key>> {
+        match &self.index {
+            Index::Array(_) => None,
+            Index::Map(items) => Some(items.keys().collect()),
+        }
+    }
+
+    /// Return the number of indexed items.
+    pub fn len(&self) -> usize {
+        match &self.index {
+            Index::Array(items) => items.len(),
+            Index::Map(items) => items.len(),
+        }
+    }
+
+    /// Return true if there are no indexed items.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Segment of a path into nested cbor arrays and maps, refer to [get_path].
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathSegment {
+    /// Value for key, in a map.
+    Key(Key),
+    /// Item at index, in an array.
+    Index(usize),
+}
+
+impl From<Key> for PathSegment {
+    fn from(key: Key) -> PathSegment {
+        PathSegment::Key(key)
+    }
+}
+
+impl From<usize> for PathSegment {
+    fn from(index: usize) -> PathSegment {
+        PathSegment::Index(index)
+    }
+}
+
+/// Decode the value at `path`, from the encoded cbor item at the beginning
+/// of `buf`, without decoding rest of the document.
+///
+/// Each segment selects an item from the array, or a value from the map, at
+/// the current level. Items preceding the selected one are skipped using
+/// their length information, without building values, while items following
+/// the selected one are not scanned. Tags enclosing arrays and maps along
+/// the path are stepped over. When a map has duplicate keys, the first
+/// occurence is selected. Return None if there is no such key or index, or
+/// if the path leads into a value that is neither array nor map.
+///
+/// ```
+/// use cbordata::{get_path, Cbor, IntoCbor, Key, PathSegment};
+///
+/// let val = Cbor::map_from(vec![(
+///     "tags".into_cbor().unwrap(),
+///     vec!["db", "cbor"].into_cbor().unwrap(),
+/// )])
+/// .unwrap();
+/// let mut buf = vec![];
+/// val.encode(&mut buf).unwrap();
+///
+/// let path = [PathSegment::Key(Key::Text("tags".to_string())), PathSegment::Index(1)];
+/// assert_eq!(get_path(&buf, &path).unwrap(), Some("cbor".into_cbor().unwrap()));
+/// ```
+pub fn get_path(buf: &[u8], path: &[PathSegment]) -> Result<Option<Cbor>> {
+    match get_path_bytes(buf, path)? {
+        Some(data) => Ok(Some(decode_exact(data)?)),
+        None => Ok(None),
+    }
+}
+
+/// Same as [get_path], but return the encoded bytes of the value without
+/// decoding them.
+pub fn get_path_bytes<'a>(
+    buf: &'a [u8],
+    path: &[PathSegment],
+) -> Result<Option<&'a [u8]>> {
+    let mut item = buf;
+    for seg in path.iter() {
+        item = match find_item(item, seg)? {
+            Some(range) => &item[range],
+            None => return Ok(None),
+        };
+    }
+    let n = LazyCbor::skip(item, &mut 0)?.end;
+    Ok(Some(&item[..n]))
+}
+
+// Return the byte-range of the item selected by `seg`, from the array or
+// map at the beginning of `buf`.
+fn find_item(buf: &[u8], seg: &PathSegment) -> Result<Option<Range<usize>>> {
+    let mut off = 0;
+    let (major, info, len) = loop {
+        let mut r = &buf[off..];
+        let (major, info, n) = decode_hdr(&mut r)?;
+        let (len, m) = decode_addnl(info, &mut r)?;
+        off += n + m;
+        if major != 6 {
+            break (major, info, len);
+        }
+    };
+
+    match (major, seg) {
+        (4, PathSegment::Index(index)) => {
+            let mut n_items = 0;
+            while LazyCbor::more(buf, &mut off, info, n_items, len)? {
+                let item = LazyCbor::skip(buf, &mut off)?;
+                if n_items == *index {
+                    return Ok(Some(item));
+                }
+                n_items += 1;
+            }
+            Ok(None)
+        }
+        (5, PathSegment::Key(key)) => {
+            let mut n_items = 0;
+            while LazyCbor::more(buf, &mut off, info, n_items, len)? {
+                let k = LazyCbor::skip(buf, &mut off)?;
+                let val = LazyCbor::skip(buf, &mut off)?;
+                if &Key::from_cbor(decode_exact(&buf[k])?)? == key {
+                    return Ok(Some(val));
+                }
+                n_items += 1;
+            }
+            Ok(None)
+        }
+        _ => Ok(None),
+    }
+}
+
+#[cfg(test)]
+#[path = "lazy_test.rs"]
+mod lazy_test;
@@ -0,0 +1,144 @@
+use arbitrary::Unstructured;
+use rand::{prelude::random, rngs::StdRng, Rng, SeedableRng};
+
+use super::*;
+use crate::IntoCbor;
+
+#[test]
+fn test_lazy_cbor() {
+    let seed: u128 = random();
+    println!("test_lazy_cbor seed:{}", seed);
+    let mut rng = {
+        let mut rng_seed = [0; 32];
+        rng_seed[0..16].copy_from_slice(&seed.to_le_bytes());
+        StdRng::from_seed(rng_seed)
+    };
+
+    for _i in 0..1000 {
+        let bytes: Vec<u8> =
+            (0..100).flat_map(|_| rng.gen::<[u8; 32]>().to_vec()).collect();
+        let mut uns = Unstructured::new(&bytes);
+
+        let items: Vec<Cbor> = uns.arbitrary().unwrap();
+        let mut buf: Vec<u8> = vec![];
+        items.clone().into_cbor().unwrap().encode(&mut buf).unwrap();
+
+        let doc = LazyCbor::new(&buf).unwrap();
+        assert_eq!(doc.len(), items.len());
+        assert!(doc.keys().is_none());
+        for (i, item) in items.iter().enumerate() {
+            let key = Key::U64(i as u64);
+            assert_eq!(doc.get(&key).unwrap().as_ref(), Some(item));
+        }
+        assert_eq!(doc.get(&Key::U64(items.len() as u64)).unwrap(), None);
+
+        let items: Vec<(Key, Cbor)> = uns.arbitrary().unwrap();
+        let mut buf: Vec<u8> = vec![];
+        items.clone().into_cbor().unwrap().encode(&mut buf).unwrap();
+
+        let doc = LazyCbor::new(&buf).unwrap();
+        for (key, val) in items.iter() {
+            let refval = items.iter().find(|(k, _)| k == key).map(|(_, v)| v);
+            assert_eq!(doc.get(key).unwrap().as_ref(), refval, "{:?}", val);
+        }
+        let mut keys: Vec<&Key> = items.iter().map(|(k, _)| k).collect();
+        keys.sort();
+        keys.dedup();
+        assert_eq!(doc.keys(), Some(keys));
+    }
+}
+
+#[test]
+fn test_lazy_cbor_indefinite() {
+    // {_ "a": [_ 1, 2], "b": 3}
+    let buf = [0xbf, 0x61, 0x61, 0x9f, 0x01, 0x02, 0xff, 0x61, 0x62, 0x03, 0xff];
+    let doc = LazyCbor::new(&buf).unwrap();
+    assert_eq!(doc.len(), 2);
+    assert_eq!(doc.get_bytes(&Key::Text("a".to_string())), Some(&buf[3..7]));
+    assert_eq!(
+        doc.get(&Key::Text("b".to_string())).unwrap(),
+        Some(3.into_cbor().unwrap())
+    );
+    assert_eq!(doc.get(&Key::U64(0)).unwrap(), None);
+
+    assert!(LazyCbor::new(&buf[..10]).is_err());
+    assert!(LazyCbor::new(&[0x01]).is_err());
+    assert!(LazyCbor::new(&[0x80, 0x01]).is_err());
+}
+
+#[test]
+fn test_get_path() {
+    let seed: u128 = random();
+    println!("test_get_path seed:{}", seed);
+    let mut rng = {
+        let mut rng_seed = [0; 32];
+        rng_seed[0..16].copy_from_slice(&seed.to_le_bytes());
+        StdRng::from_seed(rng_seed)
+    };
+
+    for _i in 0..1000 {
+        let bytes: Vec<u8> =
+            (0..100).flat_map(|_| rng.gen::<[u8; 32]>().to_vec()).collect();
+        let mut uns = Unstructured::new(&bytes);
+
+        let items: Vec<(Key, Cbor)> = uns.arbitrary().unwrap();
+        let val = vec![
+            0_u64.into_cbor().unwrap(),
+            items.clone().into_cbor().unwrap(),
+            1_u64.into_cbor().unwrap(),
+        ];
+        let mut buf: Vec<u8> = vec![];
+        val.into_cbor().unwrap().encode(&mut buf).unwrap();
+
+        for (key, _) in items.iter() {
+            let refval = items.iter().find(|(k, _)| k == key).map(|(_, v)| v);
+            let path = [PathSegment::Index(1), PathSegment::Key(key.clone())];
+            assert_eq!(get_path(&buf, &path).unwrap().as_ref(), refval);
+        }
+        let path = [PathSegment::Index(2)];
+        assert_eq!(get_path(&buf, &path).unwrap(), Some(1_u64.into_cbor().unwrap()));
+        assert_eq!(get_path(&buf, &[PathSegment::Index(3)]).unwrap(), None);
+    }
+}
+
+#[test]
+fn test_get_path_nested() {
+    // 55799({_ "a": [_ 1, {"b": h'0102'}], "c": 3})
+    let buf = [
+        0xd9, 0xd9, 0xf7, 0xbf, 0x61, 0x61, 0x9f, 0x01, 0xa1, 0x61, 0x62, 0x42, 0x01,
+        0x02, 0xff, 0x61, 0x63, 0x03, 0xff,
+    ];
+    let a = PathSegment::from(Key::Text("a".to_string()));
+    let b = PathSegment::from(Key::Text("b".to_string()));
+
+    assert_eq!(get_path_bytes(&buf, &[]).unwrap(), Some(&buf[..]));
+    assert_eq!(
+        get_path_bytes(&buf, std::slice::from_ref(&a)).unwrap(),
+        Some(&buf[6..15])
+    );
+    let path = [a.clone(), PathSegment::from(1), b.clone()];
+    assert_eq!(
+        get_path(&buf, &path).unwrap(),
+        Some(Cbor::from_bytes(vec![1, 2]).unwrap())
+    );
+    let path = [PathSegment::from(Key::Text("c".to_string()))];
+    assert_eq!(get_path(&buf, &path).unwrap(), Some(3.into_cbor().unwrap()));
+
+    // missing keys and indices, mismatching segments and scalars.
+    let paths = [
+        vec![b.clone()],
+        vec![PathSegment::Index(0)],
+        vec![a.clone(), PathSegment::Index(2)],
+        vec![a.clone(), b.clone()],
+        vec![a.clone(), PathSegment::Index(0), PathSegment::Index(0)],
+    ];
+    for path in paths.iter() {
+        assert_eq!(get_path(&buf, path).unwrap(), None, "{:?}", path);
+    }
+
+    // items following the selected one are not scanned.
+    let path = [a.clone(), PathSegment::Index(0)];
+    assert_eq!(get_path(&buf[..15], &path).unwrap(), Some(1.into_cbor().unwrap()));
+    let path = [PathSegment::from(Key::Text("c".to_string()))];
+    assert!(get_path(&buf[..15], &path).is_err());
+}
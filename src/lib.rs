@@ -9,23 +9,61 @@
 //! Use `cbordata-derive` package for procedural-macro `Cborize`, can be used on
 //! any struct or enum to implement `IntoCbor` and `FromCbor` trait for the derived
 //! struct or enum. Note that `Cborize` expects an associated constant `ID`. Refer
-//! to [get_cborize_id] for details.
+//! to [get_cborize_id] for details. Procedural-macro `FromCborRef` can be
+//! used on struct types to convert from the borrowed [CborRef] value, where
+//! fields like `&str` and `&[u8]` can refer to the input without copying.
 //!
 //! Features
 //! ========
 //!
 //! * **`arbitrary`** feature must be enabled, for [Cbor] and [Key] types to implement
-//! the `arbitrary::Arbitrary` trait.
+//!   the `arbitrary::Arbitrary` trait.
+//! * **`subtle`** feature must be enabled, for constant-time comparison of
+//!   byte-strings using [Cbor::ct_eq_bytes].
+//! * **`bytes`** feature must be enabled, for decoding from `bytes::Buf`
+//!   using [Cbor::decode_bytes], and for sharing the storage of `bytes::Bytes`
+//!   using [CborRef::to_shared_bytes].
+//! * **`chrono`** feature must be enabled, for `chrono::DateTime<Utc>` to
+//!   implement [IntoCbor] and [FromCbor], as tag-0 and tag-1 timestamps.
+//! * **`serde`** feature must be enabled, for converting types implementing
+//!   `serde::Serialize` and `serde::Deserialize` using [to_cbor] and
+//!   [from_cbor].
+//! * **`async`** feature must be enabled, for encoding and decoding over
+//!   asynchronous streams using `Cbor::encode_async`, `Cbor::decode_async`
+//!   and `Decoder::pull_async`. This feature has no dependencies, streams
+//!   implement the crate's own `AsyncRead` and `AsyncWrite` traits.
+//! * **`futures-io`** feature must be enabled, implies `async`, for passing
+//!   streams implementing `futures_io::{AsyncRead, AsyncWrite}`, wrapped in
+//!   `FuturesIo`.
+//! * **`tokio`** feature must be enabled, implies `async`, for passing
+//!   streams implementing `tokio::io::{AsyncRead, AsyncWrite}`, wrapped in
+//!   `TokioIo`.
+//! * **`ciborium`** feature is only used by tests, for cross-checking the
+//!   encoding of generated values against the `ciborium` implementation.
 //!
 //! [cbor]: https://tools.ietf.org/html/rfc7049
 
 #[cfg(any(feature = "arbitrary", test))]
 extern crate arbitrary;
+#[cfg(feature = "bytes")]
+extern crate bytes;
 extern crate cbordata_derive;
+#[cfg(feature = "chrono")]
+extern crate chrono;
+#[cfg(all(feature = "ciborium", test))]
+extern crate ciborium;
+#[cfg(feature = "futures-io")]
+extern crate futures_io;
 extern crate num_bigint;
 extern crate num_traits;
 #[cfg(test)]
 extern crate rand;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "subtle")]
+extern crate subtle;
+#[cfg(feature = "tokio")]
+extern crate tokio;
 
 use std::{error, fmt, result};
 
@@ -75,15 +113,70 @@ macro_rules! err_at {
     }};
 }
 
+/// Decode a `Cborize` value from bytes, whose type is one of the listed
+/// types, going by the type's `ID`.
+///
+/// The decoded value is converted into the target type using [Into], like
+/// an enum wrapping each of the listed types. Evaluates to
+/// `Result<Target>`, failing with [Error::FailConvert] listing the tried
+/// IDs when none of the types match. Refer to [get_cborize_id].
+///
+/// ```ignore
+/// let msg: Result<Message> = try_decode_any!(&buf, [Ping, Pong, Data]);
+/// ```
+#[macro_export]
+macro_rules! try_decode_any {
+    ($buf:expr, [$($t:ty),+ $(,)?]) => {{
+        let buf = $buf;
+        let buf: &[u8] = ::std::convert::AsRef::<[u8]>::as_ref(&buf);
+        match $crate::Cbor::decode(&mut &buf[..]) {
+            Ok((val, _)) => {
+                let id = $crate::get_cborize_id(&val);
+                let (mut val, mut tried, mut res) = (Some(val), vec![], None);
+                $(
+                    if res.is_none() {
+                        match $crate::IntoCbor::into_cbor(<$t>::ID) {
+                            Ok(tid) => {
+                                let tid: $crate::Cbor = $crate::Tag::from_identifier(tid).into();
+                                if id.as_ref() == Some(&tid) {
+                                    let val = val.take().unwrap();
+                                    res = Some(
+                                        <$t as $crate::FromCbor>::from_cbor(val)
+                                            .map(::std::convert::Into::into),
+                                    );
+                                } else {
+                                    tried.push(tid);
+                                }
+                            }
+                            Err(err) => res = Some(Err(err)),
+                        }
+                    }
+                )+
+                match res {
+                    Some(res) => res,
+                    None => Err($crate::Error::FailConvert(
+                        format!("{}:{}", file!(), line!()),
+                        format!("no type for id {:?}, tried {:?}", id, tried),
+                    )),
+                }
+            }
+            Err(err) => Err(err),
+        }
+    }};
+}
+
 /// Error variants that can be returned by this package's API.
 ///
 /// Each variant carries a prefix, typically identifying the
-/// error location.
+/// error location. `LimitExceeded` is returned when decoding exceeds a
+/// resource limit, refer to [DecodeConfig], as against `FailCbor` for
+/// malformed input.
 pub enum Error {
     Fatal(String, String),
     FailConvert(String, String),
     IOError(String, String),
     FailCbor(String, String),
+    LimitExceeded(String, String),
 }
 
 impl fmt::Display for Error {
@@ -95,6 +188,7 @@ impl fmt::Display for Error {
             FailConvert(p, msg) => write!(f, "{} FailConvert: {}", p, msg),
             IOError(p, msg) => write!(f, "{} IOError: {}", p, msg),
             FailCbor(p, msg) => write!(f, "{} FailCbor: {}", p, msg),
+            LimitExceeded(p, msg) => write!(f, "{} LimitExceeded: {}", p, msg),
         }
     }
 }
@@ -107,13 +201,147 @@ impl fmt::Debug for Error {
 
 impl error::Error for Error {}
 
+impl Error {
+    /// Prepend struct field, or enum variant's field, to the position of
+    /// the error, tracked as `at path <path>` suffix of the error message.
+    /// Used while unwinding from nested conversions, that is, conversion of
+    /// field `age` failing with `"value 300 exceeds u8 at path .age"`, nested
+    /// under array item 3 of field `users` reads as
+    /// `"value 300 exceeds u8 at path .users[3].age"`. Refer to [Error::path].
+    pub fn in_field(self, field: &str) -> Error {
+        self.in_path(&format!(".{}", field))
+    }
+
+    /// Prepend array index to the position of the error, refer to
+    /// [Error::in_field].
+    pub fn in_index(self, index: usize) -> Error {
+        self.in_path(&format!("[{}]", index))
+    }
+
+    /// Prepend map key to the position of the error, text keys are quoted
+    /// and byte-string keys are hex encoded, like `["name"]` and `[h'01ff']`.
+    /// Refer to [Error::in_field].
+    pub fn in_key(self, key: &Key) -> Error {
+        let segment = match key {
+            Key::Bool(val) => format!("[{}]", val),
+            Key::N64(val) => format!("[{}]", val),
+            Key::U64(val) => format!("[{}]", val),
+            Key::F32(val) => format!("[{:?}]", val),
+            Key::F64(val) => format!("[{:?}]", val),
+            Key::Bytes(data) => {
+                let hex: String = data.iter().map(|b| format!("{:02x}", b)).collect();
+                format!("[h'{}']", hex)
+            }
+            Key::Text(text) => format!("[{:?}]", text),
+        };
+        self.in_path(&segment)
+    }
+
+    /// Return the position of the error, if tracked by [Error::in_field],
+    /// [Error::in_index] or [Error::in_key].
+    pub fn path(&self) -> Option<&str> {
+        use Error::*;
+
+        let msg = match self {
+            Fatal(_, msg) | FailConvert(_, msg) | IOError(_, msg) => msg,
+            FailCbor(_, msg) | LimitExceeded(_, msg) => msg,
+        };
+        msg.find(PATH_MARKER).map(|off| &msg[off + PATH_MARKER.len()..])
+    }
+
+    /// Construct a [Error::FailCbor] value, prefixed with the caller's
+    /// source location, same as errors returned by this package. Useful for
+    /// downstream implementations of [FromCbor] and [IntoCbor].
+    #[track_caller]
+    pub fn fail_cbor<S: ToString>(msg: S) -> Error {
+        Error::FailCbor(caller_prefix(), msg.to_string())
+    }
+
+    /// Construct a [Error::FailConvert] value, prefixed with the caller's
+    /// source location, refer to [Error::fail_cbor].
+    #[track_caller]
+    pub fn fail_convert<S: ToString>(msg: S) -> Error {
+        Error::FailConvert(caller_prefix(), msg.to_string())
+    }
+
+    // Path is the suffix following the first PATH_MARKER in the message,
+    // segments are prepended to it. Map keys can hold the marker, but only
+    // after the first one.
+    fn in_path(self, segment: &str) -> Error {
+        use Error::*;
+
+        let with_path = |msg: String| match msg.find(PATH_MARKER) {
+            Some(off) => {
+                let (msg, path) = msg.split_at(off + PATH_MARKER.len());
+                format!("{}{}{}", msg, segment, path)
+            }
+            None => format!("{}{}{}", msg, PATH_MARKER, segment),
+        };
+
+        match self {
+            Fatal(p, msg) => Fatal(p, with_path(msg)),
+            FailConvert(p, msg) => FailConvert(p, with_path(msg)),
+            IOError(p, msg) => IOError(p, with_path(msg)),
+            FailCbor(p, msg) => FailCbor(p, with_path(msg)),
+            LimitExceeded(p, msg) => LimitExceeded(p, with_path(msg)),
+        }
+    }
+}
+
+// Separates the error message from the error's position, refer to
+// [Error::in_field].
+const PATH_MARKER: &str = " at path ";
+
+#[track_caller]
+fn caller_prefix() -> String {
+    let loc = std::panic::Location::caller();
+    format!("{}:{}", loc.file(), loc.line())
+}
+
 #[doc(hidden)]
 pub use cbordata_derive::*;
 
+#[cfg(feature = "async")]
+mod aio;
+mod borrowed;
+mod buffer;
 mod cbor;
+#[cfg(feature = "serde")]
+mod de;
+mod decoder;
+mod diag;
+mod lazy;
+#[cfg(feature = "serde")]
+mod ser;
+mod tags;
 mod types;
+mod walk;
 
-pub use cbor::{pretty_print, Cbor, Info, Key, SimpleValue, Tag, RECURSION_LIMIT};
+#[cfg(feature = "futures-io")]
+pub use aio::FuturesIo;
+#[cfg(feature = "tokio")]
+pub use aio::TokioIo;
+#[cfg(feature = "async")]
+pub use aio::{AsyncRead, AsyncWrite, DecodeFuture, EncodeFuture, PullFuture};
+pub use borrowed::{CborRef, FromCborRef};
+pub use buffer::CborBuffer;
+pub use cbor::{
+    encode_canonical_map, encode_indefinite_array, encode_indefinite_map, is_canonical,
+    pretty_print, pretty_print_with, Cbor, CborStats, DecodeConfig, DecodeMetrics,
+    DuplicateKeyPolicy, Embedded, ExpectedEncoding, Info, Key, KeyEquality, LossyText,
+    MapConfig, MapOrder, PrintConfig, SimpleValue, Tag, UnknownTagPolicy,
+    RECURSION_LIMIT,
+};
+#[cfg(feature = "serde")]
+pub use de::from_cbor;
+pub use decoder::{
+    decode_all_lenient, decode_all_lenient_with, read_frame, read_frame_with, Decoder,
+};
+pub use diag::{diagnostic, dump, from_diagnostic, JsonConfig, UnsafeIntegers};
+pub use lazy::{get_path, get_path_bytes, LazyCbor, PathSegment};
+#[cfg(feature = "serde")]
+pub use ser::to_cbor;
+pub use walk::{Visitor, VisitorMut};
 
 /// Get unique ID associated with user-defined type.
 ///
@@ -129,6 +357,44 @@ pub fn get_cborize_id(val: &Cbor) -> Option<Cbor> {
     }
 }
 
+/// Remove the first entry keyed by text `name` from `items`, used by
+/// `Cborize` generated code for `repr = "named_map"`.
+#[doc(hidden)]
+pub fn take_named_field(items: &mut Vec<(Key, Cbor)>, name: &str) -> Result<Cbor> {
+    let name = Key::Text(name.to_string());
+    match items.iter().position(|(key, _)| key == &name) {
+        Some(off) => Ok(items.remove(off).1),
+        None => err_at!(FailConvert, msg: "missing field {:?}", name),
+    }
+}
+
+/// Match the first item of `data_id`, wrapped in identifier tag, with the
+/// first item of type's `id`, both being lists. Return the unwrapped
+/// `data_id` if they match. Used by `Cborize` generated code for
+/// `id_match = "prefix"`.
+#[doc(hidden)]
+pub fn match_cborize_id_prefix(data_id: Cbor, id: &Cbor) -> Option<Cbor> {
+    let data_id = match data_id {
+        Cbor::Major6(_, Tag::Identifier(data_id)) => *data_id,
+        _ => return None,
+    };
+    match (&data_id, id) {
+        (Cbor::Major4(_, xs), Cbor::Major4(_, ys)) => match (xs.first(), ys.first()) {
+            (Some(x), Some(y)) if x == y => Some(data_id),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Check whether `val` is the `ID` of a type, used by `FromCborRef` generated
+/// code.
+#[doc(hidden)]
+pub fn is_cborize_id_ref(val: &CborRef, id: Cbor) -> bool {
+    let id: Cbor = Tag::from_identifier(id).into();
+    val.to_cbor() == id
+}
+
 /// Convert rust-native value to [Cbor], which can then be encoded into bytes
 /// using Cbor.
 ///
@@ -145,8 +411,25 @@ pub trait IntoCbor {
 pub trait FromCbor: Sized {
     /// Convert value from [Cbor] into type's value.
     fn from_cbor(val: Cbor) -> Result<Self>;
+
+    /// Same as [FromCbor::from_cbor], applying coercions enabled in `config`,
+    /// refer to `coerce_bool` and `coerce_map_keys` in [DecodeConfig]. Only
+    /// the target types that the coercion is meant for are affected, and
+    /// containers pass `config` on to their items. Default implementation
+    /// ignores `config`.
+    fn from_cbor_with(val: Cbor, _config: &DecodeConfig) -> Result<Self> {
+        Self::from_cbor(val)
+    }
 }
 
 /// Result type, for jsondata functions and methods, that require a
 /// success or failure variant.
 pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+#[path = "lib_test.rs"]
+mod lib_test;
+
+#[cfg(test)]
+#[path = "derive_test.rs"]
+mod derive_test;
@@ -0,0 +1,149 @@
+use super::*;
+
+#[test]
+fn test_error_constructors() {
+    let (err, line) = (Error::fail_cbor("bad header"), line!());
+    match err {
+        Error::FailCbor(prefix, msg) => {
+            assert_eq!(prefix, format!("{}:{}", file!(), line));
+            assert_eq!(msg, "bad header");
+        }
+        err => panic!("{:?}", err),
+    }
+
+    let (err, line) = (Error::fail_convert(format!("value {} exceeds u8", 300)), line!());
+    match err.in_field("age") {
+        Error::FailConvert(prefix, msg) => {
+            assert_eq!(prefix, format!("{}:{}", file!(), line));
+            assert_eq!(msg, "value 300 exceeds u8 at path .age");
+        }
+        err => panic!("{:?}", err),
+    }
+
+    // same prefix format as err_at!.
+    let (res, line): (Result<()>, u32) = (err_at!(FailCbor, msg: "x"), line!());
+    match res {
+        Err(Error::FailCbor(prefix, _)) => {
+            assert_eq!(prefix, format!("{}:{}", file!(), line))
+        }
+        res => panic!("{:?}", res),
+    }
+}
+
+#[test]
+fn test_error_path() {
+    // messages holding " at " are not mistaken for a path.
+    let err = Error::fail_convert("invalid text at [0x80");
+    assert_eq!(err.path(), None);
+    let err = err.in_index(2).in_field("names");
+    assert_eq!(err.path(), Some(".names[2]"));
+    match err {
+        Error::FailConvert(_, msg) => {
+            assert_eq!(msg, "invalid text at [0x80 at path .names[2]")
+        }
+        err => panic!("{:?}", err),
+    }
+
+    // keys can be any text, including the path marker.
+    let err = Error::fail_convert("bad value")
+        .in_key(&Key::Text("a at path b".to_string()))
+        .in_key(&Key::Bytes(vec![0x01, 0xff]))
+        .in_key(&Key::N64(-2))
+        .in_field("map");
+    assert_eq!(err.path(), Some(r#".map[-2][h'01ff']["a at path b"]"#));
+    match err {
+        Error::FailConvert(_, msg) => assert!(msg.starts_with("bad value at path ")),
+        err => panic!("{:?}", err),
+    }
+}
+
+#[test]
+fn test_try_decode_any() {
+    #[derive(Debug, Clone, PartialEq, LocalCborize)]
+    struct Ping {
+        seqno: u64,
+    }
+    impl Ping {
+        const ID: &'static str = "ping";
+    }
+
+    #[derive(Debug, Clone, PartialEq, LocalCborize)]
+    struct Pong {
+        seqno: u64,
+    }
+    impl Pong {
+        const ID: &'static str = "pong";
+    }
+
+    #[derive(Debug, Clone, PartialEq, LocalCborize)]
+    struct Data {
+        payload: String,
+    }
+    impl Data {
+        const ID: u64 = 3;
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Message {
+        Ping(Ping),
+        Pong(Pong),
+        Data(Data),
+    }
+    impl From<Ping> for Message {
+        fn from(val: Ping) -> Message {
+            Message::Ping(val)
+        }
+    }
+    impl From<Pong> for Message {
+        fn from(val: Pong) -> Message {
+            Message::Pong(val)
+        }
+    }
+    impl From<Data> for Message {
+        fn from(val: Data) -> Message {
+            Message::Data(val)
+        }
+    }
+
+    let encode = |val: Cbor| {
+        let mut buf = vec![];
+        val.encode(&mut buf).unwrap();
+        buf
+    };
+
+    let msgs = vec![
+        Message::Pong(Pong { seqno: 2 }),
+        Message::Ping(Ping { seqno: 1 }),
+        Message::Data(Data { payload: "hello".to_string() }),
+    ];
+    for msg in msgs.into_iter() {
+        let buf = match &msg {
+            Message::Ping(val) => encode(val.clone().into_cbor().unwrap()),
+            Message::Pong(val) => encode(val.clone().into_cbor().unwrap()),
+            Message::Data(val) => encode(val.clone().into_cbor().unwrap()),
+        };
+        let res: Result<Message> = try_decode_any!(buf, [Ping, Pong, Data]);
+        assert_eq!(res.unwrap(), msg);
+    }
+
+    #[derive(Debug, Clone, PartialEq, LocalCborize)]
+    struct Other {
+        seqno: u64,
+    }
+    impl Other {
+        const ID: &'static str = "other";
+    }
+
+    let buf = encode(Other { seqno: 1 }.into_cbor().unwrap());
+    let res: Result<Message> = try_decode_any!(&buf, [Ping, Pong, Data]);
+    match res {
+        Err(Error::FailConvert(_, msg)) => {
+            assert!(msg.starts_with("no type for id"), "{}", msg);
+            assert_eq!(msg.matches("Identifier").count(), 4, "{}", msg);
+        }
+        res => panic!("{:?}", res),
+    }
+
+    let res: Result<Message> = try_decode_any!(vec![0x1c], [Ping, Pong, Data]);
+    assert!(res.is_err());
+}
@@ -0,0 +1,367 @@
+//! Module implement serde's Serializer, converting serde types into Cbor.
+
+use serde::ser::{self, Serialize};
+
+use std::fmt;
+
+use crate::{Cbor, Error, FromCbor, IntoCbor, Key, Result};
+
+/// Convert `val`, of type implementing `serde::Serialize`, into [Cbor].
+///
+/// Types are mapped on to cbor as:
+///
+/// * unit, unit structs and `None` as null, while `Some(val)` and newtype
+///   structs as the inner value.
+/// * `i128` and `u128` as integers if they fit within 64-bit, otherwise as
+///   big-num.
+/// * sequences, tuples and tuple structs as arrays.
+/// * maps as maps, where keys must convert to [Key], and structs as maps
+///   keyed by field names as text.
+/// * unit variants as variant name in text, other variants as a map with
+///   single entry, keyed by variant name, like `{"Rect": [3, 4]}`.
+///
+/// Refer to [crate::from_cbor] for the reverse transformation.
+pub fn to_cbor<T>(val: &T) -> Result<Cbor>
+where
+    T: Serialize + ?Sized,
+{
+    val.serialize(Serializer)
+}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Error {
+        Error::FailConvert(format!("{}:{}", file!(), line!()), msg.to_string())
+    }
+}
+
+struct Serializer;
+
+impl ser::Serializer for Serializer {
+    type Ok = Cbor;
+    type Error = Error;
+
+    type SerializeSeq = SerializeVec;
+    type SerializeTuple = SerializeVec;
+    type SerializeTupleStruct = SerializeVec;
+    type SerializeTupleVariant = SerializeTupleVariant;
+    type SerializeMap = SerializeMap;
+    type SerializeStruct = SerializeMap;
+    type SerializeStructVariant = SerializeStructVariant;
+
+    fn serialize_bool(self, val: bool) -> Result<Cbor> {
+        val.into_cbor()
+    }
+
+    fn serialize_i8(self, val: i8) -> Result<Cbor> {
+        val.into_cbor()
+    }
+
+    fn serialize_i16(self, val: i16) -> Result<Cbor> {
+        val.into_cbor()
+    }
+
+    fn serialize_i32(self, val: i32) -> Result<Cbor> {
+        val.into_cbor()
+    }
+
+    fn serialize_i64(self, val: i64) -> Result<Cbor> {
+        val.into_cbor()
+    }
+
+    fn serialize_i128(self, val: i128) -> Result<Cbor> {
+        val.into_cbor()
+    }
+
+    fn serialize_u8(self, val: u8) -> Result<Cbor> {
+        val.into_cbor()
+    }
+
+    fn serialize_u16(self, val: u16) -> Result<Cbor> {
+        val.into_cbor()
+    }
+
+    fn serialize_u32(self, val: u32) -> Result<Cbor> {
+        val.into_cbor()
+    }
+
+    fn serialize_u64(self, val: u64) -> Result<Cbor> {
+        val.into_cbor()
+    }
+
+    fn serialize_u128(self, val: u128) -> Result<Cbor> {
+        val.into_cbor()
+    }
+
+    fn serialize_f32(self, val: f32) -> Result<Cbor> {
+        val.into_cbor()
+    }
+
+    fn serialize_f64(self, val: f64) -> Result<Cbor> {
+        val.into_cbor()
+    }
+
+    fn serialize_char(self, val: char) -> Result<Cbor> {
+        val.to_string().into_cbor()
+    }
+
+    fn serialize_str(self, val: &str) -> Result<Cbor> {
+        val.into_cbor()
+    }
+
+    fn serialize_bytes(self, val: &[u8]) -> Result<Cbor> {
+        val.into_cbor()
+    }
+
+    fn serialize_none(self) -> Result<Cbor> {
+        Ok(Cbor::null())
+    }
+
+    fn serialize_some<T>(self, val: &T) -> Result<Cbor>
+    where
+        T: Serialize + ?Sized,
+    {
+        val.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Cbor> {
+        Ok(Cbor::null())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Cbor> {
+        Ok(Cbor::null())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<Cbor> {
+        variant.into_cbor()
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, val: &T) -> Result<Cbor>
+    where
+        T: Serialize + ?Sized,
+    {
+        val.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        val: &T,
+    ) -> Result<Cbor>
+    where
+        T: Serialize + ?Sized,
+    {
+        variant_map(variant, val.serialize(self)?)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeVec> {
+        let items = Vec::with_capacity(len.unwrap_or(0));
+        Ok(SerializeVec { items })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeVec> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SerializeVec> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeTupleVariant> {
+        let items = Vec::with_capacity(len);
+        Ok(SerializeTupleVariant { variant, items })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<SerializeMap> {
+        let entries = Vec::with_capacity(len.unwrap_or(0));
+        Ok(SerializeMap { entries, key: None })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<SerializeMap> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeStructVariant> {
+        let entries = Vec::with_capacity(len);
+        Ok(SerializeStructVariant { variant, entries })
+    }
+}
+
+fn variant_map(variant: &str, val: Cbor) -> Result<Cbor> {
+    vec![(Key::Text(variant.to_string()), val)].into_cbor()
+}
+
+struct SerializeVec {
+    items: Vec<Cbor>,
+}
+
+impl ser::SerializeSeq for SerializeVec {
+    type Ok = Cbor;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, val: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.items.push(val.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Cbor> {
+        self.items.into_cbor()
+    }
+}
+
+impl ser::SerializeTuple for SerializeVec {
+    type Ok = Cbor;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, val: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        ser::SerializeSeq::serialize_element(self, val)
+    }
+
+    fn end(self) -> Result<Cbor> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeVec {
+    type Ok = Cbor;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, val: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        ser::SerializeSeq::serialize_element(self, val)
+    }
+
+    fn end(self) -> Result<Cbor> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+struct SerializeTupleVariant {
+    variant: &'static str,
+    items: Vec<Cbor>,
+}
+
+impl ser::SerializeTupleVariant for SerializeTupleVariant {
+    type Ok = Cbor;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, val: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.items.push(val.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Cbor> {
+        variant_map(self.variant, self.items.into_cbor()?)
+    }
+}
+
+struct SerializeMap {
+    entries: Vec<(Key, Cbor)>,
+    key: Option<Key>,
+}
+
+impl ser::SerializeMap for SerializeMap {
+    type Ok = Cbor;
+    type Error = Error;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.key = Some(Key::from_cbor(key.serialize(Serializer)?)?);
+        Ok(())
+    }
+
+    fn serialize_value<T>(&mut self, val: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        match self.key.take() {
+            Some(key) => self.entries.push((key, val.serialize(Serializer)?)),
+            None => err_at!(Fatal, msg: "serialize_value before serialize_key")?,
+        }
+        Ok(())
+    }
+
+    fn end(self) -> Result<Cbor> {
+        self.entries.into_cbor()
+    }
+}
+
+impl ser::SerializeStruct for SerializeMap {
+    type Ok = Cbor;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, val: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        let key = Key::Text(key.to_string());
+        self.entries.push((key, val.serialize(Serializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Cbor> {
+        self.entries.into_cbor()
+    }
+}
+
+struct SerializeStructVariant {
+    variant: &'static str,
+    entries: Vec<(Key, Cbor)>,
+}
+
+impl ser::SerializeStructVariant for SerializeStructVariant {
+    type Ok = Cbor;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, val: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        let key = Key::Text(key.to_string());
+        self.entries.push((key, val.serialize(Serializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Cbor> {
+        variant_map(self.variant, self.entries.into_cbor()?)
+    }
+}
+
+#[cfg(test)]
+#[path = "ser_test.rs"]
+mod ser_test;
@@ -0,0 +1,100 @@
+use serde::Serialize;
+
+use std::collections::BTreeMap;
+
+use super::*;
+
+#[test]
+fn test_to_cbor() {
+    #[derive(Serialize)]
+    struct Unit;
+
+    #[derive(Serialize)]
+    struct Meters(u32);
+
+    #[derive(Serialize)]
+    struct Pair(u8, String);
+
+    #[derive(Serialize)]
+    enum Shape {
+        Empty,
+        Circle(u32),
+        Rect(u32, u32),
+        Line { len: u32 },
+    }
+
+    #[derive(Serialize)]
+    struct Record {
+        name: String,
+        age: Option<u8>,
+        #[serde(with = "serde_bytes_like")]
+        blob: Vec<u8>,
+        shapes: Vec<Shape>,
+    }
+
+    mod serde_bytes_like {
+        pub fn serialize<S: serde::Serializer>(
+            val: &[u8],
+            s: S,
+        ) -> Result<S::Ok, S::Error> {
+            s.serialize_bytes(val)
+        }
+    }
+
+    let text = |s: &str| s.into_cbor().unwrap();
+    let map = |entries: Vec<(&str, Cbor)>| -> Cbor {
+        let entries: Vec<(Key, Cbor)> =
+            entries.into_iter().map(|(k, v)| (Key::Text(k.to_string()), v)).collect();
+        entries.into_cbor().unwrap()
+    };
+
+    assert_eq!(to_cbor(&Unit).unwrap(), Cbor::null());
+    assert_eq!(to_cbor(&()).unwrap(), Cbor::null());
+    assert_eq!(to_cbor(&Meters(10)).unwrap(), 10_u64.into_cbor().unwrap());
+    assert_eq!(to_cbor(&'x').unwrap(), text("x"));
+    assert_eq!(to_cbor(&-5_i32).unwrap(), (-5_i64).into_cbor().unwrap());
+    assert_eq!(to_cbor(&u128::MAX).unwrap(), u128::MAX.into_cbor().unwrap());
+    assert_eq!(
+        to_cbor(&Pair(1, "a".to_string())).unwrap(),
+        vec![1_u64.into_cbor().unwrap(), text("a")].into_cbor().unwrap()
+    );
+
+    let val = Record {
+        name: "x".to_string(),
+        age: None,
+        blob: vec![1, 2],
+        shapes: vec![
+            Shape::Empty,
+            Shape::Circle(3),
+            Shape::Rect(1, 2),
+            Shape::Line { len: 4 },
+        ],
+    };
+    let shapes = vec![
+        text("Empty"),
+        map(vec![("Circle", 3_u64.into_cbor().unwrap())]),
+        map(vec![("Rect", vec![1_u64, 2].into_cbor().unwrap())]),
+        map(vec![("Line", map(vec![("len", 4_u64.into_cbor().unwrap())]))]),
+    ];
+    let refv = map(vec![
+        ("name", text("x")),
+        ("age", Cbor::null()),
+        ("blob", Cbor::from_bytes(vec![1, 2]).unwrap()),
+        ("shapes", shapes.into_cbor().unwrap()),
+    ]);
+    assert_eq!(to_cbor(&val).unwrap(), refv);
+
+    let mut val: BTreeMap<i32, bool> = BTreeMap::new();
+    val.insert(-1, true);
+    val.insert(2, false);
+    let refv: Vec<(Key, Cbor)> = vec![
+        (Key::N64(-1), true.into_cbor().unwrap()),
+        (Key::U64(2), false.into_cbor().unwrap()),
+    ];
+    assert_eq!(to_cbor(&val).unwrap(), refv.into_cbor().unwrap());
+
+    // map keys must be valid cbor keys.
+    let mut val: BTreeMap<Vec<u8>, u8> = BTreeMap::new();
+    val.insert(vec![1], 1);
+    assert!(to_cbor(&val).is_err());
+}
@@ -0,0 +1,332 @@
+//! Module implement typed constructors and validation for standard tags.
+
+use std::{
+    convert::TryFrom,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    Cbor, Error, ExpectedEncoding, FromCbor, IntoCbor, Result, SimpleValue, Tag,
+};
+
+impl Tag {
+    /// Construct tag 0, RFC3339 date/time in UTC, like
+    /// `"2013-03-21T20:04:00Z"`. Sub-second part, if any, is encoded with
+    /// nano-second precision, without trailing zeros.
+    pub fn from_datetime(time: SystemTime) -> Result<Tag> {
+        let (secs, nanos) = to_epoch(time)?;
+        let (days, secs) = (secs.div_euclid(86400), secs.rem_euclid(86400));
+        let (year, month, day) = civil_from_days(days);
+        if !(0..=9999).contains(&year) {
+            err_at!(FailConvert, msg: "year {} not supported by RFC3339", year)?
+        }
+
+        let (hour, minute, second) = (secs / 3600, (secs / 60) % 60, secs % 60);
+        let mut text = format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            year, month, day, hour, minute, second
+        );
+        if nanos > 0 {
+            let frac = format!("{:09}", nanos);
+            text.push('.');
+            text.push_str(frac.trim_end_matches('0'));
+        }
+        text.push('Z');
+
+        Ok(Tag::DateTime(Box::new(text.into_cbor()?)))
+    }
+
+    /// Construct tag 1, epoch-based date/time, as integer seconds when there
+    /// is no sub-second part, and as floating-point seconds otherwise.
+    /// Floating-point seconds preserve about a micro-second, for present day
+    /// times, use [Tag::from_datetime] to preserve nano-seconds.
+    pub fn from_epoch(time: SystemTime) -> Result<Tag> {
+        let val = match to_epoch(time)? {
+            (secs, 0) => secs.into_cbor()?,
+            (secs, nanos) => (secs as f64 + f64::from(nanos) / 1e9).into_cbor()?,
+        };
+        Ok(Tag::Epoch(Box::new(val)))
+    }
+
+    /// Convert tag 0, RFC3339 date/time, or tag 1, epoch-based date/time,
+    /// into `SystemTime`. Leap seconds cannot be represented and shall fail
+    /// the conversion.
+    pub fn to_system_time(&self) -> Result<SystemTime> {
+        let (secs, nanos) = match self {
+            Tag::DateTime(val) => match val.as_ref() {
+                Cbor::Major3(_, text) => match std::str::from_utf8(text) {
+                    Ok(text) => parse_rfc3339(text)?,
+                    Err(err) => err_at!(FailConvert, msg: "{}", err)?,
+                },
+                _ => err_at!(FailConvert, msg: "tag 0 expects a text-string")?,
+            },
+            Tag::Epoch(val) => match val.as_ref() {
+                Cbor::Major7(_, sval) => match sval.as_f64() {
+                    Some(val) => from_epoch_float(val)?,
+                    None => err_at!(FailConvert, msg: "tag 1 expects a number")?,
+                },
+                val => (i64::from_cbor(val.clone())?, 0),
+            },
+            _ => err_at!(FailConvert, msg: "not a tag-0/tag-1 date/time")?,
+        };
+
+        let time = match u64::try_from(secs) {
+            Ok(secs) => UNIX_EPOCH.checked_add(Duration::new(secs, nanos)),
+            Err(_) => UNIX_EPOCH
+                .checked_sub(Duration::from_secs(secs.unsigned_abs()))
+                .and_then(|t| t.checked_add(Duration::from_nanos(nanos.into()))),
+        };
+        match time {
+            Some(time) => Ok(time),
+            None => err_at!(FailConvert, msg: "epoch {}.{} out of range", secs, nanos),
+        }
+    }
+
+    /// Construct tag 4, decimal fraction, for value `mantissa * 10^exponent`.
+    pub fn from_decimal_fraction(exponent: i64, mantissa: i128) -> Result<Tag> {
+        let val = vec![exponent.into_cbor()?, mantissa.into_cbor()?];
+        Ok(Tag::DecimalFraction(Box::new(val.into_cbor()?)))
+    }
+
+    /// Construct tag 5, big-float, for value `mantissa * 2^exponent`.
+    pub fn from_bigfloat(exponent: i64, mantissa: i128) -> Result<Tag> {
+        let val = vec![exponent.into_cbor()?, mantissa.into_cbor()?];
+        Ok(Tag::BigFloat(Box::new(val.into_cbor()?)))
+    }
+
+    /// Return the exponent and mantissa of tag 4, decimal fraction, or
+    /// tag 5, big-float. Mantissa that does not fit within `i128` shall fail
+    /// the conversion.
+    pub fn to_exponent_mantissa(&self) -> Result<(i64, i128)> {
+        match self {
+            Tag::DecimalFraction(val) | Tag::BigFloat(val) => match val.as_ref() {
+                Cbor::Major4(_, items) if items.len() == 2 => {
+                    let exponent = i64::from_cbor(items[0].clone())?;
+                    let mantissa = i128::from_cbor(items[1].clone())?;
+                    Ok((exponent, mantissa))
+                }
+                _ => err_at!(FailConvert, msg: "expected [exponent, mantissa]"),
+            },
+            _ => err_at!(FailConvert, msg: "not a tag-4/tag-5 fraction"),
+        }
+    }
+
+    /// Construct tag 21, 22 or 23, hinting the expected text encoding of
+    /// byte-strings nested under `value`.
+    pub fn from_expected_encoding(enc: ExpectedEncoding, value: Cbor) -> Tag {
+        Tag::Expected(enc, Box::new(value))
+    }
+
+    /// Construct tag 32, URI. `uri` is not validated against RFC3986.
+    pub fn from_uri(uri: &str) -> Result<Tag> {
+        Ok(Tag::Uri(Box::new(uri.into_cbor()?)))
+    }
+
+    /// Construct tag 55799, self-described cbor, wrapping `value`.
+    pub fn from_self_described(value: Cbor) -> Tag {
+        Tag::SelfDescribed(Box::new(value))
+    }
+
+    // Validate the tagged value for standard tags, while decoding.
+    pub(crate) fn validate(&self) -> Result<()> {
+        match self {
+            Tag::DateTime(val) | Tag::Uri(val) => match val.as_ref() {
+                Cbor::Major3(_, _) => Ok(()),
+                _ => {
+                    let num = self.to_tag_value();
+                    err_at!(FailCbor, msg: "tag {} expects a text-string", num)
+                }
+            },
+            Tag::Epoch(val) => match val.as_ref() {
+                Cbor::Major0(_, _) | Cbor::Major1(_, _) => Ok(()),
+                Cbor::Major7(_, sval) if sval.as_f64().is_some() => Ok(()),
+                _ => err_at!(FailCbor, msg: "tag 1 expects a number"),
+            },
+            Tag::UBigNum(val) | Tag::SBigNum(val) => match val.as_ref() {
+                Cbor::Major2(_, _) => Ok(()),
+                _ => {
+                    let num = self.to_tag_value();
+                    err_at!(FailCbor, msg: "tag {} expects a byte-string", num)
+                }
+            },
+            Tag::DecimalFraction(val) | Tag::BigFloat(val) => {
+                let ok = match val.as_ref() {
+                    Cbor::Major4(_, items) if items.len() == 2 => {
+                        matches!(items[0], Cbor::Major0(_, _) | Cbor::Major1(_, _))
+                            && matches!(
+                                items[1],
+                                Cbor::Major0(_, _)
+                                    | Cbor::Major1(_, _)
+                                    | Cbor::Major6(_, Tag::UBigNum(_) | Tag::SBigNum(_))
+                            )
+                    }
+                    _ => false,
+                };
+                if ok {
+                    Ok(())
+                } else {
+                    let num = self.to_tag_value();
+                    err_at!(FailCbor, msg: "tag {} expects [exponent, mantissa]", num)
+                }
+            }
+            // embedded value is decoded by the caller, within its limits.
+            Tag::EncodedCbor(val) => match val.as_ref() {
+                Cbor::Major2(_, _) => Ok(()),
+                _ => err_at!(FailCbor, msg: "tag 24 expects a byte-string"),
+            },
+            Tag::Expected(_, _)
+            | Tag::Identifier(_)
+            | Tag::SelfDescribed(_)
+            | Tag::Value(_, _) => Ok(()),
+        }
+    }
+}
+
+impl SimpleValue {
+    // Return floating point values as f64.
+    pub(crate) fn as_f64(&self) -> Option<f64> {
+        match self {
+            SimpleValue::F16(bits) => Some(crate::cbor::f16_to_f64(*bits)),
+            SimpleValue::F32(val) => Some(f64::from(*val)),
+            SimpleValue::F64(val) => Some(*val),
+            _ => None,
+        }
+    }
+}
+
+// Return seconds and sub-second nanos relative to UNIX_EPOCH, where
+// nanos are always positive.
+fn to_epoch(time: SystemTime) -> Result<(i64, u32)> {
+    let (secs, nanos, neg) = match time.duration_since(UNIX_EPOCH) {
+        Ok(dur) => (dur.as_secs(), dur.subsec_nanos(), false),
+        Err(err) => (err.duration().as_secs(), err.duration().subsec_nanos(), true),
+    };
+    let secs = match i64::try_from(secs) {
+        Ok(secs) => secs,
+        Err(_) => err_at!(FailConvert, msg: "time {:?} out of range", time)?,
+    };
+    match (neg, nanos) {
+        (false, nanos) => Ok((secs, nanos)),
+        (true, 0) => Ok((-secs, 0)),
+        (true, nanos) => Ok((-secs - 1, 1_000_000_000 - nanos)),
+    }
+}
+
+// Split floating-point epoch seconds into seconds and sub-second nanos,
+// nanos rounding up to a full second carry over into seconds.
+pub(crate) fn from_epoch_float(val: f64) -> Result<(i64, u32)> {
+    let secs = val.floor();
+    // i64::MAX is not exactly representable as f64, compare against 2^63.
+    if !(-9.223_372_036_854_776e18..9.223_372_036_854_776e18).contains(&secs) {
+        err_at!(FailConvert, msg: "epoch {} out of range", val)?
+    }
+    let (secs, nanos) = (secs as i64, ((val - secs) * 1e9).round() as u32);
+    match nanos {
+        1_000_000_000 => match secs.checked_add(1) {
+            Some(secs) => Ok((secs, 0)),
+            None => err_at!(FailConvert, msg: "epoch {} out of range", val),
+        },
+        nanos => Ok((secs, nanos)),
+    }
+}
+
+// Parse RFC3339 date/time, like `2013-03-21T20:04:00.5+05:30`, into seconds
+// and sub-second nanos relative to UNIX_EPOCH.
+fn parse_rfc3339(text: &str) -> Result<(i64, u32)> {
+    let num = |from: usize, till: usize| -> Result<i64> {
+        match text.get(from..till) {
+            Some(s) if s.bytes().all(|b| b.is_ascii_digit()) => Ok(s.parse().unwrap()),
+            _ => err_at!(FailConvert, msg: "bad date/time {:?}", text),
+        }
+    };
+    let sep = |off: usize, seps: &[u8]| -> Result<()> {
+        match text.as_bytes().get(off) {
+            Some(b) if seps.contains(b) => Ok(()),
+            _ => err_at!(FailConvert, msg: "bad date/time {:?}", text),
+        }
+    };
+
+    let (year, month, day) = (num(0, 4)?, num(5, 7)?, num(8, 10)?);
+    let (hour, minute, second) = (num(11, 13)?, num(14, 16)?, num(17, 19)?);
+    sep(4, b"-")?;
+    sep(7, b"-")?;
+    sep(10, b"Tt")?;
+    sep(13, b":")?;
+    sep(16, b":")?;
+
+    let mut off = 19;
+    let mut nanos = 0;
+    if text.as_bytes().get(off) == Some(&b'.') {
+        let n = text[off + 1..].bytes().take_while(|b| b.is_ascii_digit()).count();
+        if n == 0 {
+            err_at!(FailConvert, msg: "bad date/time {:?}", text)?
+        }
+        let frac = &text[off + 1..off + 1 + n.min(9)];
+        nanos = frac.parse::<u32>().unwrap() * 10_u32.pow(9 - frac.len() as u32);
+        off += n + 1;
+    }
+
+    let offset = match &text[off..] {
+        "Z" | "z" => 0,
+        zone if zone.len() == 6 => {
+            sep(off, b"+-")?;
+            sep(off + 3, b":")?;
+            let offset = num(off + 1, off + 3)? * 3600 + num(off + 4, off + 6)? * 60;
+            match zone.as_bytes()[0] {
+                b'+' => offset,
+                _ => -offset,
+            }
+        }
+        _ => err_at!(FailConvert, msg: "bad date/time {:?}", text)?,
+    };
+
+    if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+        err_at!(FailConvert, msg: "bad date {:?}", text)?
+    } else if hour > 23 || minute > 59 || second > 60 {
+        err_at!(FailConvert, msg: "bad time {:?}", text)?
+    } else if second == 60 {
+        err_at!(FailConvert, msg: "leap second {:?}", text)?
+    }
+
+    let secs =
+        days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second
+            - offset;
+    Ok((secs, nanos))
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Number of days since 1970-01-01 for proleptic Gregorian date.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+// Proleptic Gregorian date for number of days since 1970-01-01.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let doe = days.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+#[path = "tags_test.rs"]
+mod tags_test;
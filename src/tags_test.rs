@@ -0,0 +1,207 @@
+use super::*;
+
+fn roundtrip(buf: &[u8]) -> Cbor {
+    let (val, n) = Cbor::decode(&mut &buf[..]).unwrap();
+    assert_eq!(n, buf.len());
+    let mut out = vec![];
+    val.encode(&mut out).unwrap();
+    assert_eq!(out, buf);
+    val
+}
+
+fn encode(tag: Tag) -> Vec<u8> {
+    let mut buf = vec![];
+    Cbor::from(tag).encode(&mut buf).unwrap();
+    buf
+}
+
+#[test]
+fn test_tag_datetime() {
+    let refs = [
+        (UNIX_EPOCH + Duration::from_secs(1363896240), "2013-03-21T20:04:00Z"),
+        (UNIX_EPOCH + Duration::from_millis(1363896240500), "2013-03-21T20:04:00.5Z"),
+        (UNIX_EPOCH + Duration::from_nanos(1), "1970-01-01T00:00:00.000000001Z"),
+        (UNIX_EPOCH - Duration::from_secs(1), "1969-12-31T23:59:59Z"),
+        (UNIX_EPOCH - Duration::from_millis(1500), "1969-12-31T23:59:58.5Z"),
+        (UNIX_EPOCH + Duration::from_secs(951782400), "2000-02-29T00:00:00Z"),
+    ];
+    for (time, text) in refs.iter() {
+        let tag = Tag::from_datetime(*time).unwrap();
+        assert_eq!(tag.as_content(), &text.into_cbor().unwrap(), "{}", text);
+        assert_eq!(tag.to_system_time().unwrap(), *time, "{}", text);
+
+        let val = roundtrip(&encode(tag.clone()));
+        assert_eq!(val, Cbor::from(tag));
+    }
+
+    let time = UNIX_EPOCH + Duration::from_secs(1363896240);
+    for text in
+        ["2013-03-21T20:04:00Z", "2013-03-22t01:34:00+05:30", "2013-03-21T19:04:00-01:00"]
+            .iter()
+    {
+        let tag = Tag::DateTime(Box::new(text.into_cbor().unwrap()));
+        assert_eq!(tag.to_system_time().unwrap(), time, "{}", text);
+    }
+
+    let texts = [
+        "2016-12-31T23:59:60Z",
+        "2013-02-29T00:00:00Z",
+        "2013-13-01T00:00:00Z",
+        "2013-03-21T24:00:00Z",
+        "2013-03-21T20:04:00",
+        "2013-03-21T20:04:00.Z",
+        "2013-03-21 20:04:00Z",
+        "not a date",
+    ];
+    for text in texts.iter() {
+        let tag = Tag::DateTime(Box::new(text.into_cbor().unwrap()));
+        match tag.to_system_time() {
+            Err(Error::FailConvert(_, _)) => (),
+            res => panic!("{} {:?}", text, res),
+        }
+    }
+
+    let time = UNIX_EPOCH + Duration::from_secs(253402300800); // year 10000
+    assert!(Tag::from_datetime(time).is_err());
+}
+
+#[test]
+fn test_tag_epoch() {
+    let time = UNIX_EPOCH + Duration::from_secs(1363896240);
+    let buf = encode(Tag::from_epoch(time).unwrap());
+    assert_eq!(buf, [0xc1, 0x1a, 0x51, 0x4b, 0x67, 0xb0]);
+    match roundtrip(&buf) {
+        Cbor::Major6(_, tag) => assert_eq!(tag.to_system_time().unwrap(), time),
+        val => panic!("{:?}", val),
+    }
+
+    let time = UNIX_EPOCH + Duration::from_millis(1363896240500);
+    let buf = encode(Tag::from_epoch(time).unwrap());
+    assert_eq!(buf, [0xc1, 0xfb, 0x41, 0xd4, 0x52, 0xd9, 0xec, 0x20, 0x00, 0x00]);
+    match roundtrip(&buf) {
+        Cbor::Major6(_, tag) => assert_eq!(tag.to_system_time().unwrap(), time),
+        val => panic!("{:?}", val),
+    }
+
+    // floating-point seconds lose nano-seconds.
+    let time = UNIX_EPOCH + Duration::new(1_700_000_000, 123_456_789);
+    let etime = Tag::from_epoch(time).unwrap().to_system_time().unwrap();
+    assert_ne!(etime, time);
+    assert_eq!(etime, UNIX_EPOCH + Duration::new(1_700_000_000, 123_456_717));
+    let tag = Tag::from_datetime(time).unwrap();
+    assert_eq!(tag.to_system_time().unwrap(), time);
+
+    let time = UNIX_EPOCH - Duration::from_secs(10);
+    let tag = Tag::from_epoch(time).unwrap();
+    assert_eq!(tag.as_content(), &(-10_i64).into_cbor().unwrap());
+    assert_eq!(tag.to_system_time().unwrap(), time);
+
+    // half precision float
+    let (val, _) = Cbor::decode(&mut [0xc1, 0xf9, 0x3c, 0x00].as_ref()).unwrap();
+    match val {
+        Cbor::Major6(_, tag) => {
+            assert_eq!(tag.to_system_time().unwrap(), UNIX_EPOCH + Duration::from_secs(1))
+        }
+        val => panic!("{:?}", val),
+    }
+
+    let tag = Tag::Epoch(Box::new(f64::NAN.into_cbor().unwrap()));
+    assert!(tag.to_system_time().is_err());
+    let tag = Tag::from_uri("http://x").unwrap();
+    assert!(tag.to_system_time().is_err());
+}
+
+#[test]
+fn test_tag_fraction() {
+    // 273.15
+    let buf = [0xc4, 0x82, 0x21, 0x19, 0x6a, 0xb3];
+    assert_eq!(encode(Tag::from_decimal_fraction(-2, 27315).unwrap()), buf);
+    match roundtrip(&buf) {
+        Cbor::Major6(_, tag @ Tag::DecimalFraction(_)) => {
+            assert_eq!(tag.to_exponent_mantissa().unwrap(), (-2, 27315))
+        }
+        val => panic!("{:?}", val),
+    }
+
+    // 1.5
+    let buf = [0xc5, 0x82, 0x20, 0x03];
+    assert_eq!(encode(Tag::from_bigfloat(-1, 3).unwrap()), buf);
+    match roundtrip(&buf) {
+        Cbor::Major6(_, tag @ Tag::BigFloat(_)) => {
+            assert_eq!(tag.to_exponent_mantissa().unwrap(), (-1, 3))
+        }
+        val => panic!("{:?}", val),
+    }
+
+    for mantissa in [i128::MAX, i128::MIN].iter() {
+        let buf = encode(Tag::from_decimal_fraction(10, *mantissa).unwrap());
+        match roundtrip(&buf) {
+            Cbor::Major6(_, tag) => {
+                assert_eq!(tag.to_exponent_mantissa().unwrap(), (10, *mantissa))
+            }
+            val => panic!("{:?}", val),
+        }
+    }
+
+    assert!(Tag::from_self_described(Cbor::null()).to_exponent_mantissa().is_err());
+}
+
+#[test]
+fn test_tag_registry() {
+    let tag = Tag::from_uri("http://www.example.com").unwrap();
+    let mut buf = vec![0xd8, 0x20, 0x76];
+    buf.extend_from_slice(b"http://www.example.com");
+    assert_eq!(encode(tag.clone()), buf);
+    assert_eq!(roundtrip(&buf), Cbor::from(tag));
+
+    let tag = Tag::from_self_described(vec![1_u64].into_cbor().unwrap());
+    let buf = encode(tag.clone());
+    assert_eq!(buf, [0xd9, 0xd9, 0xf7, 0x81, 0x01]);
+    assert_eq!(roundtrip(&buf), Cbor::from(tag));
+
+    let refs = [
+        (ExpectedEncoding::Base64Url, 21),
+        (ExpectedEncoding::Base64, 22),
+        (ExpectedEncoding::Base16, 23),
+    ];
+    for (enc, num) in refs.iter() {
+        let tag = Tag::from_expected_encoding(*enc, Cbor::from_bytes(vec![1]).unwrap());
+        assert_eq!(tag.to_tag_value(), *num);
+        assert_eq!(tag, Tag::from_value(*num, Cbor::from_bytes(vec![1]).unwrap()));
+        let val = roundtrip(&encode(tag.clone()));
+        assert_eq!(val.as_expected_encoding(), Some(*enc));
+        assert_eq!(val, Cbor::from(tag));
+    }
+
+    // unknown tags round-trip as is.
+    let buf = [0xd9, 0x03, 0xe8, 0x63, 0x61, 0x62, 0x63];
+    match roundtrip(&buf) {
+        Cbor::Major6(_, Tag::Value(1000, val)) => {
+            assert_eq!(*val, "abc".into_cbor().unwrap())
+        }
+        val => panic!("{:?}", val),
+    }
+}
+
+#[test]
+fn test_tag_validate() {
+    let bufs: Vec<&[u8]> = vec![
+        &[0xc0, 0x01],                   // date/time as integer
+        &[0xc1, 0x61, 0x61],             // epoch as text
+        &[0xc1, 0xf5],                   // epoch as bool
+        &[0xc2, 0x01],                   // big-num as integer
+        &[0xc3, 0x61, 0x61],             // big-num as text
+        &[0xc4, 0x81, 0x01],             // fraction with single item
+        &[0xc4, 0x82, 0x61, 0x61, 0x01], // fraction with text exponent
+        &[0xc5, 0x82, 0x01, 0xf5],       // big-float with bool mantissa
+        &[0xc5, 0x01],                   // big-float as integer
+        &[0xd8, 0x20, 0x01],             // uri as integer
+        &[0xd8, 0x18, 0x01],             // encoded cbor as integer
+    ];
+    for buf in bufs.into_iter() {
+        match Cbor::decode(&mut &buf[..]) {
+            Err(Error::FailCbor(_, _)) => (),
+            res => panic!("{:?} {:?}", buf, res),
+        }
+    }
+}
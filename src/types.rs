@@ -8,9 +8,20 @@ use std::os::unix::ffi::OsStringExt;
 use std::os::windows::ffi::OsStringExt;
 
 use std::convert::{TryFrom, TryInto};
-use std::{ffi, sync::Arc};
-
-use crate::{Cbor, Error, FromCbor, IntoCbor, Key, Result, SimpleValue, Tag};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    ffi,
+    hash::Hash,
+    ops::{Range, RangeInclusive},
+    sync::Arc,
+};
+
+use crate::{
+    cbor::{decode_exact_with, f16_to_f64},
+    Cbor, DecodeConfig, Embedded, Error, FromCbor, IntoCbor, Key, MapOrder, Result,
+    SimpleValue, Tag,
+};
 
 impl<T, const N: usize> IntoCbor for [T; N]
 where
@@ -31,12 +42,17 @@ where
     T: Copy + Default + FromCbor,
 {
     fn from_cbor(val: Cbor) -> Result<[T; N]> {
+        Self::from_cbor_with(val, &DecodeConfig::default())
+    }
+
+    fn from_cbor_with(val: Cbor, config: &DecodeConfig) -> Result<[T; N]> {
         let mut arr = [T::default(); N];
         let n = arr.len();
         match val {
             Cbor::Major4(_, data) if n == data.len() => {
                 for (i, item) in data.into_iter().enumerate() {
-                    arr[i] = T::from_cbor(item)?;
+                    arr[i] =
+                        T::from_cbor_with(item, config).map_err(|err| err.in_index(i))?;
                 }
                 Ok(arr)
             }
@@ -65,6 +81,14 @@ impl FromCbor for bool {
             _ => err_at!(FailConvert, msg: "not a bool"),
         }
     }
+
+    fn from_cbor_with(val: Cbor, config: &DecodeConfig) -> Result<bool> {
+        match val {
+            Cbor::Major0(_, 0) if config.coerce_bool => Ok(false),
+            Cbor::Major0(_, 1) if config.coerce_bool => Ok(true),
+            val => bool::from_cbor(val),
+        }
+    }
 }
 
 impl IntoCbor for f32 {
@@ -104,7 +128,7 @@ macro_rules! convert_neg_num {
                 if val >= 0 {
                     Ok(err_at!(FailConvert, u64::try_from(val))?.into_cbor()?)
                 } else {
-                    let val = err_at!(FailConvert, u64::try_from(val.abs() - 1))?;
+                    let val = err_at!(FailConvert, u64::try_from(-1 - val))?;
                     let info = val.into();
                     Ok(Cbor::Major1(info, val))
                 }
@@ -113,20 +137,17 @@ macro_rules! convert_neg_num {
 
         impl FromCbor for $t {
             fn from_cbor(val: Cbor) -> Result<$t> {
-                use std::result;
-
-                let val = match val {
-                    Cbor::Major0(_, val) => {
-                        let val: result::Result<$t, _> = val.try_into();
-                        err_at!(FailConvert, val)?
-                    }
-                    Cbor::Major1(_, val) => {
-                        let val: result::Result<$t, _> = (val + 1).try_into();
-                        -err_at!(FailConvert, val)?
-                    }
+                let val: i128 = match val {
+                    Cbor::Major0(_, val) => i128::from(val),
+                    Cbor::Major1(_, val) => -1 - i128::from(val),
                     _ => err_at!(FailConvert, msg: "not a number")?,
                 };
-                Ok(val)
+                match <$t>::try_from(val) {
+                    Ok(val) => Ok(val),
+                    Err(_) => {
+                        err_at!(FailConvert, msg: "value {} exceeds {}", val, stringify!($t))
+                    }
+                }
             }
         }
     )*}
@@ -145,9 +166,16 @@ macro_rules! convert_pos_num {
 
         impl FromCbor for $t {
             fn from_cbor(val: Cbor) -> Result<$t> {
-                match val {
-                    Cbor::Major0(_, val) => Ok(err_at!(FailConvert, val.try_into())?),
-                    _ => err_at!(FailConvert, msg: "not a number"),
+                let val: i128 = match val {
+                    Cbor::Major0(_, val) => i128::from(val),
+                    Cbor::Major1(_, val) => -1 - i128::from(val),
+                    _ => err_at!(FailConvert, msg: "not a number")?,
+                };
+                match <$t>::try_from(val) {
+                    Ok(val) => Ok(val),
+                    Err(_) => {
+                        err_at!(FailConvert, msg: "value {} exceeds {}", val, stringify!($t))
+                    }
                 }
             }
         }
@@ -156,88 +184,89 @@ macro_rules! convert_pos_num {
 
 convert_pos_num! {u64 u32 u16 u8 usize}
 
+/// Values that fit in 64-bit are encoded as integers, otherwise as bignum.
 impl IntoCbor for u128 {
     fn into_cbor(self) -> Result<Cbor> {
-        BigInt::from(self).into_cbor()
+        match u64::try_from(self) {
+            Ok(val) => val.into_cbor(),
+            Err(_) => BigInt::from(self).into_cbor(),
+        }
     }
 }
 
+/// Accept both integers and bignums.
 impl FromCbor for u128 {
     fn from_cbor(val: Cbor) -> Result<u128> {
         use num_traits::cast::ToPrimitive;
 
-        let (sign, bytes) = match val {
-            Cbor::Major6(_, tag) => match tag {
-                Tag::UBigNum(val) => (Sign::Plus, val.into_bytes()?),
-                Tag::SBigNum(val) => (Sign::Minus, val.into_bytes()?),
-                _ => err_at!(FailConvert, msg: "cbor not a bigint")?,
-            },
-            _ => err_at!(FailConvert, msg: "cbor not a tag/bigint")?,
-        };
-
-        match BigInt::from_bytes_be(sign, &bytes).to_u128() {
+        let val = BigInt::from_cbor(val)?;
+        match val.to_u128() {
             Some(val) => Ok(val),
-            None => err_at!(FailConvert, msg: "from bigint to u128"),
+            None => err_at!(FailConvert, msg: "value {} exceeds u128", val),
         }
     }
 }
 
+/// Values that fit in 64-bit are encoded as integers, otherwise as bignum.
 impl IntoCbor for i128 {
     fn into_cbor(self) -> Result<Cbor> {
-        BigInt::from(self).into_cbor()
+        match i64::try_from(self) {
+            Ok(val) => val.into_cbor(),
+            Err(_) => BigInt::from(self).into_cbor(),
+        }
     }
 }
 
+/// Accept both integers and bignums.
 impl FromCbor for i128 {
     fn from_cbor(val: Cbor) -> Result<i128> {
         use num_traits::cast::ToPrimitive;
 
-        let (sign, bytes) = match val {
-            Cbor::Major6(_, tag) => match tag {
-                Tag::UBigNum(val) => (Sign::Plus, val.into_bytes()?),
-                Tag::SBigNum(val) => (Sign::Minus, val.into_bytes()?),
-                _ => err_at!(FailConvert, msg: "cbor not a bigint")?,
-            },
-            _ => err_at!(FailConvert, msg: "cbor not a bigint")?,
-        };
-
-        match BigInt::from_bytes_be(sign, &bytes).to_i128() {
+        let val = BigInt::from_cbor(val)?;
+        match val.to_i128() {
             Some(val) => Ok(val),
-            None => err_at!(FailConvert, msg: "from bigint to i128"),
+            None => err_at!(FailConvert, msg: "value {} exceeds i128", val),
         }
     }
 }
 
+/// Encoded as tag-2 for non-negative value `n`, and as tag-3 with `-1 - n`
+/// for negative value `n`.
 impl IntoCbor for BigInt {
     fn into_cbor(self) -> Result<Cbor> {
-        match self.to_bytes_be() {
-            (Sign::Plus, bytes) | (Sign::NoSign, bytes) => {
-                let val = Box::new(Cbor::from_bytes(bytes)?);
+        match self.sign() {
+            Sign::Plus | Sign::NoSign => {
+                let val = Box::new(Cbor::from_bytes(self.to_bytes_be().1)?);
                 Ok(Tag::UBigNum(val).into())
             }
-            (Sign::Minus, bytes) => {
-                let val = Box::new(Cbor::from_bytes(bytes)?);
+            Sign::Minus => {
+                let val: BigInt = -self - 1;
+                let val = Box::new(Cbor::from_bytes(val.to_bytes_be().1)?);
                 Ok(Tag::SBigNum(val).into())
             }
         }
     }
 }
 
+/// Accept both integers and bignums.
 impl FromCbor for BigInt {
     fn from_cbor(val: Cbor) -> Result<BigInt> {
-        let (sign, bytes) = match val {
-            Cbor::Major6(_, tag) => match tag {
-                Tag::UBigNum(val) => (Sign::Plus, val.into_bytes()?),
-                Tag::SBigNum(val) => (Sign::Minus, val.into_bytes()?),
-                _ => err_at!(FailConvert, msg: "cbor not a bigint")?,
-            },
-            _ => err_at!(FailConvert, msg: "cbor not a tag/bigint")?,
+        let val = match val {
+            Cbor::Major0(_, val) => BigInt::from(val),
+            Cbor::Major1(_, val) => -1 - BigInt::from(val),
+            Cbor::Major6(_, Tag::UBigNum(val)) => {
+                BigInt::from_bytes_be(Sign::Plus, &val.into_bytes()?)
+            }
+            Cbor::Major6(_, Tag::SBigNum(val)) => {
+                -1 - BigInt::from_bytes_be(Sign::Plus, &val.into_bytes()?)
+            }
+            _ => err_at!(FailConvert, msg: "cbor not an integer/bigint")?,
         };
-        Ok(BigInt::from_bytes_be(sign, &bytes))
+        Ok(val)
     }
 }
 
-impl<'a> IntoCbor for &'a [u8] {
+impl IntoCbor for &[u8] {
     fn into_cbor(self) -> Result<Cbor> {
         let n = err_at!(FailConvert, u64::try_from(self.len()))?;
         Ok(Cbor::Major2(n.into(), self.to_vec()))
@@ -263,11 +292,17 @@ where
     T: FromCbor + Sized,
 {
     fn from_cbor(val: Cbor) -> Result<Vec<T>> {
+        Self::from_cbor_with(val, &DecodeConfig::default())
+    }
+
+    fn from_cbor_with(val: Cbor, config: &DecodeConfig) -> Result<Vec<T>> {
         match val {
             Cbor::Major4(_, data) => {
                 let mut arr = vec![];
-                for item in data.into_iter() {
-                    arr.push(T::from_cbor(item)?)
+                for (i, item) in data.into_iter().enumerate() {
+                    arr.push(
+                        T::from_cbor_with(item, config).map_err(|err| err.in_index(i))?,
+                    )
                 }
                 Ok(arr)
             }
@@ -276,7 +311,7 @@ where
     }
 }
 
-impl<'a> IntoCbor for &'a str {
+impl IntoCbor for &str {
     fn into_cbor(self) -> Result<Cbor> {
         let n = err_at!(FailConvert, u64::try_from(self.len()))?;
         Ok(Cbor::Major3(n.into(), self.as_bytes().to_vec()))
@@ -303,6 +338,36 @@ impl FromCbor for String {
     }
 }
 
+impl<'a> IntoCbor for Cow<'a, str> {
+    fn into_cbor(self) -> Result<Cbor> {
+        match self {
+            Cow::Borrowed(val) => val.into_cbor(),
+            Cow::Owned(val) => val.into_cbor(),
+        }
+    }
+}
+
+impl<'a> FromCbor for Cow<'a, str> {
+    fn from_cbor(val: Cbor) -> Result<Cow<'a, str>> {
+        Ok(Cow::Owned(String::from_cbor(val)?))
+    }
+}
+
+impl<'a> IntoCbor for Cow<'a, [u8]> {
+    fn into_cbor(self) -> Result<Cbor> {
+        match self {
+            Cow::Borrowed(val) => val.into_cbor(),
+            Cow::Owned(val) => Cbor::from_bytes(val),
+        }
+    }
+}
+
+impl<'a> FromCbor for Cow<'a, [u8]> {
+    fn from_cbor(val: Cbor) -> Result<Cow<'a, [u8]>> {
+        Ok(Cow::Owned(val.into_bytes()?))
+    }
+}
+
 impl IntoCbor for ffi::OsString {
     fn into_cbor(self) -> Result<Cbor> {
         let n = err_at!(FailConvert, u64::try_from(self.len()))?;
@@ -338,19 +403,213 @@ impl FromCbor for Vec<Cbor> {
 impl IntoCbor for Vec<(Key, Cbor)> {
     fn into_cbor(self) -> Result<Cbor> {
         let n = err_at!(FailConvert, u64::try_from(self.len()))?;
-        Ok(Cbor::Major5(n.into(), self))
+        let order = MapOrder::of(&self);
+        Ok(Cbor::Major5(n.into(), self, order))
     }
 }
 
 impl FromCbor for Vec<(Key, Cbor)> {
     fn from_cbor(val: Cbor) -> Result<Vec<(Key, Cbor)>> {
         match val {
-            Cbor::Major5(_, data) => Ok(data),
+            Cbor::Major5(_, data, _) => Ok(data),
             _ => err_at!(FailConvert, msg: "not a map"),
         }
     }
 }
 
+/// Map entries are encoded in the map's iteration order, that is sorted by
+/// key, hence the encoded bytes are deterministic for the same map.
+impl<K, V> IntoCbor for BTreeMap<K, V>
+where
+    K: IntoCbor,
+    V: IntoCbor,
+{
+    fn into_cbor(self) -> Result<Cbor> {
+        let n = err_at!(FailConvert, u64::try_from(self.len()))?;
+        let mut map: Vec<(Key, Cbor)> = Vec::with_capacity(self.len());
+        for (key, val) in self.into_iter() {
+            map.push((Key::from_cbor(key.into_cbor()?)?, val.into_cbor()?))
+        }
+        let order = MapOrder::of(&map);
+        Ok(Cbor::Major5(n.into(), map, order))
+    }
+}
+
+impl<K, V> FromCbor for BTreeMap<K, V>
+where
+    K: FromCbor + Ord,
+    V: FromCbor,
+{
+    fn from_cbor(val: Cbor) -> Result<Self> {
+        Self::from_cbor_with(val, &DecodeConfig::default())
+    }
+
+    fn from_cbor_with(val: Cbor, config: &DecodeConfig) -> Result<Self> {
+        let mut map = BTreeMap::new();
+        for (key, val) in Vec::<(Key, Cbor)>::from_cbor(val)?.into_iter() {
+            let val = V::from_cbor_with(val, config).map_err(|err| err.in_key(&key))?;
+            map.insert(key_from_cbor(key, config)?, val);
+        }
+        Ok(map)
+    }
+}
+
+/// Map entries are encoded in the map's iteration order, hence the encoded
+/// bytes can differ for the same map. Use [BTreeMap], or encode using
+/// [Cbor::encode_canonical], for deterministic encoding.
+impl<K, V> IntoCbor for HashMap<K, V>
+where
+    K: IntoCbor,
+    V: IntoCbor,
+{
+    fn into_cbor(self) -> Result<Cbor> {
+        let n = err_at!(FailConvert, u64::try_from(self.len()))?;
+        let mut map: Vec<(Key, Cbor)> = Vec::with_capacity(self.len());
+        for (key, val) in self.into_iter() {
+            map.push((Key::from_cbor(key.into_cbor()?)?, val.into_cbor()?))
+        }
+        let order = MapOrder::of(&map);
+        Ok(Cbor::Major5(n.into(), map, order))
+    }
+}
+
+impl<K, V> FromCbor for HashMap<K, V>
+where
+    K: FromCbor + Eq + Hash,
+    V: FromCbor,
+{
+    fn from_cbor(val: Cbor) -> Result<Self> {
+        Self::from_cbor_with(val, &DecodeConfig::default())
+    }
+
+    fn from_cbor_with(val: Cbor, config: &DecodeConfig) -> Result<Self> {
+        let mut map = HashMap::new();
+        for (key, val) in Vec::<(Key, Cbor)>::from_cbor(val)?.into_iter() {
+            let val = V::from_cbor_with(val, config).map_err(|err| err.in_key(&key))?;
+            map.insert(key_from_cbor(key, config)?, val);
+        }
+        Ok(map)
+    }
+}
+
+// Convert map key into target type. With `coerce_map_keys`, text keys that
+// fail to convert are retried as integer keys, if they hold a decimal integer
+// in canonical form.
+fn key_from_cbor<K>(key: Key, config: &DecodeConfig) -> Result<K>
+where
+    K: FromCbor,
+{
+    let ikey = match &key {
+        Key::Text(text) if config.coerce_map_keys => match text.parse::<u64>() {
+            Ok(val) if val.to_string() == *text => Some(Key::U64(val)),
+            Ok(_) => None,
+            Err(_) => match text.parse::<i64>() {
+                Ok(val) if val < 0 && val.to_string() == *text => Some(Key::N64(val)),
+                _ => None,
+            },
+        },
+        _ => None,
+    };
+    match (K::from_cbor_with(key.into_cbor()?, config), ikey) {
+        (Err(_), Some(ikey)) => K::from_cbor_with(ikey.into_cbor()?, config),
+        (res, _) => res,
+    }
+}
+
+impl<T> IntoCbor for VecDeque<T>
+where
+    T: IntoCbor,
+{
+    fn into_cbor(self) -> Result<Cbor> {
+        Vec::from(self).into_cbor()
+    }
+}
+
+impl<T> FromCbor for VecDeque<T>
+where
+    T: FromCbor,
+{
+    fn from_cbor(val: Cbor) -> Result<Self> {
+        Ok(Vec::<T>::from_cbor(val)?.into())
+    }
+
+    fn from_cbor_with(val: Cbor, config: &DecodeConfig) -> Result<Self> {
+        Ok(Vec::<T>::from_cbor_with(val, config)?.into())
+    }
+}
+
+/// Set items are encoded as array, in sorted order.
+impl<T> IntoCbor for BTreeSet<T>
+where
+    T: IntoCbor,
+{
+    fn into_cbor(self) -> Result<Cbor> {
+        self.into_iter().collect::<Vec<T>>().into_cbor()
+    }
+}
+
+/// Array with duplicate items is rejected with [Error::FailConvert].
+impl<T> FromCbor for BTreeSet<T>
+where
+    T: FromCbor + Ord,
+{
+    fn from_cbor(val: Cbor) -> Result<Self> {
+        Self::from_cbor_with(val, &DecodeConfig::default())
+    }
+
+    fn from_cbor_with(val: Cbor, config: &DecodeConfig) -> Result<Self> {
+        let mut set = BTreeSet::new();
+        for (i, item) in Vec::<T>::from_cbor_with(val, config)?.into_iter().enumerate() {
+            if !set.insert(item) {
+                err_at!(FailConvert, msg: "duplicate set item at index {}", i)?
+            }
+        }
+        Ok(set)
+    }
+}
+
+/// Set items are encoded as array, sorted by their canonical encoding, refer
+/// to [Cbor::encode_canonical], hence the encoded bytes are deterministic for
+/// the same set, irrespective of the set's iteration order.
+impl<T> IntoCbor for HashSet<T>
+where
+    T: IntoCbor,
+{
+    fn into_cbor(self) -> Result<Cbor> {
+        let mut items: Vec<(Vec<u8>, Cbor)> = Vec::with_capacity(self.len());
+        for item in self.into_iter() {
+            let item = item.into_cbor()?;
+            let mut buf = vec![];
+            item.encode_canonical(&mut buf)?;
+            items.push((buf, item));
+        }
+        items.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let n = err_at!(FailConvert, u64::try_from(items.len()))?;
+        Ok(Cbor::Major4(n.into(), items.into_iter().map(|x| x.1).collect()))
+    }
+}
+
+/// Array with duplicate items is rejected with [Error::FailConvert].
+impl<T> FromCbor for HashSet<T>
+where
+    T: FromCbor + Eq + Hash,
+{
+    fn from_cbor(val: Cbor) -> Result<Self> {
+        Self::from_cbor_with(val, &DecodeConfig::default())
+    }
+
+    fn from_cbor_with(val: Cbor, config: &DecodeConfig) -> Result<Self> {
+        let mut set = HashSet::new();
+        for (i, item) in Vec::<T>::from_cbor_with(val, config)?.into_iter().enumerate() {
+            if !set.insert(item) {
+                err_at!(FailConvert, msg: "duplicate set item at index {}", i)?
+            }
+        }
+        Ok(set)
+    }
+}
+
 impl<T> IntoCbor for Option<T>
 where
     T: IntoCbor,
@@ -368,9 +627,13 @@ where
     T: FromCbor + Sized,
 {
     fn from_cbor(val: Cbor) -> Result<Option<T>> {
+        Self::from_cbor_with(val, &DecodeConfig::default())
+    }
+
+    fn from_cbor_with(val: Cbor, config: &DecodeConfig) -> Result<Option<T>> {
         match val {
             Cbor::Major7(_, SimpleValue::Null) => Ok(None),
-            val => Ok(Some(T::from_cbor(val)?)),
+            val => Ok(Some(T::from_cbor_with(val, config)?)),
         }
     }
 }
@@ -406,8 +669,6 @@ impl IntoCbor for Key {
 
 impl FromCbor for Key {
     fn from_cbor(val: Cbor) -> Result<Key> {
-        use std::str::from_utf8;
-
         let key = match val {
             Cbor::Major0(_, key) => Key::U64(key),
             Cbor::Major1(_, key) => {
@@ -416,11 +677,11 @@ impl FromCbor for Key {
             }
             Cbor::Major2(_, key) => Key::Bytes(key),
             Cbor::Major3(_, key) => {
-                let val = err_at!(FailConvert, from_utf8(&key))?.to_string();
-                Key::Text(val)
+                Key::Text(err_at!(FailConvert, String::from_utf8(key))?)
             }
             Cbor::Major7(_, SimpleValue::True) => Key::Bool(true),
             Cbor::Major7(_, SimpleValue::False) => Key::Bool(false),
+            Cbor::Major7(_, SimpleValue::F16(bits)) => Key::F64(f16_to_f64(bits)),
             Cbor::Major7(_, SimpleValue::F32(key)) => Key::F32(key),
             Cbor::Major7(_, SimpleValue::F64(key)) => Key::F64(key),
             _ => err_at!(FailCbor, msg: "cbor not a valid key")?,
@@ -472,6 +733,10 @@ where
     fn from_cbor(val: Cbor) -> Result<Self> {
         T::from_cbor(val).map(Arc::new)
     }
+
+    fn from_cbor_with(val: Cbor, config: &DecodeConfig) -> Result<Self> {
+        T::from_cbor_with(val, config).map(Arc::new)
+    }
 }
 
 impl<T> IntoCbor for Arc<T>
@@ -488,3 +753,252 @@ where
         }
     }
 }
+
+impl<T> IntoCbor for Embedded<T>
+where
+    T: IntoCbor,
+{
+    fn into_cbor(self) -> Result<Cbor> {
+        Ok(Tag::from_encoded_cbor(self.0.into_cbor()?)?.into())
+    }
+}
+
+impl<T> FromCbor for Embedded<T>
+where
+    T: FromCbor,
+{
+    fn from_cbor(val: Cbor) -> Result<Self> {
+        Self::from_cbor_with(val, &DecodeConfig::default())
+    }
+
+    fn from_cbor_with(val: Cbor, config: &DecodeConfig) -> Result<Self> {
+        match val {
+            Cbor::Major6(_, Tag::EncodedCbor(val)) => {
+                let data = val.into_bytes()?;
+                Ok(Embedded(T::from_cbor_with(
+                    decode_exact_with(&data, config)?,
+                    config,
+                )?))
+            }
+            _ => err_at!(FailConvert, msg: "not an encoded cbor data item"),
+        }
+    }
+}
+
+// Ranges are encoded as a 3-element list of `[start, end, inclusive]`.
+fn range_into_cbor<T>(start: T, end: T, inclusive: bool) -> Result<Cbor>
+where
+    T: IntoCbor,
+{
+    let items = vec![start.into_cbor()?, end.into_cbor()?, inclusive.into_cbor()?];
+    Ok(Cbor::Major4(3_u64.into(), items))
+}
+
+fn range_from_cbor<T>(val: Cbor, inclusive: bool, cf: &DecodeConfig) -> Result<(T, T)>
+where
+    T: FromCbor,
+{
+    match val {
+        Cbor::Major4(_, items) if items.len() == 3 => {
+            let mut iter = items.into_iter();
+            let start = T::from_cbor_with(iter.next().unwrap(), cf)?;
+            let end = T::from_cbor_with(iter.next().unwrap(), cf)?;
+            match bool::from_cbor(iter.next().unwrap())? {
+                val if val == inclusive => Ok((start, end)),
+                val => err_at!(FailConvert, msg: "range inclusive:{}", val),
+            }
+        }
+        _ => err_at!(FailConvert, msg: "not a range"),
+    }
+}
+
+impl<T> IntoCbor for Range<T>
+where
+    T: IntoCbor,
+{
+    fn into_cbor(self) -> Result<Cbor> {
+        range_into_cbor(self.start, self.end, false)
+    }
+}
+
+impl<T> FromCbor for Range<T>
+where
+    T: FromCbor,
+{
+    fn from_cbor(val: Cbor) -> Result<Self> {
+        Self::from_cbor_with(val, &DecodeConfig::default())
+    }
+
+    fn from_cbor_with(val: Cbor, config: &DecodeConfig) -> Result<Self> {
+        let (start, end) = range_from_cbor(val, false, config)?;
+        Ok(start..end)
+    }
+}
+
+impl<T> IntoCbor for RangeInclusive<T>
+where
+    T: IntoCbor,
+{
+    fn into_cbor(self) -> Result<Cbor> {
+        let (start, end) = self.into_inner();
+        range_into_cbor(start, end, true)
+    }
+}
+
+impl<T> FromCbor for RangeInclusive<T>
+where
+    T: FromCbor,
+{
+    fn from_cbor(val: Cbor) -> Result<Self> {
+        Self::from_cbor_with(val, &DecodeConfig::default())
+    }
+
+    fn from_cbor_with(val: Cbor, config: &DecodeConfig) -> Result<Self> {
+        let (start, end) = range_from_cbor(val, true, config)?;
+        Ok(start..=end)
+    }
+}
+
+/// Encoded as tag-1, epoch-based date/time, as integer seconds when there is
+/// no sub-second part, otherwise as floating-point seconds. Floating-point
+/// seconds hold about microsecond precision for present day date/times,
+/// use [Tag::from_datetime] to preserve the nano-seconds. Leap seconds cannot
+/// be represented and shall fail the conversion.
+#[cfg(feature = "chrono")]
+impl IntoCbor for chrono::DateTime<chrono::Utc> {
+    fn into_cbor(self) -> Result<Cbor> {
+        let (secs, nanos) = (self.timestamp(), self.timestamp_subsec_nanos());
+        let val = match nanos {
+            0 => secs.into_cbor()?,
+            nanos if nanos < 1_000_000_000 => {
+                (secs as f64 + f64::from(nanos) / 1e9).into_cbor()?
+            }
+            _ => err_at!(FailConvert, msg: "leap second {}", self)?,
+        };
+        let tag = Tag::Epoch(Box::new(val));
+        Ok(tag.into())
+    }
+}
+
+/// Accept tag-0, RFC3339 date/time text, and tag-1, epoch-based date/time as
+/// integer or floating-point seconds.
+#[cfg(feature = "chrono")]
+impl FromCbor for chrono::DateTime<chrono::Utc> {
+    fn from_cbor(val: Cbor) -> Result<Self> {
+        use chrono::{DateTime, Utc};
+
+        let dt = match val {
+            Cbor::Major6(_, Tag::DateTime(val)) => {
+                let text = String::from_cbor(*val)?;
+                let dt = err_at!(FailConvert, DateTime::parse_from_rfc3339(&text))?;
+                dt.with_timezone(&Utc)
+            }
+            Cbor::Major6(_, Tag::Epoch(val)) => {
+                let (secs, nanos) = match *val {
+                    Cbor::Major7(_, sval) => match sval.as_f64() {
+                        Some(val) => crate::tags::from_epoch_float(val)?,
+                        None => err_at!(FailConvert, msg: "tag 1 expects a number")?,
+                    },
+                    val => (i64::from_cbor(val)?, 0),
+                };
+                match DateTime::<Utc>::from_timestamp(secs, nanos) {
+                    Some(dt) => dt,
+                    None => {
+                        err_at!(FailConvert, msg: "epoch {}.{} out of range", secs, nanos)?
+                    }
+                }
+            }
+            _ => err_at!(FailConvert, msg: "not a tag-0/tag-1 date/time")?,
+        };
+        if dt.timestamp_subsec_nanos() >= 1_000_000_000 {
+            err_at!(FailConvert, msg: "leap second {}", dt)?
+        }
+        Ok(dt)
+    }
+}
+
+// Compare cbor values with native types, refer to [Cbor] for the semantics.
+macro_rules! eq_int {
+    ($($t:ty)*) => {$(
+        impl PartialEq<$t> for Cbor {
+            fn eq(&self, other: &$t) -> bool {
+                int_value(self) == Some(i128::from(*other))
+            }
+        }
+    )*};
+}
+
+eq_int! {u8 u16 u32 u64 i8 i16 i32 i64}
+
+impl PartialEq<usize> for Cbor {
+    fn eq(&self, other: &usize) -> bool {
+        int_value(self) == i128::try_from(*other).ok()
+    }
+}
+
+impl PartialEq<isize> for Cbor {
+    fn eq(&self, other: &isize) -> bool {
+        int_value(self) == i128::try_from(*other).ok()
+    }
+}
+
+impl PartialEq<f32> for Cbor {
+    fn eq(&self, other: &f32) -> bool {
+        float_value(self) == Some(f64::from(*other))
+    }
+}
+
+impl PartialEq<f64> for Cbor {
+    fn eq(&self, other: &f64) -> bool {
+        float_value(self) == Some(*other)
+    }
+}
+
+impl PartialEq<bool> for Cbor {
+    fn eq(&self, other: &bool) -> bool {
+        match self {
+            Cbor::Major7(_, SimpleValue::True) => *other,
+            Cbor::Major7(_, SimpleValue::False) => !*other,
+            _ => false,
+        }
+    }
+}
+
+impl PartialEq<str> for Cbor {
+    fn eq(&self, other: &str) -> bool {
+        matches!(self, Cbor::Major3(_, text) if text == other.as_bytes())
+    }
+}
+
+impl<'a> PartialEq<&'a str> for Cbor {
+    fn eq(&self, other: &&'a str) -> bool {
+        self == *other
+    }
+}
+
+impl PartialEq<String> for Cbor {
+    fn eq(&self, other: &String) -> bool {
+        self == other.as_str()
+    }
+}
+
+fn int_value(val: &Cbor) -> Option<i128> {
+    match val {
+        Cbor::Major0(_, num) => Some(i128::from(*num)),
+        Cbor::Major1(_, num) => Some(-1 - i128::from(*num)),
+        _ => None,
+    }
+}
+
+fn float_value(val: &Cbor) -> Option<f64> {
+    match val {
+        Cbor::Major7(_, SimpleValue::F16(bits)) => Some(f16_to_f64(*bits)),
+        Cbor::Major7(_, SimpleValue::F32(val)) => Some(f64::from(*val)),
+        Cbor::Major7(_, SimpleValue::F64(val)) => Some(*val),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+#[path = "types_test.rs"]
+mod types_test;
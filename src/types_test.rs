@@ -0,0 +1,506 @@
+use super::*;
+
+#[test]
+fn test_cow() {
+    let refs: Vec<Cow<str>> =
+        vec![Cow::Borrowed("hello"), Cow::Owned("world".to_string())];
+    for val in refs.into_iter() {
+        let cbor_val = val.clone().into_cbor().unwrap();
+        assert_eq!(cbor_val, val.as_ref().into_cbor().unwrap());
+        match Cow::<str>::from_cbor(cbor_val).unwrap() {
+            Cow::Owned(s) => assert_eq!(s, val),
+            Cow::Borrowed(s) => panic!("{}", s),
+        }
+    }
+
+    let data = vec![1_u8, 2, 3];
+    let refs: Vec<Cow<[u8]>> = vec![Cow::Borrowed(&data), Cow::Owned(vec![4, 5])];
+    for val in refs.into_iter() {
+        let cbor_val = val.clone().into_cbor().unwrap();
+        assert_eq!(cbor_val, Cbor::from_bytes(val.to_vec()).unwrap());
+        match Cow::<[u8]>::from_cbor(cbor_val).unwrap() {
+            Cow::Owned(s) => assert_eq!(s, val.as_ref()),
+            Cow::Borrowed(s) => panic!("{:?}", s),
+        }
+    }
+}
+
+#[test]
+fn test_bool() {
+    for (val, byt) in [(false, 0xf4), (true, 0xf5)].iter() {
+        let mut buf = vec![];
+        val.into_cbor().unwrap().encode(&mut buf).unwrap();
+        assert_eq!(buf, vec![*byt]);
+        let (cval, _) = Cbor::decode(&mut buf.as_slice()).unwrap();
+        assert_eq!(bool::from_cbor(cval).unwrap(), *val);
+    }
+
+    // only true/false simple values are accepted.
+    let vals = vec![
+        0.into_cbor().unwrap(),
+        1.into_cbor().unwrap(),
+        SimpleValue::Null.into_cbor().unwrap(),
+        "true".into_cbor().unwrap(),
+    ];
+    for val in vals.into_iter() {
+        match bool::from_cbor(val) {
+            Err(Error::FailConvert(_, _)) => (),
+            res => panic!("{:?}", res),
+        }
+    }
+}
+
+#[test]
+fn test_coerce_bool() {
+    let config = DecodeConfig { coerce_bool: true, ..DecodeConfig::default() };
+    let mut buf = vec![];
+    vec![1_u64, 0, 2].into_cbor().unwrap().encode(&mut buf).unwrap();
+
+    // decoded value keeps the integers.
+    let (val, _) = Cbor::decode_with(&mut buf.as_slice(), &config).unwrap();
+    assert_eq!(Vec::<u64>::from_cbor_with(val.clone(), &config).unwrap(), vec![1, 0, 2]);
+
+    let items = match val {
+        Cbor::Major4(_, items) => items,
+        val => panic!("{:?}", val),
+    };
+    assert!(bool::from_cbor_with(items[0].clone(), &config).unwrap());
+    assert!(!bool::from_cbor_with(items[1].clone(), &config).unwrap());
+    assert!(bool::from_cbor_with(true.into_cbor().unwrap(), &config).unwrap());
+    for (val, config) in [(&items[2], &config), (&items[0], &DecodeConfig::default())] {
+        match bool::from_cbor_with(val.clone(), config) {
+            Err(Error::FailConvert(_, _)) => (),
+            res => panic!("{:?}", res),
+        }
+    }
+
+    // passed on to container items.
+    let val = vec![1_u64, 0].into_cbor().unwrap();
+    assert_eq!(Vec::<bool>::from_cbor_with(val.clone(), &config).unwrap(), [true, false]);
+    assert!(Vec::<bool>::from_cbor(val).is_err());
+    let val = vec![Some(1_u64), None].into_cbor().unwrap();
+    let res = Vec::<Option<bool>>::from_cbor_with(val, &config).unwrap();
+    assert_eq!(res, vec![Some(true), None]);
+}
+
+#[test]
+fn test_coerce_map_keys() {
+    let config = DecodeConfig { coerce_map_keys: true, ..DecodeConfig::default() };
+    let encode = |keys: &[&str]| {
+        let map: Vec<(Key, Cbor)> =
+            keys.iter().map(|k| (Key::from(*k), true.into_cbor().unwrap())).collect();
+        let mut buf = vec![];
+        map.into_cbor().unwrap().encode(&mut buf).unwrap();
+        buf
+    };
+
+    // decoded value keeps the text keys.
+    let buf = encode(&["10", "-2"]);
+    let (val, _) = Cbor::decode_with(&mut buf.as_slice(), &config).unwrap();
+    let keys: Vec<Key> = match val.clone() {
+        Cbor::Major5(_, map, _) => map.into_iter().map(|(k, _)| k).collect(),
+        val => panic!("{:?}", val),
+    };
+    assert_eq!(keys, vec![Key::from("10"), Key::from("-2")]);
+
+    assert!(HashMap::<i64, bool>::from_cbor(val.clone()).is_err());
+    let map = HashMap::<i64, bool>::from_cbor_with(val.clone(), &config).unwrap();
+    assert_eq!(map, [(10, true), (-2, true)].iter().cloned().collect());
+    let map = BTreeMap::<i64, bool>::from_cbor_with(val.clone(), &config).unwrap();
+    assert_eq!(map, [(10, true), (-2, true)].iter().cloned().collect());
+    assert!(BTreeMap::<u64, bool>::from_cbor_with(val.clone(), &config).is_err());
+
+    // text-keyed maps are not affected.
+    let map = HashMap::<String, bool>::from_cbor_with(val.clone(), &config).unwrap();
+    assert_eq!(map.get("10"), Some(&true));
+    let map = BTreeMap::<Key, bool>::from_cbor_with(val.clone(), &config).unwrap();
+    assert_eq!(map.get(&Key::from("-2")), Some(&true));
+
+    // passed on to container items.
+    let val = vec![val].into_cbor().unwrap();
+    let maps = Vec::<BTreeMap<i64, bool>>::from_cbor_with(val, &config).unwrap();
+    assert_eq!(maps[0].get(&-2), Some(&true));
+
+    for key in ["042", "+3", "-0", "x"].iter() {
+        let buf = encode(&["10", key]);
+        let (val, _) = Cbor::decode(&mut buf.as_slice()).unwrap();
+        match HashMap::<i64, bool>::from_cbor_with(val, &config) {
+            Err(Error::FailConvert(_, _)) => (),
+            res => panic!("{} {:?}", key, res),
+        }
+    }
+}
+
+#[test]
+fn test_range() {
+    let val = (10_u64..20).into_cbor().unwrap();
+    assert_eq!(Range::<u64>::from_cbor(val.clone()).unwrap(), 10..20);
+    assert!(RangeInclusive::<u64>::from_cbor(val).is_err());
+
+    let val = (-10_i32..=20).into_cbor().unwrap();
+    assert_eq!(RangeInclusive::<i32>::from_cbor(val.clone()).unwrap(), -10..=20);
+    assert!(Range::<i32>::from_cbor(val).is_err());
+
+    let val = ("a".to_string().."z".to_string()).into_cbor().unwrap();
+    let range = Range::<String>::from_cbor(val).unwrap();
+    assert_eq!(range, "a".to_string().."z".to_string());
+
+    let vals = vec![
+        vec![1_u64, 2].into_cbor().unwrap(),
+        vec![1_u64, 2, 3].into_cbor().unwrap(),
+        10.into_cbor().unwrap(),
+    ];
+    for val in vals.into_iter() {
+        match Range::<u64>::from_cbor(val) {
+            Err(Error::FailConvert(_, _)) => (),
+            res => panic!("{:?}", res),
+        }
+    }
+}
+
+#[test]
+fn test_btree_map() {
+    let mut map: BTreeMap<String, i64> = BTreeMap::new();
+    for (i, key) in ["z", "b", "aa", "a", "c"].iter().enumerate() {
+        map.insert(key.to_string(), -(i as i64));
+    }
+
+    let mut bufs: Vec<Vec<u8>> = vec![];
+    for _ in 0..3 {
+        let mut buf: Vec<u8> = vec![];
+        map.clone().into_cbor().unwrap().encode(&mut buf).unwrap();
+        bufs.push(buf);
+    }
+    let refbuf = vec![
+        0xa5, 0x61, 0x61, 0x22, 0x62, 0x61, 0x61, 0x21, 0x61, 0x62, 0x20, 0x61, 0x63,
+        0x23, 0x61, 0x7a, 0x00,
+    ];
+    for buf in bufs.into_iter() {
+        assert_eq!(buf, refbuf);
+    }
+
+    let val = map.clone().into_cbor().unwrap();
+    assert_eq!(BTreeMap::<String, i64>::from_cbor(val).unwrap(), map);
+
+    // value conversion failure carries the key.
+    let mut map: BTreeMap<String, Vec<u16>> = BTreeMap::new();
+    map.insert("a b".to_string(), vec![1]);
+    map.insert("x".to_string(), vec![2, 300]);
+    match BTreeMap::<String, Vec<u8>>::from_cbor(map.into_cbor().unwrap()) {
+        Err(err @ Error::FailConvert(_, _)) => assert_eq!(err.path(), Some("[\"x\"][1]")),
+        res => panic!("{:?}", res),
+    }
+}
+
+#[test]
+fn test_collections() {
+    let deque: VecDeque<u64> = vec![3, 1, 2].into();
+    let val = deque.clone().into_cbor().unwrap();
+    assert_eq!(val, vec![3_u64, 1, 2].into_cbor().unwrap());
+    assert_eq!(VecDeque::<u64>::from_cbor(val).unwrap(), deque);
+
+    let set: BTreeSet<String> = ["z", "a", "m"].iter().map(|s| s.to_string()).collect();
+    let val = set.clone().into_cbor().unwrap();
+    assert_eq!(val, vec!["a", "m", "z"].into_cbor().unwrap());
+    assert_eq!(BTreeSet::<String>::from_cbor(val).unwrap(), set);
+
+    let set: HashSet<i32> = [-1, 10, 20].iter().cloned().collect();
+    let val = set.clone().into_cbor().unwrap();
+    assert_eq!(HashSet::<i32>::from_cbor(val).unwrap(), set);
+
+    let val = vec![1_u64, 2, 1].into_cbor().unwrap();
+    match BTreeSet::<u64>::from_cbor(val.clone()) {
+        Err(Error::FailConvert(_, msg)) => assert!(msg.contains("index 2"), "{}", msg),
+        res => panic!("{:?}", res),
+    }
+    match HashSet::<u64>::from_cbor(val) {
+        Err(Error::FailConvert(_, msg)) => assert!(msg.contains("index 2"), "{}", msg),
+        res => panic!("{:?}", res),
+    }
+}
+
+#[test]
+fn test_int_overflow() {
+    fn int_cbor(val: i128) -> Cbor {
+        if val >= 0 {
+            let val = val as u64;
+            Cbor::Major0(val.into(), val)
+        } else {
+            let val = (-1 - val) as u64;
+            Cbor::Major1(val.into(), val)
+        }
+    }
+
+    macro_rules! check_bounds {
+        ($($t:ty)*) => {$(
+            let (min, max) = (<$t>::MIN, <$t>::MAX);
+            assert_eq!(<$t>::from_cbor(min.into_cbor().unwrap()).unwrap(), min);
+            assert_eq!(<$t>::from_cbor(max.into_cbor().unwrap()).unwrap(), max);
+
+            let vals = vec![int_cbor(i128::from(min) - 1), int_cbor(i128::from(max) + 1)];
+            for val in vals.into_iter() {
+                match <$t>::from_cbor(val) {
+                    Err(Error::FailConvert(_, msg)) => {
+                        assert!(msg.ends_with(concat!("exceeds ", stringify!($t))), "{}", msg)
+                    }
+                    res => panic!("{:?}", res),
+                }
+            }
+        )*};
+    }
+
+    check_bounds! {u8 u16 u32 i8 i16 i32 i64}
+
+    match u8::from_cbor(300.into_cbor().unwrap()) {
+        Err(Error::FailConvert(_, msg)) => assert_eq!(msg, "value 300 exceeds u8"),
+        res => panic!("{:?}", res),
+    }
+    match u64::from_cbor((-1).into_cbor().unwrap()) {
+        Err(Error::FailConvert(_, msg)) => assert_eq!(msg, "value -1 exceeds u64"),
+        res => panic!("{:?}", res),
+    }
+    assert_eq!(u64::from_cbor(u64::MAX.into_cbor().unwrap()).unwrap(), u64::MAX);
+    let val = Cbor::Major1(u64::MAX.into(), u64::MAX);
+    assert!(i64::from_cbor(val).is_err());
+}
+
+#[test]
+fn test_int128() {
+    let big = |val: i128| BigInt::from(val).into_cbor().unwrap();
+
+    let refs: Vec<(u128, Cbor)> = vec![
+        (0, 0_u64.into_cbor().unwrap()),
+        (u128::from(u64::MAX), u64::MAX.into_cbor().unwrap()),
+        (
+            u128::from(u64::MAX) + 1,
+            BigInt::from(u128::from(u64::MAX) + 1).into_cbor().unwrap(),
+        ),
+        (u128::MAX, BigInt::from(u128::MAX).into_cbor().unwrap()),
+    ];
+    for (val, refval) in refs.into_iter() {
+        let cval = val.into_cbor().unwrap();
+        assert_eq!(cval, refval, "{}", val);
+        assert_eq!(u128::from_cbor(cval).unwrap(), val);
+    }
+
+    let refs: Vec<(i128, Cbor)> = vec![
+        (-1, (-1_i64).into_cbor().unwrap()),
+        (i128::from(i64::MIN), i64::MIN.into_cbor().unwrap()),
+        (i128::from(i64::MIN) - 1, big(i128::from(i64::MIN) - 1)),
+        (i128::from(i64::MAX), i64::MAX.into_cbor().unwrap()),
+        (i128::from(i64::MAX) + 1, big(i128::from(i64::MAX) + 1)),
+        (i128::MIN, big(i128::MIN)),
+        (i128::MAX, big(i128::MAX)),
+    ];
+    for (val, refval) in refs.into_iter() {
+        let cval = val.into_cbor().unwrap();
+        assert_eq!(cval, refval, "{}", val);
+        assert_eq!(i128::from_cbor(cval).unwrap(), val);
+    }
+
+    // integer forms in 64-bit range are accepted for 128-bit targets.
+    let val = Cbor::Major1(u64::MAX.into(), u64::MAX);
+    assert_eq!(i128::from_cbor(val.clone()).unwrap(), -1 - i128::from(u64::MAX));
+    assert!(u128::from_cbor(val).is_err());
+    assert_eq!(i128::from_cbor(u64::MAX.into_cbor().unwrap()).unwrap(), u64::MAX.into());
+
+    // tag-3 holds -1 - n.
+    let mut buf = vec![];
+    (-0x100_i128 - i128::from(u64::MAX))
+        .into_cbor()
+        .unwrap()
+        .encode(&mut buf)
+        .unwrap();
+    assert_eq!(buf, vec![0xc3, 0x49, 0x01, 0, 0, 0, 0, 0, 0, 0, 0xfe]);
+
+    // bignums beyond 128-bit.
+    let val: BigInt = BigInt::from(u128::MAX) + 1;
+    match u128::from_cbor(val.clone().into_cbor().unwrap()) {
+        Err(Error::FailConvert(_, msg)) => {
+            assert!(msg.contains("exceeds u128"), "{}", msg)
+        }
+        res => panic!("{:?}", res),
+    }
+    assert!(i128::from_cbor(val.into_cbor().unwrap()).is_err());
+    let val: BigInt = BigInt::from(i128::MIN) - 1;
+    match i128::from_cbor(val.into_cbor().unwrap()) {
+        Err(Error::FailConvert(_, msg)) => {
+            assert!(msg.contains("exceeds i128"), "{}", msg)
+        }
+        res => panic!("{:?}", res),
+    }
+}
+
+#[cfg(feature = "chrono")]
+#[test]
+fn test_chrono() {
+    use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+
+    let dts = vec![
+        Utc.timestamp_opt(0, 0).unwrap(),
+        Utc.timestamp_opt(1_363_896_240, 0).unwrap(),
+        Utc.timestamp_opt(1_363_896_240, 500_000_000).unwrap(),
+        Utc.timestamp_opt(-1_363_896_240, 250_000_000).unwrap(),
+    ];
+    for dt in dts.into_iter() {
+        let val = dt.into_cbor().unwrap();
+        match (dt.timestamp_subsec_nanos(), &val) {
+            (0, Cbor::Major6(_, Tag::Epoch(secs))) => assert!(secs.is_number()),
+            (_, Cbor::Major6(_, Tag::Epoch(secs))) => {
+                assert!(matches!(secs.as_ref(), Cbor::Major7(_, SimpleValue::F64(_))))
+            }
+            (_, val) => panic!("{} {:?}", dt, val),
+        }
+        assert_eq!(DateTime::<Utc>::from_cbor(val).unwrap(), dt);
+    }
+    // fractional seconds hold about a microsecond precision.
+    let dt = Utc.timestamp_opt(1_700_000_000, 123_456_789).unwrap();
+    let val = dt.into_cbor().unwrap();
+    let tag =
+        Tag::from_value(1, (1_700_000_000_f64 + 0.123_456_789).into_cbor().unwrap());
+    assert_eq!(val, Cbor::from(tag));
+    let diff = DateTime::<Utc>::from_cbor(val).unwrap() - dt;
+    assert!(diff.num_microseconds().unwrap().abs() < 1, "{}", diff);
+    // same instant as Tag::to_system_time, rounding up to the next second.
+    let val: Cbor = Tag::from_value(1, 1.999_999_999_9_f64.into_cbor().unwrap()).into();
+    let dt = Utc.timestamp_opt(2, 0).unwrap();
+    match &val {
+        Cbor::Major6(_, tag) => assert_eq!(tag.to_system_time().unwrap(), dt.into()),
+        val => panic!("{:?}", val),
+    }
+    assert_eq!(DateTime::<Utc>::from_cbor(val).unwrap(), dt);
+    // nano-seconds are preserved as tag-0.
+    let dt = Utc.timestamp_opt(1_700_000_000, 123_456_789).unwrap();
+    let val: Cbor = Tag::from_datetime(dt.into()).unwrap().into();
+    let text = "2023-11-14T22:13:20.123456789Z".into_cbor().unwrap();
+    assert_eq!(val, Cbor::from(Tag::from_value(0, text)));
+    assert_eq!(DateTime::<Utc>::from_cbor(val).unwrap(), dt);
+
+    // RFC 8949, appendix-A.
+    let mut buf = vec![];
+    let dt = Utc.timestamp_opt(1_363_896_240, 0).unwrap();
+    dt.into_cbor().unwrap().encode(&mut buf).unwrap();
+    assert_eq!(buf, vec![0xc1, 0x1a, 0x51, 0x4b, 0x67, 0xb0]);
+    let buf = vec![0xc1, 0xfb, 0x41, 0xd4, 0x52, 0xd9, 0xec, 0x20, 0x00, 0x00];
+    let (val, _) = Cbor::decode(&mut buf.as_slice()).unwrap();
+    let refdt = Utc.timestamp_opt(1_363_896_240, 500_000_000).unwrap();
+    assert_eq!(DateTime::<Utc>::from_cbor(val).unwrap(), refdt);
+
+    let text = "2013-03-21T20:04:00Z".into_cbor().unwrap();
+    let val: Cbor = Tag::from_value(0, text).into();
+    assert_eq!(DateTime::<Utc>::from_cbor(val).unwrap(), dt);
+
+    let text = "2013-03-21T22:04:00+02:00".into_cbor().unwrap();
+    let val: Cbor = Tag::from_value(0, text).into();
+    assert_eq!(DateTime::<Utc>::from_cbor(val).unwrap(), dt);
+
+    let leap = NaiveDate::from_ymd_opt(2016, 12, 31)
+        .unwrap()
+        .and_hms_milli_opt(23, 59, 59, 1_500)
+        .unwrap()
+        .and_utc();
+    match leap.into_cbor() {
+        Err(Error::FailConvert(_, _)) => (),
+        res => panic!("{:?}", res),
+    }
+
+    let vals: Vec<Cbor> = vec![
+        Tag::from_value(0, "2016-12-31T23:59:60Z".into_cbor().unwrap()).into(),
+        Tag::from_value(0, "not a date".into_cbor().unwrap()).into(),
+        Tag::from_value(1, i64::MAX.into_cbor().unwrap()).into(),
+        Tag::from_value(1, f64::INFINITY.into_cbor().unwrap()).into(),
+        Tag::from_value(1, 1e300_f64.into_cbor().unwrap()).into(),
+        Tag::from_value(7, 0.into_cbor().unwrap()).into(),
+        1_363_896_240.into_cbor().unwrap(),
+    ];
+    for val in vals.into_iter() {
+        match DateTime::<Utc>::from_cbor(val) {
+            Err(Error::FailConvert(_, _)) => (),
+            res => panic!("{:?}", res),
+        }
+    }
+}
+
+#[test]
+fn test_hash_collections_canonical() {
+    let keys = ["z", "b", "aa", "a", "c", "m", "q"];
+
+    let mut bufs: Vec<Vec<u8>> = vec![];
+    for i in 0..10 {
+        // every map is seeded differently.
+        let mut map: HashMap<String, i64> = HashMap::new();
+        for (j, key) in keys.iter().enumerate().cycle().skip(i).take(keys.len()) {
+            map.insert(key.to_string(), j as i64);
+        }
+        let mut buf = vec![];
+        map.into_cbor().unwrap().encode_canonical(&mut buf).unwrap();
+        bufs.push(buf);
+    }
+    let btree: BTreeMap<String, i64> =
+        keys.iter().enumerate().map(|(j, key)| (key.to_string(), j as i64)).collect();
+    let mut refbuf = vec![];
+    btree.into_cbor().unwrap().encode_canonical(&mut refbuf).unwrap();
+    for buf in bufs.into_iter() {
+        assert_eq!(buf, refbuf);
+    }
+
+    let mut vals: Vec<Cbor> = vec![];
+    for i in 0..10 {
+        let set: HashSet<String> =
+            keys.iter().cycle().skip(i).take(keys.len()).map(|s| s.to_string()).collect();
+        vals.push(set.into_cbor().unwrap());
+    }
+    let refval = vec!["a", "b", "c", "m", "q", "z", "aa"].into_cbor().unwrap();
+    for val in vals.into_iter() {
+        assert_eq!(val, refval);
+    }
+}
+
+#[test]
+fn test_native_eq() {
+    let val = 10_u64.into_cbor().unwrap();
+    assert!(val == 10_u8 && val == 10_u16 && val == 10_u32 && val == 10_u64);
+    assert!(val == 10_i8 && val == 10_i16 && val == 10_i32 && val == 10_i64);
+    assert!(val == 10_usize && val == 10_isize);
+    assert!(val != 11_u64 && val != -10_i64 && val != 10.0_f64);
+    // encoding width is ignored.
+    assert_eq!(Cbor::Major0(crate::Info::U64, 10), 10_u64);
+
+    let val = (-300_i64).into_cbor().unwrap();
+    assert!(val == -300_i16 && val == -300_i32 && val == -300_i64 && val == -300_isize);
+    assert!(val != 300_u64 && val != 300_i64);
+    assert_eq!(i64::MIN.into_cbor().unwrap(), i64::MIN);
+    assert_eq!(u64::MAX.into_cbor().unwrap(), u64::MAX);
+    assert_ne!(u64::MAX.into_cbor().unwrap(), -1_i64);
+
+    let val = 1.5_f64.into_cbor().unwrap();
+    assert!(val == 1.5_f64 && val == 1.5_f32);
+    assert!(val != 1.0_f64 && val != 1_u64);
+    assert_eq!(1.5_f32.into_cbor().unwrap(), 1.5_f64);
+    assert_eq!(SimpleValue::F16(0x3e00).into_cbor().unwrap(), 1.5_f64);
+    assert_eq!(SimpleValue::F16(0x8000).into_cbor().unwrap(), 0.0_f64);
+    assert_ne!(f64::NAN.into_cbor().unwrap(), f64::NAN);
+    assert_ne!(1_u64.into_cbor().unwrap(), 1.0_f64);
+
+    assert_eq!(true.into_cbor().unwrap(), true);
+    assert_eq!(false.into_cbor().unwrap(), false);
+    assert_ne!(true.into_cbor().unwrap(), false);
+    assert_ne!(Cbor::null(), false);
+
+    let val = "hello".into_cbor().unwrap();
+    assert_eq!(val, "hello");
+    assert_eq!(&val, &"hello");
+    assert_eq!(val, "hello".to_string());
+    assert_eq!(val, *"hello");
+    assert_ne!(val, "world");
+    assert_ne!(Cbor::from_bytes(b"hello".to_vec()).unwrap(), "hello");
+
+    // within documents.
+    let doc: Vec<(Key, Cbor)> = vec![
+        (Key::from("x"), 1_u64.into_cbor().unwrap()),
+        (Key::from("y"), "z".into_cbor().unwrap()),
+    ];
+    let doc = Vec::<(Key, Cbor)>::from_cbor(doc.into_cbor().unwrap()).unwrap();
+    assert_eq!(&doc[0].1, &1_u64);
+    assert_eq!(doc[1].1, "z");
+}
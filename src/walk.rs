@@ -0,0 +1,85 @@
+//! Module implement depth-first traversal of cbor values.
+
+use crate::Cbor;
+
+/// Visitor for [Cbor::walk], called for every value in the tree, depth-first.
+///
+/// Depth of the top-level value is 1. Map keys are not visited, map values
+/// are. Tagged values are visited as children of the tag.
+pub trait Visitor {
+    /// Called before visiting the value's children. Return false to prune
+    /// the children of `val`, its `exit` callback shall still be called.
+    fn enter(&mut self, val: &Cbor, depth: u32) -> bool;
+
+    /// Called after visiting the value's children.
+    fn exit(&mut self, _val: &Cbor, _depth: u32) {}
+}
+
+/// Visitor for [Cbor::walk_mut], can modify values in place.
+pub trait VisitorMut {
+    /// Called before visiting the value's children, which are visited after
+    /// `val` is modified. Return false to prune the children of `val`.
+    fn enter(&mut self, val: &mut Cbor, depth: u32) -> bool;
+}
+
+impl Cbor {
+    /// Walk this value and its children depth-first, refer to [Visitor].
+    /// Traversal uses an explicit stack, hence deeply nested values shall
+    /// not overflow the call stack.
+    pub fn walk<V>(&self, visitor: &mut V)
+    where
+        V: Visitor,
+    {
+        // (value, depth, entered)
+        let mut stack: Vec<(&Cbor, u32, bool)> = vec![(self, 1, false)];
+        while let Some((val, depth, entered)) = stack.pop() {
+            if entered {
+                visitor.exit(val, depth);
+                continue;
+            }
+
+            stack.push((val, depth, true));
+            if !visitor.enter(val, depth) {
+                continue;
+            }
+            match val {
+                Cbor::Major4(_, list) => {
+                    stack.extend(list.iter().rev().map(|x| (x, depth + 1, false)))
+                }
+                Cbor::Major5(_, map, _) => {
+                    stack.extend(map.iter().rev().map(|(_, x)| (x, depth + 1, false)))
+                }
+                Cbor::Major6(_, tag) => stack.push((tag.as_content(), depth + 1, false)),
+                _ => (),
+            }
+        }
+    }
+
+    /// Same as [Cbor::walk], but values can be modified in place, refer to
+    /// [VisitorMut].
+    pub fn walk_mut<V>(&mut self, visitor: &mut V)
+    where
+        V: VisitorMut,
+    {
+        let mut stack: Vec<(&mut Cbor, u32)> = vec![(self, 1)];
+        while let Some((val, depth)) = stack.pop() {
+            if !visitor.enter(val, depth) {
+                continue;
+            }
+            match val {
+                Cbor::Major4(_, list) => {
+                    stack.extend(list.iter_mut().rev().map(|x| (x, depth + 1)))
+                }
+                Cbor::Major5(_, map, _) => {
+                    stack.extend(map.iter_mut().rev().map(|(_, x)| (x, depth + 1)))
+                }
+                Cbor::Major6(_, tag) => stack.push((tag.as_content_mut(), depth + 1)),
+                _ => (),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "walk_test.rs"]
+mod walk_test;
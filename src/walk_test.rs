@@ -0,0 +1,119 @@
+use super::*;
+use crate::{IntoCbor, Tag, RECURSION_LIMIT};
+
+struct Trace {
+    events: Vec<String>,
+    prune_depth: u32,
+}
+
+impl Visitor for Trace {
+    fn enter(&mut self, val: &Cbor, depth: u32) -> bool {
+        self.events.push(format!("enter {} {}", val.to_major_val(), depth));
+        depth < self.prune_depth
+    }
+
+    fn exit(&mut self, val: &Cbor, depth: u32) {
+        self.events.push(format!("exit {} {}", val.to_major_val(), depth));
+    }
+}
+
+#[test]
+fn test_walk() {
+    // [1, {"a": [2]}, tag-39("id")]
+    let val = vec![
+        1.into_cbor().unwrap(),
+        Cbor::map_from(vec![("a".into_cbor().unwrap(), vec![2].into_cbor().unwrap())])
+            .unwrap(),
+        Tag::from_identifier("id".into_cbor().unwrap()).into(),
+    ]
+    .into_cbor()
+    .unwrap();
+
+    let mut trace = Trace { events: vec![], prune_depth: u32::MAX };
+    val.walk(&mut trace);
+    let refs = vec![
+        "enter 4 1",
+        "enter 0 2",
+        "exit 0 2",
+        "enter 5 2",
+        "enter 4 3",
+        "enter 0 4",
+        "exit 0 4",
+        "exit 4 3",
+        "exit 5 2",
+        "enter 6 2",
+        "enter 3 3",
+        "exit 3 3",
+        "exit 6 2",
+        "exit 4 1",
+    ];
+    assert_eq!(trace.events, refs);
+
+    let mut trace = Trace { events: vec![], prune_depth: 2 };
+    val.walk(&mut trace);
+    let refs = vec![
+        "enter 4 1",
+        "enter 0 2",
+        "exit 0 2",
+        "enter 5 2",
+        "exit 5 2",
+        "enter 6 2",
+        "exit 6 2",
+        "exit 4 1",
+    ];
+    assert_eq!(trace.events, refs);
+}
+
+#[test]
+fn test_walk_deep() {
+    let mut val = 1.into_cbor().unwrap();
+    for _ in 0..(RECURSION_LIMIT * 10) {
+        val = Cbor::Major4(1_u64.into(), vec![val]);
+    }
+
+    let mut trace = Trace { events: vec![], prune_depth: u32::MAX };
+    val.walk(&mut trace);
+    assert_eq!(trace.events.len(), (RECURSION_LIMIT as usize * 10 + 1) * 2);
+
+    // drop iteratively, recursive drop of deeply nested value is not our concern.
+    let mut items = vec![val];
+    while let Some(item) = items.pop() {
+        if let Cbor::Major4(_, list) = item {
+            items.extend(list)
+        }
+    }
+}
+
+#[test]
+fn test_walk_mut() {
+    struct Redact;
+
+    impl VisitorMut for Redact {
+        fn enter(&mut self, val: &mut Cbor, depth: u32) -> bool {
+            if depth > 2 && val.is_bytes() {
+                *val = "redacted".into_cbor().unwrap();
+            }
+            true
+        }
+    }
+
+    let secret = Cbor::from_bytes(vec![1, 2, 3]).unwrap();
+    let mut val = vec![
+        secret.clone(),
+        vec![secret.clone(), vec![secret.clone()].into_cbor().unwrap()]
+            .into_cbor()
+            .unwrap(),
+    ]
+    .into_cbor()
+    .unwrap();
+    val.walk_mut(&mut Redact);
+
+    let redacted = "redacted".into_cbor().unwrap();
+    let refval = vec![
+        secret,
+        vec![redacted.clone(), vec![redacted].into_cbor().unwrap()].into_cbor().unwrap(),
+    ]
+    .into_cbor()
+    .unwrap();
+    assert_eq!(val, refval);
+}